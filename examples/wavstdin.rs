@@ -0,0 +1,39 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2017 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// This example decodes a wav file from stdin and prints its peak amplitude.
+// It shows that a `WavReader` only needs an `io::Read`: the input is streamed,
+// and it does not need to be seekable.
+
+// Usage: cargo run --example wavstdin < testsamples/pop.wav
+
+extern crate hound;
+
+use std::io;
+
+fn main() {
+    let stdin = io::stdin();
+    // The locked stdin is already buffered, so it need not be wrapped in a
+    // `BufReader`.
+    let mut reader = hound::WavReader::new(stdin.lock()).unwrap();
+    let spec = reader.spec();
+    let peak = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>()
+            .map(|s| s.unwrap().abs() as f64)
+            .fold(0.0, f64::max),
+        hound::SampleFormat::Int => reader.samples::<i32>()
+            .map(|s| (s.unwrap() as f64).abs())
+            .fold(0.0, f64::max),
+    };
+    println!("{} channels, {} Hz, {} bits: peak {} ({} samples)",
+             spec.channels, spec.sample_rate, spec.bits_per_sample, peak, reader.len());
+}
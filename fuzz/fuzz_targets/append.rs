@@ -38,7 +38,7 @@ where S: hound::Sample + Copy + Debug + PartialEq,
         let cursor = io::Cursor::new(&mut buffer);
         let mut writer = match hound::WavWriter::new_append(cursor) {
             Ok(w) => w,
-            Err(hound::Error::Unsupported) => return,
+            Err(hound::Error::Unsupported(_)) => return,
             Err(err) => panic!("{:?}", err),
         };
         for _ in 0..writer.spec().channels {
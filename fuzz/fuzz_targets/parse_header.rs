@@ -0,0 +1,17 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2017 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+
+#![no_main]
+
+extern crate libfuzzer_sys;
+extern crate hound;
+
+#[export_name="rust_fuzzer_test_input"]
+pub extern fn go(data: &[u8]) {
+    // Parsing may fail, but it must not panic.
+    let _ = hound::parse_header(data);
+}
@@ -0,0 +1,53 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Uses the parts of Hound that are available without `std`.
+//!
+//! This crate is `no_std` itself, so it only builds if Hound does too.
+
+#![no_std]
+
+extern crate hound;
+
+/// Returns the largest magnitude of the 16-bit samples of a file held in memory.
+///
+/// The samples are decoded in blocks into a buffer on the stack, as samples
+/// that arrive in DMA buffers would be.
+pub fn peak(file: &[u8]) -> hound::SliceResult<i16> {
+    let header = try!(hound::Header::parse(file));
+    let mut buffer = [0_i16; 64];
+    let mut peak = 0_i16;
+    for block in header.data(file).chunks(buffer.len() * 2) {
+        let n = try!(header.read_samples_i16(block, &mut buffer));
+        for &sample in &buffer[..n] {
+            peak = peak.max(sample.saturating_abs());
+        }
+    }
+    Ok(peak)
+}
+
+#[test]
+fn peak_of_a_file_in_memory() {
+    // A mono 16-bit file with three samples.
+    let file = b"RIFF\x2a\0\0\0WAVEfmt \x10\0\0\0\x01\0\x01\0\x44\xac\0\0\x88\x58\x01\0\x02\0\x10\0\
+                 data\x06\0\0\0\x05\0\x00\x80\x07\0";
+    let header = hound::Header::parse(file).unwrap();
+    assert_eq!(header.spec(), hound::WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    });
+    assert_eq!(header.len(), 3);
+    assert_eq!(peak(file), Ok(i16::MAX));
+    assert_eq!(peak(&file[..20]), Err(hound::SliceError::UnexpectedEnd));
+}
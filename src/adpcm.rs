@@ -0,0 +1,363 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoders for IMA ADPCM and Microsoft ADPCM.
+//!
+//! Both store the samples in blocks of `nBlockAlign` bytes that can be
+//! decoded independently, and both encode a sample as a 4-bit code, the
+//! difference with a prediction, scaled by a step size that adapts to the
+//! signal.
+//!
+//! IMA ADPCM, also known as DVI ADPCM, has format tag 0x0011. A block starts
+//! with a 4-byte header for every channel: the first sample as a
+//! little-endian `i16`, the index into the step table, and a reserved byte.
+//! Then follow groups of 4 bytes for every channel in turn, each holding 8
+//! samples of that channel as 4-bit codes, the low nibble first. The
+//! prediction is the previous sample.
+//!
+//! Microsoft ADPCM has format tag 0x0002. A block starts with the index of
+//! the predictor coefficients for every channel as a byte, followed by the
+//! initial step size, the second sample and the first sample of every
+//! channel, each as an `i16`. Then follow the codes of the interleaved
+//! samples, the high nibble first. The prediction is a weighted sum of the
+//! previous two samples, with coefficients from the fmt chunk.
+
+use std::io;
+
+/// The codec of the samples, and its parameters.
+#[derive(Clone, Debug)]
+pub enum Codec {
+    /// IMA ADPCM, format tag 0x0011.
+    Ima,
+    /// Microsoft ADPCM, format tag 0x0002, with its predictor coefficients.
+    Microsoft(Vec<(i16, i16)>),
+}
+
+impl Codec {
+    /// Returns the format tag of the fmt chunk.
+    pub fn format_tag(&self) -> u16 {
+        match *self {
+            Codec::Ima => 0x0011,
+            Codec::Microsoft(..) => 0x0002,
+        }
+    }
+
+    /// Returns the number of frames in a block of `block_align` bytes.
+    pub fn samples_per_block(&self, block_align: u16, channels: u16) -> u32 {
+        match *self {
+            Codec::Ima => samples_per_block(block_align, channels),
+            Codec::Microsoft(..) => ms_samples_per_block(block_align, channels),
+        }
+    }
+
+    /// Returns the number of frames that can be decoded from `len` bytes of blocks.
+    pub fn frames_in(&self, len: u64, block_align: u16, channels: u16) -> u64 {
+        match *self {
+            Codec::Ima => frames_in(len, block_align, channels),
+            Codec::Microsoft(..) => ms_frames_in(len, block_align, channels),
+        }
+    }
+
+    /// Decodes a block into interleaved samples, which are appended to `out`.
+    pub fn decode_block(&self, block: &[u8], channels: usize, out: &mut Vec<i16>) -> io::Result<()> {
+        match *self {
+            Codec::Ima => decode_block(block, channels, out),
+            Codec::Microsoft(ref coefficients) => ms_decode_block(block, channels, coefficients, out),
+        }
+    }
+}
+
+/// The step sizes, as in the IMA ADPCM specification.
+const STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45,
+    50, 55, 60, 66, 73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230,
+    253, 279, 307, 337, 371, 408, 449, 494, 544, 598, 658, 724, 796, 876, 963,
+    1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272, 2499, 2749, 3024, 3327,
+    3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493, 10442,
+    11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794,
+    32767,
+];
+
+/// The change of the step index for every code, ignoring the sign bit.
+const INDEX_TABLE: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Returns the number of bytes of the header of a block.
+fn header_len(channels: usize) -> usize {
+    4 * channels
+}
+
+/// Returns the number of frames in a block of `block_align` bytes.
+///
+/// The block align must be a multiple of 4 bytes per channel, and hold at
+/// least the headers.
+pub fn samples_per_block(block_align: u16, channels: u16) -> u32 {
+    // The header holds one sample, every other byte holds two.
+    (block_align as u32 / channels as u32 - 4) * 2 + 1
+}
+
+/// Returns the number of frames that can be decoded from `len` bytes of blocks.
+///
+/// The last block may be shorter than `block_align`; only whole groups of it
+/// are decoded.
+pub fn frames_in(len: u64, block_align: u16, channels: u16) -> u64 {
+    let blocks = len / block_align as u64;
+    let rest = len % block_align as u64;
+    let header = header_len(channels as usize) as u64;
+    let partial = if rest < header { 0 } else { 1 + (rest - header) / header * 8 };
+    blocks * samples_per_block(block_align, channels) as u64 + partial
+}
+
+/// The state of the decoder for one channel.
+struct Channel {
+    predictor: i32,
+    index: i32,
+}
+
+impl Channel {
+    fn decode(&mut self, code: u8) -> i16 {
+        let step = STEP_TABLE[self.index as usize];
+        let mut diff = step >> 3;
+        if code & 1 != 0 { diff += step >> 2; }
+        if code & 2 != 0 { diff += step >> 1; }
+        if code & 4 != 0 { diff += step; }
+        if code & 8 != 0 {
+            self.predictor = (self.predictor - diff).max(-32768);
+        } else {
+            self.predictor = (self.predictor + diff).min(32767);
+        }
+        self.index = (self.index + INDEX_TABLE[(code & 7) as usize]).clamp(0, 88);
+        self.predictor as i16
+    }
+}
+
+/// Decodes a block into interleaved samples, which are appended to `out`.
+///
+/// A block that is shorter than a complete one, at the end of the data,
+/// yields the samples of its whole groups. Returns an error if a step index
+/// in the header is out of range.
+pub fn decode_block(block: &[u8], channels: usize, out: &mut Vec<i16>) -> io::Result<()> {
+    let header = header_len(channels);
+    if block.len() < header {
+        return Ok(())
+    }
+    let mut states = Vec::with_capacity(channels);
+    for h in block[..header].chunks(4) {
+        let predictor = i16::from_le_bytes([h[0], h[1]]);
+        if h[2] > 88 {
+            let msg = format!("IMA ADPCM step index {} exceeds 88", h[2]);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        out.push(predictor);
+        states.push(Channel { predictor: predictor as i32, index: h[2] as i32 });
+    }
+    // A group holds 8 samples for every channel, 4 bytes each.
+    let mut frames = [0_i16; 8];
+    for group in block[header..].chunks_exact(header) {
+        let start = out.len();
+        out.resize(start + 8 * channels, 0);
+        for (c, (bytes, state)) in group.chunks(4).zip(&mut states).enumerate() {
+            for (i, &byte) in bytes.iter().enumerate() {
+                frames[2 * i] = state.decode(byte & 0xf);
+                frames[2 * i + 1] = state.decode(byte >> 4);
+            }
+            for (k, &sample) in frames.iter().enumerate() {
+                out[start + k * channels + c] = sample;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The predictor coefficients that every Microsoft ADPCM file starts with.
+pub const MS_COEFFICIENTS: [(i16, i16); 7] = [
+    (256, 0), (512, -256), (0, 0), (192, 64), (240, 0), (460, -208), (392, -232),
+];
+
+/// The factor by which the step size changes for every code, divided by 256.
+const MS_ADAPTATION_TABLE: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+/// Returns the number of bytes of the header of a Microsoft ADPCM block.
+fn ms_header_len(channels: usize) -> usize {
+    7 * channels
+}
+
+/// Returns the number of frames in a Microsoft ADPCM block of `block_align` bytes.
+///
+/// The block align must hold at least the headers.
+pub fn ms_samples_per_block(block_align: u16, channels: u16) -> u32 {
+    // The header holds two samples, every other byte holds two codes.
+    let header = ms_header_len(channels as usize) as u32;
+    (block_align as u32 - header) * 2 / channels as u32 + 2
+}
+
+/// Returns the number of frames that can be decoded from `len` bytes of Microsoft ADPCM blocks.
+///
+/// The last block may be shorter than `block_align`; only its whole frames
+/// are decoded.
+pub fn ms_frames_in(len: u64, block_align: u16, channels: u16) -> u64 {
+    let blocks = len / block_align as u64;
+    let rest = len % block_align as u64;
+    let header = ms_header_len(channels as usize) as u64;
+    let partial = if rest < header { 0 } else { 2 + (rest - header) * 2 / channels as u64 };
+    blocks * ms_samples_per_block(block_align, channels) as u64 + partial
+}
+
+/// The state of the Microsoft ADPCM decoder for one channel.
+struct MsChannel {
+    coefficients: (i32, i32),
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+impl MsChannel {
+    fn decode(&mut self, code: u8) -> i16 {
+        // As in the reference implementation, the divisions round toward zero.
+        // The coefficients come from the file, so the prediction may exceed
+        // the range of an `i32`.
+        let prediction = (self.sample1 as i64 * self.coefficients.0 as i64 +
+                          self.sample2 as i64 * self.coefficients.1 as i64) / 256;
+        let signed = if code & 8 != 0 { code as i64 - 16 } else { code as i64 };
+        let sample = (prediction + signed * self.delta as i64).clamp(-32768, 32767) as i32;
+        self.sample2 = self.sample1;
+        self.sample1 = sample;
+        // Like ffmpeg, bound the step size so that it cannot overflow when
+        // the block header was malformed.
+        let delta = MS_ADAPTATION_TABLE[code as usize] * self.delta / 256;
+        self.delta = delta.clamp(16, i32::MAX / 768);
+        sample as i16
+    }
+}
+
+/// Decodes a Microsoft ADPCM block into interleaved samples, which are appended to `out`.
+///
+/// A block that is shorter than a complete one, at the end of the data,
+/// yields the samples of its whole frames. Returns an error if a block
+/// refers to a coefficient set that `coefficients` does not have.
+pub fn ms_decode_block(block: &[u8],
+                       channels: usize,
+                       coefficients: &[(i16, i16)],
+                       out: &mut Vec<i16>)
+                       -> io::Result<()> {
+    let header = ms_header_len(channels);
+    if block.len() < header {
+        return Ok(())
+    }
+    let field = |i: usize| {
+        i16::from_le_bytes([block[channels + 2 * i], block[channels + 2 * i + 1]]) as i32
+    };
+    let mut states = Vec::with_capacity(channels);
+    for (c, &predictor) in block[..channels].iter().enumerate() {
+        let (coef1, coef2) = match coefficients.get(predictor as usize) {
+            Some(&pair) => pair,
+            None => {
+                let msg = format!("Microsoft ADPCM predictor {} exceeds the {} coefficient sets",
+                                  predictor, coefficients.len());
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+        };
+        states.push(MsChannel {
+            coefficients: (coef1 as i32, coef2 as i32),
+            delta: field(c),
+            sample1: field(channels + c),
+            sample2: field(2 * channels + c),
+        });
+    }
+    // The header holds the first two samples, the oldest one last.
+    out.extend(states.iter().map(|state| state.sample2 as i16));
+    out.extend(states.iter().map(|state| state.sample1 as i16));
+    let codes = (block.len() - header) * 2;
+    let whole = codes - codes % channels;
+    for i in 0..whole {
+        let byte = block[header + i / 2];
+        let code = if i % 2 == 0 { byte >> 4 } else { byte & 0xf };
+        out.push(states[i % channels].decode(code));
+    }
+    Ok(())
+}
+
+#[test]
+fn frames_in_counts_whole_groups() {
+    // A stereo block of 256 bytes holds 8 bytes of header and 31 groups.
+    assert_eq!(samples_per_block(256, 2), 249);
+    assert_eq!(frames_in(512, 256, 2), 498);
+    assert_eq!(frames_in(512 + 7, 256, 2), 498);
+    assert_eq!(frames_in(512 + 8, 256, 2), 499);
+    assert_eq!(frames_in(512 + 15, 256, 2), 499);
+    assert_eq!(frames_in(512 + 16, 256, 2), 507);
+}
+
+#[test]
+fn decode_block_follows_the_step_table() {
+    // The first sample is the predictor, then codes 7, 0, 8 and 15.
+    let block = [0x10, 0x00, 0x00, 0x00, 0x07, 0x08 | 0xf0, 0x00, 0x00];
+    let mut out = Vec::new();
+    decode_block(&block, 1, &mut out).unwrap();
+    // Step 7: code 7 adds 0 + 1 + 3 + 7 = 11, then the index moves to 8.
+    // Step 16: code 0 adds 2, and the index moves to 7. Step 14 for code 8
+    // subtracts 1, then the index moves to 6. Step 13 for code 15 subtracts
+    // 1 + 3 + 6 + 13 = 23.
+    assert_eq!(&out[..5], [16, 27, 29, 28, 5]);
+    assert_eq!(out.len(), 9);
+
+    let mut out = Vec::new();
+    let block = [0x00, 0x00, 89, 0x00];
+    assert!(decode_block(&block, 1, &mut out).is_err());
+}
+
+#[test]
+fn ms_frames_in_counts_whole_frames() {
+    // A stereo block of 256 bytes holds 14 bytes of header and 242 frames.
+    assert_eq!(ms_samples_per_block(256, 2), 244);
+    assert_eq!(ms_frames_in(512, 256, 2), 488);
+    assert_eq!(ms_frames_in(512 + 13, 256, 2), 488);
+    assert_eq!(ms_frames_in(512 + 14, 256, 2), 490);
+    assert_eq!(ms_frames_in(512 + 17, 256, 2), 493);
+    // Mono blocks hold two frames per byte.
+    assert_eq!(ms_samples_per_block(1024, 1), 2036);
+    assert_eq!(ms_frames_in(7 + 3, 1024, 1), 8);
+}
+
+#[test]
+fn ms_decode_block_follows_the_adaptation_table() {
+    // Coefficient set 1, a step size of 16, and samples 20 and then 10.
+    let block = [0x01, 0x10, 0x00, 0x0a, 0x00, 0x14, 0x00, 0x7f, 0x08];
+    let mut out = Vec::new();
+    ms_decode_block(&block, 1, &MS_COEFFICIENTS, &mut out).unwrap();
+    // The prediction is 2 * 10 - 20 = 0, code 7 adds 7 * 16 = 112, and the
+    // step size becomes 614 * 16 / 256 = 38. The prediction is then 224 -
+    // 10 = 214, code 15 subtracts 38, and the step becomes 34. Code 0 keeps
+    // the prediction of 352 - 112 = 240, and the step becomes 30. From the
+    // prediction of 480 - 176 = 304, code 8 subtracts 8 * 30.
+    assert_eq!(out, [20, 10, 112, 176, 240, 64]);
+
+    let block = [0x07, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert!(ms_decode_block(&block, 1, &MS_COEFFICIENTS, &mut out).is_err());
+}
+
+#[test]
+fn ms_decode_block_bounds_the_step_size() {
+    // A step size of 0x7fff grows by 768 / 256 with every code 8.
+    let block = [0x00, 0xff, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x88, 0x88, 0x88, 0x88];
+    let mut out = Vec::new();
+    ms_decode_block(&block, 1, &MS_COEFFICIENTS, &mut out).unwrap();
+    assert_eq!(out, [0, 0, -32768, -32768, -32768, -32768, -32768, -32768, -32768, -32768]);
+
+    // Coefficients of -32768 with samples of -32768 predict 2^31 / 256, which
+    // is clamped. The prediction from 32767 and -32768 is then 32768 / 256.
+    let block = [0x00, 0x10, 0x00, 0x00, 0x80, 0x00, 0x80, 0x00];
+    let mut out = Vec::new();
+    ms_decode_block(&block, 1, &[(-32768, -32768)], &mut out).unwrap();
+    assert_eq!(out, [-32768, -32768, 32767, 128]);
+}
@@ -0,0 +1,273 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion between buffers of integer samples and normalized floats.
+//!
+//! An integer sample of `n` bits maps to a float by dividing it by `2^(n - 1)`,
+//! so the full scale range maps to [-1.0, 1.0). Because the divisor is a power
+//! of two, the scaling is exact. The reverse direction multiplies by the same
+//! factor and truncates toward zero, so converting to float and back returns
+//! the original samples.
+//!
+//! The loops process fixed-size chunks without branching per sample, which
+//! allows the compiler to vectorize them.
+
+use super::{Error, OverflowMode, Result};
+
+/// The number of samples converted per iteration of the inner loop.
+const LANES: usize = 16;
+
+/// Converts every sample of `src` with `f` into the corresponding element of `dst`.
+#[inline(always)]
+fn map_chunked<T: Copy, U, F: Fn(T) -> U>(src: &[T], dst: &mut [U], f: F) {
+    assert_eq!(src.len(), dst.len(), "source and destination lengths differ");
+    let mut src_chunks = src.chunks_exact(LANES);
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    for (s, d) in (&mut src_chunks).zip(&mut dst_chunks) {
+        for i in 0..LANES {
+            d[i] = f(s[i]);
+        }
+    }
+    for (s, d) in src_chunks.remainder().iter().zip(dst_chunks.into_remainder()) {
+        *d = f(*s);
+    }
+}
+
+/// Converts with `f`, which returns the clamped value and whether it was clamped.
+///
+/// With `OverflowMode::Error`, this returns `Error::TooWide` if any sample was
+/// clamped. In that case `dst` holds the clamped values.
+#[inline(always)]
+fn map_chunked_clipped<U: Copy, F>(src: &[f32], dst: &mut [U], mode: OverflowMode, f: F) -> Result<()>
+    where F: Fn(f32) -> (U, bool)
+{
+    assert_eq!(src.len(), dst.len(), "source and destination lengths differ");
+    let mut clipped = false;
+    let mut src_chunks = src.chunks_exact(LANES);
+    let mut dst_chunks = dst.chunks_exact_mut(LANES);
+    for (s, d) in (&mut src_chunks).zip(&mut dst_chunks) {
+        let mut chunk_clipped = false;
+        for i in 0..LANES {
+            let (x, c) = f(s[i]);
+            d[i] = x;
+            chunk_clipped |= c;
+        }
+        clipped |= chunk_clipped;
+    }
+    for (s, d) in src_chunks.remainder().iter().zip(dst_chunks.into_remainder()) {
+        let (x, c) = f(*s);
+        *d = x;
+        clipped |= c;
+    }
+    match mode {
+        OverflowMode::Error if clipped => Err(Error::TooWide),
+        _ => Ok(()),
+    }
+}
+
+/// Scales `x` by `scale` and clamps it to `[min, max]`.
+///
+/// The cast saturates and maps NaN to zero; NaN counts as clipped.
+#[inline(always)]
+fn clip_to_i32(x: f32, scale: f32, min: i32, max: i32) -> (i32, bool) {
+    let y = x * scale;
+    let v = y as i32;
+    let clamped = v.max(min).min(max);
+    // Casting to i32 saturates as well, so compare in float to detect that.
+    let clipped = !(y > (min as f32) - 1.0 && y < (max as f32) + 1.0);
+    (clamped, clipped)
+}
+
+/// Converts 16-bit samples to floats in the range [-1.0, 1.0).
+///
+/// Panics if `src` and `dst` differ in length.
+pub fn convert_i16_to_f32(src: &[i16], dst: &mut [f32]) {
+    map_chunked(src, dst, |x| x as f32 * (1.0 / 32768.0));
+}
+
+/// Converts 24-bit samples, stored in the low bits of an `i32`, to floats.
+///
+/// Panics if `src` and `dst` differ in length.
+pub fn convert_i24_to_f32(src: &[i32], dst: &mut [f32]) {
+    map_chunked(src, dst, |x| x as f32 * (1.0 / 8388608.0));
+}
+
+/// Converts 32-bit samples to floats.
+///
+/// A float has 24 bits of precision, so the samples are rounded to nearest.
+/// Panics if `src` and `dst` differ in length.
+pub fn convert_i32_to_f32(src: &[i32], dst: &mut [f32]) {
+    map_chunked(src, dst, |x| x as f32 * (1.0 / 2147483648.0));
+}
+
+/// Converts normalized floats to 16-bit samples.
+///
+/// Values are multiplied by 2^15 and truncated toward zero. A value that does
+/// not fit, or NaN, is clipped: with `OverflowMode::Saturate` it is clamped to
+/// `[-0x8000, 0x7fff]`, and NaN becomes zero. With `OverflowMode::Error` the
+/// same values are stored, but `Error::TooWide` is returned. Panics if `src`
+/// and `dst` differ in length.
+pub fn convert_f32_to_i16(src: &[f32], dst: &mut [i16], mode: OverflowMode) -> Result<()> {
+    map_chunked_clipped(src, dst, mode, |x| {
+        let (v, clipped) = clip_to_i32(x, 32768.0, -0x8000, 0x7fff);
+        (v as i16, clipped)
+    })
+}
+
+/// Converts normalized floats to 24-bit samples, stored in the low bits of an `i32`.
+///
+/// Like `convert_f32_to_i16()`, but values are clamped to `[-0x800000, 0x7fffff]`.
+pub fn convert_f32_to_i24(src: &[f32], dst: &mut [i32], mode: OverflowMode) -> Result<()> {
+    map_chunked_clipped(src, dst, mode, |x| clip_to_i32(x, 8388608.0, -0x800000, 0x7fffff))
+}
+
+/// Converts normalized floats to 32-bit samples.
+///
+/// Like `convert_f32_to_i16()`, but values are clamped to the range of `i32`.
+pub fn convert_f32_to_i32(src: &[f32], dst: &mut [i32], mode: OverflowMode) -> Result<()> {
+    map_chunked_clipped(src, dst, mode, |x| {
+        let y = x * 2147483648.0;
+        // 2^31 is the smallest float that does not fit, the cast saturates.
+        (y as i32, !(-2147483648.0..2147483648.0).contains(&y))
+    })
+}
+
+/// Converts normalized floats to samples of `bits` bits, stored in an `i32`.
+///
+/// This scales by 2^(bits - 1) and clamps like `convert_f32_to_i16()`, for
+/// any bit depth from 2 up to 32.
+pub(crate) fn convert_f32_to_bits(src: &[f32], dst: &mut [i32], bits: u16, mode: OverflowMode) -> Result<()> {
+    if bits == 32 {
+        return convert_f32_to_i32(src, dst, mode);
+    }
+    let max = (1_i32 << (bits - 1)) - 1;
+    let scale = (1_i32 << (bits - 1)) as f32;
+    map_chunked_clipped(src, dst, mode, |x| clip_to_i32(x, scale, -max - 1, max))
+}
+
+#[cfg(test)]
+fn random_buffers() -> Vec<Vec<u32>> {
+    // A simple xorshift generator, so failures are reproducible.
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    // Lengths around the chunk size exercise both the chunks and the remainder.
+    (0..200).map(|i| {
+        let len = if i < 40 { i } else { (next() % 1000) as usize };
+        (0..len).map(|_| next() as u32).collect()
+    }).collect()
+}
+
+#[cfg(test)]
+fn random_floats(bits: &[u32]) -> Vec<f32> {
+    bits.iter().map(|&b| match b % 4 {
+        // Arbitrary bit patterns include infinities, NaN, and subnormals.
+        0 => f32::from_bits(b),
+        // Mostly in range, with some values beyond full scale.
+        1 | 2 => (b >> 2) as f32 / (1 << 29) as f32 * 2.5 - 1.25,
+        // Exact sample values and the boundaries.
+        _ => [-1.0, 1.0, 0.0, -0.0, 0.5, -0.999, 1.0 - 1.0 / 65536.0][(b >> 2) as usize % 7],
+    }).collect()
+}
+
+/// The straightforward definition of the float to integer conversion.
+#[cfg(test)]
+fn reference_to_int(x: f32, bits: u32) -> (i64, bool) {
+    let y = x as f64 * (1_u64 << (bits - 1)) as f64;
+    let max = ((1_i64 << (bits - 1)) - 1) as f64;
+    let min = -(1_i64 << (bits - 1)) as f64;
+    if y.is_nan() {
+        (0, true)
+    } else if y.trunc() > max {
+        (max as i64, true)
+    } else if y.trunc() < min {
+        (min as i64, true)
+    } else {
+        (y.trunc() as i64, false)
+    }
+}
+
+#[test]
+fn int_to_float_matches_division() {
+    for buffer in random_buffers() {
+        let src16: Vec<i16> = buffer.iter().map(|&b| b as i16).collect();
+        let mut dst = vec![0.0; buffer.len()];
+        convert_i16_to_f32(&src16, &mut dst);
+        for (x, y) in src16.iter().zip(&dst) {
+            assert_eq!((*x as f32 / 32768.0).to_bits(), y.to_bits());
+        }
+
+        let src24: Vec<i32> = buffer.iter().map(|&b| (b as i32) >> 8).collect();
+        convert_i24_to_f32(&src24, &mut dst);
+        for (x, y) in src24.iter().zip(&dst) {
+            assert_eq!((*x as f32 / 8388608.0).to_bits(), y.to_bits());
+        }
+
+        let src32: Vec<i32> = buffer.iter().map(|&b| b as i32).collect();
+        convert_i32_to_f32(&src32, &mut dst);
+        for (x, y) in src32.iter().zip(&dst) {
+            assert_eq!((*x as f32 / 2147483648.0).to_bits(), y.to_bits());
+        }
+    }
+}
+
+#[test]
+fn float_to_int_matches_reference() {
+    for buffer in random_buffers() {
+        let src = random_floats(&buffer);
+        let any_clipped = |bits| src.iter().any(|&x| reference_to_int(x, bits).1);
+
+        let mut dst16 = vec![0; src.len()];
+        convert_f32_to_i16(&src, &mut dst16, OverflowMode::Saturate).unwrap();
+        for (x, y) in src.iter().zip(&dst16) {
+            assert_eq!(reference_to_int(*x, 16).0, *y as i64, "converting {:?}", x);
+        }
+        let result = convert_f32_to_i16(&src, &mut dst16, OverflowMode::Error);
+        assert_eq!(result.is_err(), any_clipped(16));
+
+        let mut dst32 = vec![0; src.len()];
+        convert_f32_to_i24(&src, &mut dst32, OverflowMode::Saturate).unwrap();
+        for (x, y) in src.iter().zip(&dst32) {
+            assert_eq!(reference_to_int(*x, 24).0, *y as i64, "converting {:?}", x);
+        }
+        let result = convert_f32_to_i24(&src, &mut dst32, OverflowMode::Error);
+        assert_eq!(result.is_err(), any_clipped(24));
+
+        convert_f32_to_i32(&src, &mut dst32, OverflowMode::Saturate).unwrap();
+        for (x, y) in src.iter().zip(&dst32) {
+            assert_eq!(reference_to_int(*x, 32).0, *y as i64, "converting {:?}", x);
+        }
+        let result = convert_f32_to_i32(&src, &mut dst32, OverflowMode::Error);
+        assert_eq!(result.is_err(), any_clipped(32));
+    }
+}
+
+#[test]
+fn conversion_round_trips_exactly() {
+    let src: Vec<i16> = (-0x8000..0x8000).map(|x| x as i16).collect();
+    let mut floats = vec![0.0; src.len()];
+    let mut back = vec![0; src.len()];
+    convert_i16_to_f32(&src, &mut floats);
+    convert_f32_to_i16(&floats, &mut back, OverflowMode::Error).unwrap();
+    assert_eq!(src, back);
+
+    let src: Vec<i32> = (-0x800000..0x800000).step_by(97).chain(Some(0x7fffff)).collect();
+    let mut floats = vec![0.0; src.len()];
+    let mut back = vec![0; src.len()];
+    convert_i24_to_f32(&src, &mut floats);
+    convert_f32_to_i24(&floats, &mut back, OverflowMode::Error).unwrap();
+    assert_eq!(src, back);
+}
@@ -0,0 +1,419 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Copying samples from a reader to a writer with a different sample format.
+//!
+//! Every sample is converted through a normalized value: an integer sample of
+//! `n` bits is divided by `2^(n - 1)`, as in the `convert` module. An `f64`
+//! holds every sample of up to 32 bits exactly, so widening an integer format,
+//! and converting 16 or 24 bits to float, is exact. Narrowing rounds to the
+//! nearest value, optionally after adding dither.
+
+use std::io;
+use super::{Error, Result, SampleFormat, WavSpec};
+use read::WavReader;
+use write::WavWriter;
+
+/// The number of frames that is converted at once.
+const BLOCK_FRAMES: usize = 4096;
+
+/// How the channels of the input map to the channels of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelAdapter {
+    /// The output has the same channels as the input.
+    Same,
+    /// A mono input is written to both channels of a stereo output.
+    MonoToStereo,
+    /// The two channels of a stereo input are averaged into a mono output.
+    StereoToMono,
+}
+
+/// Specifies how `copy_with_options()` converts the samples.
+#[derive(Clone, Copy, Debug)]
+pub struct CopyOptions {
+    /// How the channels of the input map to those of the output.
+    ///
+    /// Defaults to `ChannelAdapter::Same`, which requires the number of
+    /// channels of the input and the output to match.
+    pub channels: ChannelAdapter,
+
+    /// Whether to add triangular dither when the precision is reduced.
+    ///
+    /// The dither spans one least significant bit of the output on either
+    /// side, and is added before rounding when narrowing an integer format,
+    /// or when converting float to integer. It uses a fixed seed, so the
+    /// output is reproducible. Defaults to false, samples are rounded to the
+    /// nearest value.
+    pub dither: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> CopyOptions {
+        CopyOptions {
+            channels: ChannelAdapter::Same,
+            dither: false,
+        }
+    }
+}
+
+/// Reports what `copy()` did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CopyStats {
+    /// The number of frames that were written.
+    pub frames: u64,
+    /// The number of samples that did not fit the output, and were clamped.
+    pub clipped: u64,
+}
+
+/// A simple xorshift generator for the dither, so the output is reproducible.
+struct Rng(u64);
+
+impl Rng {
+    /// Returns a uniform number in [0.0, 1.0).
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1_u64 << 53) as f64
+    }
+}
+
+/// Converts normalized samples to the sample format of `spec`.
+struct Encoder {
+    spec: WavSpec,
+    /// whether precision is lost, and the dither applies
+    narrowing: bool,
+    rng: Option<Rng>,
+}
+
+impl Encoder {
+    fn new(input: WavSpec, output: WavSpec, dither: bool) -> Encoder {
+        let narrowing = output.sample_format == SampleFormat::Int && match input.sample_format {
+            SampleFormat::Float => true,
+            SampleFormat::Int => output.bits_per_sample < input.bits_per_sample,
+        };
+        let rng = if dither && narrowing { Some(Rng(0x9e37_79b9_7f4a_7c15)) } else { None };
+        Encoder { spec: output, narrowing: narrowing, rng: rng }
+    }
+
+    /// Writes the normalized sample `x`, returns whether it was clamped.
+    fn write<W: io::Write + io::Seek>(&mut self, writer: &mut WavWriter<W>, x: f64) -> Result<bool> {
+        if self.spec.sample_format == SampleFormat::Float {
+            return writer.write_sample(x as f32).map(|_| false);
+        }
+        let scale = (1_u64 << (self.spec.bits_per_sample - 1)) as f64;
+        let mut y = x * scale;
+        if self.narrowing {
+            if let Some(ref mut rng) = self.rng {
+                y += rng.next() - rng.next();
+            }
+            y = y.round();
+        }
+        let max = scale - 1.0;
+        let (v, clipped) = if y.is_nan() {
+            (0.0, true)
+        } else if y > max {
+            (max, true)
+        } else if y < -scale {
+            (-scale, true)
+        } else {
+            (y, false)
+        };
+        try!(writer.write_sample(v as i32));
+        Ok(clipped)
+    }
+}
+
+/// Reads the next block of at most `BLOCK_FRAMES` frames as normalized samples.
+fn read_block<R: io::Read>(reader: &mut WavReader<R>, block: &mut Vec<f64>) -> Result<()> {
+    let spec = reader.spec();
+    let len = BLOCK_FRAMES * spec.channels as usize;
+    block.clear();
+    match spec.sample_format {
+        SampleFormat::Float => {
+            for sample in reader.samples::<f32>().take(len) {
+                block.push(try!(sample) as f64);
+            }
+        }
+        SampleFormat::Int => {
+            let scale = 1.0 / (1_u64 << (spec.bits_per_sample - 1)) as f64;
+            for sample in reader.samples::<i32>().take(len) {
+                block.push(try!(sample) as f64 * scale);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the remaining samples of `reader` to `out`, converted to `spec`.
+///
+/// This is the same as `copy_with_options()` with `CopyOptions::default()`:
+/// the number of channels must match, and samples are rounded to the nearest
+/// value without dither.
+pub fn copy<R, W>(reader: &mut WavReader<R>, spec: WavSpec, out: W) -> Result<CopyStats>
+    where R: io::Read,
+          W: io::Write + io::Seek,
+{
+    copy_with_options(reader, spec, out, CopyOptions::default())
+}
+
+/// Writes the remaining samples of `reader` to `out`, converted to `spec` as specified by `options`.
+///
+/// The samples are converted in blocks, between any bit depth and sample
+/// format: integer formats are widened exactly or narrowed by rounding,
+/// and integer and float samples are scaled by `2^(bits - 1)`. Samples that
+/// do not fit the output are clamped, and counted in the returned stats. The
+/// sample rate must match, and so must the number of channels, unless
+/// `options.channels` adapts between mono and stereo; otherwise
+/// `Error::InvalidSpec` is returned before anything is written. The output is
+/// finalized.
+pub fn copy_with_options<R, W>(reader: &mut WavReader<R>,
+                               spec: WavSpec,
+                               out: W,
+                               options: CopyOptions)
+                               -> Result<CopyStats>
+    where R: io::Read,
+          W: io::Write + io::Seek,
+{
+    let input = reader.spec();
+    if input.sample_rate != spec.sample_rate {
+        let msg = format!("cannot copy {} Hz to {} Hz, resampling is not supported",
+                          input.sample_rate, spec.sample_rate);
+        return Err(Error::InvalidSpec(msg));
+    }
+    let channels = match options.channels {
+        ChannelAdapter::Same => (input.channels, input.channels),
+        ChannelAdapter::MonoToStereo => (1, 2),
+        ChannelAdapter::StereoToMono => (2, 1),
+    };
+    if channels != (input.channels, spec.channels) {
+        let msg = format!("cannot copy {} channels to {} channels with {:?}",
+                          input.channels, spec.channels, options.channels);
+        return Err(Error::InvalidSpec(msg));
+    }
+
+    let mut writer = try!(WavWriter::new(out, spec));
+    let mut encoder = Encoder::new(input, spec, options.dither);
+    let mut stats = CopyStats::default();
+    let mut block = Vec::with_capacity(BLOCK_FRAMES * input.channels as usize);
+    loop {
+        try!(read_block(reader, &mut block));
+        if block.is_empty() {
+            break
+        }
+        let mut clipped = 0;
+        match options.channels {
+            ChannelAdapter::Same => for &x in &block {
+                clipped += try!(encoder.write(&mut writer, x)) as u64;
+            },
+            ChannelAdapter::MonoToStereo => for &x in &block {
+                let left = try!(encoder.write(&mut writer, x));
+                let right = try!(encoder.write(&mut writer, x));
+                clipped += left as u64 + right as u64;
+            },
+            ChannelAdapter::StereoToMono => for frame in block.chunks(2) {
+                let x = frame.iter().sum::<f64>() / 2.0;
+                clipped += try!(encoder.write(&mut writer, x)) as u64;
+            },
+        }
+        stats.frames += (block.len() / input.channels as usize) as u64;
+        stats.clipped += clipped;
+    }
+    try!(writer.finalize());
+    Ok(stats)
+}
+
+#[cfg(test)]
+fn int_spec(channels: u16, bits: u16) -> WavSpec {
+    WavSpec {
+        channels: channels,
+        sample_rate: 8000,
+        bits_per_sample: bits,
+        sample_format: SampleFormat::Int,
+    }
+}
+
+#[cfg(test)]
+fn float_spec(channels: u16) -> WavSpec {
+    WavSpec {
+        channels: channels,
+        sample_rate: 8000,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    }
+}
+
+/// Returns a file with the given spec and samples.
+#[cfg(test)]
+fn build_file<S: ::Sample + Copy>(spec: WavSpec, samples: &[S]) -> Vec<u8> {
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for &s in samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    buffer.into_inner()
+}
+
+/// Copies the samples of `bytes` to `spec`, returns the stats and the output.
+#[cfg(test)]
+fn copy_bytes(bytes: &[u8], spec: WavSpec, options: CopyOptions) -> (CopyStats, Vec<u8>) {
+    let mut reader = WavReader::new(bytes).unwrap();
+    let mut out = io::Cursor::new(Vec::new());
+    let stats = copy_with_options(&mut reader, spec, &mut out, options).unwrap();
+    (stats, out.into_inner())
+}
+
+#[cfg(test)]
+fn read_samples<S: ::Sample>(bytes: &[u8]) -> Vec<S> {
+    let mut reader = WavReader::new(bytes).unwrap();
+    reader.samples().map(|s| s.unwrap()).collect()
+}
+
+/// Returns every value of `bits` bits with the given step, and the extremes.
+#[cfg(test)]
+fn int_range(bits: u16, step: usize) -> Vec<i32> {
+    let max = ((1_i64 << (bits - 1)) - 1) as i32;
+    (-max - 1..max).step_by(step).chain(Some(max)).collect()
+}
+
+#[test]
+fn copy_widens_and_narrows_int_exactly() {
+    // More samples than one block, to cover the block boundaries.
+    let src16 = int_range(16, 7);
+    let bytes = build_file(int_spec(1, 16), &src16);
+    let (stats, bytes24) = copy_bytes(&bytes, int_spec(1, 24), CopyOptions::default());
+    assert_eq!(stats, CopyStats { frames: src16.len() as u64, clipped: 0 });
+    let src24: Vec<i32> = read_samples(&bytes24);
+    let expected: Vec<i32> = src16.iter().map(|&x| x << 8).collect();
+    assert_eq!(src24, expected);
+
+    // Narrowing back returns the original samples.
+    let (_, back) = copy_bytes(&bytes24, int_spec(1, 16), CopyOptions::default());
+    assert_eq!(read_samples::<i32>(&back), src16);
+
+    // The same for 8 and 32 bits.
+    let src8 = int_range(8, 1);
+    let bytes = build_file(int_spec(1, 8), &src8);
+    let (_, bytes32) = copy_bytes(&bytes, int_spec(1, 32), CopyOptions::default());
+    let expected: Vec<i32> = src8.iter().map(|&x| x << 24).collect();
+    assert_eq!(read_samples::<i32>(&bytes32), expected);
+    let (_, back) = copy_bytes(&bytes32, int_spec(1, 8), CopyOptions::default());
+    assert_eq!(read_samples::<i32>(&back), src8);
+}
+
+#[test]
+fn copy_narrows_int_by_rounding() {
+    let src24 = int_range(24, 997);
+    let bytes = build_file(int_spec(1, 24), &src24);
+    let (stats, bytes16) = copy_bytes(&bytes, int_spec(1, 16), CopyOptions::default());
+    let out: Vec<i32> = read_samples(&bytes16);
+    for (&x, &y) in src24.iter().zip(&out) {
+        let exact = x as f64 / 256.0;
+        assert!((y as f64 - exact).abs() <= 0.5 || y == 32767, "{} became {}", x, y);
+    }
+    // Only the largest values round to beyond the largest 16-bit value.
+    let beyond = src24.iter().filter(|&&x| x as f64 / 256.0 >= 32767.5).count();
+    assert_eq!(stats.clipped, beyond as u64);
+}
+
+#[test]
+fn copy_dithers_when_narrowing() {
+    // A constant of a quarter of the least significant bit of the output.
+    let src24 = vec![64_i32; 20000];
+    let bytes = build_file(int_spec(1, 24), &src24);
+    let (_, rounded) = copy_bytes(&bytes, int_spec(1, 16), CopyOptions::default());
+    assert!(read_samples::<i32>(&rounded).iter().all(|&y| y == 0));
+
+    let options = CopyOptions { dither: true, ..CopyOptions::default() };
+    let (_, dithered) = copy_bytes(&bytes, int_spec(1, 16), options);
+    let out: Vec<i32> = read_samples(&dithered);
+    // The error is at most 1.5 bits, and the average is preserved.
+    assert!(out.iter().all(|&y| (-1..=1).contains(&y)));
+    let mean = out.iter().map(|&y| y as f64).sum::<f64>() / out.len() as f64;
+    assert!((mean - 0.25).abs() < 0.02, "mean is {}", mean);
+
+    // No dither is added when no precision is lost.
+    let bytes = build_file(int_spec(1, 16), &[1_i16, -1, 0]);
+    let (_, widened) = copy_bytes(&bytes, int_spec(1, 24), options);
+    assert_eq!(read_samples::<i32>(&widened), [256, -256, 0]);
+}
+
+#[test]
+fn copy_converts_int_to_float_and_back() {
+    for &bits in &[8, 16, 24] {
+        let src = int_range(bits, if bits == 24 { 31 } else { 1 });
+        let bytes = build_file(int_spec(1, bits), &src);
+        let (_, floats) = copy_bytes(&bytes, float_spec(1), CopyOptions::default());
+        let scale = (1_i64 << (bits - 1)) as f32;
+        let expected: Vec<f32> = src.iter().map(|&x| x as f32 / scale).collect();
+        assert_eq!(read_samples::<f32>(&floats), expected);
+        let (stats, back) = copy_bytes(&floats, int_spec(1, bits), CopyOptions::default());
+        assert_eq!(stats.clipped, 0);
+        assert_eq!(read_samples::<i32>(&back), src);
+    }
+
+    // A float has 24 bits of precision, so 32-bit samples are rounded.
+    let src = int_range(32, 0x10000 * 4099);
+    let bytes = build_file(int_spec(1, 32), &src);
+    let (_, floats) = copy_bytes(&bytes, float_spec(1), CopyOptions::default());
+    let (_, back) = copy_bytes(&floats, int_spec(1, 32), CopyOptions::default());
+    for (&x, &y) in src.iter().zip(&read_samples::<i32>(&back)) {
+        assert!((x as i64 - y as i64).abs() <= 128, "{} became {}", x, y);
+    }
+}
+
+#[test]
+fn copy_converts_float_to_int_with_clipping() {
+    let src = [0.0_f32, 0.5, -0.5, -1.0, 1.0, 2.0, -2.0, 1.0 / 3.0, f32::NAN];
+    let bytes = build_file(float_spec(1), &src);
+    let (stats, out) = copy_bytes(&bytes, int_spec(1, 24), CopyOptions::default());
+    assert_eq!(stats, CopyStats { frames: 9, clipped: 4 });
+    assert_eq!(read_samples::<i32>(&out),
+               [0, 0x400000, -0x400000, -0x800000, 0x7fffff, 0x7fffff, -0x800000, 0x2aaaab, 0]);
+
+    // Float to float is unchanged.
+    let (_, out) = copy_bytes(&bytes, float_spec(1), CopyOptions::default());
+    let floats: Vec<f32> = read_samples(&out);
+    assert_eq!(&floats[..8], &src[..8]);
+    assert!(floats[8].is_nan());
+}
+
+#[test]
+fn copy_requires_matching_channels_unless_adapted() {
+    let bytes = build_file(int_spec(2, 16), &[100_i16, 300, -100, -200]);
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    let mut out = io::Cursor::new(Vec::new());
+    match copy(&mut reader, int_spec(1, 16), &mut out) {
+        Err(Error::InvalidSpec(..)) => {}
+        Err(err) => panic!("expected InvalidSpec, got {:?}", err),
+        Ok(..) => panic!("expected InvalidSpec"),
+    }
+    assert!(out.get_ref().is_empty());
+
+    let options = CopyOptions { channels: ChannelAdapter::StereoToMono, ..CopyOptions::default() };
+    let (stats, mono) = copy_bytes(&bytes, int_spec(1, 16), options);
+    assert_eq!(stats.frames, 2);
+    assert_eq!(read_samples::<i16>(&mono), [200, -150]);
+
+    let options = CopyOptions { channels: ChannelAdapter::MonoToStereo, ..CopyOptions::default() };
+    let (stats, stereo) = copy_bytes(&mono, int_spec(2, 24), options);
+    assert_eq!(stats.frames, 2);
+    assert_eq!(read_samples::<i32>(&stereo), [200 << 8, 200 << 8, -150 << 8, -150 << 8]);
+
+    // An adapter must match the channels of the input.
+    let mut reader = WavReader::new(&stereo[..]).unwrap();
+    let mut out = io::Cursor::new(Vec::new());
+    assert!(copy_with_options(&mut reader, int_spec(2, 16), &mut out, options).is_err());
+}
@@ -29,15 +29,14 @@
 //!     bits_per_sample: 16,
 //!     sample_format: hound::SampleFormat::Int,
 //! };
-//! let mut writer = hound::WavWriter::create("sine.wav", spec).unwrap();
-//! for t in (0 .. 44100).map(|x| x as f32 / 44100.0) {
-//!     let sample = (t * 440.0 * 2.0 * PI).sin();
-//!     let amplitude = i16::MAX as f32;
-//!     writer.write_sample((sample * amplitude) as i16).unwrap();
-//! }
-//! writer.finalize().unwrap();
+//! let samples: Vec<i16> = (0 .. 44100).map(|x| x as f32 / 44100.0)
+//!     .map(|t| ((t * 440.0 * 2.0 * PI).sin() * i16::MAX as f32) as i16)
+//!     .collect();
+//! hound::write_all("sine.wav", spec, &samples).unwrap();
 //! ```
 //!
+//! To write samples as they are produced, use a `WavWriter` instead.
+//!
 //! The following example computes the root mean square (RMS) of an audio file
 //! with at most 16 bits per sample.
 //!
@@ -54,23 +53,69 @@
 //! ```
 
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate core;
 
+use core::fmt;
+use core::result;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt;
+#[cfg(feature = "std")]
 use std::io;
-use std::result;
+#[cfg(feature = "std")]
+use std::str;
+#[cfg(feature = "std")]
 use read::ReadExt;
+#[cfg(feature = "std")]
 use write::WriteExt;
 
+#[cfg(feature = "std")]
+mod adpcm;
+#[cfg(feature = "std")]
+mod convert;
+#[cfg(feature = "std")]
+mod copy;
+#[cfg(feature = "std")]
+mod md5;
+#[cfg(feature = "std")]
+mod metadata;
+#[cfg(feature = "std")]
 mod read;
+mod slice;
+#[cfg(feature = "std")]
 mod write;
 
-pub use read::{WavReader, WavIntoSamples, WavSamples, read_wave_header};
-pub use write::{SampleWriter16, WavWriter};
-
+#[cfg(feature = "testutil")]
+pub mod testutil;
+
+#[cfg(feature = "std")]
+pub use convert::{convert_f32_to_i16, convert_f32_to_i24, convert_f32_to_i32, convert_i16_to_f32,
+                  convert_i24_to_f32, convert_i32_to_f32};
+#[cfg(feature = "std")]
+pub use copy::{ChannelAdapter, CopyOptions, CopyStats, copy, copy_with_options};
+#[cfg(feature = "std")]
+pub use metadata::{AcidInfo, AdmTrack, BroadcastExtension, Cart, CartTimer, ChannelPeak,
+                   CuePoint, InstrumentInfo, ListInfo, Loudness, PeakEnvelope, PeakInfo, PlaylistSegment,
+                   RawChunk, SampleLoop, SamplerInfo};
+#[cfg(feature = "std")]
+pub use read::{WavReader, WavIntoSamples, WavSamples, WavChannelSamples, WavConcatSamples, WavFrames,
+               WavFramesFixed, WavLoopedSamples, WavMonoSamples, WavTimedSamples, WavHeader, WavSpecEx,
+               ChannelStats, Md5Status, ReadOptions, ReadSeek, MAX_CHANNELS, concat, parse_header, read_all, read_wave_header};
+#[cfg(feature = "std")]
+pub use write::{NoSeek, SampleWriter16, WavWriter, copy_metadata, rewrite_sample_rate, update_info, write_all};
+
+#[cfg(feature = "std")]
 pub use read::{ Chunk, ChunksReader };
+#[cfg(feature = "std")]
 pub use write::ChunksWriter;
 
+pub use slice::{Header, SliceError, SliceResult};
+/// Without `std`, errors are those of the operations on byte slices.
+#[cfg(not(feature = "std"))]
+pub use slice::SliceError as Error;
+
 /// A type that can be used to represent audio samples.
 ///
 /// Via this trait, decoding can be generic over `i8`, `i16`, `i32` and `f32`.
@@ -79,6 +124,7 @@ pub use write::ChunksWriter;
 /// into `i32`, but it takes up more memory. If you know beforehand that you
 /// will be reading a file with 16 bits per sample, then decoding into an `i16`
 /// will be sufficient.
+#[cfg(feature = "std")]
 pub trait Sample: Sized {
     /// Writes the audio sample to the WAVE data chunk.
     fn write<W: io::Write>(self, writer: &mut W, bits: u16) -> Result<()>;
@@ -87,9 +133,81 @@ pub trait Sample: Sized {
     /// the written sample out to `byte_width`.
     fn write_padded<W: io::Write>(self, writer: &mut W, bits: u16, byte_width: u16) -> Result<()>;
 
+    /// Writes the audio sample like `write_padded`, but clamps it to the range
+    /// of the destination bit depth instead of returning `Error::TooWide`.
+    ///
+    /// The default implementation does not clamp, it calls `write_padded`.
+    fn write_padded_saturating<W: io::Write>(self, writer: &mut W, bits: u16, byte_width: u16) -> Result<()> {
+        self.write_padded(writer, bits, byte_width)
+    }
+
     /// Reads the audio sample from the WAVE data chunk.
     fn read<R: io::Read>(reader: &mut R, SampleFormat, bytes: u16, bits: u16) -> Result<Self>;
 
+    /// Returns a function that reads a sample of the given format, if there is one.
+    ///
+    /// The sample iterators call this once when they are created, so the
+    /// format is not inspected again for every sample. The function must
+    /// return the same values as `read` for the same arguments; formats for
+    /// which `read` fails must return `None`. In that case, and by default,
+    /// the iterators call `read` for every sample.
+    #[inline]
+    fn read_fn<R: io::Read>(_fmt: SampleFormat, _bytes: u16, _bits: u16)
+                            -> Option<fn(&mut R) -> io::Result<Self>> {
+        None
+    }
+
+    /// Reads the audio sample like `read`, with the bytes in the given order.
+    ///
+    /// WAVE files are little endian, but the `RIFX` variant of the format is
+    /// big endian. The default implementation reverses the bytes of every
+    /// sample, and then calls `read`, so implementations only need to handle
+    /// little endian.
+    fn read_with_byte_order<R: io::Read>(reader: &mut R,
+                                         fmt: SampleFormat,
+                                         bytes: u16,
+                                         bits: u16,
+                                         byte_order: ByteOrder)
+                                         -> Result<Self> {
+        match byte_order {
+            ByteOrder::LittleEndian => Sample::read(reader, fmt, bytes, bits),
+            ByteOrder::BigEndian => {
+                let mut buffer = [0u8; 8];
+                if bytes as usize > buffer.len() {
+                    return Err(Error::Unsupported(UnsupportedFormat::bits(bits)));
+                }
+                let sample = &mut buffer[..bytes as usize];
+                try!(reader.read_into(sample));
+                sample.reverse();
+                Sample::read(&mut &sample[..], fmt, bytes, bits)
+            }
+        }
+    }
+
+    /// Writes the audio sample like `write_padded`, with the bytes in the given order.
+    ///
+    /// The default implementation calls `write_padded`, and reverses the
+    /// bytes of the sample for big endian, see `read_with_byte_order`.
+    fn write_padded_with_byte_order<W: io::Write>(self,
+                                                  writer: &mut W,
+                                                  bits: u16,
+                                                  byte_width: u16,
+                                                  byte_order: ByteOrder)
+                                                  -> Result<()> {
+        match byte_order {
+            ByteOrder::LittleEndian => self.write_padded(writer, bits, byte_width),
+            ByteOrder::BigEndian => {
+                let mut buffer = io::Cursor::new([0u8; 8]);
+                try!(self.write_padded(&mut buffer, bits, byte_width));
+                let len = buffer.position() as usize;
+                let sample = &mut buffer.get_mut()[..len];
+                sample.reverse();
+                try!(writer.write_all(sample));
+                Ok(())
+            }
+        }
+    }
+
     /// Cast the sample to a 16-bit sample.
     ///
     /// This does not change the value of the sample, it only casts it. The
@@ -105,11 +223,13 @@ pub trait Sample: Sized {
 /// integers. However, an 8-bit sample is instead stored as an _unsigned_
 /// integer. Hound abstracts away this idiosyncrasy by providing only signed
 /// sample types.
+#[cfg(feature = "std")]
 fn signed_from_u8(x: u8) -> i8 {
     (x as i16 - 128) as i8
 }
 
 /// Converts a signed integer in the range -128-127 to an unsigned one in the range 0-255.
+#[cfg(feature = "std")]
 fn u8_from_signed(x: i8) -> u8 {
     (x as i16 + 128) as u8
 }
@@ -126,6 +246,7 @@ fn u8_sign_conversion_is_bijective() {
 
 /// Tries to cast the sample to an 8-bit signed integer, returning an error on overflow.
 #[inline(always)]
+#[cfg(feature = "std")]
 fn narrow_to_i8(x: i32) -> Result<i8> {
     use std::i8;
     if x < i8::MIN as i32 || x > i8::MAX as i32 {
@@ -145,6 +266,7 @@ fn verify_narrow_to_i8() {
 
 /// Tries to cast the sample to a 16-bit signed integer, returning an error on overflow.
 #[inline(always)]
+#[cfg(feature = "std")]
 fn narrow_to_i16(x: i32) -> Result<i16> {
     use std::i16;
     if x < i16::MIN as i32 || x > i16::MAX as i32 {
@@ -164,6 +286,7 @@ fn verify_narrow_to_i16() {
 
 /// Tries to cast the sample to a 24-bit signed integer, returning an error on overflow.
 #[inline(always)]
+#[cfg(feature = "std")]
 fn narrow_to_i24(x: i32) -> Result<i32> {
     if x < -(1 << 23) || x > (1 << 23) - 1 {
         Err(Error::TooWide)
@@ -180,6 +303,7 @@ fn verify_narrow_to_i24() {
     assert!(narrow_to_i24(-8_388_609).is_err());
 }
 
+#[cfg(feature = "std")]
 impl Sample for i8 {
     fn write<W: io::Write>(self, writer: &mut W, bits: u16) -> Result<()> {
         self.write_padded(writer, bits, bits / 8)
@@ -192,7 +316,7 @@ impl Sample for i8 {
             (24, 3) => Ok(try!(writer.write_le_i24(self as i32))),
             (24, 4) => Ok(try!(writer.write_le_i24_4(self as i32))),
             (32, 4) => Ok(try!(writer.write_le_i32(self as i32))),
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
         }
     }
 
@@ -209,11 +333,19 @@ impl Sample for i8 {
             (1, 8) => Ok(try!(reader.read_u8().map(signed_from_u8))),
             (n, _) if n > 1 => Err(Error::TooWide),
             // TODO: add a genric decoder for any bit depth.
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
+        }
+    }
+
+    fn read_fn<R: io::Read>(fmt: SampleFormat, bytes: u16, bits: u16) -> Option<fn(&mut R) -> io::Result<i8>> {
+        match (fmt, bytes, bits) {
+            (SampleFormat::Int, 1, 8) => Some(|r| r.read_u8().map(signed_from_u8)),
+            _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Sample for i16 {
     fn write<W: io::Write>(self, writer: &mut W, bits: u16) -> Result<()> {
         self.write_padded(writer, bits, bits / 8)
@@ -228,10 +360,14 @@ impl Sample for i16 {
             (24, 3) => Ok(try!(writer.write_le_i24(self as i32))),
             (24, 4) => Ok(try!(writer.write_le_i24_4(self as i32))),
             (32, 4) => Ok(try!(writer.write_le_i32(self as i32))),
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
         }
     }
 
+    fn write_padded_saturating<W: io::Write>(self, writer: &mut W, bits: u16, byte_width: u16) -> Result<()> {
+        (self as i32).write_padded_saturating(writer, bits, byte_width)
+    }
+
     #[inline(always)]
     fn as_i16(self) -> i16 {
         self
@@ -246,11 +382,20 @@ impl Sample for i16 {
             (2, 16) => Ok(try!(reader.read_le_i16())),
             (n, _) if n > 2 => Err(Error::TooWide),
             // TODO: add a generic decoder for any bit depth.
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
+        }
+    }
+
+    fn read_fn<R: io::Read>(fmt: SampleFormat, bytes: u16, bits: u16) -> Option<fn(&mut R) -> io::Result<i16>> {
+        match (fmt, bytes, bits) {
+            (SampleFormat::Int, 1, 8) => Some(|r| r.read_u8().map(|x| signed_from_u8(x) as i16)),
+            (SampleFormat::Int, 2, 16) => Some(|r| r.read_le_i16()),
+            _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Sample for i32 {
     fn write<W: io::Write>(self, writer: &mut W, bits: u16) -> Result<()> {
         self.write_padded(writer, bits, bits / 8)
@@ -265,10 +410,20 @@ impl Sample for i32 {
             (24, 3) => Ok(try!(writer.write_le_i24(try!(narrow_to_i24(self))))),
             (24, 4) => Ok(try!(writer.write_le_i24_4(try!(narrow_to_i24(self))))),
             (32, 4) => Ok(try!(writer.write_le_i32(self))),
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
         }
     }
 
+    fn write_padded_saturating<W: io::Write>(self, writer: &mut W, bits: u16, byte_width: u16) -> Result<()> {
+        let max = match bits {
+            8 => i8::MAX as i32,
+            16 => i16::MAX as i32,
+            24 => (1 << 23) - 1,
+            _ => return self.write_padded(writer, bits, byte_width),
+        };
+        self.clamp(-max - 1, max).write_padded(writer, bits, byte_width)
+    }
+
     #[inline(always)]
     fn as_i16(self) -> i16 {
         self as i16
@@ -282,15 +437,30 @@ impl Sample for i32 {
             (1, 8) => Ok(try!(reader.read_u8().map(signed_from_u8).map(|x| x as i32))),
             (2, 16) => Ok(try!(reader.read_le_i16().map(|x| x as i32))),
             (3, 24) => Ok(try!(reader.read_le_i24())),
+            // Fewer valid bits than the container holds, such as 20-bit audio
+            // packed in 3 bytes. The valid bits are the most significant ones.
+            (3, b) if b > 16 && b < 24 => Ok(try!(reader.read_le_i24()) >> (24 - b)),
             (4, 24) => Ok(try!(reader.read_le_i24_4())),
             (4, 32) => Ok(try!(reader.read_le_i32())),
             (n, _) if n > 4 => Err(Error::TooWide),
             // TODO: add a generic decoder for any bit depth.
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
+        }
+    }
+
+    fn read_fn<R: io::Read>(fmt: SampleFormat, bytes: u16, bits: u16) -> Option<fn(&mut R) -> io::Result<i32>> {
+        match (fmt, bytes, bits) {
+            (SampleFormat::Int, 1, 8) => Some(|r| r.read_u8().map(|x| signed_from_u8(x) as i32)),
+            (SampleFormat::Int, 2, 16) => Some(|r| r.read_le_i16().map(|x| x as i32)),
+            (SampleFormat::Int, 3, 24) => Some(|r| r.read_le_i24()),
+            (SampleFormat::Int, 4, 24) => Some(|r| r.read_le_i24_4()),
+            (SampleFormat::Int, 4, 32) => Some(|r| r.read_le_i32()),
+            _ => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Sample for f32 {
     fn write<W: io::Write>(self, writer: &mut W, bits: u16) -> Result<()> {
         self.write_padded(writer, bits, bits / 8)
@@ -299,7 +469,7 @@ impl Sample for f32 {
     fn write_padded<W: io::Write>(self, writer: &mut W, bits: u16, byte_width: u16) -> Result<()> {
         match (bits, byte_width) {
             (32, 4) => Ok(try!(writer.write_le_f32(self))),
-            _ => Err(Error::Unsupported),
+            _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
         }
     }
 
@@ -313,7 +483,7 @@ impl Sample for f32 {
                 match (bytes, bits) {
                     (4, 32) => Ok(try!(reader.read_le_f32())),
                     (n, _) if n > 4 => Err(Error::TooWide),
-                    _ => Err(Error::Unsupported),
+                    _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
                 },
             SampleFormat::Int =>
                 // 32-bit IEEE floats can represent signed integers up to 24 bits wide exactly
@@ -322,12 +492,73 @@ impl Sample for f32 {
                     (1, 8) => Ok(try!(reader.read_u8().map(signed_from_u8).map(|x| x as f32))),
                     (2, 16) => Ok(try!(reader.read_le_i16().map(|x| x as f32))),
                     (3, 24) => Ok(try!(reader.read_le_i24()) as f32),
+                    (3, b) if b > 16 && b < 24 => Ok((try!(reader.read_le_i24()) >> (24 - b)) as f32),
                     (n, _) if n > 3 => Err(Error::TooWide),
                     // TODO: add a generic decoder for any bit depth.
-                    _ => Err(Error::Unsupported),
+                    _ => Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
                 }
         }
     }
+
+    fn read_fn<R: io::Read>(fmt: SampleFormat, bytes: u16, bits: u16) -> Option<fn(&mut R) -> io::Result<f32>> {
+        match (fmt, bytes, bits) {
+            (SampleFormat::Float, 4, 32) => Some(|r| r.read_le_f32()),
+            (SampleFormat::Int, 1, 8) => Some(|r| r.read_u8().map(|x| signed_from_u8(x) as f32)),
+            (SampleFormat::Int, 2, 16) => Some(|r| r.read_le_i16().map(|x| x as f32)),
+            (SampleFormat::Int, 3, 24) => Some(|r| r.read_le_i24().map(|x| x as f32)),
+            _ => None,
+        }
+    }
+}
+
+/// A sample type that frames can be averaged in, see `WavReader::samples_mono()`.
+#[cfg(feature = "std")]
+pub trait Average: Sample {
+    /// Returns the mean of `samples`, which is not empty.
+    ///
+    /// Integers are summed in an `i64`, so this does not overflow, and the
+    /// mean is rounded to the nearest integer, with halves rounded away from
+    /// zero.
+    fn average(samples: &[Self]) -> Self;
+}
+
+/// Returns the mean of integers, rounded to nearest with halves away from zero.
+#[cfg(feature = "std")]
+fn average_i64<I: Iterator<Item = i64>>(samples: I, n: usize) -> i64 {
+    let sum: i64 = samples.sum();
+    let n = n as i64;
+    // Integer division truncates toward zero, so add half of the divisor
+    // away from zero first.
+    if sum >= 0 { (sum + n / 2) / n } else { (sum - n / 2) / n }
+}
+
+#[cfg(feature = "std")]
+impl Average for i8 {
+    fn average(samples: &[i8]) -> i8 {
+        average_i64(samples.iter().map(|&x| x as i64), samples.len()) as i8
+    }
+}
+
+#[cfg(feature = "std")]
+impl Average for i16 {
+    fn average(samples: &[i16]) -> i16 {
+        average_i64(samples.iter().map(|&x| x as i64), samples.len()) as i16
+    }
+}
+
+#[cfg(feature = "std")]
+impl Average for i32 {
+    fn average(samples: &[i32]) -> i32 {
+        average_i64(samples.iter().map(|&x| x as i64), samples.len()) as i32
+    }
+}
+
+#[cfg(feature = "std")]
+impl Average for f32 {
+    fn average(samples: &[f32]) -> f32 {
+        let sum: f64 = samples.iter().map(|&x| x as f64).sum();
+        (sum / samples.len() as f64) as f32
+    }
 }
 
 /// Specifies whether a sample is stored as an "IEEE Float" or an integer.
@@ -342,6 +573,133 @@ pub enum SampleFormat {
     Int,
 }
 
+/// The position of a speaker, as assigned to a channel by a channel mask.
+///
+/// The variants are in the order of the bits of `dwChannelMask` in
+/// WAVEFORMATEXTENSIBLE, from `0x1` for `FrontLeft` to `0x20000` for
+/// `TopBackRight`. See `WavReader::speaker_positions()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpeakerPosition {
+    /// `SPEAKER_FRONT_LEFT`, bit `0x1`.
+    FrontLeft,
+    /// `SPEAKER_FRONT_RIGHT`, bit `0x2`.
+    FrontRight,
+    /// `SPEAKER_FRONT_CENTER`, bit `0x4`.
+    FrontCenter,
+    /// `SPEAKER_LOW_FREQUENCY`, bit `0x8`, the LFE channel.
+    LowFrequency,
+    /// `SPEAKER_BACK_LEFT`, bit `0x10`.
+    BackLeft,
+    /// `SPEAKER_BACK_RIGHT`, bit `0x20`.
+    BackRight,
+    /// `SPEAKER_FRONT_LEFT_OF_CENTER`, bit `0x40`.
+    FrontLeftOfCenter,
+    /// `SPEAKER_FRONT_RIGHT_OF_CENTER`, bit `0x80`.
+    FrontRightOfCenter,
+    /// `SPEAKER_BACK_CENTER`, bit `0x100`.
+    BackCenter,
+    /// `SPEAKER_SIDE_LEFT`, bit `0x200`.
+    SideLeft,
+    /// `SPEAKER_SIDE_RIGHT`, bit `0x400`.
+    SideRight,
+    /// `SPEAKER_TOP_CENTER`, bit `0x800`.
+    TopCenter,
+    /// `SPEAKER_TOP_FRONT_LEFT`, bit `0x1000`.
+    TopFrontLeft,
+    /// `SPEAKER_TOP_FRONT_CENTER`, bit `0x2000`.
+    TopFrontCenter,
+    /// `SPEAKER_TOP_FRONT_RIGHT`, bit `0x4000`.
+    TopFrontRight,
+    /// `SPEAKER_TOP_BACK_LEFT`, bit `0x8000`.
+    TopBackLeft,
+    /// `SPEAKER_TOP_BACK_CENTER`, bit `0x10000`.
+    TopBackCenter,
+    /// `SPEAKER_TOP_BACK_RIGHT`, bit `0x20000`.
+    TopBackRight,
+    /// The channel is not assigned to a speaker.
+    Unassigned,
+}
+
+impl SpeakerPosition {
+    /// The positions in the order of their bits in the channel mask.
+    const BY_BIT: [SpeakerPosition; 18] = [
+        SpeakerPosition::FrontLeft,
+        SpeakerPosition::FrontRight,
+        SpeakerPosition::FrontCenter,
+        SpeakerPosition::LowFrequency,
+        SpeakerPosition::BackLeft,
+        SpeakerPosition::BackRight,
+        SpeakerPosition::FrontLeftOfCenter,
+        SpeakerPosition::FrontRightOfCenter,
+        SpeakerPosition::BackCenter,
+        SpeakerPosition::SideLeft,
+        SpeakerPosition::SideRight,
+        SpeakerPosition::TopCenter,
+        SpeakerPosition::TopFrontLeft,
+        SpeakerPosition::TopFrontCenter,
+        SpeakerPosition::TopFrontRight,
+        SpeakerPosition::TopBackLeft,
+        SpeakerPosition::TopBackCenter,
+        SpeakerPosition::TopBackRight,
+    ];
+
+    /// Returns the position of each of `channels` channels for the given channel mask.
+    ///
+    /// Channels are assigned to the positions whose bit is set, in order of
+    /// increasing bit. Reserved bits are ignored. When there are more
+    /// channels than positions, the remaining channels are `Unassigned`, and
+    /// when there are more positions than channels, the excess is ignored.
+    #[cfg(feature = "std")]
+    pub fn for_channels(mask: u32, channels: u16) -> Vec<SpeakerPosition> {
+        let mut positions: Vec<SpeakerPosition> = SpeakerPosition::BY_BIT.iter()
+            .enumerate()
+            .filter(|&(bit, _)| mask & (1 << bit) != 0)
+            .map(|(_, &position)| position)
+            .take(channels as usize)
+            .collect();
+        positions.resize(channels as usize, SpeakerPosition::Unassigned);
+        positions
+    }
+
+    /// Returns the bit of the position in the channel mask, or 0 if it is `Unassigned`.
+    pub fn mask_bit(self) -> u32 {
+        match SpeakerPosition::BY_BIT.iter().position(|&p| p == self) {
+            Some(bit) => 1 << bit,
+            None => 0,
+        }
+    }
+}
+
+/// The order of the bytes of a sample, see `Sample::read_with_byte_order()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// The least significant byte comes first, as in `RIFF` WAVE files.
+    LittleEndian,
+    /// The most significant byte comes first, as in `RIFX` files.
+    BigEndian,
+}
+
+/// Specifies how `WavWriter` handles samples that do not fit the bit depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Return `Error::TooWide` for a sample that does not fit. This is the
+    /// default, so no sample is clipped silently.
+    Error,
+    /// Clamp a sample that does not fit to the range of the bit depth, for
+    /// example to `[-0x800000, 0x7fffff]` for 24 bits per sample.
+    Saturate,
+}
+
+/// Specifies how `WavWriter` finalizes a file that ends in an incomplete frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FinalizeMode {
+    /// Write the samples as they are, and return `Error::UnfinishedFrame`.
+    /// This is the default.
+    Strict,
+    /// Complete the last frame with silence, so the file is valid.
+    PadFrame,
+}
+
 /// Specifies properties of the audio data.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct WavSpec {
@@ -362,13 +720,191 @@ pub struct WavSpec {
     pub sample_format: SampleFormat,
 }
 
+/// Formats the spec concisely, for example as `2ch 44100Hz 16bit PCM`.
+///
+/// The rendering is `<channels>ch <sample rate>Hz <bits>bit <format>`, where
+/// the format is `PCM` or `float`. It can be parsed back with `str::parse`.
+impl fmt::Display for WavSpec {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        let format = match self.sample_format {
+            SampleFormat::Int => "PCM",
+            SampleFormat::Float => "float",
+        };
+        write!(formatter, "{}ch {}Hz {}bit {}",
+               self.channels, self.sample_rate, self.bits_per_sample, format)
+    }
+}
+
+/// The error returned when parsing a `WavSpec` from a string fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg(feature = "std")]
+pub struct ParseWavSpecError {
+    reason: String,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ParseWavSpecError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        try!(formatter.write_str("invalid wav spec: "));
+        formatter.write_str(&self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ParseWavSpecError {
+    fn description(&self) -> &str {
+        &self.reason
+    }
+}
+
+#[cfg(feature = "std")]
+fn spec_error(reason: String) -> ParseWavSpecError {
+    ParseWavSpecError { reason: reason }
+}
+
+/// Parses a spec such as `2ch 44100Hz 16bit PCM` or `1ch48000hz24bit`.
+///
+/// The string consists of a number of channels with unit `ch`, a sample rate
+/// with unit `Hz`, and a bit depth with unit `bit`, in any order. The sample
+/// format can be given as `PCM`, `int` or `float`, and defaults to `PCM`.
+/// Units are case-insensitive and may be plural (`channels`, `bits`). Parts
+/// may be separated by whitespace, commas, dashes, underscores, or nothing at
+/// all, so `2 channels, 44100 Hz, 16-bit int` is also accepted.
+#[cfg(feature = "std")]
+impl str::FromStr for WavSpec {
+    type Err = ParseWavSpecError;
+
+    fn from_str(s: &str) -> result::Result<WavSpec, ParseWavSpecError> {
+        let lower = s.to_lowercase();
+        let is_separator = |c: char| c.is_whitespace() || c == ',' || c == '-' || c == '_';
+        let mut channels = None;
+        let mut sample_rate = None;
+        let mut bits_per_sample = None;
+        let mut sample_format = None;
+        let mut rest = lower.trim_start_matches(is_separator);
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let (number, after) = rest.split_at(digits);
+            let after = if digits > 0 { after.trim_start_matches(is_separator) } else { after };
+            let letters = after.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(after.len());
+            let word = &after[..letters];
+
+            if word.is_empty() {
+                return match number {
+                    "" => Err(spec_error(format!("unexpected character in \"{}\"", s))),
+                    _ => Err(spec_error(format!("missing unit after {}", number))),
+                };
+            }
+            if number.is_empty() {
+                let format = match word {
+                    "pcm" | "int" => SampleFormat::Int,
+                    "float" => SampleFormat::Float,
+                    _ => return Err(spec_error(format!("unknown sample format \"{}\"", word))),
+                };
+                if sample_format.replace(format).is_some() {
+                    return Err(spec_error("the sample format is given twice".to_string()));
+                }
+                rest = after[letters..].trim_start_matches(is_separator);
+                continue
+            }
+
+            // The unit may be followed by the sample format without separator,
+            // as in `16bitpcm`, so match it as a prefix, longest unit first.
+            let units = ["channels", "channel", "ch", "hz", "bits", "bit"];
+            let unit = match units.iter().find(|u| word.starts_with(*u)) {
+                Some(unit) => *unit,
+                None => return Err(spec_error(format!("unknown unit \"{}\" after {}", word, number))),
+            };
+            rest = after[unit.len()..].trim_start_matches(is_separator);
+            let (field, name) = match unit {
+                "hz" => (&mut sample_rate, "sample rate"),
+                "bit" | "bits" => (&mut bits_per_sample, "bits per sample"),
+                _ => (&mut channels, "number of channels"),
+            };
+            let value = match number.parse::<u32>() {
+                Ok(value) if value > 0 => value,
+                _ => return Err(spec_error(format!("invalid {} {}", name, number))),
+            };
+            if field.replace(value).is_some() {
+                return Err(spec_error(format!("the {} is given twice", name)));
+            }
+        }
+
+        let channels = try!(channels.ok_or_else(|| spec_error("missing number of channels".to_string())));
+        let sample_rate = try!(sample_rate.ok_or_else(|| spec_error("missing sample rate".to_string())));
+        let bits_per_sample = try!(bits_per_sample.ok_or_else(|| spec_error("missing bits per sample".to_string())));
+        if channels > 0xffff {
+            return Err(spec_error(format!("invalid number of channels {}", channels)));
+        }
+        if bits_per_sample > 0xffff {
+            return Err(spec_error(format!("invalid bits per sample {}", bits_per_sample)));
+        }
+        Ok(WavSpec {
+            channels: channels as u16,
+            sample_rate: sample_rate,
+            bits_per_sample: bits_per_sample as u16,
+            sample_format: sample_format.unwrap_or(SampleFormat::Int),
+        })
+    }
+}
+
+/// Describes a format that Hound cannot read or write, see `Error::Unsupported`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsupportedFormat {
+    /// The format tag of the fmt chunk, for example 0x0002 for ADPCM.
+    ///
+    /// This is `None` when the error does not stem from a fmt chunk, for
+    /// example when a sample type cannot be converted to the bit depth.
+    pub format_tag: Option<u16>,
+    /// The number of bits per sample.
+    pub bits_per_sample: u16,
+    /// The subformat GUID of a `WAVE_FORMAT_EXTENSIBLE` fmt chunk, as stored in the file.
+    pub sub_format: Option<[u8; 16]>,
+}
+
+#[cfg(feature = "std")]
+impl UnsupportedFormat {
+    /// Describes an unsupported bit depth, independent of the format tag.
+    fn bits(bits_per_sample: u16) -> UnsupportedFormat {
+        UnsupportedFormat {
+            format_tag: None,
+            bits_per_sample: bits_per_sample,
+            sub_format: None,
+        }
+    }
+}
+
+impl fmt::Display for UnsupportedFormat {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        if let Some(tag) = self.format_tag {
+            try!(write!(formatter, "format tag 0x{:04x}, ", tag));
+        }
+        try!(write!(formatter, "{} bits per sample", self.bits_per_sample));
+        if let Some(ref g) = self.sub_format {
+            // The first three fields of a GUID are little endian.
+            try!(write!(formatter,
+                        ", subformat {:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-\
+                         {:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+                        g[3], g[2], g[1], g[0], g[5], g[4], g[7], g[6],
+                        g[8], g[9], g[10], g[11], g[12], g[13], g[14], g[15]));
+        }
+        Ok(())
+    }
+}
+
 /// The error type for operations on `WavReader` and `WavWriter`.
 #[derive(Debug)]
+#[cfg(feature = "std")]
 pub enum Error {
     /// An IO error occured in the underlying reader or writer.
     IoError(io::Error),
     /// Ill-formed WAVE data was encountered.
-    FormatError(&'static str),
+    ///
+    /// The message describes the problem, including the offending values
+    /// where possible, for example the size of an invalid chunk. Errors in a
+    /// chunk header or the fmt chunk name the chunk id and the offset of the
+    /// chunk in the file.
+    FormatError(String),
     /// The sample has more bits than the destination type.
     ///
     /// When iterating using the `samples` iterator, this means that the
@@ -376,10 +912,24 @@ pub enum Error {
     /// the sample. When writing, this means that the sample cannot be written,
     /// because it requires more bits than the bits per sample specified.
     TooWide,
-    /// The number of samples written is not a multiple of the number of channels.
+    /// The number of samples is not a multiple of the number of channels.
+    ///
+    /// This is returned when reading frames from data that ends in the
+    /// middle of one. When writing, `UnfinishedFrame` is returned instead.
     UnfinishedSample,
+    /// The last frame written is incomplete.
+    ///
+    /// The number of samples written is not a multiple of the number of
+    /// channels; `missing` more samples would complete the last frame.
+    UnfinishedFrame {
+        /// The number of samples that are missing from the last frame.
+        missing: u16,
+    },
     /// The format is not supported.
-    Unsupported,
+    ///
+    /// The details describe the format, so callers can decide whether to
+    /// fall back to a different decoder.
+    Unsupported(UnsupportedFormat),
     /// The sample format is different than the destination format.
     ///
     /// When iterating using the `samples` iterator, this means the destination
@@ -390,13 +940,18 @@ pub enum Error {
     /// samples (which have a `SampleFormat::Int`) from a wav file that
     /// contains floating point data (`SampleFormat::Float`).
     InvalidSampleFormat,
+    /// The spec does not describe a valid format, see `WavSpec::validate()`.
+    ///
+    /// The message describes the problem, for example a spec with zero channels.
+    InvalidSpec(String),
 }
 
+#[cfg(feature = "std")]
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
         match *self {
             Error::IoError(ref err) => err.fmt(formatter),
-            Error::FormatError(reason) => {
+            Error::FormatError(ref reason) => {
                 try!(formatter.write_str("Ill-formed WAVE file: "));
                 formatter.write_str(reason)
             }
@@ -407,25 +962,35 @@ impl fmt::Display for Error {
                 formatter.write_str(
                     "The number of samples written is not a multiple of the number of channels.")
             }
-            Error::Unsupported => {
-                formatter.write_str("The wave format of the file is not supported.")
+            Error::UnfinishedFrame { missing } => {
+                write!(formatter, "The last frame is incomplete: {} samples are missing.", missing)
+            }
+            Error::Unsupported(ref format) => {
+                write!(formatter, "The wave format of the file is not supported: {}.", format)
             }
             Error::InvalidSampleFormat => {
                 formatter.write_str("The sample format differs from the destination format.")
             }
+            Error::InvalidSpec(ref reason) => {
+                try!(formatter.write_str("Invalid spec: "));
+                formatter.write_str(reason)
+            }
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::IoError(ref err) => err.description(),
-            Error::FormatError(reason) => reason,
+            Error::FormatError(ref reason) => reason,
             Error::TooWide => "the sample has more bits than the destination type",
             Error::UnfinishedSample => "the number of samples written is not a multiple of the number of channels",
-            Error::Unsupported => "the wave format of the file is not supported",
+            Error::UnfinishedFrame { .. } => "the last frame is incomplete",
+            Error::Unsupported(_) => "the wave format of the file is not supported",
             Error::InvalidSampleFormat => "the sample format differs from the destination format",
+            Error::InvalidSpec(ref reason) => reason,
         }
     }
 
@@ -435,18 +1000,36 @@ impl error::Error for Error {
             Error::FormatError(_) => None,
             Error::TooWide => None,
             Error::UnfinishedSample => None,
-            Error::Unsupported => None,
+            Error::UnfinishedFrame { .. } => None,
+            Error::Unsupported(_) => None,
             Error::InvalidSampleFormat => None,
+            Error::InvalidSpec(_) => None,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::IoError(err)
     }
 }
 
+#[cfg(feature = "std")]
+impl From<SliceError> for Error {
+    fn from(err: SliceError) -> Error {
+        match err {
+            SliceError::UnexpectedEnd => {
+                Error::IoError(io::Error::new(io::ErrorKind::UnexpectedEof, "the slice ends before the header"))
+            }
+            SliceError::FormatError(reason) => Error::FormatError(reason.to_string()),
+            SliceError::Unsupported(format) => Error::Unsupported(format),
+            SliceError::TooWide => Error::TooWide,
+            SliceError::InvalidSampleFormat => Error::InvalidSampleFormat,
+        }
+    }
+}
+
 /// A type for results generated by Hound where the error type is hard-wired.
 pub type Result<T> = result::Result<T, Error>;
 
@@ -468,7 +1051,86 @@ const KSDATAFORMAT_SUBTYPE_IEEE_FLOAT: [u8; 16] = [0x03, 0x00, 0x00, 0x00, 0x00,
                                                    0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71];
 
 
+#[cfg(feature = "std")]
 impl WavSpec {
+    /// Returns a builder for a spec, which validates the spec when it is built.
+    ///
+    /// The builder starts out as mono, 44100 Hz, 16 bits per sample, integer
+    /// samples.
+    ///
+    /// ```
+    /// # fn main() -> hound::Result<()> {
+    /// let spec = hound::WavSpec::builder().channels(2).sample_rate(48000).bits(24).build()?;
+    /// assert_eq!(spec.to_string(), "2ch 48000Hz 24bit PCM");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> WavSpecBuilder {
+        WavSpecBuilder {
+            spec: WavSpec {
+                channels: 1,
+                sample_rate: 44100,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            },
+        }
+    }
+
+    /// The spec of an audio CD: stereo, 44100 Hz, 16-bit integer samples.
+    pub fn cd_quality() -> WavSpec {
+        WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        }
+    }
+
+    /// The common spec for broadcast: stereo, 48000 Hz, 24-bit integer samples.
+    pub fn broadcast_48k_24bit() -> WavSpec {
+        WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 24,
+            sample_format: SampleFormat::Int,
+        }
+    }
+
+    /// A common spec for speech, as used by speech recognition: mono, 16000 Hz,
+    /// 16-bit integer samples.
+    pub fn voice() -> WavSpec {
+        WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        }
+    }
+
+    /// Checks whether `WavWriter` can write a file with this spec.
+    ///
+    /// Returns `Error::InvalidSpec` if the spec has zero channels, a sample
+    /// rate of zero, or floating point samples of other than 32 bits, and
+    /// `Error::Unsupported` for bit depths other than 8, 16, 24 and 32. The
+    /// writer performs the same checks before it writes the header.
+    pub fn validate(&self) -> Result<()> {
+        if self.channels == 0 {
+            return Err(Error::InvalidSpec("the number of channels is zero".to_string()));
+        }
+        if self.sample_rate == 0 {
+            return Err(Error::InvalidSpec("the sample rate is zero".to_string()));
+        }
+        match self.bits_per_sample {
+            8 | 16 | 24 | 32 => {}
+            bits => return Err(Error::Unsupported(UnsupportedFormat::bits(bits))),
+        }
+        if self.sample_format == SampleFormat::Float && self.bits_per_sample != 32 {
+            let msg = format!("float samples must have 32 bits, not {}", self.bits_per_sample);
+            return Err(Error::InvalidSpec(msg));
+        }
+        Ok(())
+    }
+
     /// Get "stand-alone" wav header representing infinite or unknown size wav file.
     /// Use this if you need to write audio data to non-seekable sinks (like stdout).
     ///
@@ -528,6 +1190,152 @@ impl WavSpec {
 
         v
     }
+
+    /// Returns the header of a file with this spec and `bytes` bytes of samples.
+    ///
+    /// This is the header that `WavWriter` writes for the same spec, once
+    /// `bytes` bytes of samples have been written and the file is finalized,
+    /// so the samples can be appended to it elsewhere. Like the writer, the
+    /// RIFF size does not include a pad byte after a data chunk of odd
+    /// length. An error is returned if the spec is not supported, or if
+    /// the file would exceed the 4 GiB that the RIFF size can describe.
+    pub fn into_header_with_data_len(self, bytes: u64) -> Result<Vec<u8>> {
+        let mut c = io::Cursor::new(Vec::with_capacity(0x44));
+        try!(try!(WavWriter::new(&mut c, self)).finalize());
+        let mut v = c.into_inner();
+        let riff_len = v.len() as u64 - 8 + bytes;
+        if riff_len > u32::MAX as u64 {
+            return Err(Error::FormatError(format!("data chunk too large: {} bytes", bytes)));
+        }
+        let data_len_offset = v.len() - 4;
+        try!((&mut v[4..]).write_le_u32(riff_len as u32));
+        try!((&mut v[data_len_offset..]).write_le_u32(bytes as u32));
+        Ok(v)
+    }
+
+    /// Parses the spec from the header of a file, such as the ones returned by `into_header_with_data_len()`.
+    ///
+    /// This is a shorthand for `parse_header()`, which also returns the
+    /// position and length of the samples.
+    pub fn from_header(header: &[u8]) -> Result<WavSpec> {
+        parse_header(header).map(|header| header.spec_ex.spec)
+    }
+
+    /// Returns the size in bytes of a file with this spec and `frames` samples per channel.
+    ///
+    /// This is the size of the file that `WavWriter` produces: the RIFF
+    /// header, the fmt chunk, and the data chunk. Metadata chunks are not
+    /// included, for a writer that has those, `WavWriter::header_len()`
+    /// returns the size of everything before the samples. The result is only
+    /// meaningful for the bit depths that `WavWriter` supports.
+    pub fn estimated_file_size(&self, frames: u32) -> u64 {
+        let bytes_per_sample = self.bits_per_sample as u64 / 8;
+        let data_len = frames as u64 * self.channels as u64 * bytes_per_sample;
+        12 + write::FmtKind::for_spec(self).chunk_len() + 8 + data_len
+    }
+
+    /// Returns the largest sample value that can be represented in this format.
+    ///
+    /// For integer formats this is 2<sup>bits - 1</sup> - 1, e.g. 32767 for 16
+    /// bits per sample. Note that the range is asymmetric, the smallest value
+    /// is one further from zero, see `min_amplitude()`. For float formats the
+    /// nominal full scale of 1 is returned, although larger values can be
+    /// stored.
+    ///
+    /// # Panics
+    ///
+    /// Panics for integer formats with 0 or more than 64 bits per sample.
+    pub fn max_amplitude(&self) -> i64 {
+        match self.sample_format {
+            SampleFormat::Float => 1,
+            SampleFormat::Int => {
+                assert!(self.bits_per_sample > 0 && self.bits_per_sample <= 64,
+                        "bits per sample must be between 1 and 64");
+                ((1_u64 << (self.bits_per_sample - 1)) - 1) as i64
+            }
+        }
+    }
+
+    /// Returns the smallest sample value that can be represented in this format.
+    ///
+    /// For integer formats this is -2<sup>bits - 1</sup>, e.g. -32768 for 16
+    /// bits per sample. For float formats the nominal full scale of -1 is
+    /// returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics for integer formats with 0 or more than 64 bits per sample.
+    pub fn min_amplitude(&self) -> i64 {
+        match self.sample_format {
+            SampleFormat::Float => -1,
+            SampleFormat::Int => -self.max_amplitude() - 1,
+        }
+    }
+}
+
+/// Builds a `WavSpec` step by step, see `WavSpec::builder()`.
+#[derive(Clone, Copy, Debug)]
+#[cfg(feature = "std")]
+pub struct WavSpecBuilder {
+    spec: WavSpec,
+}
+
+#[cfg(feature = "std")]
+impl WavSpecBuilder {
+    /// Sets the number of channels.
+    pub fn channels(mut self, channels: u16) -> WavSpecBuilder {
+        self.spec.channels = channels;
+        self
+    }
+
+    /// Sets the number of samples per second.
+    pub fn sample_rate(mut self, sample_rate: u32) -> WavSpecBuilder {
+        self.spec.sample_rate = sample_rate;
+        self
+    }
+
+    /// Sets the number of bits per sample.
+    pub fn bits(mut self, bits_per_sample: u16) -> WavSpecBuilder {
+        self.spec.bits_per_sample = bits_per_sample;
+        self
+    }
+
+    /// Sets whether the samples are floating point, rather than integers.
+    pub fn float(mut self, float: bool) -> WavSpecBuilder {
+        self.spec.sample_format = if float { SampleFormat::Float } else { SampleFormat::Int };
+        self
+    }
+
+    /// Sets the sample format.
+    pub fn sample_format(mut self, sample_format: SampleFormat) -> WavSpecBuilder {
+        self.spec.sample_format = sample_format;
+        self
+    }
+
+    /// Returns the spec, or an error if `WavSpec::validate()` rejects it.
+    pub fn build(self) -> Result<WavSpec> {
+        try!(self.spec.validate());
+        Ok(self.spec)
+    }
+}
+
+#[test]
+fn sample_byte_order_reverses_the_bytes() {
+    let mut buffer = Vec::new();
+    0x123456_i32.write_padded_with_byte_order(&mut buffer, 24, 3, ByteOrder::BigEndian).unwrap();
+    (-2_i16).write_padded_with_byte_order(&mut buffer, 16, 2, ByteOrder::LittleEndian).unwrap();
+    (-2_i16).write_padded_with_byte_order(&mut buffer, 16, 2, ByteOrder::BigEndian).unwrap();
+    0.5_f32.write_padded_with_byte_order(&mut buffer, 32, 4, ByteOrder::BigEndian).unwrap();
+    assert_eq!(&buffer[..], &[0x12, 0x34, 0x56, 0xfe, 0xff, 0xff, 0xfe, 0x3f, 0x00, 0x00, 0x00]);
+
+    let mut reader = &buffer[..];
+    let int = SampleFormat::Int;
+    assert_eq!(i32::read_with_byte_order(&mut reader, int, 3, 24, ByteOrder::BigEndian).unwrap(), 0x123456);
+    assert_eq!(i16::read_with_byte_order(&mut reader, int, 2, 16, ByteOrder::LittleEndian).unwrap(), -2);
+    assert_eq!(i16::read_with_byte_order(&mut reader, int, 2, 16, ByteOrder::BigEndian).unwrap(), -2);
+    let float = f32::read_with_byte_order(&mut reader, SampleFormat::Float, 4, 32, ByteOrder::BigEndian);
+    assert_eq!(float.unwrap(), 0.5);
+    assert!(reader.is_empty());
 }
 
 #[test]
@@ -634,6 +1442,224 @@ fn write_read_i8_is_lossless() {
     }
 }
 
+#[test]
+fn write_sample_checks_range_of_wider_type() {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.write_sample(32767).unwrap();
+        writer.write_sample(-32768).unwrap();
+        match writer.write_sample(32768) {
+            Err(Error::TooWide) => {}
+            _ => panic!("expected TooWide for a sample that does not fit"),
+        }
+        writer.write_sample(-7_i8).unwrap();
+        assert_eq!(writer.len(), 3);
+        writer.finalize().unwrap();
+    }
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[32767, -32768, -7]);
+}
+
+#[test]
+fn display_wav_spec() {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    assert_eq!(spec.to_string(), "2ch 44100Hz 16bit PCM");
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    assert_eq!(format!("{}", spec), "1ch 48000Hz 32bit float");
+}
+
+#[test]
+fn parse_wav_spec_round_trips_display() {
+    let formats = [SampleFormat::Int, SampleFormat::Float];
+    for &channels in &[1, 2, 6, 0xffff] {
+        for &sample_rate in &[1, 8000, 44100, 192000, 0xffff_ffff] {
+            for &bits_per_sample in &[8, 16, 24, 32, 0xffff] {
+                for &sample_format in &formats {
+                    let spec = WavSpec {
+                        channels: channels,
+                        sample_rate: sample_rate,
+                        bits_per_sample: bits_per_sample,
+                        sample_format: sample_format,
+                    };
+                    let formatted = spec.to_string();
+                    assert_eq!(formatted.parse::<WavSpec>(), Ok(spec));
+                    // The parser should also accept variations of the rendering.
+                    let squashed = formatted.replace(" ", "");
+                    assert_eq!(squashed.parse::<WavSpec>(), Ok(spec));
+                    assert_eq!(squashed.to_uppercase().parse::<WavSpec>(), Ok(spec));
+                    let commas = formatted.replace(" ", ", ");
+                    assert_eq!(commas.parse::<WavSpec>(), Ok(spec));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn parse_wav_spec_accepts_tolerant_grammar() {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    assert_eq!("2 ch, 44100 Hz, 16-bit int".parse::<WavSpec>(), Ok(spec));
+    assert_eq!("2 channels 44100hz 16 bits".parse::<WavSpec>(), Ok(spec));
+    assert_eq!("  16bit_44100Hz_2ch  ".parse::<WavSpec>(), Ok(spec));
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    assert_eq!("1ch48000Hz24bit".parse::<WavSpec>(), Ok(spec));
+}
+
+#[test]
+fn parse_wav_spec_describes_malformed_input() {
+    let reason = |s: &str| s.parse::<WavSpec>().unwrap_err().to_string();
+    assert_eq!(reason("2ch 44100Hz"), "invalid wav spec: missing bits per sample");
+    assert_eq!(reason("2ch 16bit"), "invalid wav spec: missing sample rate");
+    assert_eq!(reason("44100Hz 16bit"), "invalid wav spec: missing number of channels");
+    assert_eq!(reason("2ch 44100 16bit"), "invalid wav spec: missing unit after 44100");
+    assert_eq!(reason("2ch 44100kHz 16bit"), "invalid wav spec: unknown unit \"khz\" after 44100");
+    assert_eq!(reason("2ch 44100Hz 16bit alaw"), "invalid wav spec: unknown sample format \"alaw\"");
+    assert_eq!(reason("2ch 1ch 44100Hz 16bit"), "invalid wav spec: the number of channels is given twice");
+    assert_eq!(reason("2ch 44100Hz 16bit PCM float"), "invalid wav spec: the sample format is given twice");
+    assert_eq!(reason("0ch 44100Hz 16bit"), "invalid wav spec: invalid number of channels 0");
+    assert_eq!(reason("2ch 99999999999Hz 16bit"), "invalid wav spec: invalid sample rate 99999999999");
+    assert_eq!(reason("65536ch 44100Hz 16bit"), "invalid wav spec: invalid number of channels 65536");
+    assert_eq!(reason("2ch/44100Hz/16bit"), "invalid wav spec: unexpected character in \"2ch/44100Hz/16bit\"");
+    assert_eq!(reason(""), "invalid wav spec: missing number of channels");
+}
+
+#[test]
+fn wav_spec_builder_validates() {
+    let spec = WavSpec::builder().channels(2).sample_rate(48000).bits(24).float(false).build();
+    assert_eq!(spec.unwrap(), WavSpec::broadcast_48k_24bit());
+    let spec = WavSpec::builder().channels(2).build();
+    assert_eq!(spec.unwrap(), WavSpec::cd_quality());
+    let spec = WavSpec::builder().sample_rate(16000).build();
+    assert_eq!(spec.unwrap(), WavSpec::voice());
+    let spec = WavSpec::builder().bits(32).float(true).build().unwrap();
+    assert_eq!(spec.sample_format, SampleFormat::Float);
+
+    match WavSpec::builder().channels(0).build() {
+        Err(Error::InvalidSpec(ref reason)) => assert_eq!(reason, "the number of channels is zero"),
+        other => panic!("expected InvalidSpec, got {:?}", other),
+    }
+    match WavSpec::builder().sample_rate(0).build() {
+        Err(Error::InvalidSpec(ref reason)) => assert_eq!(reason, "the sample rate is zero"),
+        other => panic!("expected InvalidSpec, got {:?}", other),
+    }
+    match WavSpec::builder().float(true).build() {
+        Err(Error::InvalidSpec(ref reason)) => assert_eq!(reason, "float samples must have 32 bits, not 16"),
+        other => panic!("expected InvalidSpec, got {:?}", other),
+    }
+    match WavSpec::builder().bits(12).build() {
+        Err(Error::Unsupported(format)) => assert_eq!(format.bits_per_sample, 12),
+        other => panic!("expected Unsupported, got {:?}", other),
+    }
+}
+
+#[test]
+fn writer_rejects_invalid_spec() {
+    let spec = WavSpec {
+        channels: 0,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    match WavWriter::new(io::Cursor::new(Vec::new()), spec) {
+        Err(Error::InvalidSpec(_)) => {}
+        _ => panic!("expected InvalidSpec"),
+    }
+    let spec = WavSpec { channels: 1, bits_per_sample: 16, sample_format: SampleFormat::Float, ..spec };
+    match WavWriter::new(io::Cursor::new(Vec::new()), spec) {
+        Err(Error::InvalidSpec(_)) => {}
+        _ => panic!("expected InvalidSpec"),
+    }
+}
+
+#[test]
+fn estimated_file_size_matches_written_file() {
+    let specs = [(1, 8, SampleFormat::Int), (2, 16, SampleFormat::Int),
+                 (1, 24, SampleFormat::Int), (6, 32, SampleFormat::Float)];
+    for &(channels, bits, format) in &specs {
+        let spec = WavSpec {
+            channels: channels,
+            sample_rate: 8000,
+            bits_per_sample: bits,
+            sample_format: format,
+        };
+        for &frames in &[0, 3, 100] {
+            let mut buffer = io::Cursor::new(Vec::new());
+            {
+                let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+                for _ in 0..frames * channels as u32 {
+                    match format {
+                        SampleFormat::Int => writer.write_sample(0).unwrap(),
+                        SampleFormat::Float => writer.write_sample(0.0).unwrap(),
+                    }
+                }
+                writer.finalize().unwrap();
+            }
+            assert_eq!(spec.estimated_file_size(frames), buffer.get_ref().len() as u64);
+        }
+    }
+}
+
+#[test]
+fn write_read_i24_sign_boundary() {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let samples = [0, 0x7f_ffff, -0x80_0000, -1];
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for &s in &samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    // The data chunk holds exactly three bytes per sample.
+    let bytes = buffer.into_inner();
+    let data = &bytes[bytes.len() - 12..];
+    assert_eq!(&bytes[bytes.len() - 16..bytes.len() - 12], &[12, 0, 0, 0]);
+    assert_eq!(data, &[0x00, 0x00, 0x00,
+                       0xff, 0xff, 0x7f,
+                       0x00, 0x00, 0x80,
+                       0xff, 0xff, 0xff]);
+
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    let read: Vec<i32> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&read[..], &samples[..]);
+}
+
 #[test]
 fn write_read_i24_is_lossless() {
     let mut buffer = io::Cursor::new(Vec::new());
@@ -984,6 +2010,30 @@ fn write_read_chunks_is_lossless() {
 }
 
 
+#[test]
+fn verify_amplitude_range() {
+    let spec = WavSpec {
+        bits_per_sample: 16,
+        channels: 1,
+        sample_format: SampleFormat::Int,
+        sample_rate: 16000,
+    };
+    assert_eq!(spec.max_amplitude(), 32_767);
+    assert_eq!(spec.min_amplitude(), -32_768);
+    let spec8 = WavSpec { bits_per_sample: 8, ..spec };
+    assert_eq!((spec8.min_amplitude(), spec8.max_amplitude()), (-128, 127));
+    let spec24 = WavSpec { bits_per_sample: 24, ..spec };
+    assert_eq!((spec24.min_amplitude(), spec24.max_amplitude()), (-8_388_608, 8_388_607));
+    let spec32 = WavSpec { bits_per_sample: 32, ..spec };
+    assert_eq!(spec32.min_amplitude(), i32::MIN as i64);
+    assert_eq!(spec32.max_amplitude(), i32::MAX as i64);
+    let spec64 = WavSpec { bits_per_sample: 64, ..spec };
+    assert_eq!(spec64.max_amplitude(), i64::MAX);
+    assert_eq!(spec64.min_amplitude(), i64::MIN);
+    let float = WavSpec { bits_per_sample: 32, sample_format: SampleFormat::Float, ..spec };
+    assert_eq!((float.min_amplitude(), float.max_amplitude()), (-1, 1));
+}
+
 #[cfg(test)]
 #[test]
 fn test_into_header_for_infinite_file() {
@@ -1011,3 +2061,76 @@ fmt \x28\x00\x00\x00\xfe\xff\x0a\x00\x80\x3e\x00\x00\x00\xe2\x04\x00\
 \x00\x00\x10\x00\x80\x00\x00\xaa\x00\x38\x9b\x71\
 data\xFF\xFF\xFF\xFF"[..]);
 }
+
+#[test]
+fn into_header_with_data_len_matches_the_writer() {
+    for &(channels, bits, frames) in &[(1, 8, 5), (2, 16, 300), (6, 24, 7), (1, 32, 1)] {
+        let spec = WavSpec {
+            channels: channels,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: SampleFormat::Int,
+        };
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            for i in 0..frames * channels as u32 {
+                writer.write_sample((i % 100) as i8).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        let file = buffer.into_inner();
+        let bytes = frames as u64 * channels as u64 * bits as u64 / 8;
+        let header = spec.into_header_with_data_len(bytes).unwrap();
+        assert_eq!(file.len() as u64, header.len() as u64 + bytes);
+        assert_eq!(&file[..header.len()], &header[..]);
+
+        assert_eq!(WavSpec::from_header(&header).unwrap(), spec);
+        assert_eq!(parse_header(&header).unwrap().data_len, bytes);
+        assert_eq!(WavSpec::from_header(&spec.into_header_for_infinite_file()).unwrap(), spec);
+    }
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let header = spec.into_header_with_data_len(u32::MAX as u64 - 36).unwrap();
+    assert_eq!(&header[4..8], &[0xff; 4]);
+    assert!(spec.into_header_with_data_len(u32::MAX as u64 - 35).is_err());
+    let invalid = WavSpec { bits_per_sample: 0, ..spec };
+    assert!(invalid.into_header_with_data_len(0).is_err());
+}
+
+#[test]
+fn speaker_positions_follow_the_mask_bits() {
+    use SpeakerPosition::*;
+    assert_eq!(SpeakerPosition::for_channels(0x3f, 6),
+               [FrontLeft, FrontRight, FrontCenter, LowFrequency, BackLeft, BackRight]);
+    // 7.1 with side channels.
+    assert_eq!(SpeakerPosition::for_channels(0x63f, 8),
+               [FrontLeft, FrontRight, FrontCenter, LowFrequency, BackLeft, BackRight, SideLeft, SideRight]);
+    assert_eq!(SpeakerPosition::for_channels(0x3, 3), [FrontLeft, FrontRight, Unassigned]);
+    assert_eq!(SpeakerPosition::for_channels(0x7, 1), [FrontLeft]);
+    assert_eq!(SpeakerPosition::for_channels(0x8004_0001, 2), [FrontLeft, Unassigned]);
+    assert_eq!(TopBackRight.mask_bit(), 0x20000);
+    assert_eq!(Unassigned.mask_bit(), 0);
+}
+
+#[test]
+fn average_rounds_to_nearest_and_does_not_overflow() {
+    assert_eq!(i16::average(&[32767, 32767]), 32767);
+    assert_eq!(i16::average(&[-32768, -32768]), -32768);
+    assert_eq!(i16::average(&[-32768, 32767]), -1);
+    assert_eq!(i16::average(&[1, 2]), 2);
+    assert_eq!(i16::average(&[-1, -2]), -2);
+    assert_eq!(i16::average(&[1, 1, 0]), 1);
+    assert_eq!(i16::average(&[1, 0, 0]), 0);
+    assert_eq!(i16::average(&[-1, 0, 0]), 0);
+    assert_eq!(i8::average(&[127, 127, 126]), 127);
+    assert_eq!(i32::average(&[i32::MAX, i32::MAX]), i32::MAX);
+    assert_eq!(i32::average(&[i32::MIN, i32::MIN, i32::MIN]), i32::MIN);
+    assert_eq!(f32::average(&[0.5, -0.25]), 0.125);
+    assert_eq!(f32::average(&[7.0]), 7.0);
+}
@@ -0,0 +1,168 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal MD5 implementation, for the checksum in the `MD5 ` chunk.
+//!
+//! MD5 is not secure as a cryptographic hash, but it is what the chunk
+//! specifies, and it is sufficient to detect corrupted audio data.
+
+use std::cmp;
+use std::io;
+
+/// The per-round shift amounts, as in RFC 1321.
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22,
+    5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20,
+    4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23,
+    6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// The per-round constants, the integer part of `abs(sin(i + 1)) * 2^32`.
+const CONSTANTS: [u32; 64] = [
+    0xd76aa478, 0xe8c7b756, 0x242070db, 0xc1bdceee, 0xf57c0faf, 0x4787c62a, 0xa8304613, 0xfd469501,
+    0x698098d8, 0x8b44f7af, 0xffff5bb1, 0x895cd7be, 0x6b901122, 0xfd987193, 0xa679438e, 0x49b40821,
+    0xf61e2562, 0xc040b340, 0x265e5a51, 0xe9b6c7aa, 0xd62f105d, 0x02441453, 0xd8a1e681, 0xe7d3fbc8,
+    0x21e1cde6, 0xc33707d6, 0xf4d50d87, 0x455a14ed, 0xa9e3e905, 0xfcefa3f8, 0x676f02d9, 0x8d2a4c8a,
+    0xfffa3942, 0x8771f681, 0x6d9d6122, 0xfde5380c, 0xa4beea44, 0x4bdecfa9, 0xf6bb4b60, 0xbebfbc70,
+    0x289b7ec6, 0xeaa127fa, 0xd4ef3085, 0x04881d05, 0xd9d4d039, 0xe6db99e5, 0x1fa27cf8, 0xc4ac5665,
+    0xf4292244, 0x432aff97, 0xab9423a7, 0xfc93a039, 0x655b59c3, 0x8f0ccc92, 0xffeff47d, 0x85845dd1,
+    0x6fa87e4f, 0xfe2ce6e0, 0xa3014314, 0x4e0811a1, 0xf7537e82, 0xbd3af235, 0x2ad7d2bb, 0xeb86d391,
+];
+
+/// An incremental MD5 computation.
+#[derive(Clone)]
+pub struct Md5 {
+    state: [u32; 4],
+    /// Bytes that do not yet form a complete 64-byte block.
+    buffer: [u8; 64],
+    buffered: usize,
+    /// The total number of bytes hashed.
+    len: u64,
+}
+
+impl Md5 {
+    pub fn new() -> Md5 {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            buffer: [0; 64],
+            buffered: 0,
+            len: 0,
+        }
+    }
+
+    /// Hashes `data`, following the data hashed before.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len = self.len.wrapping_add(data.len() as u64);
+        if self.buffered > 0 {
+            let n = cmp::min(64 - self.buffered, data.len());
+            self.buffer[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+            if self.buffered < 64 {
+                return
+            }
+            let block = self.buffer;
+            self.process(&block);
+            self.buffered = 0;
+        }
+        while data.len() >= 64 {
+            self.process(&data[..64]);
+            data = &data[64..];
+        }
+        self.buffer[..data.len()].copy_from_slice(data);
+        self.buffered = data.len();
+    }
+
+    /// Returns the digest of all data hashed.
+    pub fn finish(mut self) -> [u8; 16] {
+        let bit_len = self.len.wrapping_mul(8);
+        let mut padding = [0u8; 72];
+        padding[0] = 0x80;
+        // Pad to 56 bytes modulo 64, then append the length in bits.
+        let pad_len = if self.buffered < 56 { 56 - self.buffered } else { 120 - self.buffered };
+        for i in 0..8 {
+            padding[pad_len + i] = (bit_len >> (8 * i)) as u8;
+        }
+        let len = self.len;
+        self.update(&padding[..pad_len + 8]);
+        self.len = len;
+        let mut digest = [0; 16];
+        for (i, word) in self.state.iter().enumerate() {
+            for j in 0..4 {
+                digest[i * 4 + j] = (word >> (8 * j)) as u8;
+            }
+        }
+        digest
+    }
+
+    fn process(&mut self, block: &[u8]) {
+        let mut m = [0u32; 16];
+        for (i, word) in m.iter_mut().enumerate() {
+            *word = block[i * 4] as u32
+                  | (block[i * 4 + 1] as u32) << 8
+                  | (block[i * 4 + 2] as u32) << 16
+                  | (block[i * 4 + 3] as u32) << 24;
+        }
+        let [mut a, mut b, mut c, mut d] = self.state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a.wrapping_add(f)
+                           .wrapping_add(CONSTANTS[i])
+                           .wrapping_add(m[g])
+                           .rotate_left(SHIFTS[i]);
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+    }
+}
+
+impl io::Write for Md5 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn md5_hex(data: &[u8], chunk_size: usize) -> String {
+    let mut md5 = Md5::new();
+    for chunk in data.chunks(chunk_size) {
+        md5.update(chunk);
+    }
+    md5.finish().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn md5_matches_rfc_1321_test_suite() {
+    assert_eq!(md5_hex(b"", 1), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(md5_hex(b"abc", 1), "900150983cd24fb0d6963f7d28e17f72");
+    assert_eq!(md5_hex(b"message digest", 3), "f96b697d7cb7938d525a2f31aaf161d0");
+    let digits = b"12345678901234567890123456789012345678901234567890123456789012345678901234567890";
+    for &chunk_size in &[1, 7, 64, 80] {
+        assert_eq!(md5_hex(digits, chunk_size), "57edf4a22be3c955ac49da2e2107b67a");
+    }
+}
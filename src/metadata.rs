@@ -0,0 +1,1469 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed representations of the metadata chunks that Hound understands.
+//!
+//! The reader keeps the payload of every chunk that it does not interpret
+//! itself, and the types in this module are decoded from those payloads on
+//! request. A malformed metadata chunk therefore never prevents reading the
+//! audio data.
+
+use std::cmp;
+use super::{Error, Result};
+use read::ReadExt;
+
+/// A chunk that was encountered in the file, together with its payload.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawChunk {
+    /// The four-character code that identifies the chunk.
+    pub id: [u8; 4],
+    /// The contents of the chunk, excluding the header and the pad byte.
+    pub data: Vec<u8>,
+}
+
+/// Returns the part of `bytes` before the first NUL byte, decoded as text.
+///
+/// Text in RIFF chunks is specified to be ASCII, but in practice other
+/// encodings occur too. Invalid UTF-8 is replaced rather than rejected.
+fn decode_zstr(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// The contents of a `LIST` chunk with list type `INFO`.
+///
+/// The chunk stores text values identified by a four-character code, such as
+/// `INAM` for the title, or `ISFT` for the software that produced the file.
+/// Accessors are provided for the common entries, other entries can be
+/// accessed with `get()`. The order of the entries in the file is preserved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListInfo {
+    entries: Vec<([u8; 4], String)>,
+}
+
+impl ListInfo {
+    /// Creates an empty `ListInfo`.
+    pub fn new() -> ListInfo {
+        ListInfo { entries: Vec::new() }
+    }
+
+    /// Returns the value of the entry with the given id, if present.
+    pub fn get(&self, id: [u8; 4]) -> Option<&str> {
+        self.entries.iter()
+            .find(|entry| entry.0 == id)
+            .map(|entry| &entry.1[..])
+    }
+
+    /// Returns all entries in the order in which they occur in the file.
+    pub fn entries(&self) -> &[([u8; 4], String)] {
+        &self.entries[..]
+    }
+
+    /// Returns the title of the subject of the file (`INAM`).
+    pub fn title(&self) -> Option<&str> {
+        self.get(*b"INAM")
+    }
+
+    /// Returns the artist of the original subject of the file (`IART`).
+    pub fn artist(&self) -> Option<&str> {
+        self.get(*b"IART")
+    }
+
+    /// Returns the date on which the subject was created (`ICRD`).
+    pub fn creation_date(&self) -> Option<&str> {
+        self.get(*b"ICRD")
+    }
+
+    /// Returns the name of the software that created the file (`ISFT`).
+    pub fn software(&self) -> Option<&str> {
+        self.get(*b"ISFT")
+    }
+
+    /// Returns the general comments about the file (`ICMT`).
+    pub fn comment(&self) -> Option<&str> {
+        self.get(*b"ICMT")
+    }
+
+    /// Returns the copyright information of the file (`ICOP`).
+    pub fn copyright(&self) -> Option<&str> {
+        self.get(*b"ICOP")
+    }
+
+    /// Returns the genre of the subject (`IGNR`).
+    pub fn genre(&self) -> Option<&str> {
+        self.get(*b"IGNR")
+    }
+
+    /// Sets the value of the entry with the given id.
+    ///
+    /// An existing entry with the same id is replaced, otherwise the entry is
+    /// added at the end. Values are stored NUL-terminated, so a value that
+    /// contains a NUL character will be truncated when it is read back.
+    pub fn set(&mut self, id: [u8; 4], value: &str) {
+        match self.entries.iter_mut().find(|entry| entry.0 == id) {
+            Some(entry) => entry.1 = value.to_string(),
+            None => self.entries.push((id, value.to_string())),
+        }
+    }
+
+    /// Removes the entry with the given id, if present.
+    pub fn remove(&mut self, id: [u8; 4]) {
+        self.entries.retain(|entry| entry.0 != id);
+    }
+
+    /// Sets the title of the subject of the file (`INAM`).
+    pub fn set_title(&mut self, value: &str) {
+        self.set(*b"INAM", value)
+    }
+
+    /// Sets the artist of the original subject of the file (`IART`).
+    pub fn set_artist(&mut self, value: &str) {
+        self.set(*b"IART", value)
+    }
+
+    /// Sets the date on which the subject was created (`ICRD`).
+    ///
+    /// The date should be formatted as `YYYY-MM-DD`.
+    pub fn set_creation_date(&mut self, value: &str) {
+        self.set(*b"ICRD", value)
+    }
+
+    /// Sets the name of the software that created the file (`ISFT`).
+    pub fn set_software(&mut self, value: &str) {
+        self.set(*b"ISFT", value)
+    }
+
+    /// Sets the general comments about the file (`ICMT`).
+    pub fn set_comment(&mut self, value: &str) {
+        self.set(*b"ICMT", value)
+    }
+
+    /// Sets the copyright information of the file (`ICOP`).
+    pub fn set_copyright(&mut self, value: &str) {
+        self.set(*b"ICOP", value)
+    }
+
+    /// Sets the genre of the subject (`IGNR`).
+    pub fn set_genre(&mut self, value: &str) {
+        self.set(*b"IGNR", value)
+    }
+}
+
+/// Returns the list type of a `LIST` chunk payload, if it has one.
+pub fn list_type(data: &[u8]) -> Option<[u8; 4]> {
+    if data.len() < 4 {
+        None
+    } else {
+        Some([data[0], data[1], data[2], data[3]])
+    }
+}
+
+/// Splits the payload of a `LIST` chunk (after the list type) into sub-chunks.
+///
+/// Sub-chunks are padded to an even size, like top-level chunks. Some writers
+/// omit the pad byte after the last sub-chunk, that is tolerated.
+fn read_sub_chunks(mut data: &[u8]) -> Result<Vec<([u8; 4], &[u8])>> {
+    let mut chunks = Vec::new();
+    while !data.is_empty() {
+        if data.len() < 8 {
+            return Err(Error::FormatError("truncated LIST sub-chunk header".to_string()));
+        }
+        let id = [data[0], data[1], data[2], data[3]];
+        let len = try!((&data[4..8]).read_le_u32()) as usize;
+        let body = &data[8..];
+        if len > body.len() {
+            let msg = format!("LIST sub-chunk {:?} of {} bytes exceeds LIST chunk",
+                              String::from_utf8_lossy(&id), len);
+            return Err(Error::FormatError(msg));
+        }
+        chunks.push((id, &body[..len]));
+        let padded_len = len + len % 2;
+        data = if padded_len < body.len() { &body[padded_len..] } else { &[] };
+    }
+    Ok(chunks)
+}
+
+/// Appends a little endian `u32` to `out`.
+fn push_le_u32(out: &mut Vec<u8>, x: u32) {
+    out.extend_from_slice(&[x as u8, (x >> 8) as u8, (x >> 16) as u8, (x >> 24) as u8]);
+}
+
+/// Appends a little endian `u16` to `out`.
+fn push_le_u16(out: &mut Vec<u8>, x: u16) {
+    out.extend_from_slice(&[x as u8, (x >> 8) as u8]);
+}
+
+/// Appends a sub-chunk with the given body to `out`, including the pad byte.
+fn write_sub_chunk(out: &mut Vec<u8>, id: [u8; 4], body: &[u8]) {
+    out.extend_from_slice(&id);
+    push_le_u32(out, body.len() as u32);
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Encodes the payload of a `LIST` chunk of list type `INFO`.
+pub fn serialize_list_info(info: &ListInfo) -> Vec<u8> {
+    let mut out = b"INFO".to_vec();
+    for &(id, ref value) in &info.entries {
+        let mut body = value.as_bytes().to_vec();
+        body.push(0);
+        write_sub_chunk(&mut out, id, &body);
+    }
+    out
+}
+
+/// Decodes the payload of a `LIST` chunk of list type `INFO`.
+pub fn parse_list_info(data: &[u8]) -> Result<ListInfo> {
+    if list_type(data) != Some(*b"INFO") {
+        return Err(Error::FormatError("LIST chunk is not of type INFO".to_string()));
+    }
+    let mut info = ListInfo::new();
+    for (id, value) in try!(read_sub_chunks(&data[4..])) {
+        info.entries.push((id, decode_zstr(value)));
+    }
+    Ok(info)
+}
+
+/// A marker in the audio data, read from the `cue ` chunk.
+///
+/// Cue points are usually placed by an audio editor to mark positions of
+/// interest. A label for the cue point can be stored in an associated-data
+/// list (a `LIST` chunk of type `adtl`), it is looked up by `id`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CuePoint {
+    /// The identifier of the cue point, unique within the file.
+    pub id: u32,
+    /// The position of the cue point in play order, in samples.
+    pub position: u32,
+    /// The id of the chunk that contains the cue point, usually `data`.
+    pub data_chunk_id: [u8; 4],
+    /// The position of that chunk in a wave list chunk, zero for `data`.
+    pub chunk_start: u32,
+    /// The offset of the block that contains the cue point, zero for PCM data.
+    pub block_start: u32,
+    /// The position of the cue point within the block, in samples.
+    pub sample_offset: u32,
+    /// The text of the `labl` entry for this cue point, if there is one.
+    pub label: Option<String>,
+    /// The text of the `note` entry for this cue point, if there is one.
+    pub note: Option<String>,
+}
+
+impl CuePoint {
+    /// Creates a cue point at the given sample position in the data chunk.
+    ///
+    /// The position is measured in samples per channel, like
+    /// `WavWriter::duration()`.
+    pub fn new(id: u32, position: u32) -> CuePoint {
+        CuePoint {
+            id: id,
+            position: position,
+            data_chunk_id: *b"data",
+            chunk_start: 0,
+            block_start: 0,
+            sample_offset: position,
+            label: None,
+            note: None,
+        }
+    }
+}
+
+/// Decodes the payload of a `cue ` chunk, without labels.
+pub fn parse_cue(data: &[u8]) -> Result<Vec<CuePoint>> {
+    let mut reader = data;
+    let num_points = try!(reader.read_le_u32()) as usize;
+    if num_points > reader.len() / 24 {
+        let msg = format!("cue chunk of {} bytes is too short for {} cue points", data.len(), num_points);
+        return Err(Error::FormatError(msg));
+    }
+    let mut points = Vec::with_capacity(num_points);
+    for _ in 0..num_points {
+        points.push(CuePoint {
+            id: try!(reader.read_le_u32()),
+            position: try!(reader.read_le_u32()),
+            data_chunk_id: try!(reader.read_4_bytes()),
+            chunk_start: try!(reader.read_le_u32()),
+            block_start: try!(reader.read_le_u32()),
+            sample_offset: try!(reader.read_le_u32()),
+            label: None,
+            note: None,
+        });
+    }
+    Ok(points)
+}
+
+/// Decodes the `labl` and `note` entries of a `LIST` chunk of list type `adtl`.
+///
+/// Returns the sub-chunk id, cue point id, and text of every entry. Other
+/// entries, such as `ltxt`, are skipped.
+pub fn parse_adtl(data: &[u8]) -> Result<Vec<([u8; 4], u32, String)>> {
+    if list_type(data) != Some(*b"adtl") {
+        return Err(Error::FormatError("LIST chunk is not of type adtl".to_string()));
+    }
+    let mut texts = Vec::new();
+    for (id, mut body) in try!(read_sub_chunks(&data[4..])) {
+        if &id == b"labl" || &id == b"note" {
+            let cue_id = try!(body.read_le_u32());
+            texts.push((id, cue_id, decode_zstr(body)));
+        }
+    }
+    Ok(texts)
+}
+
+/// Encodes the payload of a `cue ` chunk.
+pub fn serialize_cue(points: &[CuePoint]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + points.len() * 24);
+    push_le_u32(&mut out, points.len() as u32);
+    for point in points {
+        push_le_u32(&mut out, point.id);
+        push_le_u32(&mut out, point.position);
+        out.extend_from_slice(&point.data_chunk_id);
+        push_le_u32(&mut out, point.chunk_start);
+        push_le_u32(&mut out, point.block_start);
+        push_le_u32(&mut out, point.sample_offset);
+    }
+    out
+}
+
+/// Encodes the labels and notes of the cue points as a `LIST` chunk payload.
+///
+/// Returns `None` if none of the cue points has a label or note.
+pub fn serialize_adtl(points: &[CuePoint]) -> Option<Vec<u8>> {
+    let mut out = b"adtl".to_vec();
+    for point in points {
+        let texts = [(*b"labl", &point.label), (*b"note", &point.note)];
+        for &(id, text) in &texts {
+            if let Some(ref text) = *text {
+                let mut body = Vec::with_capacity(text.len() + 5);
+                push_le_u32(&mut body, point.id);
+                body.extend_from_slice(text.as_bytes());
+                body.push(0);
+                write_sub_chunk(&mut out, id, &body);
+            }
+        }
+    }
+    if out.len() > 4 { Some(out) } else { None }
+}
+
+/// A segment of a `plst` chunk, which plays the audio from a cue point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaylistSegment {
+    /// The id of the cue point at which the segment starts.
+    pub cue_id: u32,
+    /// The length of the segment, in samples.
+    pub length: u32,
+    /// The number of times to play the segment.
+    pub repeats: u32,
+}
+
+/// Decodes the payload of a `plst` chunk.
+pub fn parse_plst(data: &[u8]) -> Result<Vec<PlaylistSegment>> {
+    let mut reader = data;
+    let num_segments = try!(reader.read_le_u32()) as usize;
+    if num_segments > reader.len() / 12 {
+        let msg = format!("plst chunk of {} bytes is too short for {} segments",
+                          data.len(), num_segments);
+        return Err(Error::FormatError(msg));
+    }
+    let mut segments = Vec::with_capacity(num_segments);
+    for _ in 0..num_segments {
+        segments.push(PlaylistSegment {
+            cue_id: try!(reader.read_le_u32()),
+            length: try!(reader.read_le_u32()),
+            repeats: try!(reader.read_le_u32()),
+        });
+    }
+    Ok(segments)
+}
+
+/// Encodes the payload of a `plst` chunk.
+pub fn serialize_plst(segments: &[PlaylistSegment]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(4 + segments.len() * 12);
+    push_le_u32(&mut out, segments.len() as u32);
+    for segment in segments {
+        push_le_u32(&mut out, segment.cue_id);
+        push_le_u32(&mut out, segment.length);
+        push_le_u32(&mut out, segment.repeats);
+    }
+    out
+}
+
+/// A loop in the sample, as stored in the `smpl` chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SampleLoop {
+    /// The identifier of the loop, it may refer to a cue point.
+    pub id: u32,
+    /// The loop type: 0 loops forward, 1 alternates, 2 loops backward.
+    ///
+    /// Values 32 and up are manufacturer specific.
+    pub loop_type: u32,
+    /// The first sample of the loop, in samples per channel.
+    pub start: u32,
+    /// The last sample of the loop, inclusive, in samples per channel.
+    pub end: u32,
+    /// The fraction of a sample at which to loop, where 2<sup>32</sup> is one sample.
+    pub fraction: u32,
+    /// The number of times to play the loop, 0 loops infinitely.
+    pub play_count: u32,
+}
+
+/// The contents of a `smpl` chunk, with information for samplers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SamplerInfo {
+    /// The MIDI manufacturer code of the intended sampler, 0 for none.
+    pub manufacturer: u32,
+    /// The product code of the intended sampler, 0 for none.
+    pub product: u32,
+    /// The duration of one sample in nanoseconds.
+    pub sample_period: u32,
+    /// The MIDI note at which the sample plays back at its original pitch.
+    pub midi_unity_note: u32,
+    /// The fraction of a semitone above the unity note, where 2<sup>32</sup> is one semitone.
+    pub midi_pitch_fraction: u32,
+    /// The SMPTE format: 0, 24, 25, 29 or 30 frames per second.
+    pub smpte_format: u32,
+    /// The SMPTE time offset of the first sample.
+    pub smpte_offset: u32,
+    /// The loops in the sample.
+    pub loops: Vec<SampleLoop>,
+    /// Manufacturer specific data that follows the loops.
+    pub sampler_data: Vec<u8>,
+}
+
+/// Decodes the payload of a `smpl` chunk.
+pub fn parse_smpl(data: &[u8]) -> Result<SamplerInfo> {
+    let mut reader = data;
+    let mut info = SamplerInfo {
+        manufacturer: try!(reader.read_le_u32()),
+        product: try!(reader.read_le_u32()),
+        sample_period: try!(reader.read_le_u32()),
+        midi_unity_note: try!(reader.read_le_u32()),
+        midi_pitch_fraction: try!(reader.read_le_u32()),
+        smpte_format: try!(reader.read_le_u32()),
+        smpte_offset: try!(reader.read_le_u32()),
+        loops: Vec::new(),
+        sampler_data: Vec::new(),
+    };
+    let num_loops = try!(reader.read_le_u32()) as usize;
+    let sampler_data_len = try!(reader.read_le_u32()) as usize;
+    if num_loops > reader.len() / 24 {
+        let msg = format!("smpl chunk of {} bytes is too short for {} loops", data.len(), num_loops);
+        return Err(Error::FormatError(msg));
+    }
+    for _ in 0..num_loops {
+        info.loops.push(SampleLoop {
+            id: try!(reader.read_le_u32()),
+            loop_type: try!(reader.read_le_u32()),
+            start: try!(reader.read_le_u32()),
+            end: try!(reader.read_le_u32()),
+            fraction: try!(reader.read_le_u32()),
+            play_count: try!(reader.read_le_u32()),
+        });
+    }
+    // Some writers get the sampler data length wrong, so take what is there.
+    let sampler_data_len = cmp::min(sampler_data_len, reader.len());
+    info.sampler_data = reader[..sampler_data_len].to_vec();
+    Ok(info)
+}
+
+/// Encodes the payload of a `smpl` chunk.
+pub fn serialize_smpl(info: &SamplerInfo) -> Vec<u8> {
+    let mut out = Vec::with_capacity(36 + info.loops.len() * 24 + info.sampler_data.len());
+    push_le_u32(&mut out, info.manufacturer);
+    push_le_u32(&mut out, info.product);
+    push_le_u32(&mut out, info.sample_period);
+    push_le_u32(&mut out, info.midi_unity_note);
+    push_le_u32(&mut out, info.midi_pitch_fraction);
+    push_le_u32(&mut out, info.smpte_format);
+    push_le_u32(&mut out, info.smpte_offset);
+    push_le_u32(&mut out, info.loops.len() as u32);
+    push_le_u32(&mut out, info.sampler_data.len() as u32);
+    for l in &info.loops {
+        push_le_u32(&mut out, l.id);
+        push_le_u32(&mut out, l.loop_type);
+        push_le_u32(&mut out, l.start);
+        push_le_u32(&mut out, l.end);
+        push_le_u32(&mut out, l.fraction);
+        push_le_u32(&mut out, l.play_count);
+    }
+    out.extend_from_slice(&info.sampler_data);
+    out
+}
+
+/// Loudness values of a version 2 `bext` chunk, as defined in EBU R 128.
+///
+/// All values are multiplied by 100, so -2305 means -23.05 LUFS (or LU, or
+/// dBTP for the true peak level).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Loudness {
+    /// Integrated loudness, in LUFS.
+    pub loudness_value: i16,
+    /// Loudness range, in LU.
+    pub loudness_range: i16,
+    /// Maximum true peak level, in dBTP.
+    pub max_true_peak_level: i16,
+    /// Highest value of the momentary loudness, in LUFS.
+    pub max_momentary_loudness: i16,
+    /// Highest value of the short-term loudness, in LUFS.
+    pub max_short_term_loudness: i16,
+}
+
+/// The contents of a `bext` chunk, as used by the Broadcast Wave Format.
+///
+/// The text fields have a fixed size in the file. When reading, they are
+/// decoded up to the first NUL byte, and trailing spaces are removed. The
+/// fields should contain ASCII only; other bytes that are not valid UTF-8 are
+/// replaced with U+FFFD.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BroadcastExtension {
+    /// Description of the sound sequence, at most 256 characters.
+    pub description: String,
+    /// Name of the originator, at most 32 characters.
+    pub originator: String,
+    /// Reference of the originator, at most 32 characters.
+    pub originator_reference: String,
+    /// Date of creation, formatted as `yyyy-mm-dd`.
+    pub origination_date: String,
+    /// Time of creation, formatted as `hh:mm:ss`.
+    pub origination_time: String,
+    /// Position of the first sample since midnight, in samples.
+    pub time_reference: u64,
+    /// Version of the chunk: 0, 1 or 2.
+    pub version: u16,
+    /// SMPTE UMID of 64 bytes, empty before version 1 or if not present.
+    pub umid: Vec<u8>,
+    /// Loudness values, only present from version 2 on.
+    pub loudness: Option<Loudness>,
+    /// The coding history, lines of free-form text terminated by CR LF.
+    pub coding_history: String,
+}
+
+/// Decodes a fixed-size text field: up to the first NUL, without trailing spaces.
+fn decode_fixed_str(bytes: &[u8]) -> String {
+    decode_zstr(bytes).trim_end_matches(' ').to_string()
+}
+
+/// Decodes the payload of a `bext` chunk.
+///
+/// The fields that were added in later versions of the chunk are only read
+/// when the version and the size of the chunk indicate that they are present.
+pub fn parse_bext(data: &[u8]) -> Result<BroadcastExtension> {
+    // Offsets of the fields, as defined in EBU Tech 3285.
+    const UMID: usize = 348;
+    const LOUDNESS: usize = 412;
+    const CODING_HISTORY: usize = 602;
+    if data.len() < UMID {
+        return Err(Error::FormatError("bext chunk is too short".to_string()));
+    }
+    let mut reader = &data[338..UMID];
+    let time_reference_low = try!(reader.read_le_u32()) as u64;
+    let time_reference_high = try!(reader.read_le_u32()) as u64;
+    let version = try!(reader.read_le_u16());
+    let mut bext = BroadcastExtension {
+        description: decode_fixed_str(&data[0..256]),
+        originator: decode_fixed_str(&data[256..288]),
+        originator_reference: decode_fixed_str(&data[288..320]),
+        origination_date: decode_fixed_str(&data[320..330]),
+        origination_time: decode_fixed_str(&data[330..338]),
+        time_reference: time_reference_high << 32 | time_reference_low,
+        version: version,
+        umid: Vec::new(),
+        loudness: None,
+        coding_history: String::new(),
+    };
+    if version >= 1 && data.len() >= LOUDNESS {
+        bext.umid = data[UMID..LOUDNESS].to_vec();
+    }
+    if version >= 2 && data.len() >= LOUDNESS + 10 {
+        let mut reader = &data[LOUDNESS..LOUDNESS + 10];
+        bext.loudness = Some(Loudness {
+            loudness_value: try!(reader.read_le_i16()),
+            loudness_range: try!(reader.read_le_i16()),
+            max_true_peak_level: try!(reader.read_le_i16()),
+            max_momentary_loudness: try!(reader.read_le_i16()),
+            max_short_term_loudness: try!(reader.read_le_i16()),
+        });
+    }
+    if data.len() > CODING_HISTORY {
+        bext.coding_history = decode_zstr(&data[CODING_HISTORY..]);
+    }
+    Ok(bext)
+}
+
+/// The contents of an `acid` chunk, with tempo information for loops.
+///
+/// Unknown flag bits and the fields without a known meaning are preserved
+/// when the chunk is written back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AcidInfo {
+    /// Flags: 0x01 one-shot (else loop), 0x02 root note set, 0x04 stretch,
+    /// 0x08 disk based, 0x10 high octave.
+    pub flags: u32,
+    /// The MIDI root note, 60 is middle C.
+    pub root_note: u16,
+    /// Unknown, usually 0x8000.
+    pub reserved1: u16,
+    /// Unknown, usually 0.
+    pub reserved2: f32,
+    /// The number of beats in the file.
+    pub num_beats: u32,
+    /// The denominator of the meter, e.g. 4 for 3/4.
+    pub meter_denominator: u16,
+    /// The numerator of the meter, e.g. 3 for 3/4.
+    pub meter_numerator: u16,
+    /// The tempo in beats per minute.
+    pub tempo: f32,
+}
+
+impl AcidInfo {
+    /// Returns whether the file is a one-shot, rather than a loop.
+    pub fn is_one_shot(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Returns whether `root_note` is valid.
+    pub fn has_root_note(&self) -> bool {
+        self.flags & 0x02 != 0
+    }
+
+    /// Returns whether the file should be time-stretched to the project tempo.
+    pub fn is_stretched(&self) -> bool {
+        self.flags & 0x04 != 0
+    }
+}
+
+/// Decodes the payload of an `acid` chunk.
+pub fn parse_acid(data: &[u8]) -> Result<AcidInfo> {
+    if data.len() < 24 {
+        return Err(Error::FormatError("acid chunk is too short".to_string()));
+    }
+    let mut reader = data;
+    Ok(AcidInfo {
+        flags: try!(reader.read_le_u32()),
+        root_note: try!(reader.read_le_u16()),
+        reserved1: try!(reader.read_le_u16()),
+        reserved2: try!(reader.read_le_f32()),
+        num_beats: try!(reader.read_le_u32()),
+        meter_denominator: try!(reader.read_le_u16()),
+        meter_numerator: try!(reader.read_le_u16()),
+        tempo: try!(reader.read_le_f32()),
+    })
+}
+
+/// Encodes the payload of an `acid` chunk.
+pub fn serialize_acid(acid: &AcidInfo) -> Vec<u8> {
+    let mut out = Vec::with_capacity(24);
+    push_le_u32(&mut out, acid.flags);
+    push_le_u32(&mut out, acid.root_note as u32 | (acid.reserved1 as u32) << 16);
+    push_le_u32(&mut out, acid.reserved2.to_bits());
+    push_le_u32(&mut out, acid.num_beats);
+    push_le_u32(&mut out, acid.meter_denominator as u32 | (acid.meter_numerator as u32) << 16);
+    push_le_u32(&mut out, acid.tempo.to_bits());
+    out
+}
+
+/// The contents of an `inst` chunk, describing how samplers play the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstrumentInfo {
+    /// The MIDI note at which the file plays at its original pitch, 0 to 127.
+    pub unshifted_note: u8,
+    /// The pitch shift in cents, -50 to 50.
+    pub fine_tune: i8,
+    /// The gain in decibels, -64 to 64.
+    pub gain: i8,
+    /// The lowest MIDI note of the key range, 0 to 127.
+    pub low_note: u8,
+    /// The highest MIDI note of the key range, 0 to 127.
+    pub high_note: u8,
+    /// The lowest MIDI velocity of the velocity range, 1 to 127.
+    pub low_velocity: u8,
+    /// The highest MIDI velocity of the velocity range, 1 to 127.
+    pub high_velocity: u8,
+}
+
+/// Decodes the payload of an `inst` chunk.
+pub fn parse_inst(data: &[u8]) -> Result<InstrumentInfo> {
+    if data.len() < 7 {
+        return Err(Error::FormatError("inst chunk is too short".to_string()));
+    }
+    Ok(InstrumentInfo {
+        unshifted_note: data[0],
+        fine_tune: data[1] as i8,
+        gain: data[2] as i8,
+        low_note: data[3],
+        high_note: data[4],
+        low_velocity: data[5],
+        high_velocity: data[6],
+    })
+}
+
+/// Encodes the payload of an `inst` chunk, checking that all values are in range.
+pub fn serialize_inst(inst: &InstrumentInfo) -> Result<Vec<u8>> {
+    let notes = [inst.unshifted_note, inst.low_note, inst.high_note];
+    if notes.iter().any(|&n| n > 127) || inst.low_note > inst.high_note {
+        return Err(Error::FormatError("invalid note in inst chunk".to_string()));
+    }
+    if inst.low_velocity < 1 || inst.high_velocity > 127 ||
+       inst.low_velocity > inst.high_velocity {
+        return Err(Error::FormatError("invalid velocity in inst chunk".to_string()));
+    }
+    if inst.fine_tune < -50 || inst.fine_tune > 50 {
+        return Err(Error::FormatError("fine tune in inst chunk is out of range".to_string()));
+    }
+    if inst.gain < -64 || inst.gain > 64 {
+        return Err(Error::FormatError("gain in inst chunk is out of range".to_string()));
+    }
+    Ok(vec![inst.unshifted_note,
+            inst.fine_tune as u8,
+            inst.gain as u8,
+            inst.low_note,
+            inst.high_note,
+            inst.low_velocity,
+            inst.high_velocity])
+}
+
+/// The peak of one channel, as stored in a `PEAK` chunk.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelPeak {
+    /// The absolute peak value, 1.0 is full scale.
+    pub value: f32,
+    /// The frame at which the peak occurs.
+    pub position: u32,
+}
+
+/// The contents of a `PEAK` chunk, with the peak amplitude of every channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeakInfo {
+    /// The version of the chunk, usually 1.
+    pub version: u32,
+    /// The time at which the peaks were determined, in seconds since 1970.
+    pub timestamp: u32,
+    /// The peak of every channel, indexed by channel.
+    pub peaks: Vec<ChannelPeak>,
+}
+
+/// Decodes the payload of a `PEAK` chunk for a file with `channels` channels.
+pub fn parse_peak(data: &[u8], channels: u16) -> Result<PeakInfo> {
+    if data.len() != 8 + 8 * channels as usize {
+        let msg = format!("PEAK chunk of {} bytes does not match {} channels", data.len(), channels);
+        return Err(Error::FormatError(msg));
+    }
+    let mut reader = data;
+    let version = try!(reader.read_le_u32());
+    let timestamp = try!(reader.read_le_u32());
+    let mut peaks = Vec::with_capacity(channels as usize);
+    for _ in 0..channels {
+        peaks.push(ChannelPeak {
+            value: try!(reader.read_le_f32()),
+            position: try!(reader.read_le_u32()),
+        });
+    }
+    Ok(PeakInfo {
+        version: version,
+        timestamp: timestamp,
+        peaks: peaks,
+    })
+}
+
+/// Encodes the payload of a `PEAK` chunk.
+pub fn serialize_peak(info: &PeakInfo) -> Vec<u8> {
+    let mut data = Vec::with_capacity(8 + 8 * info.peaks.len());
+    data.extend_from_slice(&info.version.to_le_bytes());
+    data.extend_from_slice(&info.timestamp.to_le_bytes());
+    for peak in &info.peaks {
+        data.extend_from_slice(&peak.value.to_bits().to_le_bytes());
+        data.extend_from_slice(&peak.position.to_le_bytes());
+    }
+    data
+}
+
+/// Appends `text` to `out`, padded with zeros to `len` bytes.
+fn push_fixed_str(out: &mut Vec<u8>, text: &str, len: usize) -> Result<()> {
+    if text.len() > len {
+        let msg = format!("text of {} bytes does not fit in fixed-size field of {} bytes",
+                          text.len(), len);
+        return Err(Error::FormatError(msg));
+    }
+    out.extend_from_slice(text.as_bytes());
+    out.extend((text.len()..len).map(|_| 0));
+    Ok(())
+}
+
+/// Encodes the payload of a `bext` chunk.
+///
+/// Returns an error if a text field does not fit, or if the fields are not
+/// valid for the version of the chunk.
+pub fn serialize_bext(bext: &BroadcastExtension) -> Result<Vec<u8>> {
+    if !bext.umid.is_empty() && (bext.umid.len() != 64 || bext.version < 1) {
+        return Err(Error::FormatError("bext UMID must be 64 bytes and needs version 1".to_string()));
+    }
+    if bext.loudness.is_some() && bext.version < 2 {
+        return Err(Error::FormatError("bext loudness values need version 2".to_string()));
+    }
+    let mut out = Vec::with_capacity(602 + bext.coding_history.len());
+    try!(push_fixed_str(&mut out, &bext.description, 256));
+    try!(push_fixed_str(&mut out, &bext.originator, 32));
+    try!(push_fixed_str(&mut out, &bext.originator_reference, 32));
+    try!(push_fixed_str(&mut out, &bext.origination_date, 10));
+    try!(push_fixed_str(&mut out, &bext.origination_time, 8));
+    push_le_u32(&mut out, bext.time_reference as u32);
+    push_le_u32(&mut out, (bext.time_reference >> 32) as u32);
+    out.push(bext.version as u8);
+    out.push((bext.version >> 8) as u8);
+    out.extend_from_slice(&bext.umid);
+    out.resize(412, 0);
+    if let Some(loudness) = bext.loudness {
+        let values = [
+            loudness.loudness_value,
+            loudness.loudness_range,
+            loudness.max_true_peak_level,
+            loudness.max_momentary_loudness,
+            loudness.max_short_term_loudness,
+        ];
+        for &x in &values {
+            out.push(x as u8);
+            out.push((x >> 8) as u8);
+        }
+    }
+    // The remainder of the fixed part is reserved.
+    out.resize(602, 0);
+    out.extend_from_slice(bext.coding_history.as_bytes());
+    Ok(out)
+}
+
+/// A timer marker in a `cart` chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CartTimer {
+    /// The four-character code of the timer, such as `SEG1` or `INTs`.
+    pub usage: [u8; 4],
+    /// The position of the timer, in samples from the start of the data.
+    pub value: u32,
+}
+
+/// The contents of a `cart` chunk, used by radio automation systems (AES46).
+///
+/// All text fields have a fixed size in the chunk. Dates are formatted as
+/// `yyyy-mm-dd` and times as `hh:mm:ss`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cart {
+    /// The version of the chunk, four digits such as `0101`, fixed size 4.
+    pub version: String,
+    /// The title of the cut, fixed size 64.
+    pub title: String,
+    /// The artist, fixed size 64.
+    pub artist: String,
+    /// The identifier of the cut, fixed size 64.
+    pub cut_id: String,
+    /// The identifier of the client, fixed size 64.
+    pub client_id: String,
+    /// The category, fixed size 64.
+    pub category: String,
+    /// The classification, fixed size 64.
+    pub classification: String,
+    /// The text of the out cue, fixed size 64.
+    pub out_cue: String,
+    /// The date from which the cut may be played, fixed size 10.
+    pub start_date: String,
+    /// The time from which the cut may be played, fixed size 8.
+    pub start_time: String,
+    /// The date until which the cut may be played, fixed size 10.
+    pub end_date: String,
+    /// The time until which the cut may be played, fixed size 8.
+    pub end_time: String,
+    /// The application that produced the file, fixed size 64.
+    pub producer_app_id: String,
+    /// The version of that application, fixed size 64.
+    pub producer_app_version: String,
+    /// User defined text, fixed size 64.
+    pub user_def: String,
+    /// The sample value of 0 dB reference level.
+    pub level_reference: i32,
+    /// The timer markers, at most 8.
+    pub timers: Vec<CartTimer>,
+    /// A URL with more information, fixed size 1024.
+    pub url: String,
+    /// Free form text that follows the fixed part of the chunk.
+    pub tag_text: String,
+}
+
+/// Decodes the payload of a `cart` chunk.
+pub fn parse_cart(data: &[u8]) -> Result<Cart> {
+    // Offsets of the fields, as defined in AES46-2002.
+    const LEVEL_REFERENCE: usize = 680;
+    const POST_TIMER: usize = 684;
+    const URL: usize = 1024;
+    const TAG_TEXT: usize = 2048;
+    if data.len() < TAG_TEXT {
+        return Err(Error::FormatError("cart chunk is too short".to_string()));
+    }
+    let mut reader = &data[LEVEL_REFERENCE..URL];
+    let level_reference = try!(reader.read_le_i32());
+    let mut timers = Vec::new();
+    for i in 0..8 {
+        let at = POST_TIMER + i * 8;
+        let usage = [data[at], data[at + 1], data[at + 2], data[at + 3]];
+        let mut reader = &data[at + 4..at + 8];
+        let value = try!(reader.read_le_u32());
+        // Unused timers have no usage code.
+        if usage != [0; 4] {
+            timers.push(CartTimer { usage: usage, value: value });
+        }
+    }
+    Ok(Cart {
+        version: decode_fixed_str(&data[0..4]),
+        title: decode_fixed_str(&data[4..68]),
+        artist: decode_fixed_str(&data[68..132]),
+        cut_id: decode_fixed_str(&data[132..196]),
+        client_id: decode_fixed_str(&data[196..260]),
+        category: decode_fixed_str(&data[260..324]),
+        classification: decode_fixed_str(&data[324..388]),
+        out_cue: decode_fixed_str(&data[388..452]),
+        start_date: decode_fixed_str(&data[452..462]),
+        start_time: decode_fixed_str(&data[462..470]),
+        end_date: decode_fixed_str(&data[470..480]),
+        end_time: decode_fixed_str(&data[480..488]),
+        producer_app_id: decode_fixed_str(&data[488..552]),
+        producer_app_version: decode_fixed_str(&data[552..616]),
+        user_def: decode_fixed_str(&data[616..680]),
+        level_reference: level_reference,
+        timers: timers,
+        url: decode_fixed_str(&data[URL..TAG_TEXT]),
+        tag_text: decode_zstr(&data[TAG_TEXT..]),
+    })
+}
+
+/// Encodes the payload of a `cart` chunk.
+///
+/// Returns an error if a text field does not fit, or if there are more than
+/// 8 timers.
+pub fn serialize_cart(cart: &Cart) -> Result<Vec<u8>> {
+    if cart.timers.len() > 8 {
+        return Err(Error::FormatError("cart chunk holds at most 8 timers".to_string()));
+    }
+    let mut out = Vec::with_capacity(2048 + cart.tag_text.len());
+    let fields = [
+        (&cart.version, 4),
+        (&cart.title, 64),
+        (&cart.artist, 64),
+        (&cart.cut_id, 64),
+        (&cart.client_id, 64),
+        (&cart.category, 64),
+        (&cart.classification, 64),
+        (&cart.out_cue, 64),
+        (&cart.start_date, 10),
+        (&cart.start_time, 8),
+        (&cart.end_date, 10),
+        (&cart.end_time, 8),
+        (&cart.producer_app_id, 64),
+        (&cart.producer_app_version, 64),
+        (&cart.user_def, 64),
+    ];
+    for &(text, len) in &fields {
+        try!(push_fixed_str(&mut out, text, len));
+    }
+    push_le_u32(&mut out, cart.level_reference as u32);
+    for timer in &cart.timers {
+        out.extend_from_slice(&timer.usage);
+        push_le_u32(&mut out, timer.value);
+    }
+    // Unused timers and the reserved bytes are zero.
+    out.resize(1024, 0);
+    try!(push_fixed_str(&mut out, &cart.url, 1024));
+    out.extend_from_slice(cart.tag_text.as_bytes());
+    Ok(out)
+}
+
+/// Decodes the title in a `DISP` chunk.
+///
+/// Returns `Ok(None)` if the chunk holds something other than text, such as a
+/// bitmap. Invalid UTF-8 is replaced, the raw bytes are available through the
+/// chunk itself.
+pub fn parse_disp(data: &[u8]) -> Result<Option<String>> {
+    // The clipboard format of the payload, 1 is CF_TEXT.
+    const CF_TEXT: u32 = 1;
+    if data.len() < 4 {
+        return Err(Error::FormatError("DISP chunk is too short".to_string()));
+    }
+    let kind = try!((&data[..4]).read_le_u32());
+    if kind == CF_TEXT {
+        Ok(Some(decode_zstr(&data[4..])))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Encodes a title as the payload of a `DISP` chunk.
+pub fn serialize_disp(title: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(5 + title.len());
+    push_le_u32(&mut out, 1);
+    out.extend_from_slice(title.as_bytes());
+    out.push(0);
+    out
+}
+
+/// The contents of a `levl` chunk, a peak envelope for drawing waveforms.
+///
+/// The chunk is defined in supplement 3 of EBU Tech 3285. Peak values are
+/// unsigned magnitudes, the negative peak is stored as its absolute value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeakEnvelope {
+    /// The version of the chunk, 1.
+    pub version: u32,
+    /// The format of a peak value: 1 for 8-bit, 2 for 16-bit values.
+    pub format: u32,
+    /// 1 if only the positive peak is stored, 2 for the positive and negative peak.
+    pub points_per_value: u32,
+    /// The number of frames that a peak value covers.
+    pub block_size: u32,
+    /// The number of channels.
+    pub channels: u32,
+    /// The frame of the largest peak, or `0xffffffff` if unknown.
+    pub peak_of_peaks: u32,
+    /// The time at which the envelope was made, formatted as `yyyy:mm:dd:hh:mm:ss:uuu`.
+    pub timestamp: String,
+    /// The peak values of every block, for every channel in turn, with the
+    /// positive peak before the negative peak.
+    pub peaks: Vec<u16>,
+}
+
+impl PeakEnvelope {
+    /// Returns the number of blocks that the envelope holds peaks for.
+    pub fn num_peak_frames(&self) -> u32 {
+        let per_frame = self.channels as usize * self.points_per_value as usize;
+        self.peaks.len().checked_div(per_frame).unwrap_or(0) as u32
+    }
+}
+
+/// Decodes the payload of a `levl` chunk.
+pub fn parse_levl(data: &[u8]) -> Result<PeakEnvelope> {
+    if data.len() < 120 {
+        return Err(Error::FormatError("levl chunk is too short".to_string()));
+    }
+    let mut reader = data;
+    let version = try!(reader.read_le_u32());
+    let format = try!(reader.read_le_u32());
+    let points_per_value = try!(reader.read_le_u32());
+    let block_size = try!(reader.read_le_u32());
+    let channels = try!(reader.read_le_u32());
+    let num_peak_frames = try!(reader.read_le_u32());
+    let peak_of_peaks = try!(reader.read_le_u32());
+    // The offset is relative to the start of the chunk header.
+    let offset_to_peaks = try!(reader.read_le_u32()) as usize;
+    let value_len = match format {
+        1 => 1,
+        2 => 2,
+        _ => return Err(Error::FormatError(format!("unknown levl peak format {}", format))),
+    };
+    if offset_to_peaks < 128 || offset_to_peaks - 8 > data.len() {
+        let msg = format!("invalid offset {} to levl peaks", offset_to_peaks);
+        return Err(Error::FormatError(msg));
+    }
+    let mut reader = &data[offset_to_peaks - 8..];
+    let num_values = num_peak_frames as u64 * channels as u64 * points_per_value as u64;
+    if num_values > (reader.len() / value_len) as u64 {
+        return Err(Error::FormatError("levl chunk is too short for its peaks".to_string()));
+    }
+    let mut peaks = Vec::with_capacity(num_values as usize);
+    for _ in 0..num_values {
+        peaks.push(if value_len == 1 {
+            try!(reader.read_u8()) as u16
+        } else {
+            try!(reader.read_le_u16())
+        });
+    }
+    Ok(PeakEnvelope {
+        version: version,
+        format: format,
+        points_per_value: points_per_value,
+        block_size: block_size,
+        channels: channels,
+        peak_of_peaks: peak_of_peaks,
+        timestamp: decode_fixed_str(&data[32..60]),
+        peaks: peaks,
+    })
+}
+
+/// Encodes the payload of a `levl` chunk.
+///
+/// Returns an error for an unknown format, a timestamp that does not fit, or
+/// peaks that do not fit the format.
+pub fn serialize_levl(envelope: &PeakEnvelope) -> Result<Vec<u8>> {
+    let per_frame = envelope.channels as usize * envelope.points_per_value as usize;
+    if per_frame == 0 || envelope.num_peak_frames() as usize * per_frame != envelope.peaks.len() {
+        return Err(Error::FormatError("levl peaks do not fill whole frames".to_string()));
+    }
+    let max = match envelope.format {
+        1 => 0xff,
+        2 => 0xffff,
+        _ => return Err(Error::FormatError("unknown levl peak format".to_string())),
+    };
+    if envelope.peaks.iter().any(|&p| p > max) {
+        return Err(Error::FormatError("levl peak does not fit in its format".to_string()));
+    }
+    let mut out = Vec::with_capacity(120 + envelope.peaks.len() * 2);
+    push_le_u32(&mut out, envelope.version);
+    push_le_u32(&mut out, envelope.format);
+    push_le_u32(&mut out, envelope.points_per_value);
+    push_le_u32(&mut out, envelope.block_size);
+    push_le_u32(&mut out, envelope.channels);
+    push_le_u32(&mut out, envelope.num_peak_frames());
+    push_le_u32(&mut out, envelope.peak_of_peaks);
+    // The header, including the chunk id and size, is 128 bytes.
+    push_le_u32(&mut out, 128);
+    try!(push_fixed_str(&mut out, &envelope.timestamp, 28));
+    // Reserved.
+    out.resize(120, 0);
+    for &peak in &envelope.peaks {
+        if envelope.format == 1 {
+            out.push(peak as u8);
+        } else {
+            out.push(peak as u8);
+            out.push((peak >> 8) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// One row of a `chna` chunk, which maps a track to ADM metadata.
+///
+/// The chunk is defined in ITU-R BS.2088. The ids refer to elements in the
+/// ADM XML document, which is stored in the `axml` chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AdmTrack {
+    /// The index of the track in the data chunk, starting at 1.
+    pub track_index: u16,
+    /// The `audioTrackUID` of the track, 12 characters.
+    pub uid: String,
+    /// The `audioTrackFormatID` or `audioChannelFormatID`, 14 characters.
+    pub track_ref: String,
+    /// The `audioPackFormatID`, 11 characters.
+    pub pack_ref: String,
+}
+
+/// Decodes the payload of a `chna` chunk.
+///
+/// Rows with track index 0 are unused space reserved by the writer, these
+/// are skipped.
+pub fn parse_chna(data: &[u8]) -> Result<Vec<AdmTrack>> {
+    if data.len() < 4 {
+        return Err(Error::FormatError("chna chunk is too short".to_string()));
+    }
+    let num_uids = try!((&data[2..4]).read_le_u16()) as usize;
+    let mut tracks = Vec::with_capacity(cmp::min(num_uids, data.len() / 40));
+    for row in data[4..].chunks(40) {
+        if row.len() < 40 {
+            return Err(Error::FormatError("chna chunk has a truncated row".to_string()));
+        }
+        let track_index = try!((&row[..2]).read_le_u16());
+        if track_index == 0 {
+            continue;
+        }
+        tracks.push(AdmTrack {
+            track_index: track_index,
+            uid: decode_zstr(&row[2..14]),
+            track_ref: decode_zstr(&row[14..28]),
+            pack_ref: decode_zstr(&row[28..39]),
+        });
+    }
+    if tracks.len() != num_uids {
+        let msg = format!("chna chunk has {} rows, but its header says {}", tracks.len(), num_uids);
+        return Err(Error::FormatError(msg));
+    }
+    Ok(tracks)
+}
+
+/// Encodes the payload of a `chna` chunk.
+///
+/// Returns an error if an id does not fit, or if a track index is 0.
+pub fn serialize_chna(tracks: &[AdmTrack]) -> Result<Vec<u8>> {
+    if tracks.len() > u16::MAX as usize {
+        return Err(Error::FormatError("too many rows for chna chunk".to_string()));
+    }
+    let mut indices: Vec<u16> = tracks.iter().map(|t| t.track_index).collect();
+    indices.sort();
+    indices.dedup();
+    let mut out = Vec::with_capacity(4 + tracks.len() * 40);
+    push_le_u16(&mut out, indices.len() as u16);
+    push_le_u16(&mut out, tracks.len() as u16);
+    for track in tracks {
+        if track.track_index == 0 {
+            return Err(Error::FormatError("chna track index must be positive".to_string()));
+        }
+        push_le_u16(&mut out, track.track_index);
+        try!(push_fixed_str(&mut out, &track.uid, 12));
+        try!(push_fixed_str(&mut out, &track.track_ref, 14));
+        try!(push_fixed_str(&mut out, &track.pack_ref, 11));
+        // Padding.
+        out.push(0);
+    }
+    Ok(out)
+}
+
+/// Looks up the first `LIST` chunk of the given list type.
+pub fn find_list(chunks: &[RawChunk], kind: [u8; 4]) -> Option<&RawChunk> {
+    chunks.iter().find(|c| &c.id == b"LIST" && list_type(&c.data) == Some(kind))
+}
+
+#[test]
+fn parse_list_info_handles_padding_and_terminators() {
+    let data = b"INFO\
+INAM\x04\x00\x00\x00abc\x00\
+ICMT\x03\x00\x00\x00odd\x00\
+IXYZ\x02\x00\x00\x00zz";
+    let info = parse_list_info(&data[..]).unwrap();
+    assert_eq!(info.title(), Some("abc"));
+    assert_eq!(info.comment(), Some("odd"));
+    // The last sub-chunk lacks both its NUL terminator and the pad byte.
+    assert_eq!(info.get(*b"IXYZ"), Some("zz"));
+    assert_eq!(info.entries().len(), 3);
+    assert_eq!(info.artist(), None);
+}
+
+#[test]
+fn serialize_list_info_roundtrips() {
+    let mut info = ListInfo::new();
+    info.set_title("Sine");
+    info.set_artist("Hound");
+    info.set_software("hound");
+    info.set_title("Sine wave");
+    info.set(*b"IXYZ", "");
+    let data = serialize_list_info(&info);
+    // Every sub-chunk is padded to an even length.
+    assert_eq!(&data[..], &b"INFO\
+INAM\x0a\x00\x00\x00Sine wave\x00\
+IART\x06\x00\x00\x00Hound\x00\
+ISFT\x06\x00\x00\x00hound\x00\
+IXYZ\x01\x00\x00\x00\x00\x00"[..]);
+    assert_eq!(parse_list_info(&data).unwrap(), info);
+}
+
+#[test]
+fn parse_list_info_rejects_overlong_sub_chunk() {
+    let data = b"INFOINAM\x10\x00\x00\x00abc\x00";
+    assert!(parse_list_info(&data[..]).is_err());
+    assert!(parse_list_info(&b"adtl"[..]).is_err());
+}
+
+#[test]
+fn parse_cue_rejects_truncated_chunk() {
+    let data = b"\x02\x00\x00\x00\
+\x01\x00\x00\x00\x00\x00\x00\x00data\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00";
+    // Two cue points are announced, but only one is present.
+    assert!(parse_cue(&data[..]).is_err());
+    let mut one = data.to_vec();
+    one[0] = 1;
+    let points = parse_cue(&one).unwrap();
+    assert_eq!(points.len(), 1);
+    assert_eq!(points[0].id, 1);
+    assert_eq!(&points[0].data_chunk_id, b"data");
+}
+
+#[test]
+fn serialize_cue_and_adtl_roundtrip() {
+    let mut first = CuePoint::new(1, 10);
+    first.label = Some("Onset".to_string());
+    first.note = Some("strong".to_string());
+    let second = CuePoint::new(7, 3);
+    let points = vec![first.clone(), second.clone()];
+
+    assert_eq!(parse_cue(&serialize_cue(&points)).unwrap(), vec![
+        CuePoint { label: None, note: None, ..first },
+        second,
+    ]);
+    let adtl = serialize_adtl(&points).unwrap();
+    assert_eq!(&adtl[..], &b"adtl\
+labl\x0a\x00\x00\x00\x01\x00\x00\x00Onset\x00\
+note\x0b\x00\x00\x00\x01\x00\x00\x00strong\x00\x00"[..]);
+    assert_eq!(parse_adtl(&adtl).unwrap(), vec![
+        (*b"labl", 1, "Onset".to_string()),
+        (*b"note", 1, "strong".to_string()),
+    ]);
+    assert_eq!(serialize_adtl(&[CuePoint::new(1, 0)]), None);
+}
+
+#[test]
+fn parse_smpl_tolerates_short_sampler_data() {
+    let info = SamplerInfo {
+        manufacturer: 0x47,
+        midi_unity_note: 60,
+        loops: vec![SampleLoop {
+            id: 0,
+            loop_type: 0,
+            start: 10,
+            end: 99,
+            fraction: 0,
+            play_count: 0,
+        }],
+        sampler_data: vec![1, 2, 3, 4],
+        ..SamplerInfo::default()
+    };
+    let mut data = serialize_smpl(&info);
+    assert_eq!(data.len(), 36 + 24 + 4);
+    assert_eq!(parse_smpl(&data).unwrap(), info);
+
+    // Claim more sampler data than present.
+    data[32] = 8;
+    assert_eq!(parse_smpl(&data).unwrap(), info);
+    // Claim more loops than present.
+    data[28] = 2;
+    assert!(parse_smpl(&data).is_err());
+}
+
+#[test]
+fn parse_bext_respects_version() {
+    let mut data = vec![0; 602];
+    data[..4].copy_from_slice(b"desc");
+    data[256..262].copy_from_slice(b"hound ");
+    data[338] = 1;
+    data[342] = 2;
+    data[348] = 0xaa;
+    data[412] = 0xfe;
+    data[413] = 0xff;
+
+    // Version 0 has neither UMID nor loudness, even if the bytes are there.
+    let bext = parse_bext(&data).unwrap();
+    assert_eq!(bext.description, "desc");
+    assert_eq!(bext.originator, "hound");
+    assert_eq!(bext.time_reference, 0x2_0000_0001);
+    assert_eq!(bext.umid, Vec::new());
+    assert_eq!(bext.loudness, None);
+    assert_eq!(bext.coding_history, "");
+
+    data[346] = 2;
+    data.extend_from_slice(b"A=PCM,F=48000\r\n\0");
+    let bext = parse_bext(&data).unwrap();
+    assert_eq!(bext.version, 2);
+    assert_eq!(bext.umid.len(), 64);
+    assert_eq!(bext.umid[0], 0xaa);
+    assert_eq!(bext.loudness.unwrap().loudness_value, -2);
+    assert_eq!(bext.coding_history, "A=PCM,F=48000\r\n");
+
+    // A version 1 chunk that was cut short after the UMID.
+    data[346] = 1;
+    let bext = parse_bext(&data[..412]).unwrap();
+    assert_eq!(bext.umid.len(), 64);
+    assert_eq!(bext.loudness, None);
+    assert!(parse_bext(&data[..347]).is_err());
+}
+
+#[test]
+fn parse_bext_decodes_non_ascii_description_lossily() {
+    let mut data = vec![0; 602];
+    // Latin-1 "café", UTF-8 "naïve", and garbage after the first NUL.
+    data[..5].copy_from_slice(b"caf\xe9 ");
+    data[5..11].copy_from_slice("na\u{ef}ve".as_bytes());
+    data[12..15].copy_from_slice(b"\xff\xfe\xfd");
+    // A description that fills the field, without terminating NUL.
+    data[256..288].copy_from_slice(&[0xc3; 32]);
+    let bext = parse_bext(&data).unwrap();
+    assert_eq!(bext.description, "caf\u{fffd} na\u{ef}ve");
+    assert_eq!(bext.originator.chars().count(), 32);
+    assert!(bext.originator.chars().all(|c| c == '\u{fffd}'));
+    assert_eq!(bext.originator_reference, "");
+}
+
+#[test]
+fn parse_list_info_replaces_invalid_utf8() {
+    let data = b"INFOINAM\x04\x00\x00\x00a\xffb\x00";
+    let info = parse_list_info(&data[..]).unwrap();
+    assert_eq!(info.title(), Some("a\u{fffd}b"));
+}
+
+#[test]
+fn parse_peak_checks_channel_count() {
+    let mut data = Vec::new();
+    push_le_u32(&mut data, 1);
+    push_le_u32(&mut data, 0);
+    push_le_u32(&mut data, 0.5_f32.to_bits());
+    push_le_u32(&mut data, 7);
+    assert_eq!(parse_peak(&data, 1).unwrap().peaks,
+               vec![ChannelPeak { value: 0.5, position: 7 }]);
+    assert!(parse_peak(&data, 2).is_err());
+    assert!(parse_peak(&data[..12], 1).is_err());
+}
+
+#[test]
+fn serialize_cart_roundtrips_and_rejects_long_fields() {
+    let mut cart = Cart {
+        version: "0101".to_string(),
+        title: "Station ID".to_string(),
+        timers: vec![CartTimer { usage: *b"SEG1", value: 22050 }],
+        tag_text: "<tag/>\r\n".to_string(),
+        ..Cart::default()
+    };
+    let data = serialize_cart(&cart).unwrap();
+    assert_eq!(data.len(), 2048 + 8);
+    assert_eq!(&data[684..692], b"SEG1\x22\x56\x00\x00");
+    assert_eq!(parse_cart(&data).unwrap(), cart);
+    assert!(parse_cart(&data[..2047]).is_err());
+
+    cart.start_date = "2026-10-14T".to_string();
+    assert!(serialize_cart(&cart).is_err());
+    cart.start_date = String::new();
+    cart.timers = vec![CartTimer { usage: *b"MRK ", value: 0 }; 9];
+    assert!(serialize_cart(&cart).is_err());
+}
+
+#[test]
+fn serialize_levl_roundtrips() {
+    let mut envelope = PeakEnvelope {
+        version: 1,
+        format: 1,
+        points_per_value: 1,
+        block_size: 256,
+        channels: 2,
+        peak_of_peaks: 300,
+        timestamp: "2026:10:14:12:00:00:000".to_string(),
+        peaks: vec![1, 2, 200, 255],
+    };
+    let data = serialize_levl(&envelope).unwrap();
+    assert_eq!(data.len(), 124);
+    assert_eq!(&data[20..24], &[2, 0, 0, 0]);
+    assert_eq!(parse_levl(&data).unwrap(), envelope);
+    assert!(parse_levl(&data[..123]).is_err());
+
+    envelope.peaks.push(256);
+    assert!(serialize_levl(&envelope).is_err());
+    envelope.format = 2;
+    assert!(serialize_levl(&envelope).is_err());
+    envelope.peaks.push(2);
+    assert_eq!(parse_levl(&serialize_levl(&envelope).unwrap()).unwrap(), envelope);
+}
+
+#[test]
+fn serialize_chna_roundtrips() {
+    let tracks = vec![
+        AdmTrack {
+            track_index: 1,
+            uid: "ATU_00000001".to_string(),
+            track_ref: "AT_00010001_01".to_string(),
+            pack_ref: "AP_00010002".to_string(),
+        },
+        AdmTrack {
+            track_index: 2,
+            uid: "ATU_00000002".to_string(),
+            track_ref: "AT_00010002_01".to_string(),
+            pack_ref: "AP_00010002".to_string(),
+        },
+    ];
+    let mut data = serialize_chna(&tracks).unwrap();
+    assert_eq!(data.len(), 84);
+    assert_eq!(&data[..4], &[2, 0, 2, 0]);
+    assert_eq!(parse_chna(&data).unwrap(), tracks);
+
+    // Reserved rows are skipped.
+    data.extend((0..40).map(|_| 0));
+    assert_eq!(parse_chna(&data).unwrap(), tracks);
+    assert!(parse_chna(&data[..83]).is_err());
+}
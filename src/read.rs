@@ -13,10 +13,44 @@
 use std::cmp;
 use std::fs;
 use std::io;
-use std::marker;
 use std::mem;
+use std::marker;
 use std::path;
-use super::{Error, Result, Sample, SampleFormat, WavSpec};
+use std::time;
+use std::vec;
+use super::{Average, Error, Result, Sample, SampleFormat, SpeakerPosition, UnsupportedFormat, WavSpec};
+use adpcm;
+use md5::Md5;
+use metadata;
+use metadata::{AcidInfo, AdmTrack, BroadcastExtension, Cart, CuePoint, InstrumentInfo, ListInfo,
+               PeakEnvelope, PeakInfo, PlaylistSegment, RawChunk, SamplerInfo};
+
+/// A source that can both read and seek, for use as a trait object.
+///
+/// A trait object can have only one non-auto trait, so `dyn io::Read + io::Seek`
+/// is not a valid type. This trait combines the two, and it is implemented for
+/// every type that implements both. A `WavReader<Box<dyn ReadSeek>>` can hold
+/// readers over sources of different types, and still `seek()`:
+///
+/// ```
+/// use std::fs;
+/// use std::io;
+/// use hound::{ReadSeek, WavReader};
+///
+/// let file = fs::File::open("testsamples/pop.wav").unwrap();
+/// let bytes = fs::read("testsamples/pop.wav").unwrap();
+/// let sources: Vec<Box<dyn ReadSeek>> = vec![
+///     Box::new(io::BufReader::new(file)),
+///     Box::new(io::Cursor::new(bytes)),
+/// ];
+/// for source in sources {
+///     let mut reader = WavReader::new_seekable(source).unwrap();
+///     reader.seek(1).unwrap();
+/// }
+/// ```
+pub trait ReadSeek: io::Read + io::Seek {}
+
+impl<T: io::Read + io::Seek + ?Sized> ReadSeek for T {}
 
 /// Extends the functionality of `io::Read` with additional methods.
 ///
@@ -194,6 +228,29 @@ impl<R> ReadExt for R
     }
 }
 
+#[test]
+fn read_le_i24_extends_sign_at_boundary() {
+    // Bytes are little endian: the most significant byte comes last.
+    let cases: &[([u8; 3], i32)] = &[
+        ([0x00, 0x00, 0x00], 0),
+        ([0xff, 0xff, 0x7f], 8_388_607),
+        ([0x00, 0x00, 0x80], -8_388_608),
+        ([0xff, 0xff, 0xff], -1),
+    ];
+    for &(bytes, expected) in cases {
+        let mut reader = &bytes[..];
+        assert_eq!(reader.read_le_i24().unwrap(), expected);
+        assert!(reader.is_empty());
+
+        // In a four-byte container, the padding byte is the least significant.
+        let padded = [bytes[0], bytes[1], bytes[2], 0x00];
+        assert_eq!((&padded[..]).read_le_i24_4().unwrap(), expected);
+    }
+}
+
+/// The data chunk length that streaming writers use when the length is unknown.
+const UNKNOWN_DATA_LEN: u64 = 0xffff_ffff;
+
 #[derive(Copy, Clone, Debug)]
 pub struct ChunkReadingState {
     /// total length of the data chunk, in bytes
@@ -234,6 +291,237 @@ impl ChunkReadingState {
     }
 }
 
+/// Prefixes a format error with the id of the chunk and the offset of its header.
+fn chunk_error(id: &[u8; 4], offset: u64, err: Error) -> Error {
+    match err {
+        Error::FormatError(msg) => {
+            let msg = format!("malformed chunk '{}' at offset {}: {}", String::from_utf8_lossy(id), offset, msg);
+            Error::FormatError(msg)
+        }
+        other => other,
+    }
+}
+
+/// Returns an error if a chunk of `len` bytes must not be read into memory.
+///
+/// The length is compared with `max_chunk_bytes` of the options, and with the
+/// number of bytes that are `available` in the source, if that is known, so a
+/// corrupt size does not cause a huge allocation.
+fn check_chunk_len(len: u64, available: Option<u64>, options: &ReadOptions) -> Result<()> {
+    if let Some(max) = options.max_chunk_bytes {
+        if len > max {
+            let msg = format!("chunk of {} bytes exceeds the limit of {} bytes", len, max);
+            return Err(Error::FormatError(msg));
+        }
+    }
+    if let Some(available) = available {
+        if len > available {
+            let msg = format!("chunk of {} bytes exceeds the {} bytes left in the source", len, available);
+            return Err(Error::FormatError(msg));
+        }
+    }
+    Ok(())
+}
+
+/// A run of the samples of a `LIST` chunk of type `wavl`.
+#[derive(Clone, Debug)]
+struct WavlSegment {
+    /// the position of the first byte in the expanded samples
+    start: u64,
+    /// the number of bytes
+    len: u64,
+    /// the offset of the bytes in the list, or `None` for silence
+    source: Option<usize>,
+}
+
+/// The samples of a `LIST` chunk of type `wavl`, which are read from memory.
+///
+/// The list holds `data` chunks with samples, and `slnt` chunks with the
+/// number of frames of silence between them. The silence is not stored,
+/// but produced when it is read.
+#[derive(Clone, Debug)]
+struct Wavl {
+    /// the contents of the list, after the list type
+    bytes: Vec<u8>,
+    segments: Vec<WavlSegment>,
+    /// the value of every byte of a silent sample
+    silence: u8,
+}
+
+impl Wavl {
+    /// Parses the contents of a `wavl` list, after the list type.
+    fn parse(bytes: Vec<u8>, spec_ex: WavSpecEx) -> Result<Wavl> {
+        let frame_len = spec_ex.block_align() as u64;
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut offset = 0;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let len = u32::from_le_bytes([bytes[offset + 4], bytes[offset + 5],
+                                          bytes[offset + 6], bytes[offset + 7]]) as usize;
+            let body = offset + 8;
+            if len > bytes.len() - body {
+                let msg = format!("'{}' chunk of {} bytes exceeds the list", String::from_utf8_lossy(id), len);
+                return Err(Error::FormatError(msg));
+            }
+            let segment = match id {
+                b"data" => WavlSegment { start: start, len: len as u64, source: Some(body) },
+                b"slnt" => {
+                    if len < 4 {
+                        return Err(Error::FormatError("slnt chunk must hold at least 4 bytes".to_string()));
+                    }
+                    let frames = u32::from_le_bytes([bytes[body], bytes[body + 1],
+                                                     bytes[body + 2], bytes[body + 3]]);
+                    WavlSegment { start: start, len: frames as u64 * frame_len, source: None }
+                }
+                // Other chunks are allowed in the list, but hold no samples.
+                _ => WavlSegment { start: start, len: 0, source: None },
+            };
+            if segment.len > 0 {
+                start += segment.len;
+                segments.push(segment);
+            }
+            offset = body + len + len % 2;
+        }
+        let silence = match spec_ex.spec.sample_format {
+            // Samples of 8 bits are unsigned, so silence is the middle value.
+            SampleFormat::Int if spec_ex.bytes_per_sample == 1 => 0x80,
+            _ => 0,
+        };
+        Ok(Wavl {
+            bytes: bytes,
+            segments: segments,
+            silence: silence,
+        })
+    }
+
+    /// Returns the number of bytes of the expanded samples.
+    fn len(&self) -> u64 {
+        self.segments.last().map(|s| s.start + s.len).unwrap_or(0)
+    }
+
+    /// Copies the expanded samples at `position` into `buffer`.
+    ///
+    /// This stops at the end of a segment, and returns the number of bytes
+    /// copied, which is 0 at the end.
+    fn read_at(&self, position: u64, buffer: &mut [u8]) -> usize {
+        let index = match self.segments.binary_search_by(|s| s.start.cmp(&position)) {
+            Ok(i) => i,
+            Err(0) => return 0,
+            Err(i) => i - 1,
+        };
+        let segment = &self.segments[index];
+        let skip = position - segment.start;
+        if skip >= segment.len {
+            return 0
+        }
+        let n = cmp::min(buffer.len() as u64, segment.len - skip) as usize;
+        match segment.source {
+            Some(offset) => {
+                let from = offset + skip as usize;
+                buffer[..n].copy_from_slice(&self.bytes[from..from + n]);
+            }
+            None => {
+                for byte in &mut buffer[..n] {
+                    *byte = self.silence;
+                }
+            }
+        }
+        n
+    }
+}
+
+/// The decoder of a data chunk with IMA ADPCM or Microsoft ADPCM samples.
+///
+/// The samples are decoded one block at a time, into 16-bit samples that are
+/// read like those of a data chunk with PCM samples.
+#[derive(Clone, Debug)]
+struct Adpcm {
+    codec: adpcm::Codec,
+    block_align: u16,
+    channels: u16,
+    /// the number of frames the fact chunk specifies, if any
+    fact_frames: Option<u32>,
+    /// boundaries of the encoded bytes of the data chunk
+    raw: ChunkReadingState,
+    /// the encoded bytes of the current block
+    encoded: Vec<u8>,
+    /// the decoded samples of the current block
+    decoded: Vec<i16>,
+    /// the position in `decoded`, in bytes
+    position: usize,
+}
+
+impl Adpcm {
+    fn new(codec: adpcm::Codec, block_align: u16, channels: u16) -> Adpcm {
+        Adpcm {
+            codec: codec,
+            block_align: block_align,
+            channels: channels,
+            fact_frames: None,
+            raw: ChunkReadingState { len: 0, remaining: 0 },
+            encoded: Vec::new(),
+            decoded: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Starts decoding a data chunk of `len` bytes, returns the length of the decoded samples.
+    fn start(&mut self, len: u64) -> u64 {
+        self.raw = ChunkReadingState { len: len, remaining: len };
+        self.decoded.clear();
+        self.position = 0;
+        if len == UNKNOWN_DATA_LEN {
+            return UNKNOWN_DATA_LEN
+        }
+        let mut frames = self.codec.frames_in(len, self.block_align, self.channels);
+        // The last block is usually padded, the fact chunk says where the samples end.
+        if let Some(fact_frames) = self.fact_frames {
+            frames = cmp::min(frames, fact_frames as u64);
+        }
+        frames * self.channels as u64 * 2
+    }
+
+    /// Reads and decodes the next block, which is empty at the end of the data.
+    fn next_block<R: io::Read>(&mut self, reader: &mut R) -> io::Result<()> {
+        self.decoded.clear();
+        self.position = 0;
+        self.encoded.resize(self.block_align as usize, 0);
+        let mut n = 0;
+        while n < self.encoded.len() {
+            match try!(self.raw.read(reader, &mut self.encoded[n..])) {
+                0 => break,
+                read => n += read,
+            }
+        }
+        self.codec.decode_block(&self.encoded[..n], self.channels as usize, &mut self.decoded)
+    }
+
+    fn read<R: io::Read>(&mut self, reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.decoded.len() * 2 {
+            try!(self.next_block(reader));
+        }
+        let n = cmp::min(buffer.len(), self.decoded.len() * 2 - self.position);
+        for (i, byte) in buffer[..n].iter_mut().enumerate() {
+            let p = self.position + i;
+            *byte = self.decoded[p / 2].to_le_bytes()[p % 2];
+        }
+        self.position += n;
+        Ok(n)
+    }
+
+    /// Positions the decoder at the frame `frame`, by decoding the block that holds it.
+    fn seek<R: io::Read + io::Seek>(&mut self, reader: &mut R, frame: u64) -> io::Result<()> {
+        let samples_per_block = self.codec.samples_per_block(self.block_align, self.channels) as u64;
+        let block = frame / samples_per_block;
+        try!(self.raw.seek(reader, io::SeekFrom::Start(block * self.block_align as u64)));
+        try!(self.next_block(reader));
+        let skip = (frame % samples_per_block) as usize * self.channels as usize * 2;
+        self.position = cmp::min(skip, self.decoded.len() * 2);
+        Ok(())
+    }
+}
+
 /// A reader for safe Unknown chunks access.
 ///
 /// This reader borrow the underlying low-level reader from
@@ -282,6 +570,7 @@ pub enum Chunk<'r, R: 'r + io::Read> {
 /// For simple wave file decoding, prefer the `WavReader` facade.
 /// ChunksReader should only be use when one need to access chunks
 /// not specified by the Wave format.
+#[derive(Clone)]
 pub struct ChunksReader<R: io::Read> {
     /// the underlying reader
     reader: R,
@@ -290,6 +579,20 @@ pub struct ChunksReader<R: io::Read> {
     /// when inside the main data state, keeps track of decoding and chunk
     /// boundaries
     pub data_state: Option<DataReadingState>,
+    /// the `dwChannelMask` of the fmt chunk, if it is WAVEFORMATEXTENSIBLE
+    pub channel_mask: Option<u32>,
+    /// the SubFormat GUID of the fmt chunk, if it is WAVEFORMATEXTENSIBLE
+    pub sub_format: Option<[u8; 16]>,
+    /// the offset of the next chunk header from the start of the file
+    next_offset: u64,
+    /// the samples, if they are stored in a `wavl` list instead of a data chunk
+    wavl: Option<Wavl>,
+    /// the decoder, if the fmt chunk specifies ADPCM
+    adpcm: Option<Adpcm>,
+    /// how strictly the file is parsed
+    options: ReadOptions,
+    /// the offset of the end of the RIFF chunk, or `u64::MAX` if it is unknown
+    riff_end: u64,
 }
 
 /// This struct helps represent the inner state of the ChunksReader
@@ -307,12 +610,28 @@ impl<R: io::Read> ChunksReader<R> {
     ///
     /// This function will only read the Riff header from the file
     /// in order to position the stream to the first chunk.
-    pub fn new(mut reader: R) -> Result<ChunksReader<R>> {
-        try!(read_wave_header(&mut reader));
+    pub fn new(reader: R) -> Result<ChunksReader<R>> {
+        ChunksReader::new_with_options(reader, ReadOptions::default())
+    }
+
+    fn new_with_options(mut reader: R, options: ReadOptions) -> Result<ChunksReader<R>> {
+        let riff_end = match try!(read_wave_header(&mut reader)) {
+            // A streaming writer stores the largest size when it is not known.
+            end if end == UNKNOWN_DATA_LEN + 8 => u64::MAX,
+            end => end,
+        };
         Ok(ChunksReader {
             reader: reader,
             spec_ex: None,
             data_state: None,
+            channel_mask: None,
+            sub_format: None,
+            // The chunks follow the 12-byte RIFF WAVE header.
+            next_offset: 12,
+            wavl: None,
+            adpcm: None,
+            options: options,
+            riff_end: riff_end,
         })
     }
 
@@ -335,10 +654,12 @@ impl<R: io::Read> ChunksReader<R> {
     /// This function will panic if it is called while the reader is not in
     /// the data chunk, or if the format has not been parsed.
     pub fn samples<S: Sample>(&mut self) -> WavSamples<R, S> {
-        let _data_state = self.data_state.expect("Not in the data chunk.");
+        let data = self.data_state.expect("Not in the data chunk.");
+        let spec_ex = data.spec_ex;
         WavSamples {
+            read_fn: S::read_fn(spec_ex.spec.sample_format, spec_ex.bytes_per_sample,
+                                spec_ex.spec.bits_per_sample),
             reader: self,
-            phantom_sample: marker::PhantomData,
         }
 
     }
@@ -347,10 +668,12 @@ impl<R: io::Read> ChunksReader<R> {
     ///
     /// See `samples()` for more info.
     pub fn into_samples<S: Sample>(self) -> WavIntoSamples<R, S> {
-        let _data_state = self.data_state.expect("Not in the data chunk.");
+        let data = self.data_state.expect("Not in the data chunk.");
+        let spec_ex = data.spec_ex;
         WavIntoSamples {
+            read_fn: S::read_fn(spec_ex.spec.sample_format, spec_ex.bytes_per_sample,
+                                spec_ex.spec.bits_per_sample),
             reader: self,
-            phantom_sample: marker::PhantomData,
         }
     }
 
@@ -367,18 +690,39 @@ impl<R: io::Read> ChunksReader<R> {
     /// keep track of the audio samples parsing.
     pub fn next(&mut self) -> Result<Option<Chunk<R>>> {
         if let Some(ref mut data) = self.data_state {
-            try!(data.chunk.skip_remaining(&mut self.reader))
+            if let Some(ref mut adpcm) = self.adpcm {
+                try!(adpcm.raw.skip_remaining(&mut self.reader))
+            } else if self.wavl.is_none() {
+                // The samples of a wavl list were read already.
+                try!(data.chunk.skip_remaining(&mut self.reader))
+            }
         }
         self.data_state = None;
+        self.wavl = None;
         let mut kind_str = [0; 4];
         if self.reader.read_into(&mut kind_str).is_err() {
             // FIXME EOF is indistinguishable from actual errors in read_into
             return Ok(None);
         }
         let len = try!(self.reader.read_le_u32());
+        let offset = self.next_offset;
+        self.next_offset = offset + 8 + len as u64 + len as u64 % 2;
+        if self.options.strict_riff_size && offset + 8 + len as u64 > self.riff_end {
+            let msg = format!("chunk of {} bytes extends beyond the RIFF size of {} bytes",
+                              len, self.riff_end - 8);
+            return Err(chunk_error(&kind_str, offset, Error::FormatError(msg)));
+        }
+        if let Some(max) = self.options.max_header_bytes {
+            // Only the header of the data chunk precedes the samples.
+            let end = if &kind_str == b"data" { offset + 8 } else { self.next_offset };
+            if end > max {
+                let msg = format!("the header exceeds the limit of {} bytes", max);
+                return Err(chunk_error(&kind_str, offset, Error::FormatError(msg)));
+            }
+        }
         match &kind_str {
             b"fmt " => {
-                let spec_ex = try!(self.read_fmt_chunk(len));
+                let spec_ex = try!(self.read_fmt_chunk(len).map_err(|err| chunk_error(b"fmt ", offset, err)));
                 self.spec_ex = Some(spec_ex);
                 Ok(Some(Chunk::Fmt(spec_ex)))
             }
@@ -393,18 +737,26 @@ impl<R: io::Read> ChunksReader<R> {
                 // length of the data and the container size as determined
                 // from the Format chunk.
                 // http://www-mmsp.ece.mcgill.ca/documents/audioformats/wave/wave.html
-                let _samples_per_channel = self.reader.read_le_u32();
+                let samples_per_channel = self.reader.read_le_u32();
+                if let Some(ref mut adpcm) = self.adpcm {
+                    adpcm.fact_frames = samples_per_channel.ok();
+                }
                 Ok(Some(Chunk::Fact))
             }
             b"data" => {
                 if let Some(spec_ex) = self.spec_ex {
+                    let len = match self.adpcm {
+                        Some(ref mut adpcm) => adpcm.start(len as u64),
+                        None => len as u64,
+                    };
                     self.data_state = Some(DataReadingState {
                         spec_ex: spec_ex,
-                        chunk: ChunkReadingState { len: len as u64, remaining: len as u64}
+                        chunk: ChunkReadingState { len: len, remaining: len }
                     });
                     Ok(Some(Chunk::Data))
                 } else {
-                    Err(Error::FormatError("missing fmt chunk"))
+                    let err = Error::FormatError("data chunk before fmt chunk".to_string());
+                    Err(chunk_error(b"data", offset, err))
                 }
             }
             _ => {
@@ -432,14 +784,38 @@ impl<R: io::Read> ChunksReader<R> {
         Ok(false)
     }
 
+    /// Enters the samples of a `LIST` chunk of type `wavl`, given its contents.
+    fn start_wavl(&mut self, list: Vec<u8>) -> Result<()> {
+        let spec_ex = match self.spec_ex {
+            Some(spec_ex) => spec_ex,
+            None => return Err(Error::FormatError("wavl list before fmt chunk".to_string())),
+        };
+        if self.adpcm.is_some() {
+            return Err(Error::FormatError("wavl list with ADPCM samples".to_string()));
+        }
+        let wavl = try!(Wavl::parse(list[4..].to_vec(), spec_ex));
+        let len = wavl.len();
+        self.data_state = Some(DataReadingState {
+            spec_ex: spec_ex,
+            chunk: ChunkReadingState { len: len, remaining: len },
+        });
+        self.wavl = Some(wavl);
+        Ok(())
+    }
+
     /// Reads the fmt chunk of the file, returns the information it provides.
     fn read_fmt_chunk(&mut self, chunk_len: u32) -> Result<WavSpecEx> {
+        self.channel_mask = None;
+        self.sub_format = None;
+        self.adpcm = None;
+
         // A minimum chunk length of at least 16 is assumed. Note: actually,
         // the first 14 bytes contain enough information to fully specify the
         // file. I have not encountered a file with a 14-byte fmt section
         // though. If you ever encounter such file, please contact me.
         if chunk_len < 16 {
-            return Err(Error::FormatError("invalid fmt chunk size"));
+            let msg = format!("invalid size {}, must be at least 16", chunk_len);
+            return Err(Error::FormatError(msg));
         }
 
         // Read the WAVEFORMAT struct, as defined at
@@ -486,31 +862,85 @@ impl<R: io::Read> ChunksReader<R> {
         let block_align = try!(self.reader.read_le_u16());
         let bits_per_sample = try!(self.reader.read_le_u16());
 
-        if n_channels == 0 {
-            return Err(Error::FormatError("file contains zero channels"));
+        // The different format tag definitions can be found in mmreg.h that is
+        // part of the Windows SDK. The vast majority are esoteric vendor-
+        // specific formats. We handle only a few. The following values could
+        // be of interest:
+        const PCM: u16 = 0x0001;
+        const IEEE_FLOAT: u16 = 0x0003;
+        const EXTENSIBLE: u16 = 0xfffe;
+        const MS_ADPCM: u16 = 0x0002;
+        const IMA_ADPCM: u16 = 0x0011;
+
+        // Other formats are rejected before the fields are validated, because
+        // their fields follow different rules: ADPCM has 4 bits per sample,
+        // and a block holds many samples. IMA ADPCM and Microsoft ADPCM are
+        // decoded, so they are validated separately.
+        match format_tag {
+            IMA_ADPCM => {
+                return self.read_wave_format_ima_adpcm(chunk_len, n_channels, n_samples_per_sec,
+                                                       block_align, bits_per_sample)
+            }
+            MS_ADPCM => {
+                return self.read_wave_format_ms_adpcm(chunk_len, n_channels, n_samples_per_sec,
+                                                      block_align, bits_per_sample)
+            }
+            PCM | IEEE_FLOAT | EXTENSIBLE => {}
+            _ => {
+                return Err(Error::Unsupported(UnsupportedFormat {
+                    format_tag: Some(format_tag),
+                    bits_per_sample: bits_per_sample,
+                    sub_format: None,
+                }))
+            }
         }
 
-        let bytes_per_sample = block_align / n_channels;
+        try!(self.check_channels(n_channels));
+
+        // A frame holds the same number of bytes for every channel. For many
+        // channels, a writer that computes the block align without checking
+        // for overflow stores a value that is not a multiple.
+        match block_align % n_channels {
+            _ if !self.options.verify_block_align => {}
+            0 => {}
+            _ => {
+                let msg = format!("block align of {} bytes is not a multiple of {} channels",
+                                  block_align, n_channels);
+                return Err(Error::FormatError(msg));
+            }
+        }
+        let bytes_per_sample = if self.options.verify_block_align {
+            block_align / n_channels
+        } else {
+            // Derive the size of a sample from the bits per sample instead,
+            // which must be a multiple of 8, see below.
+            bits_per_sample / 8
+        };
         // We allow bits_per_sample to be less than bytes_per_sample so that
         // we can support things such as 24 bit samples in 4 byte containers.
-        if Some(bits_per_sample) > bytes_per_sample.checked_mul(8) {
-            return Err(Error::FormatError("sample bits exceeds size of sample"));
+        if self.options.verify_block_align && Some(bits_per_sample) > bytes_per_sample.checked_mul(8) {
+            let msg = format!("{} bits per sample exceeds block align of {} bytes \
+                               for {} channels", bits_per_sample, block_align, n_channels);
+            return Err(Error::FormatError(msg));
         }
 
         // This field is redundant, and may be ignored. We do validate it to
         // fail early for ill-formed files.
         if Some(n_bytes_per_sec) != (block_align as u32).checked_mul(n_samples_per_sec) {
-            return Err(Error::FormatError("inconsistent fmt chunk"));
+            let msg = format!("{} bytes per second does not match block align of \
+                               {} bytes at {} Hz", n_bytes_per_sec, block_align, n_samples_per_sec);
+            return Err(Error::FormatError(msg));
         }
 
         // The bits per sample for a WAVEFORMAT struct is the number of bits
         // used to store a sample. Therefore, it must be a multiple of 8.
         if bits_per_sample % 8 != 0 {
-            return Err(Error::FormatError("bits per sample is not a multiple of 8"));
+            let msg = format!("{} bits per sample is not a multiple of 8", bits_per_sample);
+            return Err(Error::FormatError(msg));
         }
 
         if bits_per_sample == 0 {
-            return Err(Error::FormatError("bits per sample is 0"));
+            return Err(Error::FormatError("bits per sample is 0".to_string()));
         }
 
         let mut spec = WavSpec {
@@ -520,21 +950,11 @@ impl<R: io::Read> ChunksReader<R> {
             sample_format: SampleFormat::Int,
         };
 
-        // The different format tag definitions can be found in mmreg.h that is
-        // part of the Windows SDK. The vast majority are esoteric vendor-
-        // specific formats. We handle only a few. The following values could
-        // be of interest:
-        const PCM: u16 = 0x0001;
-        const ADPCM: u16 = 0x0002;
-        const IEEE_FLOAT: u16 = 0x0003;
-        const EXTENSIBLE: u16 = 0xfffe;
         // We may update our WavSpec based on more data we read from the header.
         match format_tag {
             PCM => try!(self.read_wave_format_pcm(chunk_len, &spec)),
-            ADPCM => return Err(Error::Unsupported),
             IEEE_FLOAT => try!(self.read_wave_format_ieee_float(chunk_len, &mut spec)),
-            EXTENSIBLE => try!(self.read_wave_format_extensible(chunk_len, &mut spec)),
-            _ => return Err(Error::Unsupported),
+            _ => try!(self.read_wave_format_extensible(chunk_len, &mut spec)),
         };
 
         Ok(WavSpecEx {
@@ -543,6 +963,154 @@ impl<R: io::Read> ChunksReader<R> {
         })
     }
 
+    /// Reads the rest of an IMA ADPCM fmt chunk, returns the format of the decoded samples.
+    fn read_wave_format_ima_adpcm(&mut self,
+                                  chunk_len: u32,
+                                  n_channels: u16,
+                                  n_samples_per_sec: u32,
+                                  block_align: u16,
+                                  bits_per_sample: u16)
+                                  -> Result<WavSpecEx> {
+        if bits_per_sample != 4 {
+            return Err(Error::Unsupported(UnsupportedFormat {
+                format_tag: Some(0x0011),
+                bits_per_sample: bits_per_sample,
+                sub_format: None,
+            }))
+        }
+        try!(self.check_channels(n_channels));
+        // A block holds a 4-byte header for every channel, followed by groups
+        // of 4 bytes for every channel.
+        let header_len = 4 * n_channels as u32;
+        let whole_groups = match block_align as u32 % header_len {
+            0 => block_align as u32 >= header_len,
+            _ => false,
+        };
+        if !whole_groups {
+            let msg = format!("block align of {} bytes is not a multiple of 4 bytes \
+                               for {} channels", block_align, n_channels);
+            return Err(Error::FormatError(msg));
+        }
+        // The WAVEFORMATEX struct is followed by `cbSize` and the number of
+        // samples per block, which is redundant.
+        let mut consumed = 16;
+        if chunk_len >= 20 {
+            let _cb_size = try!(self.reader.read_le_u16());
+            let samples_per_block = try!(self.reader.read_le_u16());
+            consumed = 20;
+            if samples_per_block as u32 != adpcm::Codec::Ima.samples_per_block(block_align, n_channels) {
+                let msg = format!("{} samples per block does not match block align of {} bytes \
+                                   for {} channels", samples_per_block, block_align, n_channels);
+                return Err(Error::FormatError(msg));
+            }
+        }
+        try!(self.reader.skip_bytes((chunk_len - consumed) as usize));
+        self.adpcm = Some(Adpcm::new(adpcm::Codec::Ima, block_align, n_channels));
+        Ok(WavSpecEx {
+            spec: WavSpec {
+                channels: n_channels,
+                sample_rate: n_samples_per_sec,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            },
+            bytes_per_sample: 2,
+        })
+    }
+
+    /// Reads the rest of a Microsoft ADPCM fmt chunk, returns the format of the decoded samples.
+    fn read_wave_format_ms_adpcm(&mut self,
+                                 chunk_len: u32,
+                                 n_channels: u16,
+                                 n_samples_per_sec: u32,
+                                 block_align: u16,
+                                 bits_per_sample: u16)
+                                 -> Result<WavSpecEx> {
+        if bits_per_sample != 4 {
+            return Err(Error::Unsupported(UnsupportedFormat {
+                format_tag: Some(0x0002),
+                bits_per_sample: bits_per_sample,
+                sub_format: None,
+            }))
+        }
+        try!(self.check_channels(n_channels));
+        // A block starts with a 7-byte header for every channel.
+        if (block_align as u32) < 7 * n_channels as u32 {
+            let msg = format!("block align of {} bytes does not hold the headers of {} channels",
+                              block_align, n_channels);
+            return Err(Error::FormatError(msg));
+        }
+        // The WAVEFORMATEX struct is followed by `cbSize`, the number of
+        // samples per block, which is redundant, and the coefficient sets.
+        // Without them, the standard coefficients apply.
+        let mut coefficients = adpcm::MS_COEFFICIENTS.to_vec();
+        let mut consumed = 16;
+        if chunk_len >= 20 {
+            let _cb_size = try!(self.reader.read_le_u16());
+            let samples_per_block = try!(self.reader.read_le_u16());
+            consumed = 20;
+            if samples_per_block as u32 != adpcm::ms_samples_per_block(block_align, n_channels) {
+                let msg = format!("{} samples per block does not match block align of {} bytes \
+                                   for {} channels", samples_per_block, block_align, n_channels);
+                return Err(Error::FormatError(msg));
+            }
+        }
+        if chunk_len >= 22 {
+            let num_coefficients = try!(self.reader.read_le_u16()) as u32;
+            consumed = 22;
+            if chunk_len < consumed + 4 * num_coefficients {
+                let msg = format!("{} coefficient sets do not fit in a fmt chunk of {} bytes",
+                                  num_coefficients, chunk_len);
+                return Err(Error::FormatError(msg));
+            }
+            coefficients.clear();
+            for _ in 0..num_coefficients {
+                let coef1 = try!(self.reader.read_le_i16());
+                let coef2 = try!(self.reader.read_le_i16());
+                coefficients.push((coef1, coef2));
+            }
+            consumed += 4 * num_coefficients;
+        }
+        try!(self.reader.skip_bytes((chunk_len - consumed) as usize));
+        let codec = adpcm::Codec::Microsoft(coefficients);
+        self.adpcm = Some(Adpcm::new(codec, block_align, n_channels));
+        Ok(WavSpecEx {
+            spec: WavSpec {
+                channels: n_channels,
+                sample_rate: n_samples_per_sec,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            },
+            bytes_per_sample: 2,
+        })
+    }
+
+    /// Returns whether the samples end cleanly when the source does.
+    ///
+    /// This is the case for a data chunk of unknown length, and with
+    /// `ReadOptions::allow_truncated_data`.
+    fn ends_with_source(&self) -> bool {
+        let data = self.data_state.expect("reader not in data chunk");
+        data.chunk.len == UNKNOWN_DATA_LEN || self.options.allow_truncated_data
+    }
+
+    /// Returns the format tag if the fmt chunk specifies ADPCM, which is decoded to 16 bits.
+    pub(crate) fn adpcm_format_tag(&self) -> Option<u16> {
+        self.adpcm.as_ref().map(|adpcm| adpcm.codec.format_tag())
+    }
+
+    /// Returns an error if the number of channels is zero, or exceeds `max_channels`.
+    fn check_channels(&self, n_channels: u16) -> Result<()> {
+        if n_channels == 0 {
+            return Err(Error::FormatError("file contains zero channels".to_string()));
+        }
+        if n_channels > self.options.max_channels {
+            let msg = format!("file contains {} channels, more than the limit of {}",
+                              n_channels, self.options.max_channels);
+            return Err(Error::FormatError(msg));
+        }
+        Ok(())
+    }
+
     fn read_wave_format_pcm(&mut self, chunk_len: u32, spec: &WavSpec) -> Result<()> {
         // When there is a PCMWAVEFORMAT struct, the chunk is 16 bytes long.
         // The WAVEFORMATEX structs includes two extra bytes, `cbSize`.
@@ -552,7 +1120,10 @@ impl<R: io::Read> ChunksReader<R> {
             // Other sizes are unexpected, but such files do occur in the wild,
             // and reading these files is still possible, so we allow this.
             40 => true,
-            _ => return Err(Error::FormatError("unexpected fmt chunk size")),
+            _ => {
+                let msg = format!("unexpected size {} for PCM format", chunk_len);
+                return Err(Error::FormatError(msg));
+            }
         };
 
         if is_wave_format_ex {
@@ -572,7 +1143,11 @@ impl<R: io::Read> ChunksReader<R> {
                 8 => {}
                 16 => {}
                 24 => {}
-                _ => return Err(Error::FormatError("bits per sample is not 8 or 16")),
+                n => {
+                    let msg = format!("{} bits per sample is not valid for PCM \
+                                       in WAVEFORMATEX", n);
+                    return Err(Error::FormatError(msg));
+                }
             }
         }
 
@@ -589,7 +1164,8 @@ impl<R: io::Read> ChunksReader<R> {
         let is_wave_format_ex = chunk_len == 18;
 
         if !is_wave_format_ex && chunk_len != 16 {
-            return Err(Error::FormatError("unexpected fmt chunk size"));
+            let msg = format!("unexpected size {} for IEEE float format", chunk_len);
+            return Err(Error::FormatError(msg));
         }
 
         if is_wave_format_ex {
@@ -597,7 +1173,8 @@ impl<R: io::Read> ChunksReader<R> {
             // be no extra data, so `cbSize` should be 0.
             let cb_size = try!(self.reader.read_le_u16());
             if cb_size != 0 {
-                return Err(Error::FormatError("unexpected WAVEFORMATEX size"));
+                let msg = format!("unexpected WAVEFORMATEX size {}", cb_size);
+                return Err(Error::FormatError(msg));
             }
         }
 
@@ -608,7 +1185,9 @@ impl<R: io::Read> ChunksReader<R> {
         // Note that some applications support 64 bits per sample. This is
         // not yet supported by hound.
         if spec.bits_per_sample != 32 {
-            return Err(Error::FormatError("bits per sample is not 32"));
+            let msg = format!("{} bits per sample is not 32 for IEEE float format",
+                              spec.bits_per_sample);
+            return Err(Error::FormatError(msg));
         }
 
         spec.sample_format = SampleFormat::Float;
@@ -620,7 +1199,8 @@ impl<R: io::Read> ChunksReader<R> {
         // field, and `cbSize` itself must be at least 22, so the chunk length
         // must be at least 40.
         if chunk_len < 40 {
-            return Err(Error::FormatError("unexpected fmt chunk size"));
+            let msg = format!("unexpected size {} for WAVEFORMATEXTENSIBLE", chunk_len);
+            return Err(Error::FormatError(msg));
         }
 
         // `cbSize` is the last field of the WAVEFORMATEX struct.
@@ -629,7 +1209,8 @@ impl<R: io::Read> ChunksReader<R> {
         // `cbSize` must be at least 22, but in this case we assume that it is
         // 22, because we would not know how to handle extra data anyway.
         if cb_size != 22 {
-            return Err(Error::FormatError("unexpected WAVEFORMATEXTENSIBLE size"));
+            let msg = format!("unexpected WAVEFORMATEXTENSIBLE size {}", cb_size);
+            return Err(Error::FormatError(msg));
         }
 
         // What follows is the rest of the `WAVEFORMATEXTENSIBLE` struct, as
@@ -647,9 +1228,10 @@ impl<R: io::Read> ChunksReader<R> {
         // } WAVEFORMATEXTENSIBLE, *PWAVEFORMATEXTENSIBLE;
         // ```
         let valid_bits_per_sample = try!(self.reader.read_le_u16());
-        let _channel_mask = try!(self.reader.read_le_u32()); // Not used for now.
+        self.channel_mask = Some(try!(self.reader.read_le_u32()));
         let mut subformat = [0u8; 16];
         try!(self.reader.read_into(&mut subformat));
+        self.sub_format = Some(subformat);
 
         // Several GUIDS are defined. At the moment, only the following are supported:
         //
@@ -658,9 +1240,21 @@ impl<R: io::Read> ChunksReader<R> {
         let sample_format = match subformat {
             super::KSDATAFORMAT_SUBTYPE_PCM => SampleFormat::Int,
             super::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT => SampleFormat::Float,
-            _ => return Err(Error::Unsupported),
+            _ => {
+                return Err(Error::Unsupported(UnsupportedFormat {
+                    format_tag: Some(0xfffe),
+                    bits_per_sample: spec.bits_per_sample,
+                    sub_format: Some(subformat),
+                }))
+            }
         };
 
+        if valid_bits_per_sample > spec.bits_per_sample {
+            let msg = format!("{} valid bits per sample exceeds container of {} bits",
+                              valid_bits_per_sample, spec.bits_per_sample);
+            return Err(Error::FormatError(msg));
+        }
+
         // Fallback to bits_per_sample if the valid_bits_per_sample is obviously wrong to support non standard headers found in the wild.
         if valid_bits_per_sample > 0 {
             spec.bits_per_sample = valid_bits_per_sample;
@@ -691,6 +1285,16 @@ impl<R: io::Read> ChunksReader<R> {
         let data = self.data_state.as_mut().expect("Not in the data chunk.");
         let wanted_sample = time as i64 * data.spec_ex.spec.channels as i64;
         let wanted_byte = wanted_sample * data.spec_ex.bytes_per_sample as i64;
+        if let Some(ref mut adpcm) = self.adpcm {
+            try!(adpcm.seek(&mut self.reader, time as u64));
+            data.chunk.remaining = data.chunk.len.saturating_sub(wanted_byte as u64);
+            return Ok(())
+        }
+        if self.wavl.is_some() {
+            // The silence is not in the file, so only the position changes.
+            data.chunk.remaining = data.chunk.len.saturating_sub(wanted_byte as u64);
+            return Ok(())
+        }
         try!(data.chunk.seek(&mut self.reader, io::SeekFrom::Start(wanted_byte as u64)));
         Ok(())
     }
@@ -699,10 +1303,97 @@ impl<R: io::Read> ChunksReader<R> {
 impl<R: io::Read> io::Read for ChunksReader<R> {
     fn read(&mut self, buffer: &mut[u8]) -> io::Result<usize> {
         let data = self.data_state.as_mut().expect("Not in the data chunk.");
+        if let Some(ref wavl) = self.wavl {
+            let max = cmp::min(buffer.len() as u64, data.chunk.remaining) as usize;
+            let read = wavl.read_at(data.chunk.len - data.chunk.remaining, &mut buffer[..max]);
+            data.chunk.remaining -= read as u64;
+            return Ok(read)
+        }
+        if let Some(ref mut adpcm) = self.adpcm {
+            let max = cmp::min(buffer.len() as u64, data.chunk.remaining) as usize;
+            let read = try!(adpcm.read(&mut self.reader, &mut buffer[..max]));
+            data.chunk.remaining -= read as u64;
+            return Ok(read)
+        }
         data.chunk.read(&mut self.reader, buffer)
     }
 }
 
+/// The default maximum number of channels of a file, see `ReadOptions::max_channels`.
+///
+/// This is well above the channel count of surround and higher order ambisonic
+/// recordings.
+pub const MAX_CHANNELS: u16 = 256;
+
+/// Specifies how strictly `WavReader` parses a file.
+///
+/// The default is the behavior of `WavReader::new()`. Every field changes one
+/// check, so the options can be combined to accept a particular kind of
+/// ill-formed file without accepting all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Whether to reject chunks that extend beyond the size of the RIFF chunk.
+    ///
+    /// The size in the RIFF header is otherwise ignored, because many writers
+    /// get it wrong. A size of 0xffffffff, which streaming writers store when
+    /// the size is not known, is not checked. Defaults to false.
+    pub strict_riff_size: bool,
+
+    /// Whether a data chunk may extend beyond the end of the source.
+    ///
+    /// When true, the samples iterators end when the source does, if it ends
+    /// between two samples, like for a data chunk of unknown length. When
+    /// false, they return an error at the end of the source. In both cases
+    /// `len()` is the length of the data chunk in the header. Defaults to
+    /// false.
+    pub allow_truncated_data: bool,
+
+    /// The maximum offset of the first sample, if any.
+    ///
+    /// The chunks that precede the data chunk are read into memory, so this
+    /// limits the memory used for a file with a huge header. A file with a
+    /// larger header is rejected with `Error::FormatError`, before the chunk
+    /// that exceeds the limit is read. Defaults to `None`, no limit.
+    pub max_header_bytes: Option<u64>,
+
+    /// Whether to check `nBlockAlign` of the fmt chunk.
+    ///
+    /// When true, the block align must be a multiple of the number of
+    /// channels, and hold the bits per sample. When false, the size of a
+    /// sample is derived from the bits per sample instead, for files with a
+    /// wrong block align. Defaults to true.
+    pub verify_block_align: bool,
+
+    /// The maximum size of a chunk that is read into memory, if any.
+    ///
+    /// Metadata chunks such as `LIST` or `bext` are read completely, before
+    /// and after the data chunk. A chunk that declares a larger size is
+    /// rejected with `Error::FormatError`, before it is read. Independently
+    /// of this limit, for a source that can seek, a chunk must not be longer
+    /// than the rest of the source. Defaults to `None`, no limit.
+    pub max_chunk_bytes: Option<u64>,
+
+    /// The maximum number of channels.
+    ///
+    /// A header that claims more channels than any real recording is likely
+    /// corrupt, and buffers that hold a frame would be huge, so such a file is
+    /// rejected with `Error::FormatError`. Defaults to `MAX_CHANNELS`.
+    pub max_channels: u16,
+}
+
+impl Default for ReadOptions {
+    fn default() -> ReadOptions {
+        ReadOptions {
+            strict_riff_size: false,
+            allow_truncated_data: false,
+            max_header_bytes: None,
+            verify_block_align: true,
+            max_chunk_bytes: None,
+            max_channels: MAX_CHANNELS,
+        }
+    }
+}
+
 /// Specifies properties of the audio data, as well as the layout of the stream.
 #[derive(Clone, Copy, Debug)]
 pub struct WavSpecEx {
@@ -716,6 +1407,31 @@ pub struct WavSpecEx {
     pub bytes_per_sample: u16,
 }
 
+impl WavSpecEx {
+    /// Returns the number of bits of the container of a sample.
+    ///
+    /// This is `wBitsPerSample` of the fmt chunk, while `spec.bits_per_sample`
+    /// is the number of valid bits, `wValidBitsPerSample`.
+    pub fn container_bits(&self) -> u16 {
+        self.bytes_per_sample * 8
+    }
+
+    /// Returns the number of bytes of a frame, `nBlockAlign` of the fmt chunk.
+    ///
+    /// This is computed without overflow, so it may not fit the `u16` field
+    /// of the header for many channels; `WavWriter` rejects such a spec.
+    pub fn block_align(&self) -> u32 {
+        self.bytes_per_sample as u32 * self.spec.channels as u32
+    }
+
+    /// Returns the number of bytes per second, `nAvgBytesPerSec` of the fmt chunk.
+    ///
+    /// Like `block_align()`, this may not fit the `u32` field of the header.
+    pub fn byte_rate(&self) -> u64 {
+        self.block_align() as u64 * self.spec.sample_rate as u64
+    }
+}
+
 /// A reader that reads the WAVE format from the underlying reader.
 ///
 /// A `WavReader` is a streaming reader. It reads data from the underlying
@@ -724,10 +1440,95 @@ pub struct WavSpecEx {
 /// added by wrapping the reader in an `io::BufReader`. The `open` constructor
 /// takes care of this for you.
 ///
+/// A `WavReader` can be cloned when the underlying reader can, for example a
+/// `Cursor` or a slice. The clone continues at the same position, and after
+/// that both read independently, provided that cloning the underlying reader
+/// copies its position. For a file opened with `open()`, use `try_fork()`.
+///
 /// `WavReader` is a wrapper around `ChunksReader`.
+#[derive(Clone)]
 pub struct WavReader<R: io::Read> {
     /// The chunk reader from which the WAVE file is read.
     reader: ChunksReader<R>,
+
+    /// The chunks other than fmt, fact and data that were encountered.
+    chunks: Vec<RawChunk>,
+
+    /// The number of chunks at the start of `chunks` that precede the data chunk.
+    leading_chunks: usize,
+
+    /// Whether the chunks after the data chunk have been read into `chunks`.
+    read_trailing: bool,
+
+    /// The `iXML` chunk before the data chunk, if it was skipped instead of read.
+    deferred_ixml: Option<DeferredChunk>,
+
+    /// Whether the reader was constructed with a `Seek` bound.
+    seekable: bool,
+
+    /// The path of the file, if the reader was created with `open()`.
+    path: Option<path::PathBuf>,
+}
+
+/// The location of a chunk that is read on demand.
+#[derive(Clone, Copy, Debug)]
+struct DeferredChunk {
+    /// The index in `WavReader::chunks` at which the chunk belongs.
+    index: usize,
+    /// The position of the chunk payload in the source.
+    offset: u64,
+    /// The length of the chunk payload in bytes.
+    len: u64,
+}
+
+/// The range of the samples of one channel, as returned by `WavReader::scan_peaks()`.
+///
+/// Values are normalized, so -1.0 is the smallest value of an integer sample,
+/// whatever its bit depth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelStats {
+    /// The smallest sample.
+    pub min: f64,
+    /// The largest sample.
+    pub max: f64,
+    /// The largest absolute value of a sample.
+    pub peak: f64,
+    /// The first frame at which the peak occurs.
+    pub peak_position: u32,
+}
+
+impl ChannelStats {
+    /// Returns stats to start from, `min` exceeds `max` until a sample is added.
+    fn new() -> ChannelStats {
+        ChannelStats {
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            peak: 0.0,
+            peak_position: 0,
+        }
+    }
+
+    fn add(&mut self, x: f64, position: u32) {
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+        if x.abs() > self.peak {
+            self.peak = x.abs();
+            self.peak_position = position;
+        }
+    }
+}
+
+/// The result of checking the audio data against the `MD5 ` chunk.
+///
+/// See `WavReader::verify_md5()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Md5Status {
+    /// The checksum of the data chunk matches the stored checksum.
+    Match,
+    /// The checksum of the data chunk differs from the stored checksum.
+    Mismatch,
+    /// The file has no `MD5 ` chunk.
+    Absent,
 }
 
 /// An iterator that yields samples of type `S` read from a `WavReader`.
@@ -738,7 +1539,7 @@ pub struct WavSamples<'wr, R, S>
     where R: io::Read + 'wr
 {
     reader: &'wr mut ChunksReader<R>,
-    phantom_sample: marker::PhantomData<S>,
+    read_fn: Option<ReadFn<R, S>>,
 }
 
 /// An iterator that yields samples of type `S` read from a `WavReader`.
@@ -747,64 +1548,943 @@ pub struct WavSamples<'wr, R, S>
 /// file, otherwise every iteration will return an error.
 pub struct WavIntoSamples<R: io::Read, S> {
     reader: ChunksReader<R>,
-    phantom_sample: marker::PhantomData<S>,
+    read_fn: Option<ReadFn<R, S>>,
 }
 
-/// Reads the RIFF WAVE header, returns the supposed file size.
-///
-/// This function can be used to quickly check if the file could be a wav file
-/// by reading 12 bytes of the header. If an `Ok` is returned, the file is
-/// likely a wav file. If an `Err` is returned, it is definitely not a wav
-/// file.
+/// An iterator that yields samples of type `S` from several readers in sequence.
 ///
-/// The returned file size cannot be larger than 2<sup>32</sup> + 7 bytes.
-pub fn read_wave_header<R: io::Read>(reader: &mut R) -> Result<u64> {
-    // Every WAVE file starts with the four bytes 'RIFF' and a file length.
-    // TODO: the old approach of having a slice on the stack and reading
-    // into it is more cumbersome, but also avoids a heap allocation. Is
-    // the compiler smart enough to avoid the heap allocation anyway? I
-    // would not expect it to be.
-    if b"RIFF" != &try!(reader.read_4_bytes())[..] {
-        return Err(Error::FormatError("no RIFF tag found"));
-    }
+/// See `concat()`.
+pub struct WavConcatSamples<R: io::Read, S> {
+    current: Option<WavIntoSamples<R, S>>,
+    rest: vec::IntoIter<WavReader<R>>,
+}
 
-    let file_len = try!(reader.read_le_u32());
+/// Reads frames of samples of type `S` into a buffer that is reused.
+///
+/// This is not an `Iterator`, because every frame borrows the buffer. Loop
+/// over the frames with `while let Some(frame) = frames.next_frame()`.
+pub struct WavFrames<'wr, R, S>
+    where R: io::Read + 'wr
+{
+    reader: &'wr mut WavReader<R>,
+    frame: Vec<S>,
+}
 
-    // Next four bytes indicate the file type, which should be WAVE.
-    if b"WAVE" != &try!(reader.read_4_bytes())[..] {
-        return Err(Error::FormatError("no WAVE tag found"));
+impl<'wr, R: io::Read, S: Sample> WavFrames<'wr, R, S> {
+    /// Returns the next frame, one sample for every channel.
+    ///
+    /// Returns `None` at the end of the data. See `WavReader::read_frame_into()`.
+    pub fn next_frame(&mut self) -> Option<Result<&[S]>> {
+        match self.reader.read_frame_into(&mut self.frame) {
+            Ok(true) => Some(Ok(&self.frame)),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        }
     }
+}
 
-    // The stored file length does not include the "RIFF" magic and 4-byte
-    // length field, so the total size is 8 bytes more than what is stored.
-    Ok(file_len as u64 + 8)
+/// An iterator that yields frames of `N` samples of type `S`.
+///
+/// See `WavReader::frames_fixed()`.
+pub struct WavFramesFixed<'wr, R, S, const N: usize>
+    where R: io::Read + 'wr
+{
+    reader: &'wr mut WavReader<R>,
+    phantom_sample: marker::PhantomData<S>,
 }
 
-impl<R> WavReader<R>
-    where R: io::Read
+impl<'wr, R, S, const N: usize> Iterator for WavFramesFixed<'wr, R, S, N>
+    where R: io::Read,
+          S: Sample + Copy + Default
 {
-    /// Attempts to create a reader that reads the WAVE format.
-    ///
-    /// The header is read immediately. Reading the data will be done on
-    /// demand.
-    pub fn new(reader: R) -> Result<WavReader<R>> {
-        let mut reader = try!(ChunksReader::new(reader));
-        try!(reader.read_until_data());
-        if reader.spec_ex.is_none() {
-            return Err(Error::FormatError("Wave file with no fmt header"))
+    type Item = Result<[S; N]>;
+
+    fn next(&mut self) -> Option<Result<[S; N]>> {
+        let mut frame = [S::default(); N];
+        match self.reader.read_frame_into(&mut frame) {
+            Ok(true) => Some(Ok(frame)),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
         }
-        Ok(WavReader {
-            reader: reader,
-        })
     }
+}
 
-    /// Returns information about the WAVE file.
-    pub fn spec(&self) -> WavSpec {
+/// An iterator that yields the mean of every frame.
+///
+/// See `WavReader::samples_mono()`.
+pub struct WavMonoSamples<'wr, R, S>
+    where R: io::Read + 'wr
+{
+    samples: WavSamples<'wr, R, S>,
+    /// The samples of the current frame, kept to avoid an allocation per frame.
+    frame: Vec<S>,
+    channels: usize,
+}
+
+impl<'wr, R, S> Iterator for WavMonoSamples<'wr, R, S>
+    where R: io::Read,
+          S: Average
+{
+    type Item = Result<S>;
+
+    fn next(&mut self) -> Option<Result<S>> {
+        self.frame.clear();
+        while self.frame.len() < self.channels {
+            match self.samples.next() {
+                Some(Ok(sample)) => self.frame.push(sample),
+                Some(Err(err)) => return Some(Err(err)),
+                None if self.frame.is_empty() => return None,
+                None => return Some(Err(Error::UnfinishedSample)),
+            }
+        }
+        Some(Ok(S::average(&self.frame)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (samples, _) = self.samples.size_hint();
+        // An incomplete frame yields an error.
+        let frames = samples / self.channels;
+        let partial = if samples % self.channels == 0 { 0 } else { 1 };
+        (frames + partial, Some(frames + partial))
+    }
+}
+
+impl<'wr, R, S> ExactSizeIterator for WavMonoSamples<'wr, R, S>
+    where R: io::Read,
+          S: Average
+{
+}
+
+/// An iterator that yields the samples of one channel.
+///
+/// See `WavReader::channel_samples()`.
+pub struct WavChannelSamples<'wr, R, S>
+    where R: io::Read + 'wr
+{
+    samples: WavSamples<'wr, R, S>,
+    channel: u64,
+    channels: u64,
+}
+
+impl<'wr, R: io::Read, S> WavChannelSamples<'wr, R, S> {
+    /// Returns the number of samples before the next one of the channel, and the samples left.
+    fn gap(&self) -> (u64, u64) {
+        let data = self.samples.reader.data_state.expect("reader not in data chunk");
+        let bytes_per_sample = data.spec_ex.bytes_per_sample as u64;
+        let position = (data.chunk.len - data.chunk.remaining) / bytes_per_sample;
+        let gap = (self.channel + self.channels - position % self.channels) % self.channels;
+        (gap, data.chunk.remaining / bytes_per_sample)
+    }
+}
+
+impl<'wr, R, S> Iterator for WavChannelSamples<'wr, R, S>
+    where R: io::Read,
+          S: Sample
+{
+    type Item = Result<S>;
+
+    fn next(&mut self) -> Option<Result<S>> {
+        let (gap, left) = self.gap();
+        if left <= gap {
+            return None;
+        }
+        let data = self.samples.reader.data_state.expect("reader not in data chunk");
+        let bytes = gap * data.spec_ex.bytes_per_sample as u64;
+        let mut skipped = io::Read::take(&mut *self.samples.reader, bytes);
+        match io::copy(&mut skipped, &mut io::sink()) {
+            // A stream of unknown length ends when the source does.
+            Ok(n) if n < bytes && self.samples.reader.ends_with_source() => return None,
+            Ok(n) if n < bytes => {
+                let err = io::Error::new(io::ErrorKind::UnexpectedEof, "data chunk is truncated");
+                return Some(Err(Error::IoError(err)));
+            }
+            Ok(..) => {}
+            Err(err) => return Some(Err(Error::IoError(err))),
+        }
+        self.samples.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (gap, left) = self.gap();
+        let n = if left <= gap { 0 } else { ((left - gap - 1) / self.channels + 1) as usize };
+        (n, Some(n))
+    }
+}
+
+impl<'wr, R, S> ExactSizeIterator for WavChannelSamples<'wr, R, S>
+    where R: io::Read,
+          S: Sample
+{
+}
+
+/// An iterator that plays a section of a file repeatedly.
+///
+/// See `WavReader::samples_looped()`.
+pub struct WavLoopedSamples<'wr, R, S>
+    where R: io::Read + 'wr
+{
+    samples: WavSamples<'wr, R, S>,
+    /// The frame to seek back to.
+    loop_start: u32,
+    /// The offset in the data chunk after the last byte of the loop.
+    loop_end: u64,
+    /// How many more times the loop is played, `None` to loop forever.
+    repeats: Option<u32>,
+}
+
+impl<'wr, R, S> Iterator for WavLoopedSamples<'wr, R, S>
+    where R: io::Read + io::Seek,
+          S: Sample
+{
+    type Item = Result<S>;
+
+    fn next(&mut self) -> Option<Result<S>> {
+        let chunk = self.samples.reader.data_state.expect("reader not in data chunk").chunk;
+        if chunk.len - chunk.remaining == self.loop_end && self.repeats != Some(0) {
+            if let Some(ref mut repeats) = self.repeats {
+                *repeats -= 1;
+            }
+            if let Err(err) = self.samples.reader.seek(self.loop_start) {
+                return Some(Err(Error::from(err)));
+            }
+        }
+        self.samples.next()
+    }
+}
+
+/// An iterator that yields samples together with their time from the start.
+///
+/// See `WavSamples::timed()`.
+pub struct WavTimedSamples<I> {
+    samples: I,
+    /// The index of the next sample, counting samples of all channels.
+    index: u64,
+    channels: u64,
+    sample_rate: u64,
+}
+
+/// Reads the RIFF WAVE header, returns the supposed file size.
+///
+/// This function can be used to quickly check if the file could be a wav file
+/// by reading 12 bytes of the header. If an `Ok` is returned, the file is
+/// likely a wav file. If an `Err` is returned, it is definitely not a wav
+/// file.
+///
+/// The returned file size cannot be larger than 2<sup>32</sup> + 7 bytes.
+pub fn read_wave_header<R: io::Read>(reader: &mut R) -> Result<u64> {
+    // Every WAVE file starts with the four bytes 'RIFF' and a file length.
+    // TODO: the old approach of having a slice on the stack and reading
+    // into it is more cumbersome, but also avoids a heap allocation. Is
+    // the compiler smart enough to avoid the heap allocation anyway? I
+    // would not expect it to be.
+    let tag = try!(reader.read_4_bytes());
+    if b"RIFF" != &tag[..] {
+        let msg = format!("no RIFF tag found, the file starts with {:?}", String::from_utf8_lossy(&tag));
+        return Err(Error::FormatError(msg));
+    }
+
+    let file_len = try!(reader.read_le_u32());
+
+    // Next four bytes indicate the file type, which should be WAVE.
+    let tag = try!(reader.read_4_bytes());
+    if b"WAVE" != &tag[..] {
+        let msg = format!("no WAVE tag found, the RIFF type is {:?}", String::from_utf8_lossy(&tag));
+        return Err(Error::FormatError(msg));
+    }
+
+    // The stored file length does not include the "RIFF" magic and 4-byte
+    // length field, so the total size is 8 bytes more than what is stored.
+    Ok(file_len as u64 + 8)
+}
+
+/// The format and the location of the samples, as returned by `parse_header()`.
+#[derive(Clone, Copy, Debug)]
+pub struct WavHeader {
+    /// The format of the samples, from the fmt chunk.
+    pub spec_ex: WavSpecEx,
+    /// The offset of the first sample, the first byte of the data chunk contents.
+    pub data_start: u64,
+    /// The length of the data chunk contents in bytes, as stored in its header.
+    ///
+    /// This may exceed the number of bytes that follow `data_start`.
+    pub data_len: u64,
+}
+
+/// Parses the header of a WAVE file in memory, up to the start of the samples.
+///
+/// This walks the chunks up to the data chunk and parses the fmt chunk, like
+/// `WavReader::new()`, but it does not copy metadata chunks or read samples.
+/// It returns an error for malformed input and never panics, so it is a
+/// suitable entry point for fuzzing the parser.
+pub fn parse_header(bytes: &[u8]) -> Result<WavHeader> {
+    let mut reader = try!(ChunksReader::new(io::Cursor::new(bytes)));
+    if !try!(reader.read_until_data()) {
+        return Err(Error::FormatError("no data chunk found".to_string()));
+    }
+    let data = reader.data_state.expect("data chunk found");
+    Ok(WavHeader {
+        spec_ex: data.spec_ex,
+        data_start: reader.into_inner().position(),
+        data_len: data.chunk.len,
+    })
+}
+
+impl<R> WavReader<R>
+    where R: io::Read
+{
+    /// Attempts to create a reader that reads the WAVE format.
+    ///
+    /// The header is read immediately. Reading the data will be done on
+    /// demand. The contents of chunks that precede the data chunk, other than
+    /// the fmt and fact chunk, are kept so metadata can be read from them.
+    ///
+    /// Instead of a data chunk, the samples may be stored in a `LIST` chunk
+    /// of type `wavl`, as `data` chunks alternating with `slnt` chunks that
+    /// hold a number of frames of silence. Such a list is read into memory,
+    /// and the samples are read as if the silence was stored: `len()`,
+    /// `duration()`, and `seek()` include it. Silence is zero, or 128 for
+    /// 8-bit samples.
+    ///
+    /// Besides PCM and IEEE float samples, IMA ADPCM (format tag 0x0011) and
+    /// Microsoft ADPCM (format tag 0x0002) are supported. Those samples are
+    /// decoded one block at a time, to 16-bit samples, so `samples::<i16>()`
+    /// yields linear PCM. The number of samples is that of the fact chunk, if
+    /// there is one.
+    ///
+    /// Any `io::Read` can be used as source, it does not need to be a file:
+    /// `io::stdin().lock()`, a network stream, or a decompressor all work,
+    /// because the file is read front to back. Only `seek()` and
+    /// `read_trailing_chunks()` additionally require `io::Seek`. No buffering
+    /// is done, so wrap unbuffered sources in an `io::BufReader`.
+    ///
+    /// A boxed trait object works too, to keep readers over different kinds
+    /// of sources in one collection. With `Box<dyn io::Read>` the methods that
+    /// require `io::Seek` are not available; use `Box<dyn ReadSeek>` to keep
+    /// them.
+    pub fn new(reader: R) -> Result<WavReader<R>> {
+        WavReader::new_with_chunk_handler(reader, |_, _| {})
+    }
+
+    /// Attempts to create a reader, calling `on_unknown_chunk` for chunks that are not parsed.
+    ///
+    /// This is the same as `new()`, but while the header is read, the handler
+    /// is called with the id and the contents of every chunk before the data
+    /// chunk other than `fmt ` and `fact`, in file order. Those are the
+    /// chunks returned by `chunks_before_data()`. This makes it possible to
+    /// parse proprietary chunks, such as `_PMX`, as the file is scanned.
+    ///
+    /// ```
+    /// use hound::WavReader;
+    ///
+    /// let mut ids = Vec::new();
+    /// let file = std::fs::File::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav").unwrap();
+    /// let reader = WavReader::new_with_chunk_handler(file, |id, _data| ids.push(*id)).unwrap();
+    /// assert_eq!(ids, [*b"LIST"]);
+    /// assert_eq!(reader.chunks_before_data().len(), 1);
+    /// ```
+    pub fn new_with_chunk_handler<F>(reader: R, on_unknown_chunk: F) -> Result<WavReader<R>>
+        where F: FnMut(&[u8; 4], &[u8])
+    {
+        WavReader::new_with_options_and_handler(reader, ReadOptions::default(), None, on_unknown_chunk)
+    }
+
+    /// Attempts to create a reader that parses the file as specified by `options`.
+    ///
+    /// This is the same as `new()`, which uses `ReadOptions::default()`.
+    pub fn new_with_options(reader: R, options: ReadOptions) -> Result<WavReader<R>> {
+        WavReader::new_with_options_and_handler(reader, options, None, |_, _| {})
+    }
+
+    /// Attempts to create a reader from a seekable source, as specified by `options`.
+    ///
+    /// The length of the source bounds the size of the chunks that are read.
+    fn new_seekable_with_options(mut reader: R, options: ReadOptions) -> Result<WavReader<R>>
+        where R: io::Seek,
+    {
+        let start = try!(reader.stream_position());
+        let end = try!(reader.seek(io::SeekFrom::End(0)));
+        try!(reader.seek(io::SeekFrom::Start(start)));
+        let stream_len = Some(end.saturating_sub(start));
+        let mut reader = try!(WavReader::new_with_options_and_handler(reader, options, stream_len, |_, _| {}));
+        reader.seekable = true;
+        if let Some(ref mut chunk) = reader.deferred_ixml {
+            chunk.offset += start;
+        }
+        Ok(reader)
+    }
+
+    /// Parses the header, with `stream_len` the length of the source, if it is known.
+    ///
+    /// The length is known for seekable sources only, and for those the
+    /// payload of an `iXML` chunk is skipped, to be read on demand.
+    fn new_with_options_and_handler<F>(reader: R,
+                                       options: ReadOptions,
+                                       stream_len: Option<u64>,
+                                       mut on_unknown_chunk: F)
+                                       -> Result<WavReader<R>>
+        where F: FnMut(&[u8; 4], &[u8])
+    {
+        let mut reader = try!(ChunksReader::new_with_options(reader, options));
+        let mut chunks = Vec::new();
+        let mut wavl = None;
+        let mut deferred_ixml = None;
+        loop {
+            let offset = reader.next_offset;
+            let chunk = match try!(reader.next()) {
+                Some(chunk) => chunk,
+                None => break,
+            };
+            match chunk {
+                Chunk::Data => break,
+                Chunk::Unknown(id, mut chunk_reader) => {
+                    let available = stream_len.map(|len| len.saturating_sub(offset + 8));
+                    try!(check_chunk_len(chunk_reader.state.len, available, &options)
+                         .map_err(|err| chunk_error(&id, offset, err)));
+                    if &id == b"iXML" && stream_len.is_some() && deferred_ixml.is_none() {
+                        // The rest of the chunk is skipped by the next call to `next()`.
+                        deferred_ixml = Some(DeferredChunk {
+                            index: chunks.len(),
+                            offset: offset + 8,
+                            len: chunk_reader.state.len,
+                        });
+                        continue
+                    }
+                    let mut data = Vec::new();
+                    try!(io::Read::read_to_end(&mut chunk_reader, &mut data));
+                    if &id == b"LIST" && data.starts_with(b"wavl") {
+                        wavl = Some(data);
+                        break
+                    }
+                    on_unknown_chunk(&id, &data);
+                    chunks.push(RawChunk { id: id, data: data });
+                }
+                Chunk::Fmt(..) | Chunk::Fact => {}
+            }
+        }
+        if let Some(data) = wavl {
+            let len = data.len() as u64;
+            let offset = reader.next_offset - 8 - len - len % 2;
+            try!(reader.start_wavl(data).map_err(|err| chunk_error(b"LIST", offset, err)));
+        }
+        if reader.spec_ex.is_none() {
+            return Err(Error::FormatError("Wave file with no fmt header".to_string()))
+        }
+        if reader.data_state.is_none() {
+            return Err(Error::FormatError("no data chunk found".to_string()))
+        }
+        Ok(WavReader {
+            reader: reader,
+            leading_chunks: chunks.len(),
+            chunks: chunks,
+            read_trailing: false,
+            deferred_ixml: deferred_ixml,
+            seekable: false,
+            path: None,
+        })
+    }
+
+    /// Attempts to create a reader that reads the WAVE format from a seekable source.
+    ///
+    /// This is the same as `new()`, but it records that the source can seek,
+    /// which is reported by `can_seek()`. A chunk that is read into memory
+    /// must not be longer than the rest of the source.
+    pub fn new_seekable(reader: R) -> Result<WavReader<R>>
+        where R: io::Seek,
+    {
+        WavReader::new_seekable_with_options(reader, ReadOptions::default())
+    }
+
+    /// Creates a reader for headerless samples, in the format given by `spec`.
+    ///
+    /// The source holds nothing but interleaved little-endian samples, for
+    /// example a raw PCM dump, and it is assumed to be positioned at the first
+    /// sample. Every sample is stored in the smallest number of whole bytes.
+    /// The spec is checked with `WavSpec::validate()`. The number of samples
+    /// cannot be known for a source that does not seek, so `len()` and
+    /// `duration()` are unreliable, like for a file with
+    /// `has_unknown_length()`: the samples iterators end when the source
+    /// does. Use `new_raw_seekable()` for sources that can seek.
+    pub fn new_raw(reader: R, spec: WavSpec) -> Result<WavReader<R>> {
+        WavReader::new_raw_with_len(reader, spec, UNKNOWN_DATA_LEN)
+    }
+
+    /// Creates a reader for headerless samples from a seekable source.
+    ///
+    /// This is the same as `new_raw()`, but the number of samples is derived
+    /// from the number of bytes between the current position and the end of
+    /// the source, so `len()`, `duration()` and `seek()` behave as for a file
+    /// with a header.
+    pub fn new_raw_seekable(mut reader: R, spec: WavSpec) -> Result<WavReader<R>>
+        where R: io::Seek,
+    {
+        let start = try!(reader.stream_position());
+        let end = try!(reader.seek(io::SeekFrom::End(0)));
+        try!(reader.seek(io::SeekFrom::Start(start)));
+        let mut reader = try!(WavReader::new_raw_with_len(reader, spec, end.saturating_sub(start)));
+        reader.seekable = true;
+        Ok(reader)
+    }
+
+    fn new_raw_with_len(reader: R, spec: WavSpec, len: u64) -> Result<WavReader<R>> {
+        let spec_ex = try!(raw_spec_ex(spec));
+        Ok(WavReader {
+            reader: ChunksReader {
+                reader: reader,
+                spec_ex: Some(spec_ex),
+                data_state: Some(DataReadingState {
+                    spec_ex: spec_ex,
+                    chunk: ChunkReadingState { len: len, remaining: len },
+                }),
+                channel_mask: None,
+                sub_format: None,
+                next_offset: 0,
+                wavl: None,
+                adpcm: None,
+                options: ReadOptions::default(),
+                riff_end: u64::MAX,
+            },
+            leading_chunks: 0,
+            chunks: Vec::new(),
+            // There is nothing after the samples.
+            read_trailing: true,
+            deferred_ixml: None,
+            seekable: false,
+            path: None,
+        })
+    }
+
+    /// Creates a reader that reads a WAVE file, but decodes it according to `spec`.
+    ///
+    /// The header is parsed as by `new()` to find the data chunk and the
+    /// metadata chunks, but the format of the fmt chunk is replaced by
+    /// `spec`, for files with a header that is known to be wrong. The number
+    /// of samples is derived from the length of the data chunk in bytes,
+    /// with every sample stored in the smallest number of whole bytes. The
+    /// spec is checked with `WavSpec::validate()`.
+    pub fn new_with_spec(reader: R, spec: WavSpec) -> Result<WavReader<R>> {
+        let spec_ex = try!(raw_spec_ex(spec));
+        let mut reader = try!(WavReader::new(reader));
+        reader.reader.spec_ex = Some(spec_ex);
+        if let Some(ref mut data) = reader.reader.data_state {
+            data.spec_ex = spec_ex;
+        }
+        Ok(reader)
+    }
+
+    /// Returns whether the reader was constructed from a seekable source.
+    ///
+    /// This is true for readers constructed with `open()` or `new_seekable()`,
+    /// and false for readers constructed with `new()`, even if the source
+    /// happens to implement `Seek`. Code that handles both files and pipes
+    /// can use this to choose between a random-access and a single-pass
+    /// algorithm, when the type of the source is not known statically.
+    pub fn can_seek(&self) -> bool {
+        self.seekable
+    }
+
+    /// Returns information about the WAVE file.
+    ///
+    /// For samples encoded as ADPCM, which are decoded as they are read,
+    /// this describes the decoded samples: 16-bit integers.
+    pub fn spec(&self) -> WavSpec {
         self.reader.spec_ex
             .expect("Using a WavReader wrapping a ChunkReader with no spec")
             .spec
     }
 
+    /// Returns the format of the samples, including how they are stored.
+    ///
+    /// Unlike `spec()`, this includes the size of the sample container,
+    /// which may exceed the valid bits per sample. The other fields of a
+    /// WAVEFORMATEXTENSIBLE fmt chunk are returned by `channel_mask()` and
+    /// `sub_format()`.
+    pub fn spec_ex(&self) -> WavSpecEx {
+        self.reader.spec_ex.expect("Using a WavReader wrapping a ChunkReader with no spec")
+    }
+
+    /// Returns the SubFormat GUID of a WAVEFORMATEXTENSIBLE fmt chunk.
+    ///
+    /// Returns `None` if the fmt chunk has a different format. See also
+    /// `channel_mask()`.
+    pub fn sub_format(&self) -> Option<[u8; 16]> {
+        self.reader.sub_format
+    }
+
+    /// Returns the chunks that precede the data chunk, in file order.
+    ///
+    /// This includes all chunks other than `fmt `, `fact` and `data`, also
+    /// the ones that Hound does not interpret, so they can be passed on to a
+    /// `WavWriter`. See also `copy_metadata()`.
+    pub fn chunks_before_data(&self) -> &[RawChunk] {
+        &self.chunks[..self.leading_chunks]
+    }
+
+    /// Returns the chunks that follow the data chunk, in file order.
+    ///
+    /// This is empty unless `read_trailing_chunks()` was called.
+    pub fn chunks_after_data(&self) -> &[RawChunk] {
+        &self.chunks[self.leading_chunks..]
+    }
+
+    /// Returns the metadata stored in the `LIST` chunk of type `INFO`.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk before the data chunk,
+    /// and an error if the chunk is malformed. The malformed chunk does not
+    /// affect reading samples.
+    pub fn info(&self) -> Result<Option<ListInfo>> {
+        match metadata::find_list(&self.chunks, *b"INFO") {
+            Some(chunk) => metadata::parse_list_info(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the cue points from the `cue ` chunk, with their labels.
+    ///
+    /// Labels and notes are taken from the `LIST` chunk of type `adtl`, if the file has
+    /// one. Returns an empty list if the file has no cue chunk. Note that the
+    /// cue chunk is often placed after the data chunk, call
+    /// `read_trailing_chunks()` first to find it there.
+    pub fn cue_points(&self) -> Result<Vec<CuePoint>> {
+        let mut points = match self.chunks.iter().find(|c| &c.id == b"cue ") {
+            Some(chunk) => try!(metadata::parse_cue(&chunk.data)),
+            None => return Ok(Vec::new()),
+        };
+        if let Some(chunk) = metadata::find_list(&self.chunks, *b"adtl") {
+            for (kind, id, text) in try!(metadata::parse_adtl(&chunk.data)) {
+                if let Some(point) = points.iter_mut().find(|p| p.id == id) {
+                    if &kind == b"labl" {
+                        point.label = Some(text);
+                    } else {
+                        point.note = Some(text);
+                    }
+                }
+            }
+        }
+        Ok(points)
+    }
+
+    /// Returns the peak envelope stored in the `levl` chunk.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk, and an error if the
+    /// chunk is malformed. The chunk is usually placed after the data chunk,
+    /// call `read_trailing_chunks()` first to find it there.
+    pub fn peak_envelope(&self) -> Result<Option<PeakEnvelope>> {
+        match self.chunks.iter().find(|c| &c.id == b"levl") {
+            Some(chunk) => metadata::parse_levl(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the segments of the `plst` chunk, which defines a play order.
+    ///
+    /// Every segment starts at one of the `cue_points()`. Returns an empty
+    /// list if the file has no playlist chunk. Like the cue chunk, it is often
+    /// placed after the data chunk, call `read_trailing_chunks()` first to
+    /// find it there.
+    pub fn playlist(&self) -> Result<Vec<PlaylistSegment>> {
+        match self.chunks.iter().find(|c| &c.id == b"plst") {
+            Some(chunk) => metadata::parse_plst(&chunk.data),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the Broadcast Wave Format metadata stored in the `bext` chunk.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk, and an error if the
+    /// chunk is malformed.
+    pub fn broadcast_extension(&self) -> Result<Option<BroadcastExtension>> {
+        match self.chunks.iter().find(|c| &c.id == b"bext") {
+            Some(chunk) => metadata::parse_bext(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the radio automation metadata stored in the `cart` chunk.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk, and an error if the
+    /// chunk is too short.
+    pub fn cart(&self) -> Result<Option<Cart>> {
+        match self.chunks.iter().find(|c| &c.id == b"cart") {
+            Some(chunk) => metadata::parse_cart(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the display title stored in the `DISP` chunk.
+    ///
+    /// Windows shows this title, and some files have it instead of a title in
+    /// `info()`. Returns `Ok(None)` if the file has no such chunk or if it
+    /// does not hold text, and an error if the chunk is malformed. Bytes that
+    /// are not valid UTF-8 are replaced; see `chunks_before_data()` for the
+    /// raw bytes.
+    pub fn display_title(&self) -> Result<Option<String>> {
+        match self.chunks.iter().find(|c| &c.id == b"DISP") {
+            Some(chunk) => metadata::parse_disp(&chunk.data),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the payload of the `iXML` chunk, an XML document with production metadata.
+    ///
+    /// The document is returned as is, it is not parsed. The chunk may be
+    /// placed after the data chunk, call `read_trailing_chunks()` first to
+    /// find it there. For a seekable source, such as a file opened with
+    /// `open()`, the document is not kept in memory when the header is read;
+    /// use `read_ixml()` then.
+    pub fn ixml(&self) -> Option<&[u8]> {
+        self.chunks.iter().find(|c| &c.id == b"iXML").map(|c| &c.data[..])
+    }
+
+    /// Reads the payload of the `iXML` chunk on demand, and returns it.
+    ///
+    /// This is the same as `ixml()`, but for a seekable source it first reads
+    /// the chunk that was skipped when the header was read, and then returns
+    /// to the current position, so reading samples is not affected. The
+    /// document is kept in memory after that, and it is included in
+    /// `chunks_before_data()`.
+    ///
+    /// This method requires that the inner reader `R` implements `Seek`.
+    pub fn read_ixml(&mut self) -> Result<Option<&[u8]>>
+        where R: io::Seek,
+    {
+        try!(self.read_deferred_chunks());
+        Ok(self.ixml())
+    }
+
+    /// Returns whether a chunk was skipped when the header was read, and is
+    /// not yet in `chunks_before_data()`.
+    pub(crate) fn has_deferred_chunks(&self) -> bool {
+        self.deferred_ixml.is_some()
+    }
+
+    /// Reads the chunks that were skipped when the header was read into `chunks`.
+    fn read_deferred_chunks(&mut self) -> Result<()>
+        where R: io::Seek,
+    {
+        let deferred = match self.deferred_ixml {
+            Some(deferred) => deferred,
+            None => return Ok(()),
+        };
+        let reader = &mut self.reader.reader;
+        let position = try!(reader.stream_position());
+        try!(reader.seek(io::SeekFrom::Start(deferred.offset)));
+        let mut data = Vec::new();
+        let result = {
+            let mut payload = io::Read::take(io::Read::by_ref(reader), deferred.len);
+            io::Read::read_to_end(&mut payload, &mut data)
+        };
+        try!(reader.seek(io::SeekFrom::Start(position)));
+        try!(result);
+        if (data.len() as u64) < deferred.len {
+            return Err(Error::FormatError("iXML chunk ends before its length".to_string()));
+        }
+        self.chunks.insert(deferred.index, RawChunk { id: *b"iXML", data: data });
+        self.leading_chunks += 1;
+        self.deferred_ixml = None;
+        Ok(())
+    }
+
+    /// Returns the rows of the `chna` chunk, which map tracks to ADM metadata.
+    ///
+    /// Files with object-based audio in the ADM format of ITU-R BS.2076 have
+    /// this chunk, together with an `axml` chunk. Returns an empty vector if
+    /// the file has no `chna` chunk. The chunk may be placed after the data
+    /// chunk, call `read_trailing_chunks()` first to find it there.
+    pub fn adm_tracks(&self) -> Result<Vec<AdmTrack>> {
+        match self.chunks.iter().find(|c| &c.id == b"chna") {
+            Some(chunk) => metadata::parse_chna(&chunk.data),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the payload of the `axml` chunk, an XML document with ADM metadata.
+    ///
+    /// The document is returned as is, it is not parsed. The chunk may be
+    /// placed after the data chunk, call `read_trailing_chunks()` first to
+    /// find it there.
+    pub fn axml(&self) -> Option<&[u8]> {
+        self.chunks.iter().find(|c| &c.id == b"axml").map(|c| &c.data[..])
+    }
+
+    /// Returns the payload of the `id3 ` chunk, an ID3v2 tag.
+    ///
+    /// The tag is returned as is, it can be parsed with an ID3 library. Some
+    /// writers use the chunk id `ID3 ` instead, that is accepted too. The
+    /// chunk is usually placed after the data chunk, call
+    /// `read_trailing_chunks()` first to find it there.
+    pub fn id3(&self) -> Option<&[u8]> {
+        self.chunks.iter()
+            .find(|c| &c.id == b"id3 " || &c.id == b"ID3 ")
+            .map(|c| &c.data[..])
+    }
+
+    /// Returns the tempo information stored in the `acid` chunk.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk, and an error if the
+    /// chunk is malformed.
+    pub fn acid_info(&self) -> Result<Option<AcidInfo>> {
+        match self.chunks.iter().find(|c| &c.id == b"acid") {
+            Some(chunk) => metadata::parse_acid(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the sampler information stored in the `smpl` chunk.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk, and an error if the
+    /// chunk is malformed. The chunk may be placed after the data chunk, call
+    /// `read_trailing_chunks()` first to find it there.
+    pub fn sampler_info(&self) -> Result<Option<SamplerInfo>> {
+        match self.chunks.iter().find(|c| &c.id == b"smpl") {
+            Some(chunk) => metadata::parse_smpl(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the instrument information stored in the `inst` chunk.
+    ///
+    /// Returns `Ok(None)` if the file has no such chunk, and an error if the
+    /// chunk is too short. The values are returned as stored, without checking
+    /// their ranges.
+    pub fn instrument_info(&self) -> Result<Option<InstrumentInfo>> {
+        match self.chunks.iter().find(|c| &c.id == b"inst") {
+            Some(chunk) => metadata::parse_inst(&chunk.data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the peak amplitudes stored in the `PEAK` chunk.
+    ///
+    /// This chunk is common in floating point files, and saves scanning the
+    /// samples to find the peaks. Returns `Ok(None)` if the file has no such
+    /// chunk, and an error if its size does not match the number of channels.
+    pub fn peak_info(&self) -> Result<Option<PeakInfo>> {
+        match self.chunks.iter().find(|c| &c.id == b"PEAK") {
+            Some(chunk) => metadata::parse_peak(&chunk.data, self.spec().channels).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the chunks that follow the data chunk, for use by the metadata accessors.
+    ///
+    /// Writers may place metadata chunks such as `cue ` or `LIST` after the
+    /// samples. This seeks past the data chunk, keeps the chunks found there,
+    /// and then returns to the current position in the data chunk, so reading
+    /// samples is not affected. Calling it again has no effect. An `iXML`
+    /// chunk before the data chunk that was not kept in memory is read too,
+    /// as by `read_ixml()`.
+    ///
+    /// This method requires that the inner reader `R` implements `Seek`.
+    pub fn read_trailing_chunks(&mut self) -> Result<()>
+        where R: io::Seek,
+    {
+        try!(self.read_deferred_chunks());
+        if self.read_trailing {
+            return Ok(())
+        }
+        let data = self.reader.data_state.expect("Not in the data chunk.");
+        let reader = &mut self.reader.reader;
+        let position = try!(reader.stream_position());
+        // The source is past a wavl list already.
+        let chunk = match self.reader.adpcm {
+            Some(ref adpcm) => adpcm.raw,
+            None => data.chunk,
+        };
+        let skip = match self.reader.wavl {
+            Some(..) => 0,
+            None => chunk.remaining + chunk.len % 2,
+        };
+        try!(reader.seek(io::SeekFrom::Current(skip as i64)));
+        let result = read_remaining_chunks(reader, &mut self.chunks, &self.reader.options);
+        try!(reader.seek(io::SeekFrom::Start(position)));
+        self.read_trailing = true;
+        result
+    }
+
+    /// Returns whether the data chunk extends beyond the end of the file.
+    ///
+    /// This compares the length of the data chunk in the header with the length
+    /// of the stream, to detect files that were cut off, for example by an
+    /// interrupted download, before decoding them. The position in the data
+    /// chunk is not affected.
+    ///
+    /// This method requires that the inner reader `R` implements `Seek`.
+    pub fn is_truncated(&mut self) -> Result<bool>
+        where R: io::Seek,
+    {
+        let data = self.reader.data_state.expect("Not in the data chunk.");
+        if self.reader.wavl.is_some() {
+            // The samples of a wavl list were read completely.
+            return Ok(false)
+        }
+        let remaining = match self.reader.adpcm {
+            Some(ref adpcm) => adpcm.raw.remaining,
+            None => data.chunk.remaining,
+        };
+        let reader = &mut self.reader.reader;
+        let position = try!(reader.stream_position());
+        let end = try!(reader.seek(io::SeekFrom::End(0)));
+        try!(reader.seek(io::SeekFrom::Start(position)));
+        Ok(position + remaining > end)
+    }
+
+    /// Checks the audio data against the checksum in the `MD5 ` chunk.
+    ///
+    /// This hashes the contents of the data chunk, and compares the digest
+    /// with the one stored in the file. Because the chunk is usually placed
+    /// after the data chunk, this reads the trailing chunks first, see
+    /// `read_trailing_chunks()`. A file that was cut off does not match. The
+    /// position in the data chunk is not affected.
+    ///
+    /// This method requires that the inner reader `R` implements `Seek`.
+    pub fn verify_md5(&mut self) -> Result<Md5Status>
+        where R: io::Seek,
+    {
+        try!(self.read_trailing_chunks());
+        let expected = match self.chunks.iter().find(|c| &c.id == b"MD5 ") {
+            Some(chunk) if chunk.data.len() == 16 => chunk.data.clone(),
+            Some(chunk) => {
+                let msg = format!("MD5 chunk must hold 16 bytes, not {}", chunk.data.len());
+                return Err(Error::FormatError(msg));
+            }
+            None => return Ok(Md5Status::Absent),
+        };
+        let data = self.reader.data_state.expect("Not in the data chunk.");
+        let mut md5 = Md5::new();
+        if let Some(ref wavl) = self.reader.wavl {
+            // Hash the samples with the silence expanded.
+            let mut buffer = [0; 4096];
+            let mut position = 0;
+            loop {
+                let n = wavl.read_at(position, &mut buffer);
+                if n == 0 {
+                    break
+                }
+                md5.update(&buffer[..n]);
+                position += n as u64;
+            }
+        } else {
+            // The checksum covers the bytes as stored, also for ADPCM.
+            let chunk = match self.reader.adpcm {
+                Some(ref adpcm) => adpcm.raw,
+                None => data.chunk,
+            };
+            let reader = &mut self.reader.reader;
+            let position = try!(reader.stream_position());
+            let start = position - (chunk.len - chunk.remaining);
+            try!(reader.seek(io::SeekFrom::Start(start)));
+            // If the file ends before the data chunk does, fewer bytes are hashed.
+            let result = io::copy(&mut io::Read::take(&mut *reader, chunk.len), &mut md5);
+            try!(reader.seek(io::SeekFrom::Start(position)));
+            try!(result);
+        }
+        if md5.finish()[..] == expected[..] {
+            Ok(Md5Status::Match)
+        } else {
+            Ok(Md5Status::Mismatch)
+        }
+    }
+
     /// Returns an iterator over all samples.
     ///
     /// The channel data is is interleaved. The iterator is streaming. That is,
@@ -831,17 +2511,175 @@ impl<R> WavReader<R>
         self.reader.into_samples()
     }
 
+    /// Reads the next frame, one sample for every channel, into `frame`.
+    ///
+    /// Returns `Ok(false)` at the end of the data, and leaves `frame`
+    /// unchanged then. If the data ends in the middle of a frame, this
+    /// returns `Error::UnfinishedSample`; errors from decoding a sample are
+    /// returned as for `samples()`. This does not allocate, so the same
+    /// buffer can be reused for every frame.
+    ///
+    /// Panics if the length of `frame` differs from the number of channels.
+    pub fn read_frame_into<S: Sample>(&mut self, frame: &mut [S]) -> Result<bool> {
+        let channels = self.spec().channels as usize;
+        assert_eq!(frame.len(), channels, "the frame must hold one sample per channel");
+        Ok(try!(self.frames_buffered(frame)) == 1)
+    }
+
+    /// Fills `buf` with the next samples, and with silence past the end of the data.
+    ///
+    /// This is for processing in blocks of a fixed size, such as the windows
+    /// of an FFT, where the last block of a file is usually incomplete. The
+    /// samples are interleaved like in the file, and read from the current
+    /// position, so this can follow `seek()`. Returns the number of samples
+    /// that were read; the rest of `buf` is set to silence, which is zero
+    /// for every sample type. At the end of the data this returns 0, with
+    /// all of `buf` silent. Errors from decoding a sample are returned as for
+    /// `samples()`. This does not allocate.
+    pub fn read_samples_into_padded<S: Sample + Default>(&mut self, buf: &mut [S]) -> Result<usize> {
+        let mut n = 0;
+        {
+            let mut samples = self.reader.samples::<S>();
+            for slot in buf.iter_mut() {
+                match samples.next() {
+                    Some(sample) => *slot = try!(sample),
+                    None => break,
+                }
+                n += 1;
+            }
+        }
+        for slot in &mut buf[n..] {
+            *slot = S::default();
+        }
+        Ok(n)
+    }
+
+    /// Reads as many whole frames as fit into `buf`, and returns how many were read.
+    ///
+    /// The samples are interleaved like in the file. Only whole frames are
+    /// read, so if the length of `buf` is not a multiple of the number of
+    /// channels, the samples at the end are left unchanged. Returns 0 at the
+    /// end of the data. If the data ends in the middle of a frame, the whole
+    /// frames before it are returned first, and then the next call returns
+    /// `Error::UnfinishedSample`. This does not allocate.
+    pub fn frames_buffered<S: Sample>(&mut self, buf: &mut [S]) -> Result<usize> {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        let channels = cmp::max(data.spec_ex.spec.channels as usize, 1);
+        let frame_len = data.spec_ex.bytes_per_sample as u64 * channels as u64;
+        let whole_frames = data.chunk.remaining / cmp::max(frame_len, 1);
+        if whole_frames == 0 && data.chunk.remaining > 0 && data.chunk.len != UNKNOWN_DATA_LEN {
+            return Err(Error::UnfinishedSample)
+        }
+        let n = cmp::min(buf.len() / channels, cmp::min(whole_frames, usize::MAX as u64) as usize);
+        let mut samples = self.reader.samples::<S>();
+        for (i, frame) in buf[..n * channels].chunks_mut(channels).enumerate() {
+            for (j, slot) in frame.iter_mut().enumerate() {
+                match samples.next() {
+                    Some(sample) => *slot = try!(sample),
+                    // A stream of unknown length may end anywhere.
+                    None if j == 0 => return Ok(i),
+                    None => return Err(Error::UnfinishedSample),
+                }
+            }
+        }
+        Ok(n)
+    }
+
+    /// Returns a reader of frames, which reuses one buffer for all of them.
+    ///
+    /// See `WavFrames::next_frame()`.
+    pub fn frames<'wr, S: Sample + Clone + Default>(&'wr mut self) -> WavFrames<'wr, R, S> {
+        let channels = self.spec().channels as usize;
+        WavFrames {
+            reader: self,
+            frame: vec![S::default(); channels],
+        }
+    }
+
+    /// Returns an iterator over frames of exactly `N` samples.
+    ///
+    /// Returns `Error::InvalidSpec` if `N` differs from the number of
+    /// channels. Every frame is returned by value, so nothing is allocated.
+    /// See `read_frame_into()` for how the end of the data is handled.
+    pub fn frames_fixed<'wr, S, const N: usize>(&'wr mut self) -> Result<WavFramesFixed<'wr, R, S, N>>
+        where S: Sample + Copy + Default
+    {
+        try!(super::write::check_frame_size(N, self.spec().channels));
+        Ok(WavFramesFixed {
+            reader: self,
+            phantom_sample: marker::PhantomData,
+        })
+    }
+
+    /// Returns an iterator over 8-bit samples that are stored as signed bytes.
+    ///
+    /// The WAVE format stores 8-bit samples unsigned, offset by 128, and
+    /// `samples()` removes that offset. Some tools wrote signed bytes instead,
+    /// with the same header. This reads the bytes of such files as they are.
+    /// The header does not tell the variants apart, so only use this when a
+    /// file is known to be signed; for a standard file it flips the sign bit
+    /// of every sample. For other formats every iteration returns an error,
+    /// like for `samples::<i8>()`.
+    pub fn samples_signed8<'wr>(&'wr mut self) -> WavSamples<'wr, R, i8> {
+        let mut samples = self.reader.samples::<i8>();
+        if samples.read_fn.is_some() {
+            samples.read_fn = Some(|r| r.read_i8());
+        }
+        samples
+    }
+
+    /// Returns an iterator over all samples, after checking that `S` can hold them.
+    ///
+    /// This is the same as `samples()`, except that a sample type that does
+    /// not match the file is reported once, before iterating, rather than on
+    /// every iteration. The error is the one that iterating would return, for
+    /// example `Error::TooWide` if `S` is too narrow for the samples, or
+    /// `Error::InvalidSampleFormat` if `S` is an integer type while the file
+    /// holds floating point samples.
+    pub fn try_samples<'wr, S: Sample>(&'wr mut self) -> Result<WavSamples<'wr, R, S>> {
+        try!(check_sample_type::<S>(self.reader.spec_ex.expect("no spec")));
+        Ok(self.reader.samples())
+    }
+
+    /// Same as `try_samples`, but takes ownership of the `WavReader`.
+    ///
+    /// See `try_samples()` for more info.
+    pub fn try_into_samples<S: Sample>(self) -> Result<WavIntoSamples<R, S>> {
+        try!(check_sample_type::<S>(self.reader.spec_ex.expect("no spec")));
+        Ok(self.reader.into_samples())
+    }
+
     /// Returns the duration of the file in samples.
     ///
     /// The duration is independent of the number of channels. It is expressed
     /// in units of samples. The duration in seconds can be obtained by
     /// dividing this number by the sample rate. The duration is independent of
     /// how many samples have been read already.
+    ///
+    /// The duration is computed from the length of the data chunk as stored
+    /// in the header, so it does not require seeking. Streaming writers that
+    /// do not know the length up front may store `0xffffffff` instead; then
+    /// the duration is unreliable, and `has_unknown_length()` returns true.
+    /// The samples of such a file can still be read until the source ends.
+    /// Use `known_duration()` to tell the two cases apart.
     pub fn duration(&self) -> u32 {
         let data = self.reader.data_state.expect("Not in the data chunk.");
         self.len() / data.spec_ex.spec.channels as u32
     }
 
+    /// Returns the duration of the file in samples, if the header specifies it.
+    ///
+    /// This is the same as `duration()`, but it returns `None` when the data
+    /// chunk length is the `0xffffffff` placeholder of a streaming writer,
+    /// rather than a duration based on that placeholder.
+    pub fn known_duration(&self) -> Option<u32> {
+        if self.has_unknown_length() {
+            None
+        } else {
+            Some(self.duration())
+        }
+    }
+
     /// Returns the number of values that the sample iterator will yield.
     ///
     /// The length of the file is its duration (in samples) times the number of
@@ -853,11 +2691,236 @@ impl<R> WavReader<R>
         (data.chunk.len as usize / data.spec_ex.bytes_per_sample as usize) as u32
     }
 
+    /// Returns the number of samples that have been read so far.
+    ///
+    /// Like `len()`, this counts the samples of all channels; divide by the
+    /// number of channels for the frame, or use `elapsed()` for the time. The
+    /// position advances with every sample that the iterators or the bulk
+    /// reads consume, also when an iterator is dropped in the middle of the
+    /// file, and samples skipped by `skip_samples()` or `channel_samples()`
+    /// count as read. After `seek()` to a frame, it is that frame times the
+    /// number of channels.
+    pub fn position(&self) -> u64 {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        (data.chunk.len - data.chunk.remaining) / data.spec_ex.bytes_per_sample as u64
+    }
+
+    /// Returns the time of the frame at `position()`, since the start of the samples.
+    ///
+    /// A partially read frame counts as the time of its start. For a file with
+    /// a sample rate of zero, the time is always zero.
+    pub fn elapsed(&self) -> time::Duration {
+        let spec = self.spec();
+        let frames = self.position() / cmp::max(spec.channels, 1) as u64;
+        let rate = spec.sample_rate as u64;
+        if rate == 0 {
+            return time::Duration::new(0, 0)
+        }
+        time::Duration::new(frames / rate, ((frames % rate) * 1_000_000_000 / rate) as u32)
+    }
+
+    /// Returns the `dwChannelMask` field of a WAVEFORMATEXTENSIBLE fmt chunk.
+    ///
+    /// Every bit that is set assigns the next channel to a speaker position,
+    /// see `speaker_positions()`. Returns `None` if the fmt chunk has a
+    /// different format, which has no channel mask.
+    pub fn channel_mask(&self) -> Option<u32> {
+        self.reader.channel_mask
+    }
+
+    /// Returns the speaker position of every channel, in channel order.
+    ///
+    /// The positions are taken from `channel_mask()`. Channels for which the
+    /// mask has no position, and all channels of a file without a mask, are
+    /// `SpeakerPosition::Unassigned`.
+    pub fn speaker_positions(&self) -> Vec<SpeakerPosition> {
+        SpeakerPosition::for_channels(self.channel_mask().unwrap_or(0), self.spec().channels)
+    }
+
+    /// Returns whether the header marks the length of the data as unknown.
+    ///
+    /// Streaming writers store a data chunk length of `0xffffffff` when the
+    /// length is not known up front. For such files `duration()` and `len()`
+    /// are upper bounds, and the sample iterators end when the source does.
+    pub fn has_unknown_length(&self) -> bool {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        data.chunk.len == UNKNOWN_DATA_LEN
+    }
+
     /// Destroys the `WavReader` and returns the underlying reader.
     pub fn into_inner(self) -> R {
         self.reader.into_inner()
     }
 
+    /// Returns the DC offset of every channel: the mean of its samples.
+    ///
+    /// Samples are normalized to the range [-1.0, 1.0), so the result does not
+    /// depend on the bit depth. A large offset indicates a bias that should be
+    /// removed with a high-pass filter. This reads the remaining samples in
+    /// one pass, afterwards the sample iterator is exhausted. The offset of a
+    /// file without samples is zero.
+    pub fn dc_offset(&mut self) -> Result<Vec<f64>> {
+        let channels = self.spec().channels as usize;
+        let mut sums = vec![0.0; channels];
+        let mut frames = 0_u64;
+        try!(self.for_each_normalized(|channel, x| {
+            sums[channel] += x;
+            if channel == channels - 1 {
+                frames += 1;
+            }
+        }));
+        if frames > 0 {
+            for sum in &mut sums {
+                *sum /= frames as f64;
+            }
+        }
+        Ok(sums)
+    }
+
+    /// Returns the smallest and largest sample, and the peak, of every channel.
+    ///
+    /// Like for `dc_offset()`, samples are normalized to the range
+    /// [-1.0, 1.0), and the remaining samples are read in one pass, so the
+    /// sample iterator is exhausted afterwards. Positions are frames counted
+    /// from the start of the data. The stats of a file without samples are
+    /// all zero.
+    pub fn scan_peaks(&mut self) -> Result<Vec<ChannelStats>> {
+        let spec = self.spec();
+        let start = {
+            let data = self.reader.data_state.expect("not in the data chunk");
+            let frame_len = data.spec_ex.bytes_per_sample as u64 * spec.channels as u64;
+            ((data.chunk.len - data.chunk.remaining) / frame_len) as u32
+        };
+        let mut stats = vec![ChannelStats::new(); spec.channels as usize];
+        match spec.sample_format {
+            SampleFormat::Float => {
+                try!(self.for_each_buffered::<f32, _>(|channel, frame, x| {
+                    stats[channel].add(x as f64, start + frame);
+                }));
+            }
+            SampleFormat::Int => {
+                let scale = 0.5_f64.powi(spec.bits_per_sample as i32 - 1);
+                try!(self.for_each_buffered::<i32, _>(|channel, frame, x| {
+                    stats[channel].add(x as f64 * scale, start + frame);
+                }));
+            }
+        }
+        for channel in &mut stats {
+            if channel.min > channel.max {
+                *channel = ChannelStats { min: 0.0, max: 0.0, peak: 0.0, peak_position: 0 };
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Calls `f` with the channel, the frame, and the value of every remaining sample.
+    ///
+    /// The samples are read with `frames_buffered()`, the frame counts from
+    /// the first sample read.
+    fn for_each_buffered<S, F>(&mut self, mut f: F) -> Result<()>
+        where S: Sample + Copy + Default,
+              F: FnMut(usize, u32, S)
+    {
+        let channels = self.spec().channels as usize;
+        let mut buffer = vec![S::default(); 1024 * channels];
+        let mut frame = 0;
+        loop {
+            let n = try!(self.frames_buffered(&mut buffer));
+            if n == 0 {
+                return Ok(());
+            }
+            for samples in buffer[..n * channels].chunks(channels) {
+                for (channel, &sample) in samples.iter().enumerate() {
+                    f(channel, frame, sample);
+                }
+                frame += 1;
+            }
+        }
+    }
+
+    /// Calls `f` with the channel and the normalized value of every remaining sample.
+    ///
+    /// Integer samples are divided by 2 to the power `bits_per_sample - 1`,
+    /// float samples are passed as they are.
+    fn for_each_normalized<F: FnMut(usize, f64)>(&mut self, mut f: F) -> Result<()> {
+        let spec = self.spec();
+        let channels = spec.channels as usize;
+        match spec.sample_format {
+            SampleFormat::Float => {
+                for (i, sample) in self.samples::<f32>().enumerate() {
+                    f(i % channels, try!(sample) as f64);
+                }
+            }
+            SampleFormat::Int => {
+                // The bit depth is not limited to 64 here, decoding fails later.
+                let scale = 0.5_f64.powi(spec.bits_per_sample as i32 - 1);
+                for (i, sample) in self.samples::<i32>().enumerate() {
+                    f(i % channels, try!(sample) as f64 * scale);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over the mean of the channels of every frame.
+    ///
+    /// This downmixes the file to mono: the iterator yields one value per
+    /// frame, the average of its samples as computed by `Average::average()`.
+    /// Integers are averaged without overflow and rounded to nearest. Frames
+    /// are read as a whole; an incomplete frame at the end of the data is an
+    /// `Error::UnfinishedSample`.
+    pub fn samples_mono<'wr, S: Average>(&'wr mut self) -> WavMonoSamples<'wr, R, S> {
+        let channels = self.spec().channels as usize;
+        WavMonoSamples {
+            samples: self.reader.samples(),
+            frame: Vec::with_capacity(channels),
+            channels: channels,
+        }
+    }
+
+    /// Returns an iterator over the samples of one channel.
+    ///
+    /// The samples of the other channels are skipped without decoding them.
+    /// They are read and discarded rather than seeked over, because they are
+    /// only a few bytes per frame, and seeking would drop the buffer of a
+    /// `BufReader`. Like `samples()`, the iterator continues where a previous
+    /// iterator stopped, also in the middle of a frame. An error is returned
+    /// if `channel` is not less than the number of channels.
+    pub fn channel_samples<'wr, S: Sample>(&'wr mut self, channel: u16) -> Result<WavChannelSamples<'wr, R, S>> {
+        let channels = self.spec().channels;
+        if channel >= channels {
+            let msg = format!("channel {} does not exist in a file with {} channels", channel, channels);
+            return Err(Error::IoError(io::Error::new(io::ErrorKind::InvalidInput, msg)));
+        }
+        Ok(WavChannelSamples {
+            samples: self.reader.samples(),
+            channel: channel as u64,
+            channels: channels as u64,
+        })
+    }
+
+    /// Skips `n` samples of every channel, without decoding them.
+    ///
+    /// Like for `seek()`, `n` is measured in samples independent of the number
+    /// of channels. Skipping past the end is not an error, the sample iterator
+    /// is exhausted afterwards. The data is read and discarded, so this works
+    /// for sources that cannot seek. When the source implements `Seek`,
+    /// `seek()` avoids reading the data altogether.
+    pub fn skip_samples(&mut self, n: u32) -> Result<()> {
+        let (remaining, bytes_per_frame) = {
+            let data = self.reader.data_state.expect("not in the data chunk");
+            let frame = data.spec_ex.bytes_per_sample as u64 * data.spec_ex.spec.channels as u64;
+            (data.chunk.remaining, frame)
+        };
+        let bytes = cmp::min(n as u64 * bytes_per_frame, remaining);
+        let skipped = try!(io::copy(&mut io::Read::take(&mut self.reader, bytes), &mut io::sink()));
+        if skipped < bytes {
+            return Err(Error::IoError(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                                     "data chunk is truncated")));
+        }
+        Ok(())
+    }
+
     /// Seek to the given time within the file.
     ///
     /// The given time is measured in number of samples (independent of the
@@ -873,30 +2936,437 @@ impl<R> WavReader<R>
     {
         self.reader.seek(time)
     }
+
+    /// Returns an iterator that plays the file, and then loops back to `loop_start`.
+    ///
+    /// The reader first seeks to the start of the data. After the last frame
+    /// of the file, it seeks back to the frame `loop_start` and continues
+    /// from there, `repeats` more times, or forever if `repeats` is `None`.
+    /// The loop always starts at a whole frame, so the channels stay in
+    /// order. An error is returned if `loop_start` is not before the end of
+    /// the file.
+    pub fn samples_looped<'wr, S: Sample>(&'wr mut self,
+                                          loop_start: u32,
+                                          repeats: Option<u32>)
+                                          -> Result<WavLoopedSamples<'wr, R, S>>
+        where R: io::Seek,
+    {
+        let end = self.duration();
+        if loop_start >= end {
+            let msg = format!("loop start {} is not before the end of the file at frame {}", loop_start, end);
+            return Err(Error::IoError(io::Error::new(io::ErrorKind::InvalidInput, msg)));
+        }
+        self.looped(loop_start, end, repeats)
+    }
+
+    /// Returns an iterator that plays the first loop of the `smpl` chunk.
+    ///
+    /// This is like `samples_looped()`, but the loop is the first one in
+    /// `sampler_info()`: after its last frame, the reader seeks back to its
+    /// first frame. When the loop has been played as many times as its play
+    /// count, playback continues to the end of the file. A play count of
+    /// zero loops forever. Returns `Ok(None)` if the file has no `smpl`
+    /// chunk, or if the chunk has no loops. The loop type is not taken into
+    /// account, every loop is played forward.
+    pub fn samples_smpl_looped<'wr, S: Sample>(&'wr mut self) -> Result<Option<WavLoopedSamples<'wr, R, S>>>
+        where R: io::Seek,
+    {
+        let sample_loop = match try!(self.sampler_info()) {
+            Some(ref info) if !info.loops.is_empty() => info.loops[0].clone(),
+            _ => return Ok(None),
+        };
+        if sample_loop.start > sample_loop.end || sample_loop.end >= self.duration() {
+            let msg = format!("smpl loop from frame {} to {} is not within the {} frames of the file",
+                              sample_loop.start, sample_loop.end, self.duration());
+            return Err(Error::FormatError(msg));
+        }
+        let repeats = match sample_loop.play_count {
+            0 => None,
+            n => Some(n - 1),
+        };
+        self.looped(sample_loop.start, sample_loop.end + 1, repeats).map(Some)
+    }
+
+    /// Returns an iterator that jumps from frame `end` back to frame `start`.
+    fn looped<'wr, S: Sample>(&'wr mut self, start: u32, end: u32, repeats: Option<u32>)
+                              -> Result<WavLoopedSamples<'wr, R, S>>
+        where R: io::Seek,
+    {
+        try!(self.seek(0));
+        let spec_ex = self.reader.data_state.expect("not in the data chunk").spec_ex;
+        let frame_len = spec_ex.spec.channels as u64 * spec_ex.bytes_per_sample as u64;
+        Ok(WavLoopedSamples {
+            samples: self.reader.samples(),
+            loop_start: start,
+            loop_end: end as u64 * frame_len,
+            repeats: repeats,
+        })
+    }
+}
+
+/// Validates `spec` for a reader that does not take the format from the header.
+fn raw_spec_ex(spec: WavSpec) -> Result<WavSpecEx> {
+    try!(spec.validate());
+    Ok(WavSpecEx {
+        spec: spec,
+        // Validation ensures the bit depth is a whole number of bytes.
+        bytes_per_sample: spec.bits_per_sample / 8,
+    })
+}
+
+/// Returns an error if samples of the file cannot be decoded into `S`.
+fn check_sample_type<S: Sample>(spec_ex: WavSpecEx) -> Result<()> {
+    // Decode a silent sample, which fails exactly when decoding real samples
+    // would fail, but without touching the file.
+    let silence = vec![0; spec_ex.bytes_per_sample as usize];
+    let spec = spec_ex.spec;
+    S::read(&mut &silence[..], spec.sample_format, spec_ex.bytes_per_sample, spec.bits_per_sample)
+        .map(|_| ())
+}
+
+/// Reads chunks until the end of the file, appends them to `chunks`.
+///
+/// The size of every chunk is checked with `check_chunk_len()` before it is read.
+fn read_remaining_chunks<R>(reader: &mut R, chunks: &mut Vec<RawChunk>, options: &ReadOptions) -> Result<()>
+    where R: io::Read + io::Seek,
+{
+    let position = try!(reader.stream_position());
+    let end = try!(reader.seek(io::SeekFrom::End(0)));
+    try!(reader.seek(io::SeekFrom::Start(position)));
+    loop {
+        let offset = try!(reader.stream_position());
+        let mut id = [0; 4];
+        if reader.read_into(&mut id).is_err() {
+            // As in `ChunksReader::next()`, end of file cannot be told apart
+            // from other errors here.
+            return Ok(())
+        }
+        let len = try!(reader.read_le_u32()) as u64;
+        // A truncated chunk is rejected here as well, before it is read.
+        let available = end.saturating_sub(offset + 8);
+        try!(check_chunk_len(len, Some(available), options).map_err(|err| chunk_error(&id, offset, err)));
+        let mut data = Vec::new();
+        try!(io::Read::read_to_end(&mut io::Read::take(&mut *reader, len), &mut data));
+        chunks.push(RawChunk { id: id, data: data });
+        if len % 2 == 1 {
+            // The pad byte of the last chunk is sometimes omitted, in that
+            // case reading the next header fails and the loop ends.
+            let _ = reader.read_u8();
+        }
+    }
+}
+
+/// Reads the spec and all samples of a file.
+///
+/// This is a shorthand for opening the file with `WavReader::open()` and
+/// collecting `samples()`. The samples are interleaved. The first error,
+/// while opening the file or decoding a sample, is returned.
+///
+/// ```
+/// let (spec, samples) = hound::read_all::<i16, _>("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+/// assert_eq!(spec.channels, 1);
+/// assert_eq!(samples.len(), 4);
+/// ```
+pub fn read_all<S: Sample, P: AsRef<path::Path>>(filename: P) -> Result<(WavSpec, Vec<S>)> {
+    let file_len = try!(fs::metadata(filename.as_ref())).len();
+    let mut reader = try!(WavReader::open(filename));
+    let spec = reader.spec();
+    let mut samples = Vec::new();
+    if !reader.has_unknown_length() {
+        // The file cannot hold more samples than bytes, do not trust the header beyond that.
+        let bytes_per_sample = reader.spec_ex().bytes_per_sample as u64;
+        let stored = file_len / cmp::max(bytes_per_sample, 1);
+        let len = cmp::min(reader.duration() as u64 * spec.channels as u64, stored);
+        samples.reserve(len as usize);
+    }
+    for sample in reader.samples() {
+        samples.push(try!(sample));
+    }
+    Ok((spec, samples))
+}
+
+/// Returns an iterator over the samples of all readers, one after the other.
+///
+/// This treats a set of files as one continuous stream. All readers must have
+/// the same spec (number of channels, sample rate, bits per sample and sample
+/// format). That is verified before any samples are read, and an error is
+/// returned if the specs differ.
+pub fn concat<R, S, I>(readers: I) -> Result<WavConcatSamples<R, S>>
+    where R: io::Read,
+          S: Sample,
+          I: IntoIterator<Item = WavReader<R>>
+{
+    let readers: Vec<WavReader<R>> = readers.into_iter().collect();
+    if let Some(first) = readers.first() {
+        let spec = first.spec();
+        if let Some(other) = readers.iter().find(|r| r.spec() != spec) {
+            let msg = format!("concatenated files have different specs: {} and {}", spec, other.spec());
+            return Err(Error::FormatError(msg));
+        }
+    }
+    Ok(WavConcatSamples {
+        current: None,
+        rest: readers.into_iter(),
+    })
+}
+
+impl WavReader<io::BufReader<fs::File>> {
+    /// Attempts to create a reader that reads from the specified file.
+    ///
+    /// This is a convenience constructor that opens a `File`, wraps it in a
+    /// `BufReader` and then constructs a `WavReader` from it.
+    pub fn open<P: AsRef<path::Path>>(filename: P) -> Result<WavReader<io::BufReader<fs::File>>> {
+        WavReader::open_with_options(filename, ReadOptions::default())
+    }
+
+    /// Attempts to create a reader that reads from the specified file as specified by `options`.
+    ///
+    /// This is the same as `open()`, which uses `ReadOptions::default()`.
+    pub fn open_with_options<P: AsRef<path::Path>>(filename: P,
+                                                   options: ReadOptions)
+                                                   -> Result<WavReader<io::BufReader<fs::File>>> {
+        let file = try!(fs::File::open(filename.as_ref()));
+        let buf_reader = io::BufReader::new(file);
+        let mut reader = try!(WavReader::new_seekable_with_options(buf_reader, options));
+        reader.path = Some(filename.as_ref().to_path_buf());
+        Ok(reader)
+    }
+
+    /// Returns a reader with its own handle to the file, at the same position.
+    ///
+    /// Handles to the same open file share their position, so the file is
+    /// opened again, and the new handle is moved to the position of this
+    /// reader. Afterwards both readers read independently. The header is not
+    /// parsed again, so this is cheap, but the file must not have changed.
+    /// An error is returned if the file cannot be opened again, or if this
+    /// reader was not created with `open()`, because then its path is not
+    /// known.
+    pub fn try_fork(&self) -> Result<WavReader<io::BufReader<fs::File>>> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => {
+                let msg = "cannot fork a reader that was not created with WavReader::open";
+                return Err(Error::IoError(io::Error::new(io::ErrorKind::InvalidInput, msg)));
+            }
+        };
+        // The position of the reader excludes the bytes that were buffered
+        // but not consumed yet.
+        let buffered = self.reader.reader.buffer().len() as u64;
+        let position = try!(io::Seek::seek(&mut self.reader.reader.get_ref(), io::SeekFrom::Current(0)));
+        let mut file = try!(fs::File::open(&path));
+        try!(io::Seek::seek(&mut file, io::SeekFrom::Start(position - buffered)));
+        Ok(WavReader {
+            reader: ChunksReader {
+                reader: io::BufReader::new(file),
+                spec_ex: self.reader.spec_ex,
+                data_state: self.reader.data_state,
+                channel_mask: self.reader.channel_mask,
+                sub_format: self.reader.sub_format,
+                next_offset: self.reader.next_offset,
+                wavl: self.reader.wavl.clone(),
+                adpcm: self.reader.adpcm.clone(),
+                options: self.reader.options,
+                riff_end: self.reader.riff_end,
+            },
+            chunks: self.chunks.clone(),
+            leading_chunks: self.leading_chunks,
+            read_trailing: self.read_trailing,
+            deferred_ixml: self.deferred_ixml,
+            seekable: self.seekable,
+            path: Some(path),
+        })
+    }
+}
+
+impl<'a> WavReader<&'a [u8]> {
+    /// Returns the remaining 16-bit samples, borrowed from the input without decoding.
+    ///
+    /// When the file is in memory, 16-bit samples are already stored as
+    /// little-endian `i16` values, so on little-endian targets they can be
+    /// used in place, which is much faster than iterating with `samples()`.
+    /// The slice starts at the current position and holds the whole samples
+    /// of the data chunk that are present in the input. It does not advance
+    /// the reader.
+    ///
+    /// Returns `None` if the samples are not 16-bit integers stored in two
+    /// bytes, if the target is big endian, or if the samples are not aligned
+    /// to two bytes in memory, which depends on where the input was
+    /// allocated and on the size of the header, or if the samples are stored
+    /// in a `wavl` list or encoded as ADPCM. Use `samples()` then.
+    pub fn samples_raw_i16(&self) -> Option<&'a [i16]> {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        if self.reader.wavl.is_some() || self.reader.adpcm.is_some() {
+            return None
+        }
+        borrow_i16(self.reader.reader, data)
+    }
+
+    /// Decodes the remaining samples on several threads.
+    ///
+    /// The samples are split into runs of whole frames, which are decoded in
+    /// parallel, one run per available core. The result, including the error
+    /// if decoding fails, is the same as collecting `samples()` into a
+    /// `Result<Vec<S>>`. Unlike `samples()`, this does not advance the reader.
+    ///
+    /// This method is only available with the `parallel` feature enabled.
+    #[cfg(feature = "parallel")]
+    pub fn read_all_parallel<S: Sample + Send>(&self) -> Result<Vec<S>> {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        if self.reader.wavl.is_some() || self.reader.adpcm.is_some() {
+            // The samples are not stored as they are decoded.
+            return self.clone().samples().collect()
+        }
+        decode_parallel(self.reader.reader, data, available_threads())
+    }
+}
+
+impl<'a> WavReader<io::Cursor<&'a [u8]>> {
+    /// Returns the remaining 16-bit samples, borrowed from the input without decoding.
+    ///
+    /// See the method of the same name on `WavReader<&[u8]>`.
+    pub fn samples_raw_i16(&self) -> Option<&'a [i16]> {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        if self.reader.wavl.is_some() || self.reader.adpcm.is_some() {
+            return None
+        }
+        let cursor = &self.reader.reader;
+        let bytes: &'a [u8] = cursor.get_ref();
+        let position = cmp::min(cursor.position(), bytes.len() as u64) as usize;
+        borrow_i16(&bytes[position..], data)
+    }
+
+    /// Decodes the remaining samples on several threads.
+    ///
+    /// See the method of the same name on `WavReader<&[u8]>`.
+    #[cfg(feature = "parallel")]
+    pub fn read_all_parallel<S: Sample + Send>(&self) -> Result<Vec<S>> {
+        let data = self.reader.data_state.expect("not in the data chunk");
+        if self.reader.wavl.is_some() || self.reader.adpcm.is_some() {
+            // The samples are not stored as they are decoded.
+            return self.clone().samples().collect()
+        }
+        let cursor = &self.reader.reader;
+        let bytes: &'a [u8] = cursor.get_ref();
+        let position = cmp::min(cursor.position(), bytes.len() as u64) as usize;
+        decode_parallel(&bytes[position..], data, available_threads())
+    }
+}
+
+/// Returns the number of threads to decode with, one per core.
+#[cfg(feature = "parallel")]
+fn available_threads() -> usize {
+    ::std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+/// Decodes the samples at the start of `bytes` on `threads` threads, see `read_all_parallel()`.
+#[cfg(feature = "parallel")]
+fn decode_parallel<S: Sample + Send>(bytes: &[u8], data: DataReadingState, threads: usize)
+                                     -> Result<Vec<S>> {
+    use std::thread;
+
+    let spec_ex = data.spec_ex;
+    let bytes_per_sample = spec_ex.bytes_per_sample as usize;
+    let frame_len = bytes_per_sample * cmp::max(spec_ex.spec.channels as usize, 1);
+    let available = cmp::min(data.chunk.remaining, bytes.len() as u64) as usize;
+    let whole = available / bytes_per_sample * bytes_per_sample;
+    let frames = whole / frame_len;
+    // Round up, so there are no more segments than threads.
+    let frames_per_thread = if frames == 0 { 1 } else { (frames - 1) / threads + 1 };
+    let segment_len = frames_per_thread * frame_len;
+
+    let decode = |mut segment: &[u8]| -> Result<Vec<S>> {
+        let mut samples = Vec::with_capacity(segment.len() / bytes_per_sample);
+        let read_fn = S::read_fn(spec_ex.spec.sample_format, spec_ex.bytes_per_sample,
+                                 spec_ex.spec.bits_per_sample);
+        while !segment.is_empty() {
+            samples.push(try!(match read_fn {
+                Some(f) => f(&mut segment).map_err(Error::from),
+                None => Sample::read(&mut segment,
+                                     spec_ex.spec.sample_format,
+                                     spec_ex.bytes_per_sample,
+                                     spec_ex.spec.bits_per_sample),
+            }));
+        }
+        Ok(samples)
+    };
+    let results: Vec<Result<Vec<S>>> = thread::scope(|scope| {
+        let workers: Vec<_> = bytes[..whole].chunks(segment_len)
+            .map(|segment| scope.spawn(move || decode(segment)))
+            .collect();
+        workers.into_iter().map(|worker| worker.join().expect("decoding thread panicked")).collect()
+    });
+
+    // Report the error of the first segment that failed, like sequential decoding would.
+    let mut samples = Vec::with_capacity(whole / bytes_per_sample);
+    for result in results {
+        samples.extend(try!(result));
+    }
+
+    // The data chunk may end in a partial sample, or the input may end before
+    // the data chunk. Decoding the rest then fails like in `samples()`,
+    // except that a stream of unknown length may end after a whole sample.
+    let ends_cleanly = available == data.chunk.remaining as usize
+        || (data.chunk.len == UNKNOWN_DATA_LEN && available == whole);
+    if whole < available || !ends_cleanly {
+        let mut rest = &bytes[whole..available];
+        let sample: Result<S> = Sample::read(&mut rest,
+                                             spec_ex.spec.sample_format,
+                                             spec_ex.bytes_per_sample,
+                                             spec_ex.spec.bits_per_sample);
+        try!(sample);
+    }
+    Ok(samples)
 }
 
-impl WavReader<io::BufReader<fs::File>> {
-    /// Attempts to create a reader that reads from the specified file.
-    ///
-    /// This is a convenience constructor that opens a `File`, wraps it in a
-    /// `BufReader` and then constructs a `WavReader` from it.
-    pub fn open<P: AsRef<path::Path>>(filename: P) -> Result<WavReader<io::BufReader<fs::File>>> {
-        let file = try!(fs::File::open(filename));
-        let buf_reader = io::BufReader::new(file);
-        WavReader::new(buf_reader)
+/// Reinterprets the samples at the start of `bytes` as `i16`, see `samples_raw_i16()`.
+fn borrow_i16(bytes: &[u8], data: DataReadingState) -> Option<&[i16]> {
+    let spec = data.spec_ex.spec;
+    if spec.sample_format != SampleFormat::Int || spec.bits_per_sample != 16
+        || data.spec_ex.bytes_per_sample != 2 || cfg!(target_endian = "big") {
+        return None
+    }
+    let len = cmp::min(data.chunk.remaining, bytes.len() as u64) as usize & !1;
+    // SAFETY: every bit pattern is a valid `i16`, and `align_to` only
+    // returns a middle slice that is correctly aligned and in bounds.
+    let (prefix, samples, _) = unsafe { bytes[..len].align_to::<i16>() };
+    if prefix.is_empty() {
+        Some(samples)
+    } else {
+        None
     }
 }
 
-fn iter_next<R, S>(reader: &mut ChunksReader<R>) -> Option<Result<S>>
+/// A function that reads one sample of a format that is known up front.
+type ReadFn<R, S> = fn(&mut ChunksReader<R>) -> io::Result<S>;
+
+/// Reads the next sample, with `read_fn` if the sample type provides one for the format.
+#[inline]
+fn iter_next<R, S>(reader: &mut ChunksReader<R>,
+                   read_fn: Option<ReadFn<R, S>>)
+                   -> Option<Result<S>>
     where R: io::Read,
           S: Sample
 {
     let data = reader.data_state.expect("reader not in data chunk");
     if data.chunk.remaining > 0 {
-        let sample = Sample::read(reader,
-                                  data.spec_ex.spec.sample_format,
-                                  data.spec_ex.bytes_per_sample,
-                                  data.spec_ex.spec.bits_per_sample);
+        let sample = match read_fn {
+            Some(f) => f(reader).map_err(Error::from),
+            None => Sample::read(reader,
+                                 data.spec_ex.spec.sample_format,
+                                 data.spec_ex.bytes_per_sample,
+                                 data.spec_ex.spec.bits_per_sample),
+        };
+        if let Err(Error::IoError(_)) = sample {
+            // A stream of unknown length ends when the source does, but only
+            // cleanly if it ends in between samples. Like in `next()`, the
+            // end of the source cannot be told apart from other errors.
+            let ends_with_source = reader.ends_with_source();
+            let state = reader.data_state.as_mut().expect("reader not in data chunk");
+            if ends_with_source && state.chunk.remaining == data.chunk.remaining {
+                state.chunk.remaining = 0;
+                return None
+            }
+        }
         Some(sample.map_err(Error::from))
     } else {
         None
@@ -916,7 +3386,7 @@ impl<'wr, R, S> Iterator for WavSamples<'wr, R, S>
     type Item = Result<S>;
 
     fn next(&mut self) -> Option<Result<S>> {
-        iter_next(&mut self.reader)
+        iter_next(self.reader, self.read_fn)
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -930,57 +3400,853 @@ impl<'wr, R, S> ExactSizeIterator for WavSamples<'wr, R, S>
 {
 }
 
-impl<R, S> Iterator for WavIntoSamples<R, S>
-    where R: io::Read,
-          S: Sample
-{
-    type Item = Result<S>;
+impl<R, S> Iterator for WavIntoSamples<R, S>
+    where R: io::Read,
+          S: Sample
+{
+    type Item = Result<S>;
+
+    fn next(&mut self) -> Option<Result<S>> {
+        iter_next(&mut self.reader, self.read_fn)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        iter_size_hint(&self.reader)
+    }
+}
+
+impl<R, S> ExactSizeIterator for WavIntoSamples<R, S>
+    where R: io::Read,
+          S: Sample
+{
+}
+
+impl<R, S> Iterator for WavConcatSamples<R, S>
+    where R: io::Read,
+          S: Sample
+{
+    type Item = Result<S>;
+
+    fn next(&mut self) -> Option<Result<S>> {
+        loop {
+            if let Some(ref mut samples) = self.current {
+                if let Some(sample) = samples.next() {
+                    return Some(sample)
+                }
+            }
+            match self.rest.next() {
+                Some(reader) => self.current = Some(reader.into_samples()),
+                None => return None,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let current = self.current.as_ref().map_or(0, |samples| samples.len());
+        let rest = self.rest.as_slice().iter().map(|r| r.len() as usize).sum::<usize>();
+        (current + rest, Some(current + rest))
+    }
+}
+
+impl<R, S> ExactSizeIterator for WavConcatSamples<R, S>
+    where R: io::Read,
+          S: Sample
+{
+}
+
+impl<I> WavTimedSamples<I> {
+    /// Wraps `samples`, which must be at the position of `data`.
+    fn new(data: DataReadingState, samples: I) -> WavTimedSamples<I> {
+        let consumed = data.chunk.len - data.chunk.remaining;
+        WavTimedSamples {
+            samples: samples,
+            index: consumed / data.spec_ex.bytes_per_sample as u64,
+            channels: data.spec_ex.spec.channels as u64,
+            sample_rate: data.spec_ex.spec.sample_rate as u64,
+        }
+    }
+}
+
+impl<'wr, R, S> WavSamples<'wr, R, S>
+    where R: io::Read,
+          S: Sample
+{
+    /// Yields every sample together with the time of its frame.
+    ///
+    /// The time is counted from the start of the audio data, so after a
+    /// `seek()` the first sample is not at zero. It is computed exactly from
+    /// the frame index and the sample rate, without accumulating rounding
+    /// errors, and truncated to whole nanoseconds.
+    pub fn timed(self) -> WavTimedSamples<Self> {
+        let data = self.reader.data_state.expect("reader not in data chunk");
+        WavTimedSamples::new(data, self)
+    }
+}
+
+impl<R, S> WavIntoSamples<R, S>
+    where R: io::Read,
+          S: Sample
+{
+    /// Yields every sample together with the time of its frame.
+    ///
+    /// See `WavSamples::timed()`.
+    pub fn timed(self) -> WavTimedSamples<Self> {
+        let data = self.reader.data_state.expect("reader not in data chunk");
+        WavTimedSamples::new(data, self)
+    }
+}
+
+impl<I, S> Iterator for WavTimedSamples<I>
+    where I: Iterator<Item = Result<S>>
+{
+    type Item = Result<(time::Duration, S)>;
+
+    fn next(&mut self) -> Option<Result<(time::Duration, S)>> {
+        let sample = match self.samples.next() {
+            Some(Ok(sample)) => sample,
+            Some(Err(err)) => return Some(Err(err)),
+            None => return None,
+        };
+        let frame = self.index / cmp::max(self.channels, 1);
+        self.index += 1;
+        // A malformed header may claim a sample rate of zero, then there is
+        // no meaningful time.
+        if self.sample_rate == 0 {
+            return Some(Ok((time::Duration::new(0, 0), sample)))
+        }
+        let secs = frame / self.sample_rate;
+        let nanos = (frame % self.sample_rate) * 1_000_000_000 / self.sample_rate;
+        Some(Ok((time::Duration::new(secs, nanos as u32), sample)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.samples.size_hint()
+    }
+}
+
+impl<I, S> ExactSizeIterator for WavTimedSamples<I>
+    where I: ExactSizeIterator<Item = Result<S>>
+{
+}
+
+#[test]
+fn skip_samples_advances_by_frames() {
+    let mut reader = WavReader::open("testsamples/waveformatex-16bit-44100Hz-stereo.wav")
+        .unwrap();
+    let all: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+
+    let mut reader = WavReader::open("testsamples/waveformatex-16bit-44100Hz-stereo.wav")
+        .unwrap();
+    reader.skip_samples(1).unwrap();
+    assert_eq!(reader.samples::<i16>().len(), all.len() - 2);
+    let rest: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&rest[..], &all[2..]);
+
+    // Skipping past the end exhausts the iterator.
+    let mut reader = WavReader::open("testsamples/waveformatex-16bit-44100Hz-stereo.wav")
+        .unwrap();
+    reader.skip_samples(u32::MAX).unwrap();
+    assert!(reader.samples::<i16>().next().is_none());
+}
+
+#[test]
+fn is_truncated_detects_cut_off_files() {
+    let mut reader = WavReader::open("testsamples/waveformatex-16bit-44100Hz-stereo.wav")
+        .unwrap();
+    assert!(!reader.is_truncated().unwrap());
+
+    let mut bytes = Vec::new();
+    let mut file = fs::File::open("testsamples/waveformatex-16bit-44100Hz-stereo.wav").unwrap();
+    io::Read::read_to_end(&mut file, &mut bytes).unwrap();
+    let len = bytes.len();
+    bytes.truncate(len - 2);
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    let first: i16 = reader.samples().next().unwrap().unwrap();
+    assert!(reader.is_truncated().unwrap());
+    // The position in the data chunk is retained.
+    let second: i16 = reader.samples().next().unwrap().unwrap();
+    assert_eq!((first, second), (2, -3));
+}
+
+#[test]
+fn try_samples_rejects_mismatched_type_upfront() {
+    let mut reader = WavReader::open("testsamples/waveformatextensible-32bit-48kHz-stereo.wav")
+        .unwrap();
+    match reader.try_samples::<i16>() {
+        Err(Error::TooWide) => {}
+        _ => panic!("expected TooWide for i16 samples"),
+    }
+    // Nothing was consumed by the check.
+    assert_eq!(reader.try_samples::<i32>().unwrap().len(), reader.len() as usize);
+
+    let mut reader = WavReader::open("testsamples/waveformatex-ieeefloat-44100Hz-mono.wav")
+        .unwrap();
+    match reader.try_samples::<i32>() {
+        Err(Error::InvalidSampleFormat) => {}
+        _ => panic!("expected InvalidSampleFormat for i32 samples"),
+    }
+
+    let reader = WavReader::open("testsamples/pcmwaveformat-8bit-44100Hz-mono.wav").unwrap();
+    let expected = reader.len() as usize;
+    assert_eq!(reader.try_into_samples::<i8>().unwrap().len(), expected);
+}
+
+#[test]
+fn concat_yields_samples_in_sequence() {
+    let files = [
+        "testsamples/pcmwaveformat-16bit-44100Hz-mono.wav",
+        "testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav",
+    ];
+    let readers = files.iter().map(|f| WavReader::open(f).unwrap());
+    let samples = concat::<_, i16, _>(readers).unwrap();
+    assert_eq!(samples.len(), 8);
+    let samples: Vec<i16> = samples.map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[2, -3, 5, -7, 2, -3, 5, -7]);
+
+    let empty: Vec<WavReader<io::BufReader<fs::File>>> = Vec::new();
+    assert_eq!(concat::<_, i16, _>(empty).unwrap().count(), 0);
+}
+
+#[test]
+fn concat_rejects_different_specs() {
+    let files = [
+        "testsamples/pcmwaveformat-16bit-44100Hz-mono.wav",
+        "testsamples/waveformatex-16bit-44100Hz-stereo.wav",
+    ];
+    let readers = files.iter().map(|f| WavReader::open(f).unwrap());
+    match concat::<_, i16, _>(readers) {
+        Err(Error::FormatError(msg)) => {
+            assert_eq!(msg, "concatenated files have different specs: \
+                             1ch 44100Hz 16bit PCM and 2ch 44100Hz 16bit PCM");
+        }
+        _ => panic!("expected FormatError for files with different specs"),
+    }
+}
+
+#[test]
+fn format_errors_describe_the_problem() {
+    use std::fs;
+
+    let valid = fs::read("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    let message = |patch: &dyn Fn(&mut Vec<u8>)| {
+        let mut bytes = valid.clone();
+        patch(&mut bytes);
+        match WavReader::new(io::Cursor::new(bytes)) {
+            Err(Error::FormatError(msg)) => msg,
+            _ => panic!("expected FormatError"),
+        }
+    };
+    assert_eq!(message(&|b| b[3] = b'X'), "no RIFF tag found, the file starts with \"RIFX\"");
+    // Set the size of the fmt chunk.
+    assert_eq!(message(&|b| b[16] = 20),
+               "malformed chunk 'fmt ' at offset 12: unexpected size 20 for PCM format");
+    // Set the bits per sample.
+    assert_eq!(message(&|b| b[34] = 12),
+               "malformed chunk 'fmt ' at offset 12: 12 bits per sample is not a multiple of 8");
+    // Set the bytes per second.
+    assert_eq!(message(&|b| b[28] = 0),
+               "malformed chunk 'fmt ' at offset 12: 88064 bytes per second does not match \
+                block align of 2 bytes at 44100 Hz");
+    // Rename the fmt chunk, so the data chunk comes first.
+    assert_eq!(message(&|b| b[12] = b'X'), "malformed chunk 'data' at offset 36: data chunk before fmt chunk");
+}
+
+#[test]
+fn duration_and_len_agree() {
+    let files = &["testsamples/pcmwaveformat-16bit-44100Hz-mono.wav",
+                  "testsamples/waveformatex-16bit-44100Hz-stereo.wav",
+                  "testsamples/waveformatextensible-32bit-48kHz-stereo.wav"];
+
+    for fname in files {
+        let reader = WavReader::open(fname).unwrap();
+        assert_eq!(reader.spec().channels as u32 * reader.duration(),
+                   reader.len());
+    }
+}
+
+#[test]
+fn read_wav_with_unknown_data_length() {
+    use write::WavWriter;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for s in 0..6_i16 {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    // Mark the length as unknown, like a streaming writer would.
+    let mut bytes = buffer.into_inner();
+    let data_len_pos = bytes.len() - 12 - 4;
+    assert_eq!(&bytes[data_len_pos - 4..data_len_pos], b"data");
+    bytes[data_len_pos..data_len_pos + 4].copy_from_slice(&[0xff; 4]);
+
+    // A slice is not seekable, so this also checks that opening does not seek.
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    assert!(reader.has_unknown_length());
+    assert_eq!(reader.duration(), 0xffff_ffff / 4);
+    assert_eq!(reader.known_duration(), None);
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(samples, vec![0, 1, 2, 3, 4, 5]);
+
+    // A source that ends halfway a sample is still an error.
+    let mut reader = WavReader::new(&bytes[..bytes.len() - 1]).unwrap();
+    let result: Result<Vec<i16>> = reader.samples().collect();
+    assert!(result.is_err());
+
+    let reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    assert!(!reader.has_unknown_length());
+    assert_eq!(reader.known_duration(), Some(4));
+}
+
+#[test]
+fn read_raw_samples_matches_wav() {
+    use std::fs;
+
+    let fname = "testsamples/waveformatex-16bit-44100Hz-stereo.wav";
+    let mut wav = WavReader::open(fname).unwrap();
+    let spec = wav.spec();
+    let expected: Vec<i16> = wav.samples().map(|r| r.unwrap()).collect();
+    let bytes = fs::read(fname).unwrap();
+    let data_at = bytes.windows(4).position(|w| w == b"data").unwrap() + 8;
+    let raw = bytes[data_at..data_at + expected.len() * 2].to_vec();
+
+    let mut reader = WavReader::new_raw_seekable(io::Cursor::new(raw.clone()), spec).unwrap();
+    assert!(reader.can_seek());
+    assert_eq!(reader.spec(), spec);
+    assert_eq!(reader.len() as usize, expected.len());
+    assert_eq!(reader.duration(), wav.duration());
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(samples, expected);
+    reader.seek(1).unwrap();
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), expected[2]);
+    reader.read_trailing_chunks().unwrap();
+    assert!(reader.chunks_after_data().is_empty());
+
+    // Without seeking, the samples are read until the source ends.
+    let mut reader = WavReader::new_raw(&raw[..], spec).unwrap();
+    assert!(reader.has_unknown_length());
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(samples, expected);
+
+    let invalid = WavSpec { channels: 0, ..spec };
+    match WavReader::new_raw(&raw[..], invalid) {
+        Err(Error::InvalidSpec(_)) => {}
+        _ => panic!("expected InvalidSpec"),
+    }
+}
+
+#[test]
+fn read_wav_with_overridden_spec() {
+    let fname = "testsamples/waveformatex-16bit-44100Hz-stereo.wav";
+    let mut wav = WavReader::open(fname).unwrap();
+    let expected: Vec<i16> = wav.samples().map(|r| r.unwrap()).collect();
+
+    // Decode the stereo file as mono at a different rate.
+    let spec = WavSpec { channels: 1, sample_rate: 8000, ..wav.spec() };
+    let file = io::BufReader::new(::std::fs::File::open(fname).unwrap());
+    let mut reader = WavReader::new_with_spec(file, spec).unwrap();
+    assert_eq!(reader.spec(), spec);
+    assert_eq!(reader.duration(), wav.duration() * 2);
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+fn parse_header_never_panics_on_malformed_input() {
+    use std::fs;
+
+    let bytes = fs::read("testsamples/waveformatextensible-32bit-48kHz-stereo.wav").unwrap();
+    let header = parse_header(&bytes).unwrap();
+    assert_eq!(header.spec_ex.spec.channels, 2);
+    assert_eq!(header.spec_ex.bytes_per_sample, 4);
+    assert_eq!(&bytes[header.data_start as usize - 8..header.data_start as usize - 4], b"data");
+
+    for fname in &["testsamples/pcmwaveformat-16bit-44100Hz-mono.wav",
+                   "testsamples/waveformatex-16bit-44100Hz-stereo.wav",
+                   "testsamples/waveformatextensible-32bit-48kHz-stereo.wav",
+                   "testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav"] {
+        let bytes = fs::read(fname).unwrap();
+        let header_len = parse_header(&bytes).unwrap().data_start as usize;
+        for len in 0..header_len {
+            assert!(parse_header(&bytes[..len]).is_err());
+        }
+        // Overwrite every header byte with values that are likely to hit
+        // edge cases, the result does not matter as long as it is returned.
+        let mut mutated = bytes.clone();
+        for i in 0..header_len {
+            for &b in &[0x00, 0x01, 0x7f, 0x80, 0xfe, 0xff] {
+                mutated[i] = b;
+                let _ = parse_header(&mutated);
+            }
+            mutated[i] = bytes[i];
+        }
+    }
+}
+
+#[test]
+fn samples_raw_i16_borrows_the_data_chunk() {
+    use std::fs;
+
+    let bytes = fs::read("testsamples/waveformatex-16bit-44100Hz-stereo.wav").unwrap();
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    let expected: Vec<i16> = WavReader::new(&bytes[..]).unwrap()
+        .samples().map(|r| r.unwrap()).collect();
+
+    // Whether the samples are aligned depends on the allocation, so copy the
+    // file to an offset where the samples are aligned, and one where they
+    // are not.
+    let data_start = parse_header(&bytes).unwrap().data_start as usize;
+    let mut storage = vec![0_u8; bytes.len() + 2];
+    let even = (storage.as_ptr() as usize + data_start) & 1;
+    let odd = 1 - even;
+    storage[even..even + bytes.len()].copy_from_slice(&bytes);
+    {
+        let aligned = WavReader::new(&storage[even..even + bytes.len()]).unwrap();
+        assert_eq!(aligned.samples_raw_i16(), Some(&expected[..]));
+        let cursor = WavReader::new(io::Cursor::new(&storage[even..even + bytes.len()])).unwrap();
+        assert_eq!(cursor.samples_raw_i16(), Some(&expected[..]));
+    }
+    storage[odd..odd + bytes.len()].copy_from_slice(&bytes);
+    let misaligned = WavReader::new(&storage[odd..odd + bytes.len()]).unwrap();
+    assert_eq!(misaligned.samples_raw_i16(), None);
+
+    // The slice starts at the current position.
+    reader.samples::<i16>().next().unwrap().unwrap();
+    let shifted = reader.samples_raw_i16();
+    assert!(shifted.is_none() || shifted.unwrap() == &expected[1..]);
+
+    let bytes = fs::read("testsamples/waveformatextensible-32bit-48kHz-stereo.wav").unwrap();
+    assert_eq!(WavReader::new(&bytes[..]).unwrap().samples_raw_i16(), None);
+}
+
+/// Calls every reader method that parses a part of the file.
+#[cfg(test)]
+fn read_everything(bytes: &[u8]) {
+    let mut reader = match WavReader::new(io::Cursor::new(bytes)) {
+        Ok(reader) => reader,
+        Err(..) => return,
+    };
+    let _ = reader.read_trailing_chunks();
+    let _ = reader.info();
+    let _ = reader.cue_points();
+    let _ = reader.peak_envelope();
+    let _ = reader.playlist();
+    let _ = reader.broadcast_extension();
+    let _ = reader.cart();
+    let _ = reader.display_title();
+    let _ = reader.adm_tracks();
+    let _ = reader.acid_info();
+    let _ = reader.sampler_info();
+    let _ = reader.instrument_info();
+    let _ = reader.peak_info();
+    let _ = reader.is_truncated();
+    let _ = reader.verify_md5();
+    let _ = reader.duration();
+    let _ = reader.dc_offset();
+    let _ = reader.seek(1);
+    let _ = reader.skip_samples(1);
+    match reader.spec().sample_format {
+        SampleFormat::Int => for sample in reader.samples::<i32>() {
+            if sample.is_err() { break }
+        },
+        SampleFormat::Float => for sample in reader.samples::<f32>() {
+            if sample.is_err() { break }
+        },
+    }
+    let _ = parse_header(bytes);
+}
+
+#[test]
+fn malformed_files_return_errors_instead_of_panicking() {
+    use std::fs;
+
+    // A simple xorshift generator, so failures are reproducible.
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut fnames: Vec<_> = fs::read_dir("testsamples").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(::std::ffi::OsStr::new("wav")))
+        .collect();
+    fnames.sort();
+    for fname in &fnames {
+        let bytes = fs::read(fname).unwrap();
+        for _ in 0..200 {
+            let mut mutated = bytes.clone();
+            for _ in 0..1 + next() % 4 {
+                // Most of a file is samples, so focus on the chunk headers
+                // and metadata at the start and the end.
+                let n = mutated.len() as u64;
+                let i = match next() % 3 {
+                    0 => next() % cmp::min(n, 512),
+                    1 => n - 1 - next() % cmp::min(n, 512),
+                    _ => next() % n,
+                } as usize;
+                match next() % 3 {
+                    // Chunk sizes are 32-bit fields, so also write extreme values.
+                    0 => {
+                        let end = cmp::min(i + 4, mutated.len());
+                        let value = [0xff, 0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0x7f];
+                        let offset = (next() % 2) as usize * 4;
+                        mutated[i..end].copy_from_slice(&value[offset..offset + end - i]);
+                    }
+                    1 => mutated[i] = next() as u8,
+                    _ => mutated[i] = 0,
+                }
+            }
+            let len = mutated.len() - (next() % 2 * next() % mutated.len() as u64) as usize;
+            read_everything(&mutated[..len]);
+        }
+    }
+}
+
+/// Tests reading a wave file with the PCMWAVEFORMAT struct.
+#[test]
+fn read_wav_pcm_wave_format_pcm() {
+    let mut wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
+        .unwrap();
+
+    assert_eq!(wav_reader.spec().channels, 1);
+    assert_eq!(wav_reader.spec().sample_rate, 44100);
+    assert_eq!(wav_reader.spec().bits_per_sample, 16);
+    assert_eq!(wav_reader.spec().sample_format, SampleFormat::Int);
+
+    let samples: Vec<i16> = wav_reader.samples()
+        .map(|r| r.unwrap())
+        .collect();
+
+    // The test file has been prepared with these exact four samples.
+    assert_eq!(&samples[..], &[2, -3, 5, -7]);
+}
+
+#[test]
+fn can_seek_reflects_constructor() {
+    let fname = "testsamples/pcmwaveformat-16bit-44100Hz-mono.wav";
+    assert!(WavReader::open(fname).unwrap().can_seek());
+    let file = fs::File::open(fname).unwrap();
+    assert!(WavReader::new_seekable(file).unwrap().can_seek());
+    let file = fs::File::open(fname).unwrap();
+    assert!(!WavReader::new(file).unwrap().can_seek());
+}
+
+#[test]
+fn read_wav_list_info() {
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav")
+        .unwrap();
+    let info = wav_reader.info().unwrap().unwrap();
+    assert_eq!(info.title(), Some("Four samples"));
+    assert_eq!(info.artist(), Some("Hound"));
+    assert_eq!(info.creation_date(), Some("2017-10-14"));
+    assert_eq!(info.software(), Some("Lavf58.29.100"));
+    // The comment has an odd length, so it is followed by a pad byte.
+    assert_eq!(info.comment(), Some("odd"));
+    assert_eq!(info.entries().len(), 5);
+
+    let mut wav_reader = wav_reader;
+    let samples: Vec<i16> = wav_reader.samples()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(&samples[..], &[2, -3, 5, -7]);
+
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(wav_reader.info().unwrap(), None);
+}
+
+#[test]
+fn read_wav_broadcast_extension() {
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-48kHz-stereo-bext.wav")
+        .unwrap();
+    let bext = wav_reader.broadcast_extension().unwrap().unwrap();
+    assert_eq!(bext.description, "Interview, take 3");
+    assert_eq!(bext.originator, "BWF MetaEdit");
+    assert_eq!(bext.originator_reference, "USHOUND0000000000042");
+    assert_eq!(bext.origination_date, "2017-10-14");
+    assert_eq!(bext.origination_time, "10:00:00");
+    assert_eq!(bext.time_reference, 48_000 * 3600 * 10 + 123);
+    assert_eq!(bext.version, 2);
+    assert_eq!(bext.umid, (0..64).collect::<Vec<u8>>());
+    let loudness = bext.loudness.unwrap();
+    assert_eq!(loudness.loudness_value, -2300);
+    assert_eq!(loudness.loudness_range, 510);
+    assert_eq!(loudness.max_true_peak_level, -120);
+    assert_eq!(loudness.max_momentary_loudness, -1800);
+    assert_eq!(loudness.max_short_term_loudness, -2000);
+    assert_eq!(bext.coding_history, "A=PCM,F=48000,W=16,M=stereo,T=original\r\n\
+A=PCM,F=48000,W=16,M=stereo,T=hound\r\n");
+    assert_eq!(wav_reader.spec().sample_rate, 48000);
+
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(wav_reader.broadcast_extension().unwrap(), None);
+}
+
+#[test]
+fn read_wav_acid_info() {
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-acid.wav")
+        .unwrap();
+    let acid = wav_reader.acid_info().unwrap().unwrap();
+    // A stretched loop with root note, and an unknown flag bit.
+    assert_eq!(acid.flags, 0x0106);
+    assert!(!acid.is_one_shot());
+    assert!(acid.has_root_note());
+    assert!(acid.is_stretched());
+    assert_eq!(acid.root_note, 57);
+    assert_eq!(acid.reserved1, 0x8000);
+    assert_eq!(acid.num_beats, 8);
+    assert_eq!(acid.meter_denominator, 4);
+    assert_eq!(acid.meter_numerator, 3);
+    assert_eq!(acid.tempo, 127.5);
+
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(wav_reader.acid_info().unwrap(), None);
+}
+
+#[test]
+fn read_wav_peak_info() {
+    let wav_reader = WavReader::open("testsamples/waveformatex-ieeefloat-44100Hz-stereo-peak.wav")
+        .unwrap();
+    let peak = wav_reader.peak_info().unwrap().unwrap();
+    assert_eq!(peak.version, 1);
+    assert_eq!(peak.timestamp, 1_700_000_000);
+    assert_eq!(peak.peaks.len(), 2);
+    assert_eq!(peak.peaks[0].value, 0.5);
+    assert_eq!(peak.peaks[0].position, 1);
+    assert_eq!(peak.peaks[1].value, 0.75);
+    assert_eq!(peak.peaks[1].position, 2);
+
+    let wav_reader = WavReader::open("testsamples/waveformatex-ieeefloat-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(wav_reader.peak_info().unwrap(), None);
+}
+
+#[test]
+fn read_wav_instrument_info() {
+    // The odd-sized inst chunk is padded, and followed by a smpl chunk.
+    let mut wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-inst.wav")
+        .unwrap();
+    let inst = wav_reader.instrument_info().unwrap().unwrap();
+    assert_eq!(inst.unshifted_note, 64);
+    assert_eq!(inst.fine_tune, -25);
+    assert_eq!(inst.gain, 3);
+    assert_eq!((inst.low_note, inst.high_note), (0, 127));
+    assert_eq!((inst.low_velocity, inst.high_velocity), (1, 127));
+    assert_eq!(wav_reader.sampler_info().unwrap().unwrap().midi_unity_note, 64);
+    let samples: Vec<i16> = wav_reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[1, -1, 2, -2]);
+}
+
+#[test]
+fn read_wav_cart() {
+    let mut wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-cart.wav")
+        .unwrap();
+    let cart = wav_reader.cart().unwrap().unwrap();
+    assert_eq!(cart.version, "0101");
+    assert_eq!(cart.title, "Morning Jingle");
+    assert_eq!(cart.artist, "KHND Imaging");
+    assert_eq!(cart.cut_id, "J0042");
+    assert_eq!(cart.category, "JINGLE");
+    assert_eq!(cart.classification, "");
+    assert_eq!(cart.out_cue, "...and now the news");
+    assert_eq!((&cart.start_date[..], &cart.start_time[..]), ("2026-01-01", "00:00:00"));
+    assert_eq!((&cart.end_date[..], &cart.end_time[..]), ("2026-12-31", "23:59:59"));
+    assert_eq!(cart.producer_app_id, "PlayoutPro");
+    assert_eq!(cart.producer_app_version, "4.2.1");
+    assert_eq!(cart.level_reference, 32768);
+    let timers: Vec<(&[u8], u32)> = cart.timers.iter().map(|t| (&t.usage[..], t.value)).collect();
+    assert_eq!(timers, vec![(&b"SEGs"[..], 1), (&b"INTe"[..], 3), (&b"SEC1"[..], 5)]);
+    assert_eq!(cart.url, "http://example.com/cuts/J0042");
+    assert_eq!(cart.tag_text, "Produced for KHND\r\n");
+    // The odd-sized chunk is padded.
+    let samples: Vec<i16> = wav_reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[4, -4, 8, -8, 16, -16]);
+}
+
+#[test]
+fn read_wav_display_title() {
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-disp.wav")
+        .unwrap();
+    // The title is Latin-1 encoded, the invalid byte is replaced.
+    assert_eq!(wav_reader.display_title().unwrap().unwrap(), "Caf\u{fffd} recording");
+    assert_eq!(wav_reader.info().unwrap().unwrap().title(), Some("Cafe"));
+
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(wav_reader.display_title().unwrap(), None);
+}
+
+#[test]
+fn read_wav_adm_chunks() {
+    let mut wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-48000Hz-stereo-adm.wav")
+        .unwrap();
+    let tracks = wav_reader.adm_tracks().unwrap();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[1].track_index, 2);
+    assert_eq!(tracks[1].uid, "ATU_00000002");
+    assert_eq!(tracks[1].track_ref, "AT_00010002_01");
+    assert_eq!(tracks[1].pack_ref, "AP_00010002");
+
+    // The axml chunk follows the data chunk.
+    assert_eq!(wav_reader.axml(), None);
+    wav_reader.read_trailing_chunks().unwrap();
+    assert!(wav_reader.axml().unwrap().starts_with(b"<?xml"));
+    let samples: Vec<i16> = wav_reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(&samples[..], &[1, -1, 2, -2]);
+}
+
+#[test]
+fn read_wav_from_boxed_trait_objects() {
+    use std::fs;
+
+    let path = "testsamples/pcmwaveformat-16bit-44100Hz-mono.wav";
+    let bytes = fs::read(path).unwrap();
+    let expected: Vec<i16> = WavReader::open(path).unwrap().samples().map(|s| s.unwrap()).collect();
 
-    fn next(&mut self) -> Option<Result<S>> {
-        iter_next(&mut self.reader)
+    let sources: Vec<Box<dyn io::Read>> = vec![
+        Box::new(fs::File::open(path).unwrap()),
+        Box::new(io::Cursor::new(bytes.clone())),
+    ];
+    let mut readers: Vec<WavReader<Box<dyn io::Read>>> = sources.into_iter()
+        .map(|source| WavReader::new(source).unwrap())
+        .collect();
+    for reader in &mut readers {
+        let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, expected);
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        iter_size_hint(&self.reader)
+    let sources: Vec<Box<dyn ReadSeek>> = vec![
+        Box::new(fs::File::open(path).unwrap()),
+        Box::new(io::Cursor::new(bytes)),
+    ];
+    for source in sources {
+        let mut reader = WavReader::new_seekable(source).unwrap();
+        assert!(reader.can_seek());
+        reader.seek(1).unwrap();
+        assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), expected[1]);
+        assert!(!reader.is_truncated().unwrap());
     }
 }
 
-impl<R, S> ExactSizeIterator for WavIntoSamples<R, S>
-    where R: io::Read,
-          S: Sample
-{
+#[test]
+fn dc_offset_is_mean_per_channel() {
+    use write::WavWriter;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for i in 0..100 {
+            writer.write_sample(16384_i16).unwrap();
+            writer.write_sample(if i & 1 == 0 { 1000_i16 } else { -1000 }).unwrap();
+        }
+    }
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    assert_eq!(reader.dc_offset().unwrap(), vec![0.5, 0.0]);
+    // The samples have been consumed.
+    assert_eq!(reader.dc_offset().unwrap(), vec![0.0, 0.0]);
+
+    let mut reader = WavReader::open("testsamples/waveformatex-ieeefloat-44100Hz-mono.wav")
+        .unwrap();
+    let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+    let mean = samples.iter().map(|&x| x as f64).sum::<f64>() / samples.len() as f64;
+    let mut reader = WavReader::open("testsamples/waveformatex-ieeefloat-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(reader.dc_offset().unwrap(), vec![mean]);
 }
 
 #[test]
-fn duration_and_len_agree() {
-    let files = &["testsamples/pcmwaveformat-16bit-44100Hz-mono.wav",
-                  "testsamples/waveformatex-16bit-44100Hz-stereo.wav",
-                  "testsamples/waveformatextensible-32bit-48kHz-stereo.wav"];
+fn verify_md5_detects_corruption() {
+    use std::fs;
 
-    for fname in files {
-        let reader = WavReader::open(fname).unwrap();
-        assert_eq!(reader.spec().channels as u32 * reader.duration(),
-                   reader.len());
-    }
+    let mut bytes = fs::read("testsamples/pcmwaveformat-16bit-44100Hz-mono-md5.wav").unwrap();
+    let mut reader = WavReader::new(io::Cursor::new(bytes.clone())).unwrap();
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), 1000);
+    assert_eq!(reader.verify_md5().unwrap(), Md5Status::Match);
+    // The position in the data chunk is preserved.
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), -1000);
+
+    // Flip one bit of the fourth sample.
+    bytes[50] ^= 1;
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.verify_md5().unwrap(), Md5Status::Mismatch);
+
+    let mut reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    assert_eq!(reader.verify_md5().unwrap(), Md5Status::Absent);
 }
 
-/// Tests reading a wave file with the PCMWAVEFORMAT struct.
 #[test]
-fn read_wav_pcm_wave_format_pcm() {
-    let mut wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
-        .unwrap();
-
-    assert_eq!(wav_reader.spec().channels, 1);
-    assert_eq!(wav_reader.spec().sample_rate, 44100);
-    assert_eq!(wav_reader.spec().bits_per_sample, 16);
-    assert_eq!(wav_reader.spec().sample_format, SampleFormat::Int);
+fn read_wav_sampler_info() {
+    use metadata::SampleLoop;
 
-    let samples: Vec<i16> = wav_reader.samples()
-        .map(|r| r.unwrap())
-        .collect();
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-smpl.wav")
+        .unwrap();
+    let info = wav_reader.sampler_info().unwrap().unwrap();
+    assert_eq!(info.manufacturer, 0x47);
+    assert_eq!(info.product, 0x5e);
+    assert_eq!(info.sample_period, 22_676);
+    assert_eq!(info.midi_unity_note, 60);
+    assert_eq!(info.midi_pitch_fraction, 0x4000_0000);
+    assert_eq!(info.smpte_format, 25);
+    assert_eq!(info.smpte_offset, 0x0102_0304);
+    assert_eq!(info.loops, vec![
+        SampleLoop { id: 0, loop_type: 0, start: 1, end: 7, fraction: 0, play_count: 0 },
+        SampleLoop { id: 1, loop_type: 1, start: 2, end: 5, fraction: 0x8000_0000, play_count: 3 },
+    ]);
+    assert_eq!(&info.sampler_data[..], &[1, 2]);
+    assert_eq!(wav_reader.len(), 8);
+
+    let wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(wav_reader.sampler_info().unwrap(), None);
+}
 
-    // The test file has been prepared with these exact four samples.
-    assert_eq!(&samples[..], &[2, -3, 5, -7]);
+#[test]
+fn read_wav_cue_points_after_data() {
+    // The cue and adtl chunks follow the data chunk, like in Audacity exports.
+    let mut wav_reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-cue.wav")
+        .unwrap();
+    assert_eq!(wav_reader.cue_points().unwrap(), Vec::new());
+
+    let head: Vec<i16> = wav_reader.samples().take(3).map(|r| r.unwrap()).collect();
+    assert_eq!(&head[..], &[0, 100, 200]);
+
+    wav_reader.read_trailing_chunks().unwrap();
+    let points = wav_reader.cue_points().unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].id, 1);
+    assert_eq!(points[0].position, 2);
+    assert_eq!(&points[0].data_chunk_id, b"data");
+    assert_eq!(points[0].sample_offset, 2);
+    assert_eq!(points[0].label.as_ref().map(|s| &s[..]), Some("Verse"));
+    assert_eq!(points[1].position, 7);
+    assert_eq!(points[1].label.as_ref().map(|s| &s[..]), Some("Chorus"));
+    // The file has cue points, but no playlist.
+    assert_eq!(wav_reader.playlist().unwrap(), Vec::new());
+
+    // Reading the trailing chunks does not disturb reading samples.
+    let tail: Vec<i16> = wav_reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&tail[..], &[300, 400, 500, 600, 700, 800, 900]);
+    wav_reader.read_trailing_chunks().unwrap();
+    assert_eq!(wav_reader.cue_points().unwrap().len(), 2);
 }
 
 #[test]
@@ -1215,6 +4481,32 @@ fn read_wav_wave_format_extensible_pcm_24bit() {
     assert_eq!(&samples[..], &[-17, 4_194_319, -6_291_437, 8_355_817]);
 }
 
+/// 20-bit samples in a 3 byte container, where `wValidBitsPerSample` signals the precision.
+#[test]
+fn read_wav_wave_format_extensible_pcm_20bit() {
+    let mut wav_reader = WavReader::open("testsamples/waveformatextensible-20bit-48kHz-mono.wav")
+        .unwrap();
+
+    assert_eq!(wav_reader.spec().channels, 1);
+    assert_eq!(wav_reader.spec().sample_rate, 48_000);
+    assert_eq!(wav_reader.spec().bits_per_sample, 20);
+    assert_eq!(wav_reader.spec().sample_format, SampleFormat::Int);
+
+    let samples: Vec<i32> = wav_reader.samples()
+                                      .map(|r| r.unwrap())
+                                      .collect();
+
+    // The test file has been prepared with these exact four samples, which
+    // include the extremes of the 20-bit range.
+    assert_eq!(&samples[..], &[-17, 524_287, -524_288, 1]);
+
+    // The samples do not fit in 16 bits.
+    wav_reader.seek(0).unwrap();
+    assert!(wav_reader.samples::<i16>().next().unwrap().is_err());
+    wav_reader.seek(0).unwrap();
+    assert_eq!(wav_reader.samples::<f32>().next().unwrap().unwrap(), -17.0);
+}
+
 /// This test sample tests both reading the WAVEFORMATEXTENSIBLE header, and 24-bit samples with a
 /// 4 byte container size.
 #[test]
@@ -1269,6 +4561,38 @@ fn read_wav_wave_format_extensible_ieee_float() {
     assert_eq!(&samples[..], &[2.0, 3.0, -16411.0, 1019.0]);
 }
 
+#[test]
+fn read_wav_adpcm_is_decoded() {
+    // This file holds Microsoft ADPCM, a single block of silence.
+    let mut reader = WavReader::open("testsamples/waveformatex-adpcm-4bit-44100Hz-mono.wav")
+        .unwrap();
+    assert_eq!(reader.spec(), WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    });
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), 2036);
+    assert!(samples.iter().all(|&s| s == 0));
+}
+
+#[test]
+fn read_wav_wave_format_extensible_unknown_subformat_is_unsupported() {
+    match WavReader::open("testsamples/waveformatextensible-alaw-8000Hz-mono.wav") {
+        Err(Error::Unsupported(format)) => {
+            assert_eq!(format.format_tag, Some(0xfffe));
+            assert_eq!(format.bits_per_sample, 8);
+            let sub_format = format.sub_format.unwrap();
+            assert_eq!(sub_format[..4], [0x06, 0x00, 0x00, 0x00]);
+            assert_eq!(Error::Unsupported(format).to_string(),
+                       "The wave format of the file is not supported: format tag 0xfffe, \
+                        8 bits per sample, subformat 00000006-0000-0010-8000-00aa00389b71.");
+        }
+        _ => panic!("expected Unsupported for an A-law file"),
+    }
+}
+
 #[test]
 fn read_wav_nonstandard_01() {
     // The test sample here is adapted from a file encountered in the wild (data
@@ -1454,3 +4778,945 @@ fn seek_is_consistent() {
         assert!(reader.samples::<i32>().next().is_none());
     }
 }
+
+#[cfg(test)]
+fn assert_read_fn_matches_read<S: Sample + ::std::fmt::Debug>(fname: &path::Path) {
+    use std::io::Read;
+
+    // An unsupported format does not consume the sample, and then every
+    // iteration returns the same error, so take no more than the length.
+    let mut reader = WavReader::open(fname).unwrap();
+    let samples = reader.samples::<S>();
+    let len = samples.len();
+    let fast: Vec<String> = samples.take(len).map(|s| format!("{:?}", s)).collect();
+
+    // Decode the same bytes with `Sample::read`, which checks the format for
+    // every sample.
+    let mut reader = WavReader::open(fname).unwrap();
+    let spec_ex = reader.reader.data_state.unwrap().spec_ex;
+    let mut bytes = Vec::new();
+    reader.reader.read_to_end(&mut bytes).unwrap();
+    let expected: Vec<String> = bytes.chunks_exact(spec_ex.bytes_per_sample as usize).map(|mut sample| {
+        let s: Result<S> = Sample::read(&mut sample,
+                                        spec_ex.spec.sample_format,
+                                        spec_ex.bytes_per_sample,
+                                        spec_ex.spec.bits_per_sample);
+        format!("{:?}", s)
+    }).collect();
+    assert_eq!(fast, expected, "decoding {:?} as {}", fname, ::std::any::type_name::<S>());
+}
+
+#[test]
+fn samples_decode_like_sample_read_for_every_format() {
+    let mut fnames: Vec<_> = fs::read_dir("testsamples").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(::std::ffi::OsStr::new("wav")))
+        .collect();
+    fnames.sort();
+    for fname in &fnames {
+        if WavReader::open(fname).is_err() {
+            continue
+        }
+        assert_read_fn_matches_read::<i8>(fname);
+        assert_read_fn_matches_read::<i16>(fname);
+        assert_read_fn_matches_read::<i32>(fname);
+        assert_read_fn_matches_read::<f32>(fname);
+    }
+}
+
+#[test]
+fn timed_samples_report_the_frame_time() {
+    use std::time::Duration;
+    use write::WavWriter;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 3,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for i in 0..8 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    let bytes = buffer.into_inner();
+    let at = |nanos| Duration::new(0, nanos);
+    let mut reader = WavReader::new(io::Cursor::new(&bytes[..])).unwrap();
+    let timed: Vec<_> = reader.samples::<i16>().timed().map(|s| s.unwrap()).collect();
+    // A third of a second has no exact representation, it is truncated.
+    assert_eq!(timed, vec![(at(0), 0), (at(0), 1),
+                           (at(333_333_333), 2), (at(333_333_333), 3),
+                           (at(666_666_666), 4), (at(666_666_666), 5),
+                           (Duration::new(1, 0), 6), (Duration::new(1, 0), 7)]);
+
+    // After a seek, the time continues from the new position.
+    reader.seek(2).unwrap();
+    let timed: Vec<_> = reader.into_samples::<i16>().timed().map(|s| s.unwrap()).collect();
+    assert_eq!(timed[0], (at(666_666_666), 4));
+    assert_eq!(timed.len(), 4);
+}
+
+#[test]
+fn samples_signed8_reads_bytes_without_offset() {
+    use write::WavWriter;
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 8,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for &s in &[-128_i8, -1, 0, 1, 127] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    let bytes = buffer.into_inner();
+    // The file stores the bytes 0, 127, 128, 129, and 255.
+    let mut reader = WavReader::new(io::Cursor::new(&bytes[..])).unwrap();
+    let samples: Vec<i8> = reader.samples_signed8().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [0, 127, -128, -127, -1]);
+
+    let mut reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    assert!(reader.samples_signed8().next().unwrap().is_err());
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn read_all_parallel_matches_samples() {
+    fn check<S: Sample + Send + PartialEq + ::std::fmt::Debug>(bytes: &[u8]) {
+        let mut reader = match WavReader::new(bytes) {
+            Ok(reader) => reader,
+            Err(..) => return,
+        };
+        let samples = reader.samples::<S>();
+        // Take no more than the length, an unsupported format never ends.
+        let len = samples.len();
+        let expected: Result<Vec<S>> = samples.take(len + 1).collect();
+        let reader = WavReader::new(bytes).unwrap();
+        if reader.reader.adpcm.is_some() || reader.reader.wavl.is_some() {
+            // These are decoded serially, the raw bytes are not the samples.
+            let result: Result<Vec<S>> = reader.read_all_parallel();
+            assert_eq!(format!("{:?}", expected), format!("{:?}", result));
+            return
+        }
+        let data = reader.reader.data_state.unwrap();
+        for &threads in &[1, 2, 3, 7, 64] {
+            let result: Result<Vec<S>> = decode_parallel(reader.reader.reader, data, threads);
+            match (&expected, &result) {
+                (Ok(x), Ok(y)) => assert_eq!(x, y),
+                (Err(x), Err(y)) => assert_eq!(format!("{:?}", x), format!("{:?}", y)),
+                _ => panic!("expected {:?}, got {:?}", expected, result),
+            }
+        }
+    }
+
+    let mut fnames: Vec<_> = fs::read_dir("testsamples").unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(::std::ffi::OsStr::new("wav")))
+        .collect();
+    fnames.sort();
+    for fname in &fnames {
+        let bytes = fs::read(fname).unwrap();
+        // Also cut the data short, to end in the middle of a sample.
+        for &cut in &[0, 1, 2, 3, 5] {
+            let bytes = &bytes[..bytes.len().saturating_sub(cut)];
+            check::<i16>(bytes);
+            check::<i32>(bytes);
+            check::<f32>(bytes);
+        }
+    }
+}
+
+#[test]
+fn read_frame_into_matches_samples() {
+    use std::io::Read;
+
+    let fname = "testsamples/waveformatextensible-24bit-4byte-48kHz-stereo.wav";
+    let samples: Vec<i32> = WavReader::open(fname).unwrap()
+        .samples().map(|s| s.unwrap()).collect();
+
+    let mut reader = WavReader::open(fname).unwrap();
+    let mut frame = [0_i32; 2];
+    let mut frames = Vec::new();
+    while reader.read_frame_into(&mut frame).unwrap() {
+        frames.extend_from_slice(&frame);
+    }
+    assert_eq!(frames, samples);
+    assert!(!reader.read_frame_into(&mut frame).unwrap());
+
+    let mut reader = WavReader::open(fname).unwrap();
+    let mut frames = Vec::new();
+    {
+        let mut reused = reader.frames::<i32>();
+        while let Some(frame) = reused.next_frame() {
+            frames.extend_from_slice(frame.unwrap());
+        }
+    }
+    assert_eq!(frames, samples);
+
+    // Drop the last sample, the data now ends in the middle of a frame.
+    let mut bytes = Vec::new();
+    fs::File::open(fname).unwrap().read_to_end(&mut bytes).unwrap();
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    reader.reader.data_state.as_mut().unwrap().chunk.remaining -= 4;
+    for _ in 0..samples.len() / 2 - 1 {
+        assert!(reader.read_frame_into(&mut frame).unwrap());
+    }
+    match reader.read_frame_into(&mut frame) {
+        Err(Error::UnfinishedSample) => {}
+        other => panic!("expected UnfinishedSample, got {:?}", other),
+    }
+}
+
+#[test]
+fn frames_buffered_reads_whole_frames() {
+    let fname = "testsamples/waveformatextensible-24bit-4byte-48kHz-stereo.wav";
+    let samples: Vec<i32> = WavReader::open(fname).unwrap()
+        .samples().map(|s| s.unwrap()).collect();
+
+    // Five samples hold two stereo frames, the last one is never touched.
+    let mut reader = WavReader::open(fname).unwrap();
+    let mut buf = [0_i32; 5];
+    let mut frames = Vec::new();
+    loop {
+        buf[4] = 42;
+        let n = reader.frames_buffered(&mut buf).unwrap();
+        assert_eq!(buf[4], 42);
+        if n == 0 {
+            break
+        }
+        frames.extend_from_slice(&buf[..n * 2]);
+    }
+    assert_eq!(frames, samples);
+
+    // With one sample less in the data chunk, the last frame is incomplete.
+    let mut bytes = Vec::new();
+    io::Read::read_to_end(&mut fs::File::open(fname).unwrap(), &mut bytes).unwrap();
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    reader.reader.data_state.as_mut().unwrap().chunk.remaining -= 4;
+    let mut buf = vec![0_i32; samples.len()];
+    assert_eq!(reader.frames_buffered(&mut buf).unwrap(), samples.len() / 2 - 1);
+    match reader.frames_buffered(&mut buf) {
+        Err(Error::UnfinishedSample) => {}
+        other => panic!("expected UnfinishedSample, got {:?}", other),
+    }
+}
+
+#[test]
+fn frames_fixed_checks_the_channels() {
+    let fname = "testsamples/waveformatex-16bit-44100Hz-stereo.wav";
+    let samples: Vec<i16> = WavReader::open(fname).unwrap()
+        .samples().map(|s| s.unwrap()).collect();
+    let mut reader = WavReader::open(fname).unwrap();
+    let frames: Vec<[i16; 2]> = reader.frames_fixed().unwrap().map(|f| f.unwrap()).collect();
+    assert_eq!(frames.concat(), samples);
+
+    let mut reader = WavReader::open(fname).unwrap();
+    match reader.frames_fixed::<i16, 1>() {
+        Err(Error::InvalidSpec(..)) => {}
+        Err(err) => panic!("expected InvalidSpec, got {:?}", err),
+        Ok(..) => panic!("expected InvalidSpec for a mono frame of a stereo file"),
+    }
+
+    let fname = "testsamples/pcmwaveformat-16bit-44100Hz-mono.wav";
+    let mut reader = WavReader::open(fname).unwrap();
+    let frames: Vec<[i16; 1]> = reader.frames_fixed().unwrap().map(|f| f.unwrap()).collect();
+    assert_eq!(frames, [[2], [-3], [5], [-7]]);
+}
+
+#[test]
+fn chunk_handler_sees_unparsed_chunks() {
+    let fname = "testsamples/pcmwaveformat-16bit-44100Hz-mono-inst.wav";
+    let mut seen = Vec::new();
+    let reader = WavReader::new_with_chunk_handler(fs::File::open(fname).unwrap(), |id, data| {
+        seen.push(RawChunk { id: *id, data: data.to_vec() });
+    }).unwrap();
+    assert_eq!(seen.iter().map(|c| c.id).collect::<Vec<_>>(), [*b"inst", *b"smpl"]);
+    assert_eq!(&seen[..], reader.chunks_before_data());
+}
+
+#[test]
+fn cloned_readers_read_independently() {
+    use std::fs;
+
+    let fname = "testsamples/waveformatex-16bit-44100Hz-stereo.wav";
+    let expected: Vec<i16> = WavReader::open(fname).unwrap().samples().map(|s| s.unwrap()).collect();
+    assert!(expected.len() > 4);
+    let mut cursor = WavReader::new(io::Cursor::new(fs::read(fname).unwrap())).unwrap();
+    let mut file = WavReader::open(fname).unwrap();
+    assert_eq!(cursor.samples::<i16>().next().unwrap().unwrap(), expected[0]);
+    assert_eq!(file.samples::<i16>().next().unwrap().unwrap(), expected[0]);
+
+    let mut cursor_clone = cursor.clone();
+    let mut file_fork = file.try_fork().unwrap();
+    let mut samples = [Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+    // Read from the readers alternately, each should see all remaining samples.
+    for _ in 1..expected.len() {
+        samples[0].push(cursor.samples::<i16>().next().unwrap().unwrap());
+        samples[1].push(cursor_clone.samples::<i16>().next().unwrap().unwrap());
+        samples[2].push(file.samples::<i16>().next().unwrap().unwrap());
+        samples[3].push(file_fork.samples::<i16>().next().unwrap().unwrap());
+    }
+    for s in &samples {
+        assert_eq!(&s[..], &expected[1..]);
+    }
+    assert!(cursor_clone.samples::<i16>().next().is_none());
+    assert!(file_fork.samples::<i16>().next().is_none());
+
+    let reader = WavReader::new(io::BufReader::new(fs::File::open(fname).unwrap())).unwrap();
+    assert!(reader.try_fork().is_err());
+}
+
+#[test]
+fn channel_mask_names_the_speakers() {
+    use SpeakerPosition::*;
+
+    let reader = WavReader::open("testsamples/waveformatextensible-32bit-48kHz-stereo.wav").unwrap();
+    assert_eq!(reader.channel_mask(), Some(0x3));
+    assert_eq!(reader.speaker_positions(), [FrontLeft, FrontRight]);
+
+    let reader = WavReader::open("testsamples/waveformatextensible-20bit-48kHz-mono.wav").unwrap();
+    assert_eq!(reader.channel_mask(), Some(0x4));
+    assert_eq!(reader.speaker_positions(), [FrontCenter]);
+
+    let reader = WavReader::open("testsamples/waveformatex-16bit-44100Hz-stereo.wav").unwrap();
+    assert_eq!(reader.channel_mask(), None);
+    assert_eq!(reader.speaker_positions(), [Unassigned, Unassigned]);
+}
+
+#[test]
+fn samples_looped_jumps_back_at_whole_frames() {
+    use metadata::SampleLoop;
+    use write::WavWriter;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for i in 0..10_i16 {
+            writer.write_sample(i).unwrap();
+            writer.write_sample(-i).unwrap();
+        }
+        writer.set_sampler_info(&SamplerInfo {
+            loops: vec![SampleLoop { id: 0, loop_type: 0, start: 2, end: 4, fraction: 0, play_count: 3 }],
+            ..SamplerInfo::default()
+        }).unwrap();
+        writer.finalize().unwrap();
+    }
+    let frames = |range: ::std::ops::Range<i16>| range.flat_map(|i| vec![i, -i]);
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    // Reading some samples first does not matter, playback starts at the beginning.
+    reader.samples::<i16>().next().unwrap().unwrap();
+
+    let samples: Vec<i16> = reader.samples_looped(7, Some(2)).unwrap().map(|s| s.unwrap()).collect();
+    let expected: Vec<i16> = frames(0..10).chain(frames(7..10)).chain(frames(7..10)).collect();
+    assert_eq!(samples, expected);
+
+    let samples: Vec<i16> = reader.samples_looped(9, None).unwrap().take(26).map(|s| s.unwrap()).collect();
+    let expected: Vec<i16> = frames(0..10).chain(frames(9..10).cycle().take(6)).collect();
+    assert_eq!(samples, expected);
+
+    let samples: Vec<i16> = reader.samples_looped(0, Some(0)).unwrap().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, frames(0..10).collect::<Vec<_>>());
+    assert!(reader.samples_looped::<i16>(10, None).is_err());
+
+    // The smpl loop is inclusive, it is played three times in total.
+    reader.read_trailing_chunks().unwrap();
+    let samples: Vec<i16> = reader.samples_smpl_looped().unwrap().unwrap().map(|s| s.unwrap()).collect();
+    let expected: Vec<i16> = frames(0..5).chain(frames(2..5)).chain(frames(2..10)).collect();
+    assert_eq!(samples, expected);
+
+    let mut reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    assert!(reader.samples_smpl_looped::<i16>().unwrap().is_none());
+}
+
+#[test]
+fn channel_samples_skips_the_other_channels() {
+    use write::WavWriter;
+
+    let spec = WavSpec {
+        channels: 5,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for frame in 0..7_i16 {
+            for channel in 0..5 {
+                writer.write_sample(frame * 10 + channel).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+    }
+    let bytes = buffer.into_inner();
+    for channel in 0..5 {
+        let mut reader = WavReader::new(&bytes[..]).unwrap();
+        let samples = reader.channel_samples::<i16>(channel).unwrap();
+        assert_eq!(samples.len(), 7);
+        let samples: Vec<i16> = samples.map(|s| s.unwrap()).collect();
+        let expected: Vec<i16> = (0..7).map(|f| f * 10 + channel as i16).collect();
+        assert_eq!(samples, expected);
+    }
+
+    // Continue after the third sample of the first frame, and stop after the
+    // third sample of the last frame.
+    let truncate = |reader: &mut WavReader<&[u8]>| {
+        let chunk = &mut reader.reader.data_state.as_mut().unwrap().chunk;
+        chunk.len -= 4;
+        chunk.remaining -= 4;
+    };
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    truncate(&mut reader);
+    assert_eq!(reader.samples::<i16>().take(3).map(|s| s.unwrap()).collect::<Vec<_>>(), [0, 1, 2]);
+    let samples = reader.channel_samples::<i16>(1).unwrap();
+    assert_eq!(samples.len(), 6);
+    assert_eq!(samples.map(|s| s.unwrap()).collect::<Vec<_>>(), [11, 21, 31, 41, 51, 61]);
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    truncate(&mut reader);
+    let samples: Vec<i16> = reader.channel_samples(4).unwrap().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [4, 14, 24, 34, 44, 54]);
+
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    assert!(reader.channel_samples::<i16>(5).is_err());
+}
+
+#[test]
+fn samples_mono_averages_every_frame() {
+    let fname = "testsamples/waveformatex-16bit-44100Hz-stereo.wav";
+    let samples: Vec<i16> = WavReader::open(fname).unwrap().samples().map(|s| s.unwrap()).collect();
+    let expected: Vec<i16> = samples.chunks(2).map(i16::average).collect();
+    let mut reader = WavReader::open(fname).unwrap();
+    let mono = reader.samples_mono::<i16>();
+    assert_eq!(mono.len(), expected.len());
+    assert_eq!(mono.map(|s| s.unwrap()).collect::<Vec<_>>(), expected);
+
+    let mut reader = WavReader::open(fname).unwrap();
+    let floats: Vec<f32> = WavReader::open(fname).unwrap().samples().map(|s| s.unwrap()).collect();
+    let mono: Vec<f32> = reader.samples_mono().map(|s: Result<f32>| s.unwrap()).collect();
+    assert_eq!(mono.len(), floats.len() / 2);
+    assert_eq!(mono[0], (floats[0] + floats[1]) / 2.0);
+
+    // An incomplete frame at the end is an error.
+    let mut reader = WavReader::open(fname).unwrap();
+    reader.reader.data_state.as_mut().unwrap().chunk.remaining = 6;
+    let mono: Vec<Result<i16>> = reader.samples_mono().collect();
+    assert_eq!(mono.len(), 2);
+    match mono[1] {
+        Err(Error::UnfinishedSample) => {}
+        ref other => panic!("expected UnfinishedSample, got {:?}", other),
+    }
+}
+
+#[test]
+fn scan_peaks_finds_the_range_of_every_channel() {
+    use write::WavWriter;
+
+    let frames: &[[i32; 3]] = &[[0, 100, -5], [-32768, 200, 5], [32767, -200, 0], [1, 30000, -32767]];
+    for &(bits, format) in &[(8, SampleFormat::Int), (16, SampleFormat::Int), (24, SampleFormat::Int),
+                             (32, SampleFormat::Int), (32, SampleFormat::Float)] {
+        let spec = WavSpec {
+            channels: 3,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: format,
+        };
+        // Scale the 16-bit values to the bit depth, so the normalized stats are the same.
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            for frame in frames {
+                for &x in frame {
+                    match (bits, format) {
+                        (8, _) => writer.write_sample((x >> 8) as i8).unwrap(),
+                        (_, SampleFormat::Int) => writer.write_sample(x << (bits - 16)).unwrap(),
+                        (_, SampleFormat::Float) => writer.write_sample(x as f32 / 32768.0).unwrap(),
+                    }
+                }
+            }
+            writer.finalize().unwrap();
+        }
+        buffer.set_position(0);
+        let mut reader = WavReader::new(buffer).unwrap();
+        let stats = reader.scan_peaks().unwrap();
+        let v = |x: i32| if bits == 8 { (x >> 8) as f64 / 128.0 } else { x as f64 / 32768.0 };
+        assert_eq!(stats[0], ChannelStats { min: -1.0, max: v(32767), peak: 1.0, peak_position: 1 },
+                   "channel 0 at {} bits", bits);
+        assert_eq!(stats[1], ChannelStats { min: v(-200), max: v(30000), peak: v(30000), peak_position: 3 },
+                   "channel 1 at {} bits", bits);
+        assert_eq!(stats[2], ChannelStats { min: v(-32767), max: v(5), peak: -v(-32767), peak_position: 3 },
+                   "channel 2 at {} bits", bits);
+        assert!(reader.samples::<i32>().next().is_none());
+    }
+
+    // Positions count from the start of the data, also after reading some samples.
+    let mut reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    reader.samples::<i16>().next().unwrap().unwrap();
+    let stats = reader.scan_peaks().unwrap();
+    let expected = ChannelStats { min: -7.0 / 32768.0, max: 5.0 / 32768.0, peak: 7.0 / 32768.0, peak_position: 3 };
+    assert_eq!(stats, [expected]);
+    let stats = reader.scan_peaks().unwrap();
+    assert_eq!(stats, [ChannelStats { min: 0.0, max: 0.0, peak: 0.0, peak_position: 0 }]);
+}
+
+#[test]
+fn spec_ex_describes_the_fmt_chunk() {
+    let reader = WavReader::open("testsamples/waveformatextensible-24bit-4byte-48kHz-stereo.wav").unwrap();
+    let spec_ex = reader.spec_ex();
+    assert_eq!(spec_ex.spec, reader.spec());
+    assert_eq!(spec_ex.spec.bits_per_sample, 24);
+    assert_eq!(spec_ex.container_bits(), 32);
+    assert_eq!(spec_ex.block_align(), 8);
+    assert_eq!(reader.sub_format(), Some(super::KSDATAFORMAT_SUBTYPE_PCM));
+
+    let reader = WavReader::open("testsamples/waveformatextensible-ieeefloat-44100Hz-mono.wav").unwrap();
+    assert_eq!(reader.sub_format(), Some(super::KSDATAFORMAT_SUBTYPE_IEEE_FLOAT));
+
+    let reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    assert_eq!(reader.spec_ex().container_bits(), 16);
+    assert_eq!(reader.spec_ex().block_align(), 2);
+    assert_eq!(reader.sub_format(), None);
+}
+
+/// Builds a file with the samples in a wavl list, with the given chunks in the list.
+#[cfg(test)]
+fn build_wavl_file(spec: WavSpec, list: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let mut fmt = Vec::new();
+    {
+        let writer = ::write::WavWriter::new(io::Cursor::new(&mut fmt), spec).unwrap();
+        writer.finalize().unwrap();
+    }
+    // Keep the RIFF header and the fmt chunk, drop the empty data chunk.
+    fmt.truncate(fmt.len() - 8);
+    let mut wavl = b"wavl".to_vec();
+    for &(id, ref data) in list {
+        wavl.extend_from_slice(id);
+        wavl.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wavl.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            wavl.push(0);
+        }
+    }
+    let mut bytes = fmt;
+    bytes.extend_from_slice(b"LIST");
+    bytes.extend_from_slice(&(wavl.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&wavl);
+    let riff_len = bytes.len() as u32 - 8;
+    bytes[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    bytes
+}
+
+#[test]
+fn wavl_list_expands_the_silence() {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let le = |samples: &[i16]| samples.iter().flat_map(|s| s.to_le_bytes().to_vec()).collect::<Vec<u8>>();
+    let bytes = build_wavl_file(spec, &[
+        (b"data", le(&[1, -1, 2, -2])),
+        (b"slnt", 3_u32.to_le_bytes().to_vec()),
+        (b"data", le(&[3, -3])),
+    ]);
+    let expected = [1, -1, 2, -2, 0, 0, 0, 0, 0, 0, 3, -3];
+
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    assert_eq!(reader.len(), 12);
+    assert_eq!(reader.duration(), 6);
+    assert!(reader.chunks_before_data().is_empty());
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, expected);
+
+    // Seeking into and across the silence accounts for the frames that are not stored.
+    let mut reader = WavReader::new(io::Cursor::new(&bytes[..])).unwrap();
+    for frame in 0..6 {
+        reader.seek(frame).unwrap();
+        let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, &expected[frame as usize * 2..]);
+    }
+
+    // For 8 bits, silence is the middle value.
+    let spec = WavSpec { bits_per_sample: 8, ..spec };
+    let bytes = build_wavl_file(spec, &[
+        (b"slnt", 1_u32.to_le_bytes().to_vec()),
+        (b"data", vec![0x81, 0x7f]),
+    ]);
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    let samples: Vec<i8> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [0, 0, 1, -1]);
+}
+
+#[test]
+fn read_wav_ima_adpcm() {
+    let path = "testsamples/waveformatex-ima-adpcm-4bit-8000Hz-stereo.wav";
+    let mut reader = WavReader::open(path).unwrap();
+    assert_eq!(reader.spec(), WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    });
+    // The data holds 5 blocks of 249 frames, but the fact chunk ends the
+    // samples in the fifth block.
+    assert_eq!(reader.duration(), 1000);
+    assert_eq!(reader.len(), 2000);
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), 2000);
+    assert_eq!(&samples[..12], [0, 0, 11, -11, 41, -41, 104, -104, 240, -240, 533, -533]);
+    // Frames 249 and 250 are the first of the second block.
+    assert_eq!(&samples[498..502], [-11290, -5195, -11705, -5606]);
+    assert_eq!(&samples[1992..], [-11787, -2472, -10541, -1897, -7895, -1225, -4116, -592]);
+    assert_eq!(samples.iter().map(|&s| s as i64).sum::<i64>(), -252620);
+
+    // Seeking decodes the block that holds the frame.
+    for &frame in &[0, 5, 248, 249, 250, 996, 999] {
+        reader.seek(frame).unwrap();
+        let rest: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(rest, &samples[frame as usize * 2..]);
+    }
+    assert_eq!(reader.samples_mono::<i16>().len(), 0);
+}
+
+#[test]
+fn read_wav_ima_adpcm_validates_the_block_align() {
+    let mut bytes = fs::read("testsamples/waveformatex-ima-adpcm-4bit-8000Hz-stereo.wav").unwrap();
+    // A block align of 254 bytes is not a whole number of groups for 2 channels.
+    bytes[32] = 254;
+    bytes[33] = 0;
+    match WavReader::new(&bytes[..]) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("block align"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+}
+
+#[test]
+fn read_wav_ms_adpcm() {
+    let path = "testsamples/waveformatex-ms-adpcm-4bit-8000Hz-stereo.wav";
+    let mut reader = WavReader::open(path).unwrap();
+    assert_eq!(reader.spec(), WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    });
+    // The data holds 5 blocks of 244 frames, but the fact chunk ends the
+    // samples in the fifth block.
+    assert_eq!(reader.duration(), 1000);
+    assert_eq!(reader.len(), 2000);
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples.len(), 2000);
+    assert_eq!(&samples[..12], [0, 0, 4064, -627, 5856, -1254, 10154, -1881, 11626, -2508,
+                                11626, -3135]);
+    // Frames 243 and 244 are the last of the first block and the first of the second.
+    assert_eq!(&samples[486..490], [8634, -1860, 5781, -2472]);
+    assert_eq!(&samples[1992..], [-11516, -2467, -10057, -1858, -7439, -1256, -3999, -632]);
+    assert_eq!(samples.iter().map(|&s| s as i64).sum::<i64>(), -231916);
+
+    // Seeking decodes the block that holds the frame.
+    for &frame in &[0, 5, 243, 244, 245, 996, 999] {
+        reader.seek(frame).unwrap();
+        let rest: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(rest, &samples[frame as usize * 2..]);
+    }
+}
+
+#[test]
+fn read_wav_ms_adpcm_validates_the_block_align() {
+    let mut bytes = fs::read("testsamples/waveformatex-ms-adpcm-4bit-8000Hz-stereo.wav").unwrap();
+    // A block of 13 bytes cannot hold the 7-byte headers of 2 channels.
+    bytes[32] = 13;
+    bytes[33] = 0;
+    match WavReader::new(&bytes[..]) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("block align"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+}
+
+/// Returns a file with `samples`, 16-bit mono at 8 kHz.
+#[cfg(test)]
+fn build_mono_file(samples: &[i16]) -> Vec<u8> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = ::write::WavWriter::new(&mut buffer, spec).unwrap();
+        for &s in samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    buffer.into_inner()
+}
+
+#[test]
+fn read_options_strict_riff_size() {
+    let mut bytes = build_mono_file(&[1, 2, 3]);
+    // The RIFF size covers the fmt chunk, but not the data chunk.
+    bytes[4..8].copy_from_slice(&(4_u32 + 24).to_le_bytes());
+    assert!(WavReader::new(&bytes[..]).is_ok());
+    let options = ReadOptions { strict_riff_size: true, ..ReadOptions::default() };
+    match WavReader::new_with_options(&bytes[..], options) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("beyond the RIFF size"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+    // A correct size passes the check.
+    let bytes = build_mono_file(&[1, 2, 3]);
+    assert!(WavReader::new_with_options(&bytes[..], options).is_ok());
+}
+
+#[test]
+fn read_options_allow_truncated_data() {
+    let mut bytes = build_mono_file(&[1, 2, 3, 4, 5]);
+    // Cut off the last two samples.
+    let len = bytes.len();
+    bytes.truncate(len - 4);
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    assert!(reader.samples::<i16>().collect::<Result<Vec<_>>>().is_err());
+
+    let options = ReadOptions { allow_truncated_data: true, ..ReadOptions::default() };
+    let mut reader = WavReader::new_with_options(&bytes[..], options).unwrap();
+    assert_eq!(reader.len(), 5);
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [1, 2, 3]);
+}
+
+#[test]
+fn read_options_max_header_bytes() {
+    let path = "testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav";
+    let bytes = fs::read(path).unwrap();
+    let data_start = parse_header(&bytes).unwrap().data_start;
+    let options = ReadOptions { max_header_bytes: Some(data_start), ..ReadOptions::default() };
+    assert!(WavReader::open_with_options(path, options).is_ok());
+    let options = ReadOptions { max_header_bytes: Some(data_start - 1), ..ReadOptions::default() };
+    match WavReader::open_with_options(path, options) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("exceeds the limit"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+}
+
+#[test]
+fn read_options_verify_block_align() {
+    let mut bytes = build_mono_file(&[1, -1, 2]);
+    // A block align of 1 byte for 16 bits, with a byte rate that matches it.
+    bytes[28..32].copy_from_slice(&8000_u32.to_le_bytes());
+    bytes[32..34].copy_from_slice(&1_u16.to_le_bytes());
+    match WavReader::new(&bytes[..]) {
+        Err(Error::FormatError(..)) => {}
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+    let options = ReadOptions { verify_block_align: false, ..ReadOptions::default() };
+    let mut reader = WavReader::new_with_options(&bytes[..], options).unwrap();
+    assert_eq!(reader.spec_ex().block_align(), 2);
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [1, -1, 2]);
+}
+
+#[test]
+fn read_options_max_channels() {
+    let mut bytes = build_mono_file(&[1, 2, 3, 4, 5, 6]);
+    // Claim 257 channels of 16 bits, with a matching block align and byte rate.
+    bytes[22..24].copy_from_slice(&257_u16.to_le_bytes());
+    bytes[28..32].copy_from_slice(&(8000_u32 * 514).to_le_bytes());
+    bytes[32..34].copy_from_slice(&514_u16.to_le_bytes());
+    match WavReader::new(&bytes[..]) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("257 channels"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+    let options = ReadOptions { max_channels: 257, ..ReadOptions::default() };
+    let reader = WavReader::new_with_options(&bytes[..], options).unwrap();
+    assert_eq!(reader.spec().channels, 257);
+
+    // The limit is inclusive.
+    bytes[22..24].copy_from_slice(&MAX_CHANNELS.to_le_bytes());
+    bytes[28..32].copy_from_slice(&(8000_u32 * 512).to_le_bytes());
+    bytes[32..34].copy_from_slice(&512_u16.to_le_bytes());
+    assert_eq!(WavReader::new(&bytes[..]).unwrap().spec().channels, 256);
+}
+
+#[test]
+fn read_options_max_chunk_bytes() {
+    let path = "testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav";
+    let options = ReadOptions { max_chunk_bytes: Some(4), ..ReadOptions::default() };
+    match WavReader::open_with_options(path, options) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("exceeds the limit of 4 bytes"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+
+    // The limit applies to the chunks after the data chunk as well.
+    let mut bytes = build_mono_file(&[1, 2]);
+    bytes.extend_from_slice(b"bext\x08\x00\x00\x00abcdefgh");
+    let options = ReadOptions { max_chunk_bytes: Some(8), ..ReadOptions::default() };
+    let mut reader = WavReader::new_with_options(io::Cursor::new(&bytes[..]), options).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let options = ReadOptions { max_chunk_bytes: Some(7), ..ReadOptions::default() };
+    let mut reader = WavReader::new_with_options(io::Cursor::new(&bytes[..]), options).unwrap();
+    match reader.read_trailing_chunks() {
+        Err(Error::FormatError(..)) => {}
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+}
+
+#[test]
+fn chunks_larger_than_the_source_are_not_read() {
+    // A LIST chunk of 3 GB, in a file of less than 100 bytes.
+    let path = "testsamples/fuzz/oom-9a5a39f1d586c76bace16211d161eafd9649ec4f.wav";
+    match WavReader::open(path) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("bytes left in the source"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+    // The chunk can still be skipped without reading it.
+    let bytes = fs::read(path).unwrap();
+    let mut reader = ChunksReader::new(io::Cursor::new(bytes)).unwrap();
+    match reader.next().unwrap() {
+        Some(Chunk::Fmt(..)) => {}
+        _ => panic!("expected the fmt chunk"),
+    }
+    match reader.next().unwrap() {
+        Some(Chunk::Unknown(id, _)) => assert_eq!(&id, b"LIST"),
+        _ => panic!("expected the LIST chunk"),
+    }
+    assert!(reader.next().unwrap().is_none());
+
+    // A bext chunk of 4 GB after the data chunk.
+    let path = "testsamples/fuzz/oom-938292402cbacf2dbf50bee46924008ab236c5d4.wav";
+    let mut reader = WavReader::open(path).unwrap();
+    match reader.read_trailing_chunks() {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("malformed chunk 'bext'"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [1, 2]);
+}
+
+#[test]
+fn read_all_returns_the_spec_and_the_samples() {
+    let path = "testsamples/waveformatextensible-24bit-4byte-48kHz-stereo.wav";
+    let (spec, samples) = read_all::<i32, _>(path).unwrap();
+    let mut reader = WavReader::open(path).unwrap();
+    assert_eq!(spec, reader.spec());
+    let expected: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, expected);
+
+    // The sample type is too narrow, the first error is returned.
+    assert!(read_all::<i16, _>(path).is_err());
+    assert!(read_all::<i16, _>("testsamples/does-not-exist.wav").is_err());
+}
+
+#[test]
+fn read_samples_into_padded_pads_the_last_block() {
+    let samples: Vec<i16> = (1..13).collect();
+    let bytes = build_mono_file(&samples);
+
+    // 12 samples are 3 whole blocks of 4.
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    let mut buf = [-1_i16; 4];
+    for block in samples.chunks(4) {
+        assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 4);
+        assert_eq!(buf, block);
+    }
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 0);
+    assert_eq!(buf, [0; 4]);
+
+    // Blocks of 5 leave 2 samples for the last block.
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    let mut buf = [-1_i16; 5];
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 5);
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 5);
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 2);
+    assert_eq!(buf, [11, 12, 0, 0, 0]);
+
+    // After seeking, the block starts at the new position.
+    let mut reader = WavReader::new(io::Cursor::new(&bytes[..])).unwrap();
+    reader.seek(9).unwrap();
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 3);
+    assert_eq!(buf, [10, 11, 12, 0, 0]);
+
+    // For 8 bits, the decoded silence is zero too.
+    let mut reader = WavReader::open("testsamples/pcmwaveformat-8bit-44100Hz-mono.wav").unwrap();
+    let len = reader.len() as usize;
+    let mut buf = vec![1_i8; len + 3];
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), len);
+    assert_eq!(&buf[len..], [0, 0, 0]);
+}
+
+#[test]
+fn position_follows_reads_and_seeks() {
+    let path = "testsamples/waveformatex-16bit-44100Hz-stereo.wav";
+    let mut reader = WavReader::open(path).unwrap();
+    assert_eq!(reader.position(), 0);
+    assert_eq!(reader.elapsed(), time::Duration::new(0, 0));
+
+    // A partially consumed iterator leaves the position after its last sample.
+    assert_eq!(reader.samples::<i16>().take(3).count(), 3);
+    assert_eq!(reader.position(), 3);
+    assert_eq!(reader.elapsed(), time::Duration::new(0, 22675));
+
+    let mut frame = [0_i16; 2];
+    assert!(reader.read_frame_into(&mut frame).unwrap());
+    assert_eq!(reader.position(), 5);
+
+    reader.seek(1).unwrap();
+    assert_eq!(reader.position(), 2);
+    let mut buf = [0_i16; 8];
+    assert_eq!(reader.read_samples_into_padded(&mut buf).unwrap(), 6);
+    assert_eq!(reader.position(), 8);
+    assert_eq!(reader.position(), reader.len() as u64);
+
+    reader.seek(0).unwrap();
+    assert_eq!(reader.channel_samples::<i16>(1).unwrap().next().unwrap().unwrap(), -3);
+    assert_eq!(reader.position(), 2);
+    assert_eq!(reader.samples::<i16>().count(), 6);
+    assert_eq!(reader.position(), 8);
+
+    // The position counts decoded samples for compressed files.
+    let path = "testsamples/waveformatex-ima-adpcm-4bit-8000Hz-stereo.wav";
+    let mut reader = WavReader::open(path).unwrap();
+    reader.seek(300).unwrap();
+    assert_eq!(reader.position(), 600);
+    assert_eq!(reader.samples::<i16>().take(5).count(), 5);
+    assert_eq!(reader.position(), 605);
+    assert_eq!(reader.elapsed(), time::Duration::new(0, 37_750_000));
+}
+
+#[test]
+fn channel_samples_of_ima_adpcm() {
+    // The decoded samples are skipped, not the encoded blocks.
+    let path = "testsamples/waveformatex-ima-adpcm-4bit-8000Hz-stereo.wav";
+    let mut reader = WavReader::open(path).unwrap();
+    let all: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    let mut reader = WavReader::open(path).unwrap();
+    let right: Vec<i16> = reader.channel_samples(1).unwrap().map(|s| s.unwrap()).collect();
+    let expected: Vec<i16> = all.iter().skip(1).step_by(2).cloned().collect();
+    assert_eq!(right, expected);
+}
@@ -0,0 +1,390 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing of headers and decoding of samples held in byte slices.
+//!
+//! Unlike the rest of the crate, this needs neither `std` nor an allocator,
+//! so it is available when Hound is built without the default `std`
+//! feature, for example for an embedded target that reads a file from flash
+//! or receives it in DMA buffers. `Header::parse()` reads the chunks up to
+//! the data chunk, and the `read_samples_*` methods of `Header` decode a
+//! block of the data chunk into a buffer of the caller. PCM and IEEE float
+//! samples are supported, also in WAVE_FORMAT_EXTENSIBLE.
+
+use core::fmt;
+use core::result;
+use super::{KSDATAFORMAT_SUBTYPE_IEEE_FLOAT, KSDATAFORMAT_SUBTYPE_PCM, SampleFormat,
+            UnsupportedFormat, WavSpec};
+
+/// The error type for operations on byte slices, which does not depend on `std`.
+///
+/// Without the `std` feature, this is the `Error` type of the crate. With it,
+/// it converts into `Error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliceError {
+    /// The slice ends before the header does.
+    UnexpectedEnd,
+    /// Ill-formed WAVE data was encountered.
+    FormatError(&'static str),
+    /// The format is not supported.
+    Unsupported(UnsupportedFormat),
+    /// The sample has more bits than the destination type.
+    TooWide,
+    /// The sample format is different than the destination format.
+    InvalidSampleFormat,
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> result::Result<(), fmt::Error> {
+        match *self {
+            SliceError::UnexpectedEnd => formatter.write_str("The slice ends before the header."),
+            SliceError::FormatError(reason) => {
+                try!(formatter.write_str("Ill-formed WAVE file: "));
+                formatter.write_str(reason)
+            }
+            SliceError::Unsupported(ref format) => {
+                write!(formatter, "The wave format of the file is not supported: {}.", format)
+            }
+            SliceError::TooWide => {
+                formatter.write_str("The sample has more bits than the destination type.")
+            }
+            SliceError::InvalidSampleFormat => {
+                formatter.write_str("The sample format differs from the destination format.")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for SliceError {
+    fn description(&self) -> &str {
+        match *self {
+            SliceError::UnexpectedEnd => "the slice ends before the header",
+            SliceError::FormatError(reason) => reason,
+            SliceError::Unsupported(_) => "the wave format of the file is not supported",
+            SliceError::TooWide => "the sample has more bits than the destination type",
+            SliceError::InvalidSampleFormat => "the sample format differs from the destination format",
+        }
+    }
+}
+
+/// A type for results of operations on byte slices.
+pub type SliceResult<T> = result::Result<T, SliceError>;
+
+/// The header of a WAVE file, parsed from a byte slice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    spec: WavSpec,
+    bytes_per_sample: u16,
+    data_offset: usize,
+    data_len: u32,
+}
+
+fn le_u16(bytes: &[u8], at: usize) -> u16 {
+    bytes[at] as u16 | (bytes[at + 1] as u16) << 8
+}
+
+fn le_u32(bytes: &[u8], at: usize) -> u32 {
+    le_u16(bytes, at) as u32 | (le_u16(bytes, at + 2) as u32) << 16
+}
+
+impl Header {
+    /// Parses the header at the start of `bytes`.
+    ///
+    /// The chunks are read up to the header of the data chunk; those other
+    /// than `fmt ` are skipped. The slice does not need to hold the samples,
+    /// so they can be decoded from other buffers, starting at
+    /// `data_offset()` in the file.
+    pub fn parse(bytes: &[u8]) -> SliceResult<Header> {
+        if bytes.len() < 12 {
+            return Err(SliceError::UnexpectedEnd)
+        }
+        if &bytes[0..4] != b"RIFF" {
+            return Err(SliceError::FormatError("no RIFF tag found"))
+        }
+        if &bytes[8..12] != b"WAVE" {
+            return Err(SliceError::FormatError("no WAVE tag found"))
+        }
+        let mut format = None;
+        let mut offset: usize = 12;
+        loop {
+            if bytes.len() < offset.saturating_add(8) {
+                return Err(SliceError::UnexpectedEnd)
+            }
+            let len = le_u32(bytes, offset + 4);
+            let start = offset + 8;
+            match &bytes[offset..offset + 4] {
+                b"fmt " => {
+                    if bytes.len() < start.saturating_add(len as usize) {
+                        return Err(SliceError::UnexpectedEnd)
+                    }
+                    format = Some(try!(parse_fmt(&bytes[start..start + len as usize])));
+                }
+                b"data" => {
+                    let (spec, bytes_per_sample) = match format {
+                        Some(format) => format,
+                        None => return Err(SliceError::FormatError("no fmt chunk before the data chunk")),
+                    };
+                    return Ok(Header {
+                        spec: spec,
+                        bytes_per_sample: bytes_per_sample,
+                        data_offset: start,
+                        data_len: len,
+                    })
+                }
+                _ => {}
+            }
+            offset = start.saturating_add(len as usize).saturating_add(len as usize % 2);
+        }
+    }
+
+    /// Returns the format of the samples.
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Returns the number of bytes that a sample occupies in the data chunk.
+    pub fn bytes_per_sample(&self) -> u16 {
+        self.bytes_per_sample
+    }
+
+    /// Returns the offset of the first sample in the file.
+    pub fn data_offset(&self) -> usize {
+        self.data_offset
+    }
+
+    /// Returns the length of the data chunk in bytes, as stored in its header.
+    pub fn data_len(&self) -> u32 {
+        self.data_len
+    }
+
+    /// Returns the number of samples in the data chunk, for all channels.
+    pub fn len(&self) -> u32 {
+        self.data_len / self.bytes_per_sample as u32
+    }
+
+    /// Returns whether the data chunk holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of frames in the data chunk.
+    pub fn duration(&self) -> u32 {
+        self.len() / self.spec.channels as u32
+    }
+
+    /// Returns the part of the data chunk that `file` holds, for a slice with the entire file.
+    pub fn data<'a>(&self, file: &'a [u8]) -> &'a [u8] {
+        let start = if self.data_offset < file.len() { self.data_offset } else { file.len() };
+        let end = start + (file.len() - start).min(self.data_len as usize);
+        &file[start..end]
+    }
+
+    /// Decodes the samples in `data` into `out`, returns the number of samples decoded.
+    ///
+    /// The data must start at a sample boundary of the data chunk. As many
+    /// samples are decoded as fit in `out`; an incomplete sample at the end
+    /// of `data` is not decoded. Like `WavReader::samples::<i16>()`, this
+    /// returns an error for float samples and samples wider than 16 bits.
+    pub fn read_samples_i16(&self, data: &[u8], out: &mut [i16]) -> SliceResult<usize> {
+        if self.spec.sample_format != SampleFormat::Int {
+            return Err(SliceError::InvalidSampleFormat)
+        }
+        if self.spec.bits_per_sample > 16 {
+            return Err(SliceError::TooWide)
+        }
+        Ok(self.decode_int(data, out, |x| x as i16))
+    }
+
+    /// Decodes the samples in `data` into `out`, returns the number of samples decoded.
+    ///
+    /// This is like `read_samples_i16()`, for integer samples of any width.
+    pub fn read_samples_i32(&self, data: &[u8], out: &mut [i32]) -> SliceResult<usize> {
+        if self.spec.sample_format != SampleFormat::Int {
+            return Err(SliceError::InvalidSampleFormat)
+        }
+        Ok(self.decode_int(data, out, |x| x))
+    }
+
+    /// Decodes the samples in `data` into `out`, returns the number of samples decoded.
+    ///
+    /// This is like `read_samples_i16()`, for float samples.
+    pub fn read_samples_f32(&self, data: &[u8], out: &mut [f32]) -> SliceResult<usize> {
+        if self.spec.sample_format != SampleFormat::Float {
+            return Err(SliceError::InvalidSampleFormat)
+        }
+        let n = out.len().min(data.len() / 4);
+        for (sample, bytes) in out[..n].iter_mut().zip(data.chunks(4)) {
+            *sample = f32::from_bits(le_u32(bytes, 0));
+        }
+        Ok(n)
+    }
+
+    fn decode_int<T, F: Fn(i32) -> T>(&self, data: &[u8], out: &mut [T], convert: F) -> usize {
+        let width = self.bytes_per_sample as usize;
+        let bits = self.spec.bits_per_sample;
+        let n = out.len().min(data.len() / width);
+        for (sample, bytes) in out[..n].iter_mut().zip(data.chunks(width)) {
+            let x = match width {
+                1 => bytes[0] as i32 - 128,
+                2 => le_u16(bytes, 0) as i16 as i32,
+                // Shifting left and back extends the sign of the top bit.
+                // With fewer valid bits than the container holds, such as
+                // 20 bits in 3 bytes, the valid bits are the upper ones.
+                3 => ((le_u16(bytes, 0) as u32 | (bytes[2] as u32) << 16) << 8) as i32 >> (32 - bits),
+                // 24 bits in 4 bytes are the low bytes.
+                _ if bits == 24 => (le_u32(bytes, 0) << 8) as i32 >> 8,
+                _ => le_u32(bytes, 0) as i32,
+            };
+            *sample = convert(x);
+        }
+        n
+    }
+}
+
+/// Parses the contents of a fmt chunk, returns the spec and the bytes per sample.
+fn parse_fmt(fmt: &[u8]) -> SliceResult<(WavSpec, u16)> {
+    if fmt.len() < 16 {
+        return Err(SliceError::FormatError("fmt chunk is too short"))
+    }
+    let format_tag = le_u16(fmt, 0);
+    let channels = le_u16(fmt, 2);
+    let sample_rate = le_u32(fmt, 4);
+    let block_align = le_u16(fmt, 12);
+    let mut bits_per_sample = le_u16(fmt, 14);
+    let unsupported = |bits_per_sample, sub_format| {
+        SliceError::Unsupported(UnsupportedFormat {
+            format_tag: Some(format_tag),
+            bits_per_sample: bits_per_sample,
+            sub_format: sub_format,
+        })
+    };
+    let sample_format = match format_tag {
+        0x0001 => SampleFormat::Int,
+        0x0003 => SampleFormat::Float,
+        0xfffe => {
+            if fmt.len() < 40 {
+                return Err(SliceError::FormatError("fmt chunk is too short for WAVE_FORMAT_EXTENSIBLE"))
+            }
+            let mut sub_format = [0; 16];
+            sub_format.copy_from_slice(&fmt[24..40]);
+            // The valid bits may be fewer than the container holds.
+            let valid_bits = le_u16(fmt, 18);
+            if valid_bits != 0 {
+                bits_per_sample = valid_bits;
+            }
+            match sub_format {
+                KSDATAFORMAT_SUBTYPE_PCM => SampleFormat::Int,
+                KSDATAFORMAT_SUBTYPE_IEEE_FLOAT => SampleFormat::Float,
+                _ => return Err(unsupported(bits_per_sample, Some(sub_format))),
+            }
+        }
+        _ => return Err(unsupported(bits_per_sample, None)),
+    };
+    if channels == 0 {
+        return Err(SliceError::FormatError("file contains zero channels"))
+    }
+    let bytes_per_sample = match block_align % channels {
+        0 => block_align / channels,
+        _ => return Err(SliceError::FormatError("block align is not a multiple of the number of channels")),
+    };
+    let supported = match (sample_format, bytes_per_sample, bits_per_sample) {
+        (SampleFormat::Int, 1, 8) => true,
+        (SampleFormat::Int, 2, 16) => true,
+        (SampleFormat::Int, 3, bits) => bits > 16 && bits <= 24,
+        (SampleFormat::Int, 4, 24) => true,
+        (SampleFormat::Int, 4, 32) => true,
+        (SampleFormat::Float, 4, 32) => true,
+        _ => false,
+    };
+    if !supported {
+        return Err(unsupported(bits_per_sample, None))
+    }
+    let spec = WavSpec {
+        channels: channels,
+        sample_rate: sample_rate,
+        bits_per_sample: bits_per_sample,
+        sample_format: sample_format,
+    };
+    Ok((spec, bytes_per_sample))
+}
+
+#[test]
+fn header_parse_matches_the_reader() {
+    use std::fs;
+    use read::WavReader;
+
+    for fname in &["testsamples/pcmwaveformat-16bit-44100Hz-mono-metadata.wav",
+                   "testsamples/pcmwaveformat-24bit-4byte-48kHz-stereo.wav",
+                   "testsamples/waveformatex-8bit-11025Hz-mono.wav",
+                   "testsamples/waveformatex-ieeefloat-44100Hz-mono.wav",
+                   "testsamples/waveformatextensible-20bit-48kHz-mono.wav",
+                   "testsamples/waveformatextensible-24bit-192kHz-mono.wav",
+                   "testsamples/waveformatextensible-32bit-48kHz-stereo.wav"] {
+        let bytes = fs::read(fname).unwrap();
+        let header = Header::parse(&bytes).unwrap();
+        let mut reader = WavReader::new(&bytes[..]).unwrap();
+        assert_eq!(header.spec(), reader.spec(), "{}", fname);
+        assert_eq!(header.len(), reader.len(), "{}", fname);
+        assert_eq!(header.duration(), reader.duration(), "{}", fname);
+
+        // Decode in blocks, as from a DMA buffer.
+        let data = header.data(&bytes);
+        let block_len = 3 * header.bytes_per_sample() as usize;
+        match header.spec().sample_format {
+            SampleFormat::Int => {
+                let expected: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+                let mut samples = Vec::new();
+                for block in data.chunks(block_len) {
+                    let mut out = [0; 3];
+                    let n = header.read_samples_i32(block, &mut out).unwrap();
+                    samples.extend_from_slice(&out[..n]);
+                }
+                assert_eq!(samples, expected, "{}", fname);
+            }
+            SampleFormat::Float => {
+                let expected: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+                let mut samples = vec![0.0; data.len() / 4];
+                assert_eq!(header.read_samples_f32(data, &mut samples).unwrap(), samples.len());
+                assert_eq!(samples, expected, "{}", fname);
+            }
+        }
+    }
+}
+
+#[test]
+fn header_parse_reports_errors() {
+    use std::fs;
+
+    let bytes = fs::read("testsamples/waveformatex-16bit-44100Hz-stereo.wav").unwrap();
+    let header = Header::parse(&bytes).unwrap();
+    // The header is complete without the samples.
+    assert_eq!(Header::parse(&bytes[..header.data_offset()]), Ok(header));
+    assert_eq!(Header::parse(&bytes[..header.data_offset() - 1]), Err(SliceError::UnexpectedEnd));
+
+    let data = header.data(&bytes);
+    let mut out = [0_i16; 5];
+    // An incomplete sample at the end is not decoded.
+    assert_eq!(header.read_samples_i16(&data[..7], &mut out), Ok(3));
+    assert_eq!(header.read_samples_f32(data, &mut [0.0; 4]), Err(SliceError::InvalidSampleFormat));
+
+    let bytes = fs::read("testsamples/waveformatextensible-24bit-192kHz-mono.wav").unwrap();
+    let header = Header::parse(&bytes).unwrap();
+    assert_eq!(header.read_samples_i16(header.data(&bytes), &mut out), Err(SliceError::TooWide));
+
+    let bytes = fs::read("testsamples/waveformatex-ima-adpcm-4bit-8000Hz-stereo.wav").unwrap();
+    match Header::parse(&bytes) {
+        Err(SliceError::Unsupported(format)) => assert_eq!(format.format_tag, Some(0x0011)),
+        other => panic!("expected Unsupported, got {:?}", other),
+    }
+    assert_eq!(Header::parse(b"RIFX\0\0\0\0WAVE"), Err(SliceError::FormatError("no RIFF tag found")));
+}
@@ -0,0 +1,61 @@
+// Hound -- A wav encoding and decoding library in Rust
+// Copyright (C) 2015 Ruud van Asseldonk
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// A copy of the License has been included in the root of the repository.
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for testing code that uses Hound.
+//!
+//! This module is only available with the `testutil` feature enabled.
+
+use std::io;
+use super::{Result, Sample, WavReader, WavSpec, WavWriter};
+
+/// Writes the samples to an in-memory wav file, and reads them back.
+///
+/// This exercises the same code paths as writing and reading a file on disk.
+/// The number of samples must be a multiple of the number of channels, and
+/// the samples must fit in `spec.bits_per_sample`, otherwise the error from
+/// the writer is returned. For supported formats, the returned samples are
+/// equal to the input samples.
+pub fn roundtrip<S: Sample + Copy>(spec: WavSpec, samples: &[S]) -> Result<Vec<S>> {
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = try!(WavWriter::new(&mut buffer, spec));
+        for &sample in samples {
+            try!(writer.write_sample(sample));
+        }
+        try!(writer.finalize());
+    }
+    let mut reader = try!(WavReader::new(io::Cursor::new(buffer.into_inner())));
+    reader.samples().collect()
+}
+
+#[test]
+fn roundtrip_is_lossless() {
+    use SampleFormat;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let samples = [0_i32, 8_388_607, -8_388_608, 17];
+    assert_eq!(roundtrip(spec, &samples).unwrap(), samples.to_vec());
+
+    let spec = WavSpec { bits_per_sample: 32, sample_format: SampleFormat::Float, ..spec };
+    let samples = [0.5_f32, -1.0];
+    assert_eq!(roundtrip(spec, &samples).unwrap(), samples.to_vec());
+
+    // An unfinished frame or a sample that does not fit is an error.
+    let spec = WavSpec { bits_per_sample: 8, sample_format: SampleFormat::Int, ..spec };
+    assert!(roundtrip(spec, &[1_i16]).is_err());
+    assert!(roundtrip(spec, &[1_i16, 128]).is_err());
+}
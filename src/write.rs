@@ -10,15 +10,22 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp;
 use std::fs;
 use std::io;
 use std::mem;
 use std::io::{Seek, Write};
 use std::mem::MaybeUninit;
 use std::path;
-use super::{Error, Result, Sample, SampleFormat, WavSpec};
+use std::time;
+use super::{Error, FinalizeMode, OverflowMode, Result, Sample, SampleFormat, UnsupportedFormat, WavSpec};
+use ::convert;
 use ::read;
 use read::{WavSpecEx};
+use md5::Md5;
+use metadata;
+use metadata::{AcidInfo, AdmTrack, BroadcastExtension, Cart, ChannelPeak, CuePoint, InstrumentInfo, ListInfo,
+               PeakEnvelope, PeakInfo, PlaylistSegment, RawChunk, SamplerInfo};
 
 /// Extends the functionality of `io::Write` with additional methods.
 ///
@@ -127,32 +134,88 @@ impl<W> WriteExt for W
 /// if `channels` is greater than the number of bits in the channel mask, 18 non-reserved bits,
 /// extra channels are not assigned to any physical speaker location.  In this scenario, this
 /// function will return a filled channel mask.
-fn channel_mask(channels: u16) -> u32 {
+fn default_channel_mask(channels: u16) -> u32 {
     // clamp to 0-18 to stay within reserved bits
     (0..channels.clamp(0, 18) as u32).map(|c| 1 << c).fold(0, |a, c| a | c)
 }
 
 #[test]
 fn verify_channel_mask() {
-    assert_eq!(channel_mask(0), 0);
-    assert_eq!(channel_mask(1), 1);
-    assert_eq!(channel_mask(2), 3);
-    assert_eq!(channel_mask(3), 7);
-    assert_eq!(channel_mask(4), 0xF);
-    assert_eq!(channel_mask(8), 0xFF);
-    assert_eq!(channel_mask(16), 0xFFFF);
+    assert_eq!(default_channel_mask(0), 0);
+    assert_eq!(default_channel_mask(1), 1);
+    assert_eq!(default_channel_mask(2), 3);
+    assert_eq!(default_channel_mask(3), 7);
+    assert_eq!(default_channel_mask(4), 0xF);
+    assert_eq!(default_channel_mask(8), 0xFF);
+    assert_eq!(default_channel_mask(16), 0xFFFF);
     // expect channels >= 18 to yield the same mask
-    assert_eq!(channel_mask(18), 0x3FFFF);
-    assert_eq!(channel_mask(32), 0x3FFFF);
-    assert_eq!(channel_mask(64), 0x3FFFF);
-    assert_eq!(channel_mask(129), 0x3FFFF);
+    assert_eq!(default_channel_mask(18), 0x3FFFF);
+    assert_eq!(default_channel_mask(32), 0x3FFFF);
+    assert_eq!(default_channel_mask(64), 0x3FFFF);
+    assert_eq!(default_channel_mask(129), 0x3FFFF);
 }
 
-enum FmtKind {
+#[test]
+fn write_le_i24_emits_three_bytes() {
+    let cases: &[(i32, [u8; 3])] = &[
+        (0, [0x00, 0x00, 0x00]),
+        (8_388_607, [0xff, 0xff, 0x7f]),
+        (-8_388_608, [0x00, 0x00, 0x80]),
+        (-1, [0xff, 0xff, 0xff]),
+    ];
+    for &(x, expected) in cases {
+        let mut buffer = Vec::new();
+        buffer.write_le_i24(x).unwrap();
+        assert_eq!(&buffer[..], &expected[..]);
+
+        let mut buffer = Vec::new();
+        buffer.write_le_i24_4(x).unwrap();
+        assert_eq!(&buffer[..3], &expected[..]);
+        assert_eq!(buffer[3], 0x00);
+    }
+}
+
+pub enum FmtKind {
     PcmWaveFormat,
     WaveFormatExtensible,
 }
 
+impl FmtKind {
+    /// Returns the kind of fmt chunk that is written for the given spec.
+    ///
+    /// The older PCMWAVEFORMAT structure is used if possible, because it is
+    /// more widely supported. For more than two channels or more than 16 bits
+    /// per sample, the newer WAVEFORMATEXTENSIBLE is required. See also
+    /// https://msdn.microsoft.com/en-us/library/ms713497.aspx.
+    pub fn for_spec(spec: &WavSpec) -> FmtKind {
+        if spec.channels > 2 || spec.bits_per_sample > 16 {
+            FmtKind::WaveFormatExtensible
+        } else {
+            FmtKind::PcmWaveFormat
+        }
+    }
+
+    /// Returns the kind of fmt chunk that is written for the given spec.
+    ///
+    /// Like `for_spec()`, but samples that are padded to more bytes than
+    /// their bit depth requires can only be described by WAVEFORMATEXTENSIBLE.
+    pub fn for_spec_ex(spec_ex: &WavSpecEx) -> FmtKind {
+        if spec_ex.bytes_per_sample as u32 * 8 != spec_ex.spec.bits_per_sample as u32 {
+            FmtKind::WaveFormatExtensible
+        } else {
+            FmtKind::for_spec(&spec_ex.spec)
+        }
+    }
+
+    /// Returns the size of the fmt chunk, including its header.
+    pub fn chunk_len(&self) -> u64 {
+        match *self {
+            FmtKind::PcmWaveFormat => 8 + 16,
+            FmtKind::WaveFormatExtensible => 8 + 40,
+        }
+    }
+}
+
 /// A writer for an embedded arbitrary chunk.
 ///
 /// It is recommended to use the `finalize()` method instead of just letting the
@@ -232,6 +295,18 @@ impl ChunkWritingState {
     }
 }
 
+/// Returns `Error::InvalidSpec` if a frame of `n` samples does not have one sample per channel.
+pub(crate) fn check_frame_size(n: usize, channels: u16) -> Result<()> {
+    if n == channels as usize {
+        Ok(())
+    } else {
+        Err(Error::InvalidSpec(format!("a frame of {} samples does not match {} channels", n, channels)))
+    }
+}
+
+/// The offset of the size of the RIFF chunk, which is updated as the file grows.
+const RIFF_LEN_OFFSET: u64 = 4;
+
 /// A Riff chunk Wave writer, allowing to write arbitrary chunks to a file.
 ///
 /// For simple out-of-the-box wav usage, prefer the `WavWriter` facade.
@@ -244,6 +319,275 @@ pub struct ChunksWriter<W: io::Write + io::Seek> {
     pub data_state: Option<ChunkWritingState>,
     dirty: bool,
     sample_writer_buffer: Vec<MaybeUninit<u8>>,
+    /// metadata chunks to be written after the data chunk on finalize
+    trailing_chunks: Vec<RawChunk>,
+    /// whether chunks can no longer be inserted before the data chunk
+    layout_fixed: bool,
+    /// the metadata chunks that were written before the data chunk, with
+    /// their offsets
+    written_chunks: Vec<(u64, RawChunk)>,
+    /// cue points to be written after the data chunk on finalize
+    cue_points: Vec<CuePoint>,
+    /// playlist segments to be written after the cue points on finalize
+    playlist: Vec<PlaylistSegment>,
+    /// boundary to align the first sample to, if any
+    data_alignment: Option<u32>,
+    /// size of the `JUNK` chunk between the data chunk and the chunk before it
+    filler_len: u64,
+    /// offset of the first byte of the data chunk contents
+    data_start: u64,
+    /// the peaks of the samples written, if a `levl` chunk is to be written
+    peak_envelope: Option<PeakAccumulator>,
+    /// the checksum of the data written, if an `MD5 ` chunk is to be written
+    md5: Option<Md5>,
+    /// the peak of every channel, if a `PEAK` chunk is to be written
+    peak_info: Option<PeakTracker>,
+    /// how samples that do not fit the bit depth are handled
+    overflow_mode: OverflowMode,
+    /// how out of range floats passed to `write_samples_f32()` are handled
+    float_overflow_mode: OverflowMode,
+    /// the channel mask to write instead of the default one, if any
+    channel_mask: Option<u32>,
+    /// the length of the data chunk, if it was written up front
+    fixed_len: Option<u32>,
+    /// whether to complete the last frame when finalizing
+    finalize_mode: FinalizeMode,
+    /// whether `finalize()` was called, so the destructor has nothing left to do
+    finalized: bool,
+}
+
+/// Writes a sample in the format of `spec_ex`, handling overflow as specified.
+#[inline]
+fn encode_sample<S: Sample, W: io::Write>(sample: S,
+                                         writer: &mut W,
+                                         spec_ex: WavSpecEx,
+                                         overflow_mode: OverflowMode)
+                                         -> Result<()> {
+    let bits = spec_ex.spec.bits_per_sample;
+    match overflow_mode {
+        OverflowMode::Error => sample.write_padded(writer, bits, spec_ex.bytes_per_sample),
+        OverflowMode::Saturate => sample.write_padded_saturating(writer, bits, spec_ex.bytes_per_sample),
+    }
+}
+
+/// Accumulates the peaks of the samples written, for a `levl` chunk.
+///
+/// Peaks are kept on a 16-bit scale, as 16-bit envelopes with both the
+/// positive and the negative peak.
+struct PeakAccumulator {
+    spec_ex: WavSpecEx,
+    block_size: u32,
+    /// the positive and negative peak of every channel in the current block
+    block: Vec<(u16, u16)>,
+    /// the channel of the next sample
+    channel: usize,
+    /// the number of complete frames in the current block
+    frames_in_block: u32,
+    /// the index of the frame of the next sample
+    frame: u32,
+    peaks: Vec<u16>,
+    /// the largest peak so far, and the frame at which it occurs
+    peak_of_peaks: (u16, u32),
+}
+
+impl PeakAccumulator {
+    fn new(spec_ex: WavSpecEx, block_size: u32) -> PeakAccumulator {
+        PeakAccumulator {
+            spec_ex: spec_ex,
+            block_size: block_size,
+            block: vec![(0, 0); spec_ex.spec.channels as usize],
+            channel: 0,
+            frames_in_block: 0,
+            frame: 0,
+            peaks: Vec::new(),
+            peak_of_peaks: (0, u32::MAX),
+        }
+    }
+
+    /// Adds the samples in `bytes`, encoded as in the data chunk.
+    fn feed(&mut self, bytes: &[u8]) {
+        let spec = self.spec_ex.spec;
+        let bits = spec.bits_per_sample;
+        for mut sample in bytes.chunks(self.spec_ex.bytes_per_sample as usize) {
+            let bytes_per_sample = self.spec_ex.bytes_per_sample;
+            let value = match spec.sample_format {
+                SampleFormat::Int => match i32::read(&mut sample, spec.sample_format, bytes_per_sample, bits) {
+                    Ok(x) if bits > 16 => x >> (bits - 16),
+                    Ok(x) => x << (16 - bits),
+                    Err(_) => 0,
+                },
+                SampleFormat::Float => match f32::read(&mut sample, spec.sample_format, bytes_per_sample, bits) {
+                    Ok(x) => (x * 32768.0).clamp(-32768.0, 32767.0) as i32,
+                    Err(_) => 0,
+                },
+            };
+            self.add(value);
+        }
+    }
+
+    /// Adds a sample on a 16-bit scale.
+    fn add(&mut self, value: i32) {
+        let peak = value.unsigned_abs() as u16;
+        {
+            let entry = &mut self.block[self.channel];
+            if value >= 0 {
+                entry.0 = cmp::max(entry.0, peak);
+            } else {
+                entry.1 = cmp::max(entry.1, peak);
+            }
+        }
+        if peak > self.peak_of_peaks.0 {
+            self.peak_of_peaks = (peak, self.frame);
+        }
+        self.channel += 1;
+        if self.channel == self.block.len() {
+            self.channel = 0;
+            self.frame += 1;
+            self.frames_in_block += 1;
+            if self.frames_in_block == self.block_size {
+                self.finish_block();
+            }
+        }
+    }
+
+    /// Appends the peaks of the current block.
+    fn finish_block(&mut self) {
+        for entry in &mut self.block {
+            self.peaks.push(entry.0);
+            self.peaks.push(entry.1);
+            *entry = (0, 0);
+        }
+        self.frames_in_block = 0;
+    }
+
+    /// Returns the envelope, including the peaks of the last, partial block.
+    fn into_envelope(mut self) -> PeakEnvelope {
+        if self.frames_in_block > 0 || self.channel > 0 {
+            self.finish_block();
+        }
+        let timestamp = match time::SystemTime::now().duration_since(time::UNIX_EPOCH) {
+            Ok(t) => format_timestamp(t.as_secs(), t.subsec_millis()),
+            Err(_) => String::new(),
+        };
+        PeakEnvelope {
+            version: 1,
+            format: 2,
+            points_per_value: 2,
+            block_size: self.block_size,
+            channels: self.block.len() as u32,
+            peak_of_peaks: self.peak_of_peaks.1,
+            timestamp: timestamp,
+            peaks: self.peaks,
+        }
+    }
+}
+
+/// Tracks the normalized peak of every channel, for a `PEAK` chunk.
+struct PeakTracker {
+    spec_ex: WavSpecEx,
+    peaks: Vec<ChannelPeak>,
+    /// the channel of the next sample
+    channel: usize,
+    /// the index of the frame of the next sample
+    frame: u32,
+}
+
+impl PeakTracker {
+    fn new(spec_ex: WavSpecEx) -> PeakTracker {
+        PeakTracker {
+            spec_ex: spec_ex,
+            peaks: vec![ChannelPeak { value: 0.0, position: 0 }; spec_ex.spec.channels as usize],
+            channel: 0,
+            frame: 0,
+        }
+    }
+
+    /// Adds the samples in `bytes`, encoded as in the data chunk.
+    fn feed(&mut self, bytes: &[u8]) {
+        let spec = self.spec_ex.spec;
+        let bits = spec.bits_per_sample;
+        let bytes_per_sample = self.spec_ex.bytes_per_sample;
+        for mut sample in bytes.chunks(bytes_per_sample as usize) {
+            let value = match spec.sample_format {
+                SampleFormat::Int => match i32::read(&mut sample, spec.sample_format, bytes_per_sample, bits) {
+                    Ok(x) => x.unsigned_abs() as f32 / (1u32 << (bits - 1)) as f32,
+                    Err(_) => 0.0,
+                },
+                SampleFormat::Float => match f32::read(&mut sample, spec.sample_format, bytes_per_sample, bits) {
+                    Ok(x) => x.abs(),
+                    Err(_) => 0.0,
+                },
+            };
+            {
+                let peak = &mut self.peaks[self.channel];
+                // Keep the first occurrence of the peak; NaN never compares greater.
+                if value > peak.value {
+                    peak.value = value;
+                    peak.position = self.frame;
+                }
+            }
+            self.channel += 1;
+            if self.channel == self.peaks.len() {
+                self.channel = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    fn into_peak_info(self) -> PeakInfo {
+        let timestamp = match time::SystemTime::now().duration_since(time::UNIX_EPOCH) {
+            Ok(t) => cmp::min(t.as_secs(), u32::MAX as u64) as u32,
+            Err(_) => 0,
+        };
+        PeakInfo {
+            version: 1,
+            timestamp: timestamp,
+            peaks: self.peaks,
+        }
+    }
+}
+
+/// Formats a time in UTC as `yyyy:mm:dd:hh:mm:ss:uuu`, as used by `levl` chunks.
+fn format_timestamp(secs: u64, millis: u32) -> String {
+    // Convert days since 1970-01-01 into a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = secs / 86400 + 719468;
+    let era = z / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let t = secs % 86400;
+    format!("{:04}:{:02}:{:02}:{:02}:{:02}:{:02}:{:03}",
+            year, month, day, t / 3600, t / 60 % 60, t % 60, millis)
+}
+
+/// Writes a complete chunk, including header and pad byte.
+fn write_raw_chunk<W: io::Write>(writer: &mut W, chunk: &RawChunk) -> io::Result<()> {
+    try!(writer.write_all(&chunk.id));
+    try!(writer.write_le_u32(chunk.data.len() as u32));
+    try!(writer.write_all(&chunk.data));
+    if chunk.data.len() % 2 == 1 {
+        try!(writer.write_u8(0));
+    }
+    Ok(())
+}
+
+/// The id of a chunk, and the list type for `LIST` chunks.
+type ChunkKey = ([u8; 4], Option<[u8; 4]>);
+
+/// Returns the key of the chunk.
+///
+/// At most one metadata chunk with a given key is written by `WavWriter`.
+fn chunk_key(chunk: &RawChunk) -> ChunkKey {
+    if &chunk.id == b"LIST" {
+        (chunk.id, metadata::list_type(&chunk.data))
+    } else {
+        (chunk.id, None)
+    }
 }
 
 impl<W: io::Write + io::Seek> ChunksWriter<W> {
@@ -259,17 +603,304 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
             dirty: false,
             data_state: None,
             sample_writer_buffer: vec!(),
+            trailing_chunks: Vec::new(),
+            layout_fixed: false,
+            written_chunks: Vec::new(),
+            cue_points: Vec::new(),
+            playlist: Vec::new(),
+            data_alignment: None,
+            filler_len: 0,
+            data_start: 0,
+            peak_envelope: None,
+            md5: None,
+            peak_info: None,
+            overflow_mode: OverflowMode::Error,
+            float_overflow_mode: OverflowMode::Saturate,
+            channel_mask: None,
+            fixed_len: None,
+            finalize_mode: FinalizeMode::Strict,
+            finalized: false,
         })
     }
 
+    /// Creates a ChunksWriter that continues an existing data chunk.
+    ///
+    /// The writer must be positioned at the end of the data chunk, which must
+    /// be the last chunk in the file.
+    fn new_append(writer: W, spec_ex: WavSpecEx, data_len: u32, data_start: u32) -> ChunksWriter<W> {
+        ChunksWriter {
+            spec_ex: Some(spec_ex),
+            writer: writer,
+            sample_writer_buffer: Vec::new(),
+            dirty: true,
+            data_state: Some(ChunkWritingState { len: data_len }),
+            trailing_chunks: Vec::new(),
+            layout_fixed: true,
+            written_chunks: Vec::new(),
+            cue_points: Vec::new(),
+            playlist: Vec::new(),
+            data_alignment: None,
+            filler_len: 0,
+            data_start: data_start as u64,
+            peak_envelope: None,
+            md5: None,
+            peak_info: None,
+            overflow_mode: OverflowMode::Error,
+            float_overflow_mode: OverflowMode::Saturate,
+            channel_mask: None,
+            fixed_len: None,
+            finalize_mode: FinalizeMode::Strict,
+            finalized: false,
+        }
+    }
+
+    /// Returns whether chunks can still be inserted before the data chunk.
+    ///
+    /// This is the case until the first sample is written, except when
+    /// appending to an existing file.
+    fn can_insert_before_data(&self) -> bool {
+        !self.layout_fixed && match self.data_state {
+            Some(ref state) => state.len == 0,
+            None => false,
+        }
+    }
+
+    /// Adds a metadata chunk, to be written before or after the data chunk.
+    ///
+    /// If no samples have been written yet, the chunk is inserted before the
+    /// data chunk right away, otherwise it is written after the data chunk
+    /// when the writer is finalized. If `replace` is true, a chunk with the
+    /// same key that was added before is replaced. If that chunk was already
+    /// written to the output, it is turned into a `JUNK` chunk, so readers
+    /// ignore it.
+    fn add_metadata_chunk(&mut self, chunk: RawChunk, replace: bool) -> Result<()> {
+        if self.fixed_len.is_some() {
+            return Err(Error::FormatError(format!("{:?} chunk can not be added when the length \
+                                                   is fixed up front",
+                                                  String::from_utf8_lossy(&chunk.id))));
+        }
+        if chunk.data.len() > u32::MAX as usize - 1 {
+            let msg = format!("{:?} chunk too large: {} bytes",
+                              String::from_utf8_lossy(&chunk.id), chunk.data.len());
+            return Err(Error::FormatError(msg));
+        }
+        let key = chunk_key(&chunk);
+        let before_data = self.can_insert_before_data();
+        if replace {
+            self.trailing_chunks.retain(|c| chunk_key(c) != key);
+            try!(self.junk_written_chunks(key));
+        }
+        if before_data {
+            try!(self.write_chunk_before_data(chunk));
+        } else {
+            self.trailing_chunks.push(chunk);
+        }
+        Ok(())
+    }
+
+    /// Renames metadata chunks with the given key that precede the data chunk to `JUNK`.
+    fn junk_written_chunks(&mut self, key: ChunkKey) -> Result<()> {
+        if !self.written_chunks.iter().any(|entry| chunk_key(&entry.1) == key) {
+            return Ok(())
+        }
+        let end = try!(self.writer.stream_position());
+        for &mut (offset, ref mut chunk) in &mut self.written_chunks {
+            if chunk_key(chunk) == key {
+                try!(self.writer.seek(io::SeekFrom::Start(offset)));
+                try!(self.writer.write_all(b"JUNK"));
+                chunk.id = *b"JUNK";
+            }
+        }
+        try!(self.writer.seek(io::SeekFrom::Start(end)));
+        Ok(())
+    }
+
+    /// Writes a metadata chunk in front of the data chunk, which must be empty.
+    fn write_chunk_before_data(&mut self, chunk: RawChunk) -> Result<()> {
+        // The writer is positioned right after the header of the empty data
+        // chunk. Overwrite the header and the filler, and write them again
+        // after the new chunk.
+        let offset = try!(self.writer.seek(io::SeekFrom::Current(-8 - self.filler_len as i64)));
+        try!(write_raw_chunk(&mut self.writer, &chunk));
+        try!(self.write_empty_data_chunk_header());
+        self.written_chunks.push((offset, chunk));
+        Ok(())
+    }
+
+    /// Writes the header of an empty data chunk, preceded by filler if needed.
+    ///
+    /// The filler is a `JUNK` chunk sized such that the first sample starts at
+    /// a multiple of `data_alignment`.
+    fn write_empty_data_chunk_header(&mut self) -> Result<()> {
+        let mut filler_len = 0;
+        if let Some(alignment) = self.data_alignment {
+            let alignment = alignment as u64;
+            let data_start = try!(self.writer.stream_position()) + 8;
+            filler_len = (alignment - data_start % alignment) % alignment;
+            // The filler chunk needs room for its own header.
+            while filler_len > 0 && filler_len < 8 {
+                filler_len += alignment;
+            }
+        }
+        if filler_len > 0 {
+            try!(self.writer.write_all(b"JUNK"));
+            try!(self.writer.write_le_u32(filler_len as u32 - 8));
+            try!(self.writer.write_all(&vec![0; filler_len as usize - 8]));
+        }
+        try!(self.writer.write_all(b"data"));
+        try!(self.writer.write_le_u32(0));
+        self.filler_len = filler_len;
+        self.data_start = try!(self.writer.stream_position());
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Returns the bytes that precede the samples, as they are after a flush.
+    ///
+    /// See `WavWriter::header_bytes()`.
+    fn header_bytes(&self) -> Vec<u8> {
+        let spec_ex = self.spec_ex.expect("Format should have written before this call");
+        if let Some(data_len) = self.fixed_len {
+            // The header was written with the final sizes, and it cannot change.
+            return ChunksWriter::<W>::encode_header(spec_ex, data_len)
+                .expect("Rebuilding the header should not fail");
+        }
+        assert!(!self.layout_fixed, "the header is not known when appending");
+        let data_len = self.data_state.expect("Can only be called positioned in data chunk").len;
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            // Writing into a vector cannot fail, and the format was valid
+            // when it was written to the actual output.
+            let msg = "Rebuilding the header should not fail";
+            let mut header = ChunksWriter::new(&mut buffer).expect(msg);
+            header.channel_mask = self.channel_mask;
+            header.write_fmt(spec_ex).expect(msg);
+            header.start_data_chunk().expect(msg);
+            if let Some(alignment) = self.data_alignment {
+                header.set_data_alignment(alignment).expect(msg);
+            }
+            for entry in &self.written_chunks {
+                header.write_chunk_before_data(entry.1.clone()).expect(msg);
+            }
+            header.flush().expect(msg);
+        }
+        let mut bytes = buffer.into_inner();
+        let n = bytes.len();
+        let riff_len = (n as u32 - 8).wrapping_add(data_len);
+        let msg = "Patching the header should not fail";
+        (&mut bytes[4..8]).write_le_u32(riff_len).expect(msg);
+        (&mut bytes[n - 4..]).write_le_u32(data_len).expect(msg);
+        bytes
+    }
+
+    /// Aligns the start of the audio data to a multiple of `alignment` bytes.
+    ///
+    /// See `WavWriter::set_data_alignment()`.
+    fn set_data_alignment(&mut self, alignment: u32) -> Result<()> {
+        assert!(alignment > 0 && alignment & 1 == 0, "alignment must be a positive even number");
+        if !self.can_insert_before_data() {
+            panic!("data alignment must be set before writing samples");
+        }
+        self.data_alignment = Some(alignment);
+        try!(self.writer.seek(io::SeekFrom::Current(-8 - self.filler_len as i64)));
+        self.write_empty_data_chunk_header()
+    }
+
+    /// Rewrites the fmt chunk with the given channel mask.
+    ///
+    /// See `WavWriter::set_channel_mask()`.
+    fn set_channel_mask(&mut self, mask: u32) -> Result<()> {
+        if !self.can_insert_before_data() {
+            panic!("channel mask must be set before writing samples");
+        }
+        let spec_ex = self.spec_ex.expect("Format should have written before this call");
+        self.channel_mask = Some(mask);
+        // The fmt chunk follows the RIFF header. It can only grow, so
+        // everything after it is written again, at a new offset if needed.
+        try!(self.writer.seek(io::SeekFrom::Start(12)));
+        try!(self.write_fmt(spec_ex));
+        for entry in &mut self.written_chunks {
+            entry.0 = try!(self.writer.stream_position());
+            try!(write_raw_chunk(&mut self.writer, &entry.1));
+        }
+        self.write_empty_data_chunk_header()
+    }
+
+    /// Writes the trailing metadata chunks after the data chunk.
+    ///
+    /// This finishes the data chunk, no samples can be written afterwards.
+    fn write_trailing_chunks(&mut self) -> Result<()> {
+        if !self.cue_points.is_empty() {
+            let points = mem::take(&mut self.cue_points);
+            self.trailing_chunks.push(RawChunk {
+                id: *b"cue ",
+                data: metadata::serialize_cue(&points),
+            });
+            if let Some(adtl) = metadata::serialize_adtl(&points) {
+                self.trailing_chunks.push(RawChunk { id: *b"LIST", data: adtl });
+            }
+        }
+        if let Some(envelope) = self.peak_envelope.take() {
+            self.trailing_chunks.push(RawChunk {
+                id: *b"levl",
+                data: try!(metadata::serialize_levl(&envelope.into_envelope())),
+            });
+        }
+        if let Some(md5) = self.md5.take() {
+            self.trailing_chunks.push(RawChunk {
+                id: *b"MD5 ",
+                data: md5.finish().to_vec(),
+            });
+        }
+        if let Some(tracker) = self.peak_info.take() {
+            self.trailing_chunks.push(RawChunk {
+                id: *b"PEAK",
+                data: metadata::serialize_peak(&tracker.into_peak_info()),
+            });
+        }
+        if !self.playlist.is_empty() {
+            let segments = mem::take(&mut self.playlist);
+            self.trailing_chunks.push(RawChunk {
+                id: *b"plst",
+                data: metadata::serialize_plst(&segments),
+            });
+        }
+        if self.trailing_chunks.is_empty() {
+            return Ok(())
+        }
+        if self.fixed_len.is_some() {
+            // The sizes in the header are final, nothing can follow the data.
+            self.trailing_chunks.clear();
+            let msg = "chunks after the data chunk can not be written when the length is fixed up front";
+            return Err(Error::FormatError(msg.to_string()));
+        }
+        let mut result = Ok(());
+        if let Some(data_state) = self.data_state {
+            // Update the data chunk header while it is still the last chunk.
+            result = self.update_data_chunk_header();
+            if let Err(Error::IoError(_)) = result {
+                return result
+            }
+            if data_state.len % 2 == 1 {
+                try!(self.writer.write_u8(0));
+            }
+        }
+        self.data_state = None;
+        for chunk in mem::take(&mut self.trailing_chunks) {
+            try!(write_raw_chunk(&mut self.writer, &chunk));
+        }
+        self.dirty = true;
+        result
+    }
+
     /// Update the file length field in the RIFF header.
     ///
     /// The writer is then repositioned at end of file.
     fn update_riff_header(&mut self) -> io::Result<()> {
         let full_len = try!(self.writer.seek(io::SeekFrom::Current(0)));
-        try!(self.writer.seek(io::SeekFrom::Start(4)));
+        try!(self.writer.seek(io::SeekFrom::Start(RIFF_LEN_OFFSET)));
         try!(self.writer.write_le_u32(full_len as u32 - 8));
-        try!(self.writer.seek(io::SeekFrom::Current(full_len as i64 - 8)));
+        try!(self.writer.seek(io::SeekFrom::Start(full_len)));
         Ok(())
     }
 
@@ -281,23 +912,23 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
     ///
     /// This method panics if the writer is not currently writing the data
     /// chunk.
-    /// It will error with `Error::UnfinishedSample` if the latest sample is
+    /// It will error with `Error::UnfinishedFrame` if the last frame is
     /// not whole.
     fn update_data_chunk_header(&mut self) -> Result<()> {
         let data_state = self.data_state.expect("Should only be called in data chunk");
         let spec_ex = self.spec_ex.expect("Data chunk implies known format");
-        try!(self.writer.seek(io::SeekFrom::End(-(data_state.len as i64 + 4))));
+        // The size field is right in front of the first sample.
+        try!(self.writer.seek(io::SeekFrom::Start(self.data_start - 4)));
         try!(self.writer.write_le_u32(data_state.len));
         try!(self.writer.seek(io::SeekFrom::End(0)));
 
         // Signal error if the last sample was not finished, but do so after
         // everything has been written, so that no data is lost, even though
         // the file is now ill-formed.
-        if (data_state.len / spec_ex.bytes_per_sample as u32)
-            % spec_ex.spec.channels as u32 != 0 {
-            Err(Error::UnfinishedSample)
-        } else {
-            Ok(())
+        let channels = spec_ex.spec.channels as u32;
+        match (data_state.len / spec_ex.bytes_per_sample as u32) % channels {
+            0 => Ok(()),
+            partial => Err(Error::UnfinishedFrame { missing: (channels - partial) as u16 }),
         }
     }
 
@@ -323,15 +954,19 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
         if self.spec_ex.is_none() {
             panic!("Format must be written before data");
         }
-        try!(self.writer.write_all(b"data"));
-        try!(self.writer.write_le_u32(0));
+        try!(self.writer.write_all(b"data\0\0\0\0"));
         self.data_state = Some(ChunkWritingState { len: 0 });
+        self.data_start = try!(self.writer.stream_position());
         self.dirty = true;
         Ok(())
     }
 
     /// Update RIFF and data chunk header
     pub fn update_headers(&mut self) -> Result<()> {
+        if self.fixed_len.is_some() {
+            // The headers were written with the final sizes.
+            return Ok(())
+        }
         if self.data_state.is_some() {
             try!(self.update_data_chunk_header())
         }
@@ -358,62 +993,124 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
     /// is not called, the destructor will finalize the file, but any errors
     /// that occur in the process cannot be observed in that manner.
     pub fn finalize(mut self) -> Result<()> {
+        let result = self.finish();
+        self.finalized = true;
+        result
+    }
+
+    /// Finalizes the file like `finalize()`, without consuming the writer.
+    fn finish(&mut self) -> Result<()> {
+        try!(self.pad_frame());
+        let trailing = self.write_trailing_chunks();
         // We need to perform a flush here to truly capture all errors before
         // the writer is dropped: for a buffered writer, the write to the buffer
         // may succeed, but the write to the underlying writer may fail. So
         // flush explicitly.
         try!(self.flush());
+        try!(self.check_fixed_length());
+        trailing
+    }
+
+    /// Completes the last frame with silence, if the finalize mode says so.
+    fn pad_frame(&mut self) -> Result<()> {
+        if self.finalize_mode != FinalizeMode::PadFrame {
+            return Ok(())
+        }
+        self.complete_frame()
+    }
+
+    /// Completes the last frame with silence.
+    fn complete_frame(&mut self) -> Result<()> {
+        let (spec_ex, data_state) = match (self.spec_ex, self.data_state) {
+            (Some(spec_ex), Some(data_state)) => (spec_ex, data_state),
+            _ => return Ok(()),
+        };
+        let channels = spec_ex.spec.channels as u32;
+        let partial = (data_state.len / spec_ex.bytes_per_sample as u32) % channels;
+        if partial == 0 {
+            return Ok(())
+        }
+        for _ in partial..channels {
+            // Zero is silence for both formats; for 8 bits it is stored as 128.
+            match spec_ex.spec.sample_format {
+                SampleFormat::Float => try!(self.write_sample(0.0_f32)),
+                SampleFormat::Int => try!(self.write_sample(0_i32)),
+            }
+        }
         Ok(())
     }
 
+    /// Returns an error if the data written differs from the length fixed up front.
+    fn check_fixed_length(&self) -> Result<()> {
+        let spec_ex = self.spec_ex.expect("Format should have written before this call");
+        match (self.fixed_len, self.data_state) {
+            (Some(expected), Some(data_state)) if data_state.len != expected => {
+                let bytes_per_sample = spec_ex.bytes_per_sample as u32;
+                let msg = format!("{} samples were written, but the length was fixed at {} samples",
+                                  data_state.len / bytes_per_sample, expected / bytes_per_sample);
+                Err(Error::FormatError(msg))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Encode and write the provided spec as a format header in the stream.
     pub fn write_fmt(&mut self, spec_ex: WavSpecEx) -> Result<()> {
-        let spec = spec_ex.spec;
+        let chunk = try!(Self::encode_fmt_chunk(spec_ex, self.channel_mask));
+        try!(self.writer.write_all(&chunk));
+        self.spec_ex = Some(spec_ex);
+        Ok(())
+    }
 
-        // Write the older PCMWAVEFORMAT structure if possible, because it is
-        // more widely supported. For more than two channels or more than 16
-        // bits per sample, the newer WAVEFORMATEXTENSIBLE is required. See also
-        // https://msdn.microsoft.com/en-us/library/ms713497.aspx.
-        let fmt_kind = if spec.channels > 2 || spec.bits_per_sample > 16 {
-            FmtKind::WaveFormatExtensible
-        } else {
-            FmtKind::PcmWaveFormat
+    /// Returns the fmt chunk for the provided spec, including the chunk header.
+    ///
+    /// A custom channel mask can only be stored in WAVEFORMATEXTENSIBLE.
+    fn encode_fmt_chunk(spec_ex: WavSpecEx, channel_mask: Option<u32>) -> Result<Vec<u8>> {
+        let fmt_kind = match channel_mask {
+            Some(..) => FmtKind::WaveFormatExtensible,
+            None => FmtKind::for_spec_ex(&spec_ex),
         };
 
-        // Hound can only write those bit depths. If something else was
+        // Hound can only write some bit depths. If something else was
         // requested, fail early, rather than writing a header but then failing
         // at the first sample.
-        let supported = match spec.bits_per_sample {
-            8 => true,
-            16 => true,
-            24 => true,
-            32 => true,
-            _ => false,
-        };
-
-        if !supported {
-            return Err(Error::Unsupported)
-        }
+        try!(spec_ex.spec.validate());
+        try!(check_header_fields(spec_ex));
 
-        let mut header = [0u8; 48];
-        try!(self.writer.write(b"fmt "));
+        let mut chunk = [0u8; 52];
+        chunk[..4].copy_from_slice(b"fmt ");
         let written = {
-            let mut buffer = io::Cursor::new(&mut header[..]);
+            let mut buffer = io::Cursor::new(&mut chunk[4..]);
             match fmt_kind {
                 FmtKind::PcmWaveFormat => {
                     try!(Self::write_pcmwaveformat(spec_ex, &mut buffer));
                 }
                 FmtKind::WaveFormatExtensible => {
-                    try!(Self::write_waveformatextensible(spec_ex, &mut buffer));
+                    let mask = channel_mask.unwrap_or_else(|| default_channel_mask(spec_ex.spec.channels));
+                    try!(Self::write_waveformatextensible(spec_ex, mask, &mut buffer));
                 }
             }
-            buffer.position()
+            buffer.position() as usize
         };
-        try!(self.writer.write_all(&header[..written as usize]));
-
-        self.spec_ex = Some(spec_ex);
+        Ok(chunk[..4 + written].to_vec())
+    }
 
-        Ok(())
+    /// Returns everything that precedes the samples of a new file.
+    ///
+    /// That is the RIFF header, the fmt chunk, and the header of a data chunk
+    /// of `data_len` bytes. This defines the layout of the header, the sizes
+    /// that are updated later are the RIFF size at `RIFF_LEN_OFFSET`, and the
+    /// data chunk size in the last four bytes.
+    fn encode_header(spec_ex: WavSpecEx, data_len: u32) -> Result<Vec<u8>> {
+        let fmt = try!(Self::encode_fmt_chunk(spec_ex, None));
+        let mut header = Vec::with_capacity(12 + fmt.len() + 8);
+        header.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        header.extend_from_slice(&fmt);
+        header.extend_from_slice(b"data");
+        try!(header.write_le_u32(data_len));
+        let riff_len = (header.len() as u32 - 8).wrapping_add(data_len);
+        try!((&mut header[RIFF_LEN_OFFSET as usize..]).write_le_u32(riff_len));
+        Ok(header)
     }
 
     /// Writes the content of the fmt chunk as PCMWAVEFORMAT struct.
@@ -456,7 +1153,10 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
     }
 
     /// Writes the contents of the fmt chunk as WAVEFORMATEXTENSIBLE struct.
-    fn write_waveformatextensible(spec:WavSpecEx, buffer: &mut io::Cursor<&mut [u8]>) -> io::Result<()> {
+    fn write_waveformatextensible(spec: WavSpecEx,
+                                  channel_mask: u32,
+                                  buffer: &mut io::Cursor<&mut [u8]>)
+                                  -> io::Result<()> {
         // Write the size of the WAVE header chunk.
         try!(buffer.write_le_u32(40));
 
@@ -478,9 +1178,7 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
         // The field wValidBitsPerSample, the real number of bits per sample.
         try!(buffer.write_le_u16(spec.spec.bits_per_sample));
         // The field dwChannelMask.
-        // TODO: add the option to specify the channel mask. For now, use
-        // the default assignment.
-        try!(buffer.write_le_u32(channel_mask(spec.spec.channels)));
+        try!(buffer.write_le_u32(channel_mask));
 
         // The field SubFormat.
         let subformat_guid = match spec.spec.sample_format {
@@ -512,15 +1210,12 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
 
         // The field nSamplesPerSec.
         try!(buffer.write_le_u32(spec.spec.sample_rate));
-        let bytes_per_sec = spec.spec.sample_rate
-                          * spec.bytes_per_sample as u32
-                          * spec.spec.channels as u32;
 
-        // The field nAvgBytesPerSec;
-        try!(buffer.write_le_u32(bytes_per_sec));
+        // The field nAvgBytesPerSec. Both fields fit, see `check_header_fields()`.
+        try!(buffer.write_le_u32(spec.byte_rate() as u32));
 
         // The field nBlockAlign. Block align * sample rate = bytes per sec.
-        try!(buffer.write_le_u16((bytes_per_sec / spec.spec.sample_rate) as u16));
+        try!(buffer.write_le_u16(spec.block_align() as u16));
 
         Ok(())
     }
@@ -533,11 +1228,26 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
     #[inline]
     pub fn write_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
         let spec_ex = self.spec_ex.expect("Format should have written before this call");
-        try!(sample.write_padded(
-            &mut self.writer,
-            spec_ex.spec.bits_per_sample,
-            spec_ex.bytes_per_sample
-        ));
+        let overflow_mode = self.overflow_mode;
+        if self.peak_envelope.is_none() && self.md5.is_none() && self.peak_info.is_none() {
+            try!(encode_sample(sample, &mut self.writer, spec_ex, overflow_mode));
+        } else {
+            // Encode the sample first, so the peak and the checksum can be
+            // taken from the bytes.
+            let mut buffer = io::Cursor::new([0u8; 4]);
+            try!(encode_sample(sample, &mut buffer, spec_ex, overflow_mode));
+            let bytes = &buffer.get_ref()[..buffer.position() as usize];
+            try!(self.writer.write_all(bytes));
+            if let Some(ref mut envelope) = self.peak_envelope {
+                envelope.feed(bytes);
+            }
+            if let Some(ref mut md5) = self.md5 {
+                md5.update(bytes);
+            }
+            if let Some(ref mut tracker) = self.peak_info {
+                tracker.feed(bytes);
+            }
+        }
         let written = spec_ex.bytes_per_sample as u32;
         self.data_state.as_mut().expect("Can only be called positioned in data chunk").len += written;
         Ok(())
@@ -589,12 +1299,22 @@ impl<W: io::Write + io::Seek> ChunksWriter<W> {
             data_bytes_written:
                 &mut self.data_state.as_mut().expect("Can only be called positioned in data chunk").len,
             index: 0,
+            peak_envelope: self.peak_envelope.as_mut(),
+            md5: self.md5.as_mut(),
+            peak_info: self.peak_info.as_mut(),
         }
     }
 }
 
 impl<W: io::Write + io::Seek> Drop for ChunksWriter<W> {
     fn drop(&mut self) {
+        if self.finalized {
+            return
+        }
+        // An incomplete frame cannot be reported from here. It is only
+        // completed if the mode says so, the samples are not made up.
+        let _ = self.pad_frame();
+        let _ = self.write_trailing_chunks();
         let _ = self.flush();
     }
 }
@@ -608,6 +1328,10 @@ impl<W: io::Write + io::Seek> Drop for ChunksWriter<W> {
 /// finalized. This can be done by calling `finalize`. If `finalize` is not
 /// called, the file will be finalized upon drop. However, finalization may
 /// fail, and without calling `finalize`, such a failure cannot be observed.
+/// For the same reason, when the last frame is incomplete, the destructor
+/// writes the samples as they are, while `finalize` returns
+/// `Error::UnfinishedFrame`. Only with `FinalizeMode::PadFrame` is the frame
+/// completed with silence.
 pub struct WavWriter<W>
     where W: io::Write + io::Seek
 {
@@ -627,6 +1351,13 @@ impl<W> WavWriter<W>
     ///
     /// This writes parts of the header immediately, hence a `Result` is
     /// returned.
+    ///
+    /// The `fmt ` chunk is kept as small as the spec allows, because some
+    /// decoders accept nothing else. For up to two channels of 8 or 16 bits
+    /// per sample, it is a 16-byte PCMWAVEFORMAT, without the `cbSize` field
+    /// of WAVEFORMATEX. Only for more channels, more bits per sample, or
+    /// padded samples is the 40-byte WAVEFORMATEXTENSIBLE written, because
+    /// PCMWAVEFORMAT cannot describe those.
     pub fn new(writer: W, spec: WavSpec) -> Result<WavWriter<W>> {
         let spec_ex = WavSpecEx {
             spec: spec,
@@ -645,9 +1376,93 @@ impl<W> WavWriter<W>
     /// This writes parts of the header immediately, hence a `Result` is
     /// returned.
     pub fn new_with_spec_ex(writer: W, spec: WavSpecEx) -> Result<WavWriter<W>> {
-        let mut chunks_writer = try!(ChunksWriter::new(writer));
-        try!(chunks_writer.write_fmt(spec));
-        try!(chunks_writer.start_data_chunk());
+        Ok(WavWriter { writer: try!(Self::start_file(writer, spec)) })
+    }
+
+    /// Writes the header for a new file, returns a writer positioned at the first sample.
+    fn start_file(writer: W, spec: WavSpecEx) -> Result<ChunksWriter<W>> {
+        let header = try!(ChunksWriter::<W>::encode_header(spec, 0));
+        let mut writer = writer;
+        try!(writer.write_all(&header));
+        let mut chunks_writer = ChunksWriter::new_append(writer, spec, 0, header.len() as u32);
+        // Unlike for an appended file, the layout is known, so chunks can
+        // still be inserted before the data chunk.
+        chunks_writer.layout_fixed = false;
+        Ok(chunks_writer)
+    }
+
+    /// Finalizes the current file, and starts a new one in `new_writer`.
+    ///
+    /// This is like calling `finalize()` and then `WavWriter::new()`, but
+    /// it reuses the writer and its internal buffers, which avoids
+    /// allocations when many files are written in a row. Everything else is
+    /// reset: settings such as the overflow mode, metadata, and checksums
+    /// apply to the old file only. The old underlying writer is dropped.
+    ///
+    /// An invalid `spec`, or an error while writing the header of the new
+    /// file, is reported before anything is finalized, and the writer is left
+    /// at the old file. If finalizing the old file fails, the error is
+    /// returned as by `finalize()`, but the writer has moved on to the new
+    /// file regardless, so it remains usable.
+    pub fn reset(&mut self, new_writer: W, spec: WavSpec) -> Result<()> {
+        try!(spec.validate());
+        // The spec is valid, so the bit depth is a multiple of 8.
+        let spec_ex = WavSpecEx {
+            spec: spec,
+            bytes_per_sample: spec.bits_per_sample / 8,
+        };
+        let mut writer = try!(Self::start_file(new_writer, spec_ex));
+        mem::swap(&mut writer.sample_writer_buffer, &mut self.writer.sample_writer_buffer);
+        let old_writer = mem::replace(&mut self.writer, writer);
+        // This marks the old writer as finalized, also when it fails, so
+        // dropping it does not write to the old file again.
+        old_writer.finalize()
+    }
+
+    /// Creates a writer for exactly `total_frames` samples per channel, which never seeks.
+    ///
+    /// The final sizes are written to the header right away, so the writer
+    /// only writes forward. That makes it possible to write to a sink that
+    /// cannot seek, such as a pipe, without buffering the file: wrap it in
+    /// `NoSeek`. When the writer is finalized, an error is returned if the
+    /// number of samples written differs from `total_frames` times the number
+    /// of channels. Metadata chunks cannot be added to such a writer, because
+    /// they would change the sizes.
+    ///
+    /// ```
+    /// use hound::{NoSeek, SampleFormat, WavSpec, WavWriter};
+    ///
+    /// let spec = WavSpec {
+    ///     channels: 1,
+    ///     sample_rate: 44100,
+    ///     bits_per_sample: 16,
+    ///     sample_format: SampleFormat::Int,
+    /// };
+    /// let mut writer = WavWriter::new_with_length(NoSeek::new(Vec::new()), spec, 3).unwrap();
+    /// for s in 0..3 {
+    ///     writer.write_sample(s as i16).unwrap();
+    /// }
+    /// writer.finalize().unwrap();
+    /// ```
+    pub fn new_with_length(writer: W, spec: WavSpec, total_frames: u32) -> Result<WavWriter<W>> {
+        // Bit depths that are not a multiple of 8 are rejected by `write_fmt()`.
+        let spec_ex = WavSpecEx {
+            spec: spec,
+            bytes_per_sample: spec.bits_per_sample / 8,
+        };
+        let data_len = total_frames as u64 * spec.channels as u64 * spec_ex.bytes_per_sample as u64;
+        // Leave room for the header in the RIFF size.
+        if data_len > u32::MAX as u64 - 128 {
+            return Err(Error::FormatError(format!("data chunk too large: {} bytes", data_len)));
+        }
+
+        // Write the header with the final sizes.
+        let header = try!(ChunksWriter::<W>::encode_header(spec_ex, data_len as u32));
+        let mut writer = writer;
+        try!(writer.write_all(&header));
+        let mut chunks_writer = ChunksWriter::new_append(writer, spec_ex, 0, header.len() as u32);
+        chunks_writer.fixed_len = Some(data_len as u32);
+        chunks_writer.dirty = false;
         Ok(WavWriter { writer: chunks_writer })
     }
 
@@ -656,11 +1471,97 @@ impl<W> WavWriter<W>
     /// WAVE interleaves channel data, so the channel that this writes the
     /// sample to depends on previous writes. This will return an error if the
     /// sample does not fit in the number of bits specified in the `WavSpec`.
+    ///
+    /// The sample type need not match the bit depth of the file: an integer
+    /// literal, which is an `i32` by default, can be written to a 16-bit file
+    /// without a cast. Such a sample is range checked, and `Error::TooWide` is
+    /// returned if it does not fit, rather than truncating it silently.
     #[inline]
     pub fn write_sample<S: Sample>(&mut self, sample: S) -> Result<()> {
         self.writer.write_sample(sample)
     }
 
+    /// Writes one frame of `N` samples, one for every channel.
+    ///
+    /// Returns `Error::InvalidSpec` without writing anything if `N` differs
+    /// from the number of channels. Otherwise this is the same as writing
+    /// the samples one by one with `write_sample()`.
+    pub fn write_frame_fixed<S: Sample + Copy, const N: usize>(&mut self, frame: &[S; N]) -> Result<()> {
+        try!(check_frame_size(N, self.spec().channels));
+        for &sample in frame {
+            try!(self.write_sample(sample));
+        }
+        Ok(())
+    }
+
+    /// Writes normalized floats, converted to the sample format of the spec.
+    ///
+    /// For a floating point spec the samples are written unchanged. For an
+    /// integer spec of `n` bits, a sample `x` is multiplied by 2^(n - 1) and
+    /// truncated toward zero; this is the inverse of the normalization that
+    /// the reader applies, so for 16 bits, -1.0 becomes -32768 and 0.5
+    /// becomes 16384. Values that do not fit, including 1.0 itself, are
+    /// clipped to the range of the bit depth, and NaN becomes 0. How clipping
+    /// is handled is set with `set_float_overflow_mode()`: by default values
+    /// are clamped. With `OverflowMode::Error`, `Error::TooWide` is returned
+    /// instead and none of the samples are written.
+    ///
+    /// The samples are interleaved like those passed to `write_sample()`.
+    pub fn write_samples_f32(&mut self, samples: &[f32]) -> Result<()> {
+        let spec = self.spec();
+        if spec.sample_format == SampleFormat::Float {
+            for &sample in samples {
+                try!(self.write_sample(sample));
+            }
+            return Ok(());
+        }
+        let mut converted = vec![0; samples.len()];
+        try!(convert::convert_f32_to_bits(samples, &mut converted, spec.bits_per_sample,
+                                          self.writer.float_overflow_mode));
+        for &sample in &converted {
+            try!(self.write_sample(sample));
+        }
+        Ok(())
+    }
+
+    /// Writes `frames` samples of silence for every channel.
+    ///
+    /// This is equivalent to writing a zero sample `frames` times the number
+    /// of channels, but it writes the bytes in large blocks. Note that silence
+    /// in 8-bit files, which are unsigned, is stored as 128.
+    pub fn write_silence(&mut self, frames: u32) -> Result<()> {
+        let spec_ex = self.writer.spec_ex.expect("Format should have written before this call");
+        let bytes = frames as u64
+                  * spec_ex.spec.channels as u64
+                  * spec_ex.bytes_per_sample as u64;
+        let len = self.writer.data_state.expect("Can only be called positioned in data chunk").len;
+        if len as u64 + bytes > u32::MAX as u64 {
+            let msg = format!("data chunk too large: {} bytes of silence do not fit after {} bytes",
+                              bytes, len);
+            return Err(Error::FormatError(msg));
+        }
+        let zero = if spec_ex.spec.bits_per_sample == 8 { 0x80 } else { 0 };
+        let block = vec![zero; cmp::min(bytes, 64 * 1024) as usize];
+        let mut remaining = bytes;
+        while remaining > 0 {
+            let n = cmp::min(remaining, block.len() as u64) as usize;
+            try!(self.writer.writer.write_all(&block[..n]));
+            if let Some(ref mut envelope) = self.writer.peak_envelope {
+                envelope.feed(&block[..n]);
+            }
+            if let Some(ref mut md5) = self.writer.md5 {
+                md5.update(&block[..n]);
+            }
+            if let Some(ref mut tracker) = self.writer.peak_info {
+                tracker.feed(&block[..n]);
+            }
+            remaining -= n as u64;
+        }
+        self.writer.data_state.as_mut().expect("Can only be called positioned in data chunk").len
+            += bytes as u32;
+        Ok(())
+    }
+
     /// Create an efficient writer that writes 16-bit integer samples only.
     ///
     /// When it is known what the kind of samples will be, many dynamic checks
@@ -695,7 +1596,7 @@ impl<W> WavWriter<W>
     /// Note that if the number of samples written is not a multiple of the
     /// channel count, the intermediate wav file will not be valid. In that case
     /// `flush()` will still flush the data and write the (invalid) wav file,
-    /// but `Error::UnfinishedSample` will be returned afterwards.
+    /// but `Error::UnfinishedFrame` will be returned afterwards.
     ///
     /// It is not necessary to call `finalize()` directly after `flush()`, if no
     /// samples have been written after flushing.
@@ -708,6 +1609,13 @@ impl<W> WavWriter<W>
     /// This method must be called after all samples have been written. If it
     /// is not called, the destructor will finalize the file, but any errors
     /// that occur in the process cannot be observed in that manner.
+    ///
+    /// If the last frame is incomplete, the samples are still written, but
+    /// `Error::UnfinishedFrame` is returned, with the number of samples that
+    /// are missing, because the file is ill-formed. The destructor cannot
+    /// report this, so a file that is not finalized is silently left
+    /// ill-formed. Use `missing_samples()` before finalizing to pad the frame
+    /// yourself, or let the writer pad it with `set_finalize_mode()`.
     pub fn finalize(self) -> Result<()> {
         // We need to perform a flush here to truly capture all errors before
         // the writer is dropped: for a buffered writer, the write to the buffer
@@ -716,24 +1624,429 @@ impl<W> WavWriter<W>
         self.writer.finalize()
     }
 
-    /// Returns information about the WAVE file being written.
+    /// Returns the length of the header, the bytes that precede the samples.
     ///
-    /// This is the same spec that was passed to `WavWriter::new()`. For a
-    /// writer constructed with `WavWriter::new_append()` or
-    /// `WavWriter::append()`, this method returns the spec of the file being
-    /// appended to.
-    pub fn spec(&self) -> WavSpec {
-        let spec_ex = self.writer.spec_ex.expect("ChunkWriter with no spec");
-        spec_ex.spec
+    /// The samples start at this offset in the output.
+    pub fn header_len(&self) -> u64 {
+        self.writer.data_start
     }
 
-    /// Returns the duration of the file written so far, in samples.
+    /// Returns the header, the bytes that precede the samples.
     ///
-    /// The duration is independent of the number of channels. It is expressed
-    /// in units of samples. The duration in seconds can be obtained by
-    /// dividing this number by the sample rate.
-    pub fn duration(&self) -> u32 {
-        let spec_ex = self.writer.spec_ex.expect("ChunkWriter with no spec");
+    /// The header is returned as it is in the output after `flush()`: the
+    /// sizes in it account for the samples written so far. When the header is
+    /// obtained right after construction, the sizes are those of an empty
+    /// file. This can be used to send the header to a client before the
+    /// samples are available, for progressive delivery, and to send an
+    /// updated header later. The header changes when chunks are inserted
+    /// before the data chunk, and when samples are written. For a writer
+    /// created with `new_with_length()`, the header has the final sizes from
+    /// the start, and it never changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the writer appends to an existing file, because then the
+    /// header was not written by this writer.
+    pub fn header_bytes(&self) -> Vec<u8> {
+        self.writer.header_bytes()
+    }
+
+    /// Inserts filler before the data chunk to align the first sample.
+    ///
+    /// The first sample will start at a multiple of `alignment` bytes from the
+    /// start of the file, for example 4096 to match the sector size of a disk.
+    /// This avoids unaligned writes when recording to disk in real time. The
+    /// filler is a `JUNK` chunk, which readers ignore. Metadata chunks that are
+    /// inserted before the data chunk afterwards keep the alignment. By
+    /// default no filler is inserted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` is odd or zero, or if samples have been written
+    /// already, or if the writer appends to an existing file.
+    pub fn set_data_alignment(&mut self, alignment: u32) -> Result<()> {
+        self.writer.set_data_alignment(alignment)
+    }
+
+    /// Sets the `dwChannelMask` field, which assigns channels to speaker positions.
+    ///
+    /// By default, the first channels are assigned to the first positions of
+    /// `SpeakerPosition`, and a 16-byte PCMWAVEFORMAT fmt chunk without a
+    /// mask is written where possible. Setting a mask makes the writer use
+    /// WAVEFORMATEXTENSIBLE, so the mask can be stored. Every bit that is set
+    /// assigns the next channel, see `SpeakerPosition::mask_bit()`. The mask
+    /// is not checked against the number of channels, speakers that have no
+    /// channel and channels that have no speaker are both allowed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if samples have been written already, or if the writer appends
+    /// to an existing file.
+    pub fn set_channel_mask(&mut self, mask: u32) -> Result<()> {
+        self.writer.set_channel_mask(mask)
+    }
+
+    /// Sets how samples that do not fit the bit depth are handled.
+    ///
+    /// By default, `write_sample` returns `Error::TooWide` for such a sample,
+    /// so no sample is clipped silently. With `OverflowMode::Saturate`, the
+    /// sample is clamped to the range of the bit depth instead, for example
+    /// an `i32` written to a 24-bit file is clamped to
+    /// `[-0x800000, 0x7fffff]`. Floating point samples are never clamped.
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.writer.overflow_mode = mode;
+    }
+
+    /// Sets how the writer finalizes a file that ends in an incomplete frame.
+    ///
+    /// By default, `finalize()` returns `Error::UnfinishedFrame` when the
+    /// number of samples written is not a multiple of the number of channels.
+    /// With `FinalizeMode::PadFrame`, the last frame is completed with
+    /// silence instead, both by `finalize()` and by the destructor. This is
+    /// useful for a recorder that may stop in the middle of a frame.
+    pub fn set_finalize_mode(&mut self, mode: FinalizeMode) {
+        self.writer.finalize_mode = mode;
+    }
+
+    /// Sets how `write_samples_f32()` handles floats outside of the sample range.
+    ///
+    /// Unlike `set_overflow_mode()`, this defaults to `OverflowMode::Saturate`,
+    /// because floats slightly beyond full scale are common in audio
+    /// processing, and 1.0 itself does not fit an integer sample. It does not
+    /// affect other methods.
+    pub fn set_float_overflow_mode(&mut self, mode: OverflowMode) {
+        self.writer.float_overflow_mode = mode;
+    }
+
+    /// Generates a `levl` chunk with the peak envelope of the samples.
+    ///
+    /// The writer records the positive and negative peak of every channel for
+    /// every `block_size` frames, 256 is a common choice. This works for all
+    /// ways of writing samples, including `get_i16_writer()`. The chunk is
+    /// written after the data chunk when the writer is finalized, as a 16-bit
+    /// envelope in which full scale is 32768.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero, if samples have been written already,
+    /// or if the writer appends to an existing file.
+    pub fn enable_peak_envelope(&mut self, block_size: u32) {
+        assert!(block_size > 0, "block size must be positive");
+        if !self.writer.can_insert_before_data() {
+            panic!("peak envelope must be enabled before writing samples");
+        }
+        let spec_ex = self.writer.spec_ex.expect("ChunkWriter with no spec");
+        self.writer.peak_envelope = Some(PeakAccumulator::new(spec_ex, block_size));
+    }
+
+    /// Generates an `MD5 ` chunk with a checksum of the audio data.
+    ///
+    /// The writer hashes the bytes of the data chunk as they are written, and
+    /// writes the digest after the data chunk when the writer is finalized.
+    /// This works for all ways of writing samples, including
+    /// `get_i16_writer()`. The bytes that are written do not change. Readers
+    /// can check the sample data with `WavReader::verify_md5()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if samples have been written already, or if the writer appends
+    /// to an existing file.
+    pub fn enable_md5(&mut self) {
+        if !self.writer.can_insert_before_data() {
+            panic!("MD5 checksum must be enabled before writing samples");
+        }
+        self.writer.md5 = Some(Md5::new());
+    }
+
+    /// Generates a `PEAK` chunk with the peak amplitude of every channel.
+    ///
+    /// The writer records the largest absolute value of every channel, where
+    /// 1.0 is full scale, and the frame at which it first occurs. This works
+    /// for all ways of writing samples, including `get_i16_writer()`. The
+    /// chunk is written after the data chunk when the writer is finalized;
+    /// readers find it with `WavReader::peak_info()` after calling
+    /// `read_trailing_chunks()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if samples have been written already, or if the writer appends
+    /// to an existing file.
+    pub fn enable_peak_info(&mut self) {
+        if !self.writer.can_insert_before_data() {
+            panic!("peak info must be enabled before writing samples");
+        }
+        let spec_ex = self.writer.spec_ex.expect("ChunkWriter with no spec");
+        self.writer.peak_info = Some(PeakTracker::new(spec_ex));
+    }
+
+    /// Sets the `LIST` chunk of type `INFO`, with textual metadata.
+    ///
+    /// When this is called before any samples have been written, the chunk is
+    /// placed before the data chunk, which is where most readers expect it.
+    /// Otherwise the chunk is written after the data chunk, when the writer is
+    /// finalized. When appending to an existing file, the chunk is always
+    /// written after the data chunk. Calling this again replaces the
+    /// previous value; a copy that was already written is turned into a
+    /// `JUNK` chunk.
+    pub fn set_info(&mut self, info: &ListInfo) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"LIST",
+            data: metadata::serialize_list_info(info),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `bext` chunk, with metadata for the Broadcast Wave Format.
+    ///
+    /// The chunk is written before the data chunk. Text fields that do not
+    /// fit in their fixed size in the chunk are rejected with an error, they
+    /// are not truncated. The text should be ASCII. Unused bytes are set to
+    /// zero. Loudness values can only be written with version 2 or later, and
+    /// the UMID must be empty or 64 bytes. Calling this again replaces the
+    /// previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if samples have been written already, or if the writer appends
+    /// to an existing file.
+    pub fn set_broadcast_extension(&mut self, bext: &BroadcastExtension) -> Result<()> {
+        if !self.writer.can_insert_before_data() {
+            panic!("bext chunk must be set before writing samples");
+        }
+        let chunk = RawChunk {
+            id: *b"bext",
+            data: try!(metadata::serialize_bext(bext)),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `cart` chunk, with metadata for radio automation systems.
+    ///
+    /// Text fields that do not fit in their fixed size in the chunk are
+    /// rejected with an error, like for `set_broadcast_extension()`, and so is
+    /// a list of more than 8 timers. The chunk is placed like the chunk
+    /// written by `set_info()`, and calling this again replaces the previous
+    /// value.
+    pub fn set_cart(&mut self, cart: &Cart) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"cart",
+            data: try!(metadata::serialize_cart(cart)),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `DISP` chunk, with the title that Windows displays for the file.
+    ///
+    /// The chunk is placed like the chunk written by `set_info()`, and calling
+    /// this again replaces the previous value.
+    pub fn set_display_title(&mut self, title: &str) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"DISP",
+            data: metadata::serialize_disp(title),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `iXML` chunk, an XML document with production metadata.
+    ///
+    /// The document is written as is. The chunk is placed like the chunk
+    /// written by `set_info()`, and calling this again replaces the previous
+    /// value.
+    pub fn set_ixml(&mut self, ixml: &[u8]) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"iXML",
+            data: ixml.to_vec(),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `chna` chunk, which maps tracks to ADM metadata.
+    ///
+    /// Together with `set_axml()`, this writes the metadata of object-based
+    /// audio in the ADM format of ITU-R BS.2076. The chunk is placed like the
+    /// chunk written by `set_info()`, and calling this again replaces the
+    /// previous value. Returns an error if an id does not fit, or if a track
+    /// index is not between 1 and the number of channels.
+    pub fn set_adm_tracks(&mut self, tracks: &[AdmTrack]) -> Result<()> {
+        let channels = self.spec().channels;
+        if let Some(track) = tracks.iter().find(|t| t.track_index > channels) {
+            let msg = format!("chna track index {} exceeds number of channels {}",
+                              track.track_index, channels);
+            return Err(Error::FormatError(msg));
+        }
+        let chunk = RawChunk {
+            id: *b"chna",
+            data: try!(metadata::serialize_chna(tracks)),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `axml` chunk, an XML document with ADM metadata.
+    ///
+    /// The document is written as is. The chunk is placed like the chunk
+    /// written by `set_info()`, and calling this again replaces the previous
+    /// value.
+    pub fn set_axml(&mut self, axml: &[u8]) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"axml",
+            data: axml.to_vec(),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `id3 ` chunk, which holds an ID3v2 tag.
+    ///
+    /// The tag is written as is. The chunk is placed like the chunk written by
+    /// `set_info()`, and calling this again replaces the previous value.
+    pub fn set_id3(&mut self, id3: &[u8]) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"id3 ",
+            data: id3.to_vec(),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Adds a chunk that is written as is, for example one copied from a `WavReader`.
+    ///
+    /// A pad byte is added after a payload of odd length, and the chunk is
+    /// counted in the RIFF size. The chunk is placed like the chunk written by
+    /// `set_info()`. Unlike the typed setters, this does not replace chunks
+    /// with the same id. An error is returned for the `fmt `, `fact` and
+    /// `data` chunks, which are the responsibility of the writer itself.
+    pub fn add_chunk(&mut self, chunk: RawChunk) -> Result<()> {
+        try!(check_raw_chunk(&chunk));
+        self.writer.add_metadata_chunk(chunk, false)
+    }
+
+    /// Writes a chunk with application-specific data under a chunk id of its own.
+    ///
+    /// This is `add_chunk()` for a payload that is not already a `RawChunk`.
+    /// Before the first sample is written, the chunk is placed before the data
+    /// chunk, afterwards it is written after the data chunk when the writer is
+    /// finalized. An error is returned for the `fmt `, `fact` and `data` ids.
+    pub fn write_chunk(&mut self, id: [u8; 4], payload: &[u8]) -> Result<()> {
+        self.add_chunk(RawChunk { id: id, data: payload.to_vec() })
+    }
+
+    /// Adds a chunk that is written as is after the data chunk.
+    ///
+    /// The chunk is written when the writer is finalized, after the chunks
+    /// added with `add_chunk()` once samples were written.
+    pub fn add_chunk_after_data(&mut self, chunk: RawChunk) -> Result<()> {
+        try!(check_raw_chunk(&chunk));
+        if chunk.data.len() > u32::MAX as usize - 1 {
+            let msg = format!("{:?} chunk too large: {} bytes",
+                              String::from_utf8_lossy(&chunk.id), chunk.data.len());
+            return Err(Error::FormatError(msg));
+        }
+        self.writer.trailing_chunks.push(chunk);
+        Ok(())
+    }
+
+    /// Sets the `acid` chunk, with tempo information for loops.
+    ///
+    /// The chunk is placed like the chunk written by `set_info()`, and calling
+    /// this again replaces the previous value.
+    pub fn set_acid_info(&mut self, acid: &AcidInfo) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"acid",
+            data: metadata::serialize_acid(acid),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `smpl` chunk, with loop points and tuning for samplers.
+    ///
+    /// The chunk is placed like the chunk written by `set_info()`. Loops must
+    /// lie within the samples written so far, so usually this is called after
+    /// writing the samples, and the chunk ends up after the data chunk. An
+    /// error is returned for a loop that ends before it starts, or that
+    /// extends past the last sample. Calling this again replaces the previous
+    /// value.
+    pub fn set_sampler_info(&mut self, info: &SamplerInfo) -> Result<()> {
+        let duration = self.duration();
+        for l in &info.loops {
+            if l.start > l.end || l.end >= duration {
+                let msg = format!("loop from {} to {} is outside of the data of {} samples",
+                                  l.start, l.end, duration);
+                return Err(Error::FormatError(msg));
+            }
+        }
+        let chunk = RawChunk {
+            id: *b"smpl",
+            data: metadata::serialize_smpl(info),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Sets the `inst` chunk, with the pitch and key range for samplers.
+    ///
+    /// An error is returned if a value is outside of the range documented on
+    /// `InstrumentInfo`, or if a range has its low end above its high end.
+    pub fn set_instrument_info(&mut self, inst: &InstrumentInfo) -> Result<()> {
+        let chunk = RawChunk {
+            id: *b"inst",
+            data: try!(metadata::serialize_inst(inst)),
+        };
+        self.writer.add_metadata_chunk(chunk, true)
+    }
+
+    /// Adds a cue point, a marker that is shown by audio editors.
+    ///
+    /// The cue points are written to a `cue ` chunk after the data chunk when
+    /// the writer is finalized, together with a `LIST` chunk of type `adtl`
+    /// that holds their labels and notes, if any. The id of the cue point must
+    /// be unique, and its position must not exceed the duration written so
+    /// far, otherwise an error is returned and the cue point is not added.
+    pub fn add_cue_point(&mut self, point: CuePoint) -> Result<()> {
+        if self.writer.cue_points.iter().any(|p| p.id == point.id) {
+            return Err(Error::FormatError(format!("duplicate cue point id {}", point.id)));
+        }
+        if point.position > self.duration() {
+            let msg = format!("cue point at {} is beyond the end of the data at {}",
+                              point.position, self.duration());
+            return Err(Error::FormatError(msg));
+        }
+        self.writer.cue_points.push(point);
+        Ok(())
+    }
+
+    /// Sets the `plst` chunk, which defines a play order of segments.
+    ///
+    /// Every segment starts at a cue point, so add the cue points first: an
+    /// error is returned if a segment refers to a cue point id that was not
+    /// added with `add_cue_point()`. The chunk is written after the cue chunk
+    /// when the writer is finalized. Calling this again replaces the previous
+    /// playlist.
+    pub fn set_playlist(&mut self, segments: &[PlaylistSegment]) -> Result<()> {
+        for segment in segments {
+            if !self.writer.cue_points.iter().any(|p| p.id == segment.cue_id) {
+                let msg = format!("playlist refers to missing cue point {}", segment.cue_id);
+                return Err(Error::FormatError(msg));
+            }
+        }
+        self.writer.playlist = segments.to_vec();
+        Ok(())
+    }
+
+    /// Returns information about the WAVE file being written.
+    ///
+    /// This is the same spec that was passed to `WavWriter::new()`. For a
+    /// writer constructed with `WavWriter::new_append()` or
+    /// `WavWriter::append()`, this method returns the spec of the file being
+    /// appended to.
+    pub fn spec(&self) -> WavSpec {
+        let spec_ex = self.writer.spec_ex.expect("ChunkWriter with no spec");
+        spec_ex.spec
+    }
+
+    /// Returns the duration of the file written so far, in samples.
+    ///
+    /// The duration is independent of the number of channels. It is expressed
+    /// in units of samples. The duration in seconds can be obtained by
+    /// dividing this number by the sample rate.
+    pub fn duration(&self) -> u32 {
+        let spec_ex = self.writer.spec_ex.expect("ChunkWriter with no spec");
         let writer_state = self.writer.data_state.expect("ChunkWriter in weird state");
         writer_state.len / (spec_ex.bytes_per_sample as u32 * self.spec().channels as u32)
     }
@@ -747,6 +2060,81 @@ impl<W> WavWriter<W>
         let writer_state = self.writer.data_state.expect("ChunkWriter in weird state");
         writer_state.len / spec_ex.bytes_per_sample as u32
     }
+
+    /// Returns the number of samples needed to complete the last frame.
+    ///
+    /// This is zero when the number of samples written is a multiple of the
+    /// number of channels. Otherwise `finalize()` returns
+    /// `Error::UnfinishedFrame` with this number, unless this many samples
+    /// are written first.
+    pub fn missing_samples(&self) -> u16 {
+        let channels = self.spec().channels as u32;
+        match self.len() % channels {
+            0 => 0,
+            partial => (channels - partial) as u16,
+        }
+    }
+}
+
+/// Returns an error if the block align or the byte rate do not fit the fmt chunk.
+///
+/// Both are derived from the spec, and stored in a `u16` and a `u32`.
+fn check_header_fields(spec_ex: WavSpecEx) -> Result<()> {
+    if spec_ex.block_align() > u16::MAX as u32 {
+        let msg = format!("block align of {} bytes for {} channels does not fit 16 bits",
+                          spec_ex.block_align(), spec_ex.spec.channels);
+        return Err(Error::InvalidSpec(msg));
+    }
+    if spec_ex.byte_rate() > u32::MAX as u64 {
+        let msg = format!("byte rate of {} bytes per second does not fit 32 bits",
+                          spec_ex.byte_rate());
+        return Err(Error::InvalidSpec(msg));
+    }
+    Ok(())
+}
+
+/// Returns an error for chunks that a `WavWriter` writes itself.
+fn check_raw_chunk(chunk: &RawChunk) -> Result<()> {
+    match &chunk.id {
+        b"fmt " | b"fact" | b"data" => {
+            let msg = format!("{:?} chunk is written by the writer", String::from_utf8_lossy(&chunk.id));
+            Err(Error::FormatError(msg))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Copies the metadata chunks of `reader` to `writer`.
+///
+/// All chunks other than `fmt `, `fact`, `data` and `JUNK` padding are
+/// copied as is, vendor chunks included. Chunks that precede the data chunk
+/// in the input are placed before it in the output if no samples have been
+/// written yet, so call this right after creating the writer. Chunks that
+/// follow the data chunk are only known after `read_trailing_chunks()`, and
+/// are written after the data chunk, in the same order.
+///
+/// A seekable reader skips the `iXML` chunk when it reads the header. Rather
+/// than dropping it, this returns an error until the chunk was read with
+/// `read_ixml()` or `read_trailing_chunks()`.
+///
+/// Chunks such as `cue ` that refer to sample positions are not adjusted, so
+/// when the audio is edited, set those again with the typed setters instead.
+pub fn copy_metadata<R, W>(reader: &read::WavReader<R>, writer: &mut WavWriter<W>) -> Result<()>
+    where R: io::Read,
+          W: io::Write + io::Seek
+{
+    if reader.has_deferred_chunks() {
+        let msg = "iXML chunk not read yet, call read_ixml() before copying metadata";
+        return Err(Error::FormatError(msg.to_string()));
+    }
+    let is_padding = |c: &&RawChunk| &c.id == b"JUNK" || &c.id == b"PAD ";
+    for chunk in reader.chunks_before_data().iter().filter(|c| !is_padding(c)) {
+        try!(writer.add_chunk(chunk.clone()));
+    }
+    for chunk in reader.chunks_after_data().iter().filter(|c| !is_padding(c)) {
+        try!(writer.add_chunk_after_data(chunk.clone()));
+    }
+    Ok(())
 }
 
 /// Reads the relevant parts of the header required to support append.
@@ -755,7 +2143,17 @@ impl<W> WavWriter<W>
 fn read_append<W: io::Read + io::Seek>(reader: &mut W) -> Result<(WavSpecEx, u32, u32)> {
     let mut chunk_reader = try!(read::ChunksReader::new(reader));
     try!(chunk_reader.read_until_data());
-    let spec_ex = try!(chunk_reader.spec_ex.ok_or(Error::FormatError("DATA found before fmt")));
+    let spec_ex = try!(chunk_reader.spec_ex.ok_or_else(|| {
+        Error::FormatError("DATA found before fmt".to_string())
+    }));
+    if let Some(format_tag) = chunk_reader.adpcm_format_tag() {
+        // Samples are written as PCM, they cannot extend compressed data.
+        return Err(Error::Unsupported(UnsupportedFormat {
+            format_tag: Some(format_tag),
+            bits_per_sample: 4,
+            sub_format: None,
+        }));
+    }
     let data_len = chunk_reader.data_state.expect("Invalid state, should be in DATA").chunk.len;
     let data_start = try!(chunk_reader.into_inner().seek(io::SeekFrom::Current(0)));
 
@@ -764,7 +2162,8 @@ fn read_append<W: io::Read + io::Seek>(reader: &mut W) -> Result<(WavSpecEx, u32
     // There must not be trailing bytes in the data chunk, otherwise the
     // bytes we write will be off.
     if num_samples * spec_ex.bytes_per_sample as u64 != data_len {
-        let msg = "data chunk length is not a multiple of sample size";
+        let msg = format!("data chunk length {} is not a multiple of sample size {}",
+                          data_len, spec_ex.bytes_per_sample);
         return Err(Error::FormatError(msg));
     }
 
@@ -779,19 +2178,68 @@ fn read_append<W: io::Read + io::Seek>(reader: &mut W) -> Result<(WavSpecEx, u32
     };
 
     if !supported {
-        return Err(Error::Unsupported);
+        return Err(Error::Unsupported(UnsupportedFormat::bits(spec_ex.spec.bits_per_sample)));
     }
 
     // The number of samples must be a multiple of the number of channels,
     // otherwise the last inter-channel sample would not have data for all
     // channels.
     if num_samples % spec_ex.spec.channels as u64 != 0 {
-        return Err(Error::FormatError("invalid data chunk length"));
+        let msg = format!("data chunk holds {} samples, not a multiple of {} channels",
+                          num_samples, spec_ex.spec.channels);
+        return Err(Error::FormatError(msg));
     }
 
     Ok((spec_ex, data_len as u32, data_start as u32))
 }
 
+/// Wraps a writer that cannot seek, for use with `WavWriter::new_with_length()`.
+///
+/// `WavWriter` requires `io::Seek`, but a writer created with
+/// `new_with_length()` only ever asks for the current position. This adapter
+/// tracks the position, so that any `io::Write`, such as standard output, can
+/// be used. Every other seek fails with an error.
+pub struct NoSeek<W> {
+    writer: W,
+    position: u64,
+}
+
+impl<W: io::Write> NoSeek<W> {
+    /// Wraps `writer`, which is assumed to be at offset 0.
+    pub fn new(writer: W) -> NoSeek<W> {
+        NoSeek {
+            writer: writer,
+            position: 0,
+        }
+    }
+
+    /// Destroys the `NoSeek` and returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: io::Write> io::Write for NoSeek<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = try!(self.writer.write(buf));
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: io::Write> io::Seek for NoSeek<W> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        match pos {
+            io::SeekFrom::Current(0) => Ok(self.position),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "the underlying writer cannot seek")),
+        }
+    }
+}
+
 impl WavWriter<io::BufWriter<fs::File>> {
     /// Creates a writer that writes the WAVE format to a file.
     ///
@@ -828,24 +2276,242 @@ impl WavWriter<io::BufWriter<fs::File>> {
         let full_len = try!(file.seek(io::SeekFrom::End(0)));
 
         if full_len as u32 != data_start + data_len {
-            return Err(Error::FormatError("Can not append to a wave file with trailing chunks"))
+            let msg = format!("Can not append to a wave file with trailing chunks: the data chunk \
+                               ends at {}, the file at {}", data_start + data_len, full_len);
+            return Err(Error::FormatError(msg))
         }
         let buf_writer = io::BufWriter::new(file);
 
         let writer = WavWriter {
-            writer: ChunksWriter {
-                spec_ex: Some(spec_ex),
-                writer: buf_writer,
-                sample_writer_buffer: Vec::new(),
-                dirty: true,
-                data_state: Some(ChunkWritingState { len: data_len }),
-            }
+            writer: ChunksWriter::new_append(buf_writer, spec_ex, data_len, data_start),
         };
 
         Ok(writer)
     }
 }
 
+/// Creates a file with the given spec and samples, and finalizes it.
+///
+/// This is a shorthand for `WavWriter::create()`, writing every sample, and
+/// `finalize()`. The samples are interleaved, so their number must be a
+/// multiple of the number of channels; otherwise `Error::InvalidSpec` is
+/// returned before the file is created. The first error is returned.
+///
+/// ```
+/// let spec = hound::WavSpec {
+///     channels: 2,
+///     sample_rate: 44100,
+///     bits_per_sample: 16,
+///     sample_format: hound::SampleFormat::Int,
+/// };
+/// hound::write_all("stereo.wav", spec, &[0_i16, 1, 2, 3]).unwrap();
+/// # std::fs::remove_file("stereo.wav").unwrap();
+/// ```
+pub fn write_all<S, P>(filename: P, spec: WavSpec, samples: &[S]) -> Result<()>
+    where S: Sample + Copy,
+          P: AsRef<path::Path>,
+{
+    match samples.len() % spec.channels.max(1) as usize {
+        0 => {}
+        _ => {
+            let msg = format!("{} samples do not make whole frames of {} channels",
+                              samples.len(), spec.channels);
+            return Err(Error::InvalidSpec(msg));
+        }
+    }
+    let mut writer = try!(WavWriter::create(filename, spec));
+    for &sample in samples {
+        try!(writer.write_sample(sample));
+    }
+    writer.finalize()
+}
+
+/// Replaces the `LIST` chunk of type `INFO` of an existing file, without rewriting the audio.
+///
+/// The file is opened for reading and writing, and only the chunk headers are
+/// read, so this is fast even for large files. The data chunk is not touched.
+/// The existing `INFO` chunk, before or after the data chunk, is replaced as
+/// follows:
+///
+/// * If the new chunk has the same size, or is at least 8 bytes smaller, it
+///   is written in place. Remaining space is filled with a `JUNK` chunk.
+/// * If the chunk is the last one in the file, it is written in place, and
+///   the file grows if needed.
+/// * Otherwise the existing chunk is turned into a `JUNK` chunk, and the new
+///   chunk is appended at the end of the file.
+///
+/// If the file has no `INFO` chunk, it is appended at the end of the file.
+/// The RIFF size in the header is updated in all cases.
+pub fn update_info<P: AsRef<path::Path>>(filename: P, info: &ListInfo) -> Result<()> {
+    let mut file = try!(fs::OpenOptions::new().read(true).write(true).open(filename));
+    rewrite_info(&mut file, info)
+}
+
+/// Replaces the `INFO` chunk of a file stream, see `update_info()`.
+fn rewrite_info<F>(file: &mut F, info: &ListInfo) -> Result<()>
+    where F: io::Read + io::Write + io::Seek
+{
+    use read::ReadExt;
+
+    let riff_end = try!(read::read_wave_header(file));
+    let stream_len = try!(file.seek(io::SeekFrom::End(0)));
+    let limit = cmp::min(riff_end, stream_len);
+
+    // Walk the chunk headers, and remember the offset and padded size of the
+    // first INFO chunk.
+    let mut offset = 12;
+    let mut data_found = false;
+    let mut existing = None;
+    while offset + 8 <= limit {
+        try!(file.seek(io::SeekFrom::Start(offset)));
+        let id = try!(file.read_4_bytes());
+        let len = try!(file.read_le_u32()) as u64;
+        if &id == b"data" {
+            data_found = true;
+        }
+        if &id == b"LIST" && len >= 4 && existing.is_none() &&
+           &try!(file.read_4_bytes()) == b"INFO" {
+            existing = Some((offset, 8 + len + len % 2));
+        }
+        offset += 8 + len + len % 2;
+    }
+    if !data_found {
+        return Err(Error::FormatError("no data chunk found".to_string()));
+    }
+    // Allow for a missing pad byte after the last chunk.
+    if offset > stream_len + 1 {
+        let msg = format!("chunk extends to {}, beyond the end of the file at {}", offset, stream_len);
+        return Err(Error::FormatError(msg));
+    }
+
+    let chunk = RawChunk {
+        id: *b"LIST",
+        data: metadata::serialize_list_info(info),
+    };
+    let size = 8 + chunk.data.len() as u64;
+    // In the worst case, the chunk is appended.
+    if offset + size - 8 > u32::MAX as u64 {
+        return Err(Error::FormatError(format!("file too large: {} bytes", offset + size)));
+    }
+    let mut end = offset;
+    match existing {
+        Some((at, old_size)) if size == old_size || size + 8 <= old_size => {
+            try!(file.seek(io::SeekFrom::Start(at)));
+            try!(write_raw_chunk(file, &chunk));
+            if size < old_size {
+                let filler = old_size - size - 8;
+                try!(file.write_all(b"JUNK"));
+                try!(file.write_le_u32(filler as u32));
+                try!(file.write_all(&vec![0; filler as usize]));
+            }
+        }
+        Some((at, old_size)) if at + old_size == offset && size > old_size => {
+            try!(file.seek(io::SeekFrom::Start(at)));
+            try!(write_raw_chunk(file, &chunk));
+            end = at + size;
+        }
+        Some((at, _)) => {
+            try!(file.seek(io::SeekFrom::Start(at)));
+            try!(file.write_all(b"JUNK"));
+            try!(file.seek(io::SeekFrom::Start(offset)));
+            try!(write_raw_chunk(file, &chunk));
+            end = offset + size;
+        }
+        None => {
+            try!(file.seek(io::SeekFrom::Start(offset)));
+            try!(write_raw_chunk(file, &chunk));
+            end = offset + size;
+        }
+    }
+    try!(file.seek(io::SeekFrom::Start(4)));
+    try!(file.write_le_u32((end - 8) as u32));
+    try!(file.flush());
+    Ok(())
+}
+
+/// Changes the sample rate in the header of an existing file, without rewriting the audio.
+///
+/// Only the sample rate and the byte rate fields of the `fmt ` chunk are
+/// patched in place, so the samples play back at a different speed. Other
+/// chunks that express positions in time rather than in samples, such as
+/// the time reference of a `bext` chunk, are not adjusted. For IMA ADPCM and
+/// Microsoft ADPCM, the byte rate is scaled along with the sample rate. For
+/// other formats than those and PCM or IEEE float, `Error::Unsupported` is
+/// returned.
+pub fn rewrite_sample_rate<P: AsRef<path::Path>>(filename: P, sample_rate: u32) -> Result<()> {
+    let mut file = try!(fs::OpenOptions::new().read(true).write(true).open(filename));
+    patch_sample_rate(&mut file, sample_rate)
+}
+
+/// Patches the sample rate of a file stream, see `rewrite_sample_rate()`.
+fn patch_sample_rate<F>(file: &mut F, sample_rate: u32) -> Result<()>
+    where F: io::Read + io::Write + io::Seek
+{
+    use read::ReadExt;
+
+    if sample_rate == 0 {
+        return Err(Error::InvalidSpec("the sample rate is zero".to_string()));
+    }
+    let riff_end = try!(read::read_wave_header(file));
+    let mut offset = 12;
+    while offset + 8 <= riff_end {
+        try!(file.seek(io::SeekFrom::Start(offset)));
+        let id = try!(file.read_4_bytes());
+        let len = try!(file.read_le_u32()) as u64;
+        if &id == b"data" {
+            break
+        }
+        if &id != b"fmt " {
+            offset += 8 + len + len % 2;
+            continue
+        }
+        if len < 16 {
+            return Err(Error::FormatError(format!("fmt chunk: unexpected size {}", len)));
+        }
+        // The fields are format tag, channels, sample rate, byte rate, block
+        // align and bits per sample. For PCM the byte rate is the sample rate
+        // times block align. An ADPCM block holds many frames, so there the
+        // byte rate is scaled along with the sample rate.
+        try!(file.seek(io::SeekFrom::Start(offset + 8)));
+        let format_tag = try!(file.read_le_u16());
+        let _channels = try!(file.read_le_u16());
+        let old_sample_rate = try!(file.read_le_u32());
+        let old_byte_rate = try!(file.read_le_u32());
+        let block_align = try!(file.read_le_u16());
+        let bits_per_sample = try!(file.read_le_u16());
+        let byte_rate = match format_tag {
+            // PCM, IEEE float and WAVE_FORMAT_EXTENSIBLE.
+            0x0001 | 0x0003 | 0xfffe => sample_rate as u64 * block_align as u64,
+            // Microsoft ADPCM and IMA ADPCM.
+            0x0002 | 0x0011 => {
+                if old_sample_rate == 0 {
+                    return Err(Error::FormatError("fmt chunk: sample rate is zero".to_string()));
+                }
+                let old_sample_rate = old_sample_rate as u64;
+                (old_byte_rate as u64 * sample_rate as u64 + old_sample_rate / 2) / old_sample_rate
+            }
+            _ => {
+                return Err(Error::Unsupported(UnsupportedFormat {
+                    format_tag: Some(format_tag),
+                    bits_per_sample: bits_per_sample,
+                    sub_format: None,
+                }))
+            }
+        };
+        if byte_rate > u32::MAX as u64 {
+            let msg = format!("byte rate of {} Hz with block align {} does not fit the fmt chunk",
+                              sample_rate, block_align);
+            return Err(Error::InvalidSpec(msg));
+        }
+        try!(file.seek(io::SeekFrom::Start(offset + 12)));
+        try!(file.write_le_u32(sample_rate));
+        try!(file.write_le_u32(byte_rate as u32));
+        try!(file.flush());
+        return Ok(())
+    }
+    Err(Error::FormatError("no fmt chunk found before the data chunk".to_string()))
+}
+
 impl<W> WavWriter<W> where W: io::Read + io::Write + io::Seek {
     /// Creates a writer that appends samples to an existing file stream.
     ///
@@ -860,16 +2526,10 @@ impl<W> WavWriter<W> where W: io::Read + io::Write + io::Seek {
     /// is not an issue, because Hound never writes a fact chunk. For all the
     /// formats that Hound can write, the fact chunk is redundant.
     pub fn new_append(mut writer: W) -> Result<WavWriter<W>> {
-        let (spec_ex, data_len, _data_start) = try!(read_append(&mut writer));
+        let (spec_ex, data_len, data_start) = try!(read_append(&mut writer));
         try!(writer.seek(io::SeekFrom::Current(data_len as i64)));
         let writer = WavWriter {
-            writer: ChunksWriter {
-                spec_ex: Some(spec_ex),
-                writer: writer,
-                sample_writer_buffer: Vec::new(),
-                dirty: true,
-                data_state: Some(ChunkWritingState { len: data_len }),
-            }
+            writer: ChunksWriter::new_append(writer, spec_ex, data_len, data_start),
         };
 
         Ok(writer)
@@ -905,6 +2565,15 @@ pub struct SampleWriter16<'parent, W> where W: io::Write + io::Seek + 'parent {
 
     /// The index into the buffer where the next bytes will be written.
     index: u32,
+
+    /// The peaks of the written samples, if the writer keeps track of those.
+    peak_envelope: Option<&'parent mut PeakAccumulator>,
+
+    /// The checksum of the written samples, if the writer computes one.
+    md5: Option<&'parent mut Md5>,
+
+    /// The peak of every channel, if the writer keeps track of those.
+    peak_info: Option<&'parent mut PeakTracker>,
 }
 
 impl<'parent, W: io::Write + io::Seek> SampleWriter16<'parent, W> {
@@ -973,6 +2642,15 @@ impl<'parent, W: io::Write + io::Seek> SampleWriter16<'parent, W> {
         let slice = unsafe { &*(self.buffer as *const [MaybeUninit<u8>] as *const [u8]) };
 
         try!(self.writer.write_all(slice));
+        if let Some(envelope) = self.peak_envelope {
+            envelope.feed(slice);
+        }
+        if let Some(md5) = self.md5 {
+            md5.update(slice);
+        }
+        if let Some(tracker) = self.peak_info {
+            tracker.feed(slice);
+        }
 
         *self.data_bytes_written += self.buffer.len() as u32;
         Ok(())
@@ -980,28 +2658,133 @@ impl<'parent, W: io::Write + io::Seek> SampleWriter16<'parent, W> {
 }
 
 #[test]
-fn short_write_should_signal_error() {
-    use SampleFormat;
-
-    let mut buffer = io::Cursor::new(Vec::new());
-
-    let write_spec = WavSpec {
-        channels: 17,
-        sample_rate: 48000,
-        bits_per_sample: 8,
+fn unfinished_frame_can_be_padded() {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
         sample_format: SampleFormat::Int,
     };
-
-    // Deliberately write one sample less than 17 * 5.
-    let mut writer = WavWriter::new(&mut buffer, write_spec).unwrap();
+    for &pad in &[false, true] {
+        let mut buffer = io::Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        assert_eq!(writer.missing_samples(), 0);
+        for s in 0..3_i16 {
+            writer.write_sample(s).unwrap();
+        }
+        assert_eq!(writer.missing_samples(), 1);
+        if pad {
+            for _ in 0..writer.missing_samples() {
+                writer.write_sample(0_i16).unwrap();
+            }
+            assert_eq!(writer.missing_samples(), 0);
+            writer.finalize().unwrap();
+        } else {
+            match writer.finalize() {
+                Err(Error::UnfinishedFrame { missing: 1 }) => {}
+                _ => panic!("UnfinishedFrame error should have been returned."),
+            }
+        }
+    }
+}
+
+#[test]
+fn dropping_a_writer_completes_an_unfinished_frame_only_when_padding() {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    for &(finalize, mode) in &[(false, FinalizeMode::Strict),
+                               (true, FinalizeMode::Strict),
+                               (false, FinalizeMode::PadFrame)] {
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            writer.set_finalize_mode(mode);
+            for s in 1..4_i16 {
+                writer.write_sample(s).unwrap();
+            }
+            if finalize {
+                match writer.finalize() {
+                    Err(Error::UnfinishedFrame { missing: 1 }) => {}
+                    _ => panic!("UnfinishedFrame error should have been returned."),
+                }
+            }
+        }
+        let bytes = buffer.into_inner();
+        if mode == FinalizeMode::Strict {
+            // The samples are kept as they were written.
+            assert_eq!(read_le_u32_at(&bytes, 40), 6);
+        } else {
+            let mut reader = read::WavReader::new(&bytes[..]).unwrap();
+            let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+            assert_eq!(samples, [1, 2, 3, 0]);
+        }
+    }
+}
+
+#[test]
+fn finalize_mode_pad_frame_completes_the_last_frame() {
+    for &(bits, sample_format) in &[(8, SampleFormat::Int), (24, SampleFormat::Int), (32, SampleFormat::Float)] {
+        let spec = WavSpec {
+            channels: 3,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: sample_format,
+        };
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            writer.set_finalize_mode(FinalizeMode::PadFrame);
+            for _ in 0..4 {
+                match sample_format {
+                    SampleFormat::Float => writer.write_sample(0.5_f32).unwrap(),
+                    SampleFormat::Int => writer.write_sample(7_i8).unwrap(),
+                }
+            }
+            writer.finalize().unwrap();
+        }
+        buffer.set_position(0);
+        let mut reader = read::WavReader::new(buffer).unwrap();
+        assert_eq!(reader.len(), 6);
+        match sample_format {
+            SampleFormat::Float => {
+                let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+                assert_eq!(samples, [0.5, 0.5, 0.5, 0.5, 0.0, 0.0]);
+            }
+            SampleFormat::Int => {
+                let samples: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+                assert_eq!(samples, [7, 7, 7, 7, 0, 0]);
+            }
+        }
+    }
+}
+
+#[test]
+fn short_write_should_signal_error() {
+    use SampleFormat;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+
+    let write_spec = WavSpec {
+        channels: 17,
+        sample_rate: 48000,
+        bits_per_sample: 8,
+        sample_format: SampleFormat::Int,
+    };
+
+    // Deliberately write one sample less than 17 * 5.
+    let mut writer = WavWriter::new(&mut buffer, write_spec).unwrap();
     for s in 0..17 * 5 - 1 {
         writer.write_sample(s as i16).unwrap();
     }
     let error = writer.finalize().err().unwrap();
 
     match error {
-        Error::UnfinishedSample => {}
-        _ => panic!("UnfinishedSample error should have been returned."),
+        Error::UnfinishedFrame { missing: 1 } => {}
+        _ => panic!("UnfinishedFrame error should have been returned."),
     }
 }
 
@@ -1040,6 +2823,36 @@ fn wide_write_should_signal_error() {
     }
 }
 
+#[test]
+fn wide_write_should_not_write_truncated_sample() {
+    let mut buffer = io::Cursor::new(Vec::new());
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 8,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.write_sample(-128_i16).unwrap();
+        match writer.write_sample(30000_i16) {
+            Err(Error::TooWide) => {}
+            _ => panic!("TooWide error should have been returned."),
+        }
+        match writer.write_sample(-129_i32) {
+            Err(Error::TooWide) => {}
+            _ => panic!("TooWide error should have been returned."),
+        }
+        // The rejected samples must not end up in the data chunk.
+        assert_eq!(writer.len(), 1);
+        writer.finalize().unwrap();
+    }
+
+    // RIFF header, fmt chunk, data chunk header, and a single sample.
+    assert_eq!(buffer.into_inner().len(), 12 + 24 + 8 + 1);
+}
+
 #[test]
 fn s24_wav_write() {
     use std::fs::File;
@@ -1071,3 +2884,1597 @@ fn s24_wav_write() {
 
     assert_eq!(buffer.into_inner(), expected);
 }
+
+#[cfg(test)]
+fn read_le_u32_at(bytes: &[u8], offset: usize) -> u32 {
+    use read::ReadExt;
+    (&bytes[offset..offset + 4]).read_le_u32().unwrap()
+}
+
+#[test]
+fn write_info_before_data() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut info = ListInfo::new();
+    info.set_title("Four samples");
+    info.set_software("Hound");
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_info(&info).unwrap();
+        for &s in &[2_i16, -3, 5, -7] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    // The LIST chunk follows the fmt chunk directly.
+    assert_eq!(&bytes[36..40], b"LIST");
+
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.info().unwrap(), Some(info));
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[2, -3, 5, -7]);
+}
+
+#[test]
+fn write_info_after_data() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 8,
+        sample_format: SampleFormat::Int,
+    };
+    let mut info = ListInfo::new();
+    info.set_comment("odd");
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for &s in &[1_i8, 2, 3] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.set_info(&info).unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(read_le_u32_at(&bytes, 40), 3);
+    // The odd-sized data chunk is padded before the LIST chunk.
+    assert_eq!(bytes[47], 0);
+    assert_eq!(&bytes[48..52], b"LIST");
+    let list_len = read_le_u32_at(&bytes, 52) as usize;
+    assert_eq!(bytes.len(), 56 + list_len);
+    assert_eq!(metadata::parse_list_info(&bytes[56..]).unwrap(), info);
+
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.info().unwrap(), None);
+    reader.read_trailing_chunks().unwrap();
+    assert_eq!(reader.info().unwrap(), Some(info));
+    let samples: Vec<i8> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[1, 2, 3]);
+}
+
+#[test]
+fn write_info_replaces_previous_info() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut first = ListInfo::new();
+    first.set_title("first");
+    let mut second = ListInfo::new();
+    second.set_title("second");
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_info(&first).unwrap();
+        writer.set_info(&second).unwrap();
+        writer.write_sample(1_i16).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    assert_eq!(&bytes[36..40], b"JUNK");
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.info().unwrap(), Some(second));
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), 1);
+}
+
+#[test]
+fn write_cue_points() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut onset = CuePoint::new(1, 2);
+    onset.label = Some("Onset".to_string());
+    let mut end = CuePoint::new(2, 5);
+    end.label = Some("End".to_string());
+    end.note = Some("last frame".to_string());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for s in 0..10 {
+            writer.write_sample(s as i16).unwrap();
+        }
+        writer.add_cue_point(onset.clone()).unwrap();
+        writer.add_cue_point(end.clone()).unwrap();
+        assert!(writer.add_cue_point(CuePoint::new(1, 0)).is_err());
+        assert!(writer.add_cue_point(CuePoint::new(3, 6)).is_err());
+        writer.finalize().unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    assert_eq!(reader.cue_points().unwrap(), vec![onset, end]);
+    assert_eq!(reader.samples::<i16>().count(), 10);
+}
+
+#[test]
+fn write_sampler_info() {
+    use metadata::SampleLoop;
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let a_loop = SampleLoop {
+        id: 0,
+        loop_type: 0,
+        start: 2,
+        end: 7,
+        fraction: 0,
+        play_count: 0,
+    };
+    let info = SamplerInfo {
+        sample_period: 22_676,
+        midi_unity_note: 69,
+        loops: vec![a_loop.clone()],
+        ..SamplerInfo::default()
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        // Without samples, the loop cannot be valid yet.
+        assert!(writer.set_sampler_info(&info).is_err());
+        for s in 0..8 {
+            writer.write_sample(s as i16).unwrap();
+        }
+        let mut too_long = info.clone();
+        too_long.loops[0].end = 8;
+        assert!(writer.set_sampler_info(&too_long).is_err());
+        let mut reversed = info.clone();
+        reversed.loops[0].start = 5;
+        reversed.loops[0].end = 4;
+        assert!(writer.set_sampler_info(&reversed).is_err());
+        writer.set_sampler_info(&info).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let mut reader = WavReader::new(io::Cursor::new(buffer.into_inner())).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    assert_eq!(reader.sampler_info().unwrap(), Some(info));
+}
+
+#[test]
+fn write_aligned_data_chunk() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut info = ListInfo::new();
+    info.set_title("aligned");
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_data_alignment(4096).unwrap();
+        writer.set_info(&info).unwrap();
+        writer.write_sample(17_i16).unwrap();
+        writer.write_sample(-17_i16).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    assert_eq!(bytes.len(), 4096 + 4);
+    assert_eq!(&bytes[4096 - 8..4096 - 4], b"data");
+    assert_eq!(read_le_u32_at(&bytes, 4096 - 4), 4);
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.info().unwrap(), Some(info));
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[17, -17]);
+}
+
+#[test]
+fn write_aligned_data_chunk_without_room_for_filler() {
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        // Without filler the samples would start at 44, which is 4 bytes short
+        // of a multiple of 16. That is too small for a filler chunk.
+        writer.set_data_alignment(16).unwrap();
+    }
+    let bytes = buffer.into_inner();
+    assert_eq!(bytes.len(), 64);
+    assert_eq!(&bytes[36..40], b"JUNK");
+    assert_eq!(read_le_u32_at(&bytes, 40), 12);
+}
+
+#[test]
+fn write_broadcast_extension() {
+    use metadata::{BroadcastExtension, Loudness};
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 48000,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let bext = BroadcastExtension {
+        description: "Interview".to_string(),
+        originator: "hound".to_string(),
+        origination_date: "2017-10-14".to_string(),
+        origination_time: "10:00:00".to_string(),
+        time_reference: 48_000 * 3600 * 10,
+        version: 2,
+        loudness: Some(Loudness { loudness_value: -2300, ..Loudness::default() }),
+        coding_history: "A=PCM,F=48000,W=24,M=stereo,T=hound\r\n".to_string(),
+        ..BroadcastExtension::default()
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        let too_long = BroadcastExtension {
+            originator: "an originator name of over 32 bytes".to_string(),
+            ..bext.clone()
+        };
+        assert!(writer.set_broadcast_extension(&too_long).is_err());
+        let loudness_v1 = BroadcastExtension { version: 1, ..bext.clone() };
+        assert!(writer.set_broadcast_extension(&loudness_v1).is_err());
+        writer.set_broadcast_extension(&bext).unwrap();
+        writer.write_sample(1_i32).unwrap();
+        writer.write_sample(-1_i32).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    // The bext chunk follows the fmt chunk, which is of the extensible kind.
+    assert_eq!(&bytes[60..64], b"bext");
+    // The fixed part plus the coding history of odd length, and a pad byte.
+    assert_eq!(read_le_u32_at(&bytes, 64), 602 + 37);
+    assert_eq!(bytes[68 + 602 + 37], 0);
+    assert_eq!(&bytes[68 + 602 + 38..68 + 602 + 42], b"data");
+
+    let reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    let read_bext = reader.broadcast_extension().unwrap().unwrap();
+    assert_eq!(read_bext.umid.len(), 64);
+    assert_eq!(read_bext, BroadcastExtension { umid: vec![0; 64], ..bext });
+}
+
+#[test]
+fn write_ixml_roundtrips() {
+    use read::WavReader;
+
+    // An iXML document as written by field recorders, of odd length.
+    let ixml = &b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r\n\
+<BWFXML><IXML_VERSION>1.61</IXML_VERSION><PROJECT>Hound</PROJECT>\
+<SCENE>12A</SCENE><TAKE>13</TAKE><TAPE>221014</TAPE><CIRCLED>TRUE</CIRCLED>\
+<NOTE>wind noise</NOTE><TRACK_LIST><TRACK_COUNT>1</TRACK_COUNT><TRACK>\
+<CHANNEL_INDEX>1</CHANNEL_INDEX><NAME>Boom</NAME></TRACK></TRACK_LIST></BWFXML>\r\n"[..];
+    assert_eq!(ixml.len() % 2, 1);
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_ixml(b"<BWFXML/>").unwrap();
+        writer.set_ixml(ixml).unwrap();
+        writer.write_sample(5_i16).unwrap();
+    }
+
+    let bytes = buffer.into_inner();
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.ixml(), Some(ixml));
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), 5);
+}
+
+#[test]
+fn ixml_is_read_on_demand_from_seekable_sources() {
+    use read::WavReader;
+
+    let ixml = &b"<BWFXML><SCENE>12A</SCENE><TAKE>13</TAKE></BWFXML>"[..];
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.add_chunk(RawChunk { id: *b"aBcD", data: vec![1, 2] }).unwrap();
+        writer.set_ixml(ixml).unwrap();
+        writer.add_chunk(RawChunk { id: *b"eFgH", data: vec![3] }).unwrap();
+        writer.write_sample(5_i16).unwrap();
+        writer.write_sample(7_i16).unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = WavReader::new_seekable(buffer).unwrap();
+    let ids = |chunks: &[RawChunk]| chunks.iter().map(|c| c.id).collect::<Vec<_>>();
+    // The payload is skipped when the header is read.
+    assert_eq!(reader.ixml(), None);
+    assert_eq!(ids(reader.chunks_before_data()), vec![*b"aBcD", *b"eFgH"]);
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), 5);
+
+    assert_eq!(reader.read_ixml().unwrap(), Some(ixml));
+    assert_eq!(reader.ixml(), Some(ixml));
+    assert_eq!(ids(reader.chunks_before_data()), vec![*b"aBcD", *b"iXML", *b"eFgH"]);
+    // Reading the chunk does not move the position in the data chunk.
+    assert_eq!(reader.samples::<i16>().next().unwrap().unwrap(), 7);
+    assert_eq!(reader.read_ixml().unwrap(), Some(ixml));
+}
+
+#[test]
+fn copy_metadata_does_not_drop_a_skipped_ixml_chunk() {
+    use read::WavReader;
+
+    let ixml = &b"<BWFXML><NOTE>wind</NOTE></BWFXML>"[..];
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_ixml(ixml).unwrap();
+        writer.write_sample(5_i16).unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = WavReader::new_seekable(buffer).unwrap();
+    let mut copy = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut copy, spec).unwrap();
+        match copy_metadata(&reader, &mut writer) {
+            Err(Error::FormatError(..)) => {}
+            _ => panic!("copying should fail before the iXML chunk is read"),
+        }
+        reader.read_ixml().unwrap();
+        copy_metadata(&reader, &mut writer).unwrap();
+        writer.write_sample(5_i16).unwrap();
+    }
+
+    copy.set_position(0);
+    let reader = WavReader::new(copy).unwrap();
+    assert_eq!(reader.ixml(), Some(ixml));
+}
+
+#[test]
+fn id3_survives_trimming() {
+    use read::WavReader;
+
+    // The header of an ID3v2.4 tag with a single TIT2 frame.
+    let id3 = &b"ID3\x04\x00\x00\x00\x00\x00\x11TIT2\x00\x00\x00\x07\x00\x00\x03Pilot"[..];
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut original = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut original, spec).unwrap();
+        for s in 0..10 {
+            writer.write_sample(s as i16).unwrap();
+        }
+        // Podcast tools put the tag after the data chunk.
+        writer.set_id3(id3).unwrap();
+    }
+
+    // Keep only the first half of the samples, and the tag.
+    let mut reader = WavReader::new(io::Cursor::new(original.into_inner())).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let mut trimmed = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut trimmed, reader.spec()).unwrap();
+        writer.set_id3(reader.id3().unwrap()).unwrap();
+        for sample in reader.samples::<i16>().take(5) {
+            writer.write_sample(sample.unwrap()).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    let mut reader = WavReader::new(io::Cursor::new(trimmed.into_inner())).unwrap();
+    assert_eq!(reader.id3(), Some(id3));
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn header_bytes_match_output() {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    let (header_after_new, header_after_info, header_final, header_len) = {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        assert_eq!(writer.header_len(), 44);
+        let header_after_new = writer.header_bytes();
+        assert_eq!(header_after_new.len(), 44);
+
+        let mut info = ListInfo::new();
+        info.set_title("first");
+        writer.set_info(&info).unwrap();
+        writer.set_data_alignment(64).unwrap();
+        info.set_title("second");
+        writer.set_info(&info).unwrap();
+        let header_after_info = writer.header_bytes();
+        assert_eq!(header_after_info.len() as u64, writer.header_len());
+
+        writer.write_sample(1_i16).unwrap();
+        writer.write_sample(2_i16).unwrap();
+        let header_final = writer.header_bytes();
+        let header_len = writer.header_len() as usize;
+        writer.finalize().unwrap();
+        (header_after_new, header_after_info, header_final, header_len)
+    };
+
+    let bytes = buffer.into_inner();
+    assert_eq!(header_len % 64, 0);
+    assert_eq!(&header_final[..], &bytes[..header_len]);
+    assert_eq!(bytes.len(), header_len + 4);
+    // Apart from the sizes, the header did not change by writing samples.
+    assert_eq!(&header_after_info[8..header_len - 4], &header_final[8..header_len - 4]);
+    assert_eq!(read_le_u32_at(&header_after_info, header_len - 4), 0);
+    assert_eq!(read_le_u32_at(&header_after_new, 4), 36);
+    assert_eq!(read_le_u32_at(&header_after_new, 40), 0);
+}
+
+#[test]
+fn write_acid_info_preserves_fields() {
+    use read::WavReader;
+
+    let acid = AcidInfo {
+        // One-shot, and an unknown flag bit.
+        flags: 0x8001,
+        root_note: 60,
+        reserved1: 0x8000,
+        reserved2: 0.25,
+        num_beats: 4,
+        meter_denominator: 4,
+        meter_numerator: 4,
+        tempo: 93.75,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_acid_info(&acid).unwrap();
+    }
+    let bytes = buffer.into_inner();
+    assert_eq!(&bytes[36..44], b"acid\x18\x00\x00\x00");
+    // The tempo is a little endian float.
+    assert_eq!(&bytes[64..68], &[0x00, 0x80, 0xbb, 0x42]);
+    let reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    let read_acid = reader.acid_info().unwrap().unwrap();
+    assert!(read_acid.is_one_shot());
+    assert_eq!(read_acid, acid);
+}
+
+#[test]
+fn write_instrument_info_with_pad_byte() {
+    use read::WavReader;
+
+    let inst = InstrumentInfo {
+        unshifted_note: 60,
+        fine_tune: -12,
+        gain: -6,
+        low_note: 48,
+        high_note: 72,
+        low_velocity: 1,
+        high_velocity: 127,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        let mut invalid = inst;
+        invalid.fine_tune = 51;
+        assert!(writer.set_instrument_info(&invalid).is_err());
+        invalid = inst;
+        invalid.low_note = 73;
+        assert!(writer.set_instrument_info(&invalid).is_err());
+        invalid = inst;
+        invalid.low_velocity = 0;
+        assert!(writer.set_instrument_info(&invalid).is_err());
+
+        writer.set_instrument_info(&inst).unwrap();
+        writer.write_sample(17_i16).unwrap();
+    }
+    let bytes = buffer.into_inner();
+    // The chunk is 7 bytes long, followed by a pad byte.
+    assert_eq!(&bytes[36..44], b"inst\x07\x00\x00\x00");
+    assert_eq!(&bytes[44..52], &[60, 0xf4, 0xfa, 48, 72, 1, 127, 0]);
+    assert_eq!(&bytes[52..56], b"data");
+
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.instrument_info().unwrap(), Some(inst));
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[17]);
+}
+
+#[test]
+fn copy_metadata_preserves_chunks() {
+    use read::WavReader;
+
+    let mut reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-metadata.wav")
+        .unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, reader.spec()).unwrap();
+        copy_metadata(&reader, &mut writer).unwrap();
+        // Keep only the first half of the samples.
+        let n = reader.len() / 2;
+        for s in reader.samples::<i16>().take(n as usize) {
+            writer.write_sample(s.unwrap()).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut copy = WavReader::new(buffer).unwrap();
+    copy.read_trailing_chunks().unwrap();
+    let ids = |chunks: &[RawChunk]| chunks.iter().map(|c| c.id).collect::<Vec<_>>();
+    // The JUNK padding is dropped, the vendor chunk is kept.
+    assert_eq!(ids(copy.chunks_before_data()), vec![*b"bext", *b"LIST", *b"aBcD"]);
+    assert_eq!(ids(copy.chunks_after_data()), vec![*b"cue ", *b"id3 "]);
+    assert_eq!(&copy.chunks_before_data()[..2], &reader.chunks_before_data()[..2]);
+    assert_eq!(&copy.chunks_before_data()[2], &reader.chunks_before_data()[3]);
+    assert_eq!(copy.chunks_after_data(), reader.chunks_after_data());
+    assert_eq!(copy.info().unwrap().unwrap().title(), Some("Take 3"));
+    let samples: Vec<i16> = copy.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[0, 50, 100, 150, 200]);
+}
+
+#[test]
+fn add_chunk_rejects_reserved_ids() {
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+    let chunk = RawChunk { id: *b"data", data: vec![0, 0] };
+    assert!(writer.add_chunk(chunk.clone()).is_err());
+    assert!(writer.add_chunk_after_data(chunk).is_err());
+}
+
+#[test]
+fn write_chunk_pads_and_counts_in_riff_size() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.write_chunk(*b"appl", b"{\"a\":1}").unwrap();
+        assert!(writer.write_chunk(*b"fact", &[0, 0, 0, 0]).is_err());
+        writer.write_sample(5_i16).unwrap();
+        writer.write_chunk(*b"appz", b"xyz").unwrap();
+        writer.finalize().unwrap();
+    }
+    let bytes = buffer.into_inner();
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    // Both payloads have an odd length, so both are followed by a pad byte.
+    assert_eq!(&bytes[36..52], b"appl\x07\x00\x00\x00{\"a\":1}\x00");
+    assert_eq!(&bytes[bytes.len() - 12..], b"appz\x03\x00\x00\x00xyz\x00");
+
+    let mut reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    assert_eq!(reader.chunks_before_data()[0].data, b"{\"a\":1}".to_vec());
+    assert_eq!(reader.chunks_after_data()[0].id, *b"appz");
+    let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+    assert_eq!(&samples[..], &[5]);
+}
+
+#[test]
+fn rewrite_info_keeps_audio_intact() {
+    use read::WavReader;
+
+    let mut bytes = Vec::new();
+    let mut file = fs::File::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav").unwrap();
+    io::Read::read_to_end(&mut file, &mut bytes).unwrap();
+    let data_at = bytes.windows(4).position(|w| w == b"data").unwrap();
+    let audio = bytes[data_at..].to_vec();
+    let read_info = |bytes: &Vec<u8>| {
+        assert_eq!(read_le_u32_at(bytes, 4) as usize, bytes.len() - 8);
+        let mut reader = WavReader::new(io::Cursor::new(bytes.clone())).unwrap();
+        reader.read_trailing_chunks().unwrap();
+        let samples: Vec<i16> = reader.samples().map(|r| r.unwrap()).collect();
+        assert_eq!(&samples[..], &[2, -3, 5, -7]);
+        reader.info().unwrap().unwrap()
+    };
+
+    // A smaller chunk is written in place, followed by a JUNK chunk.
+    let mut info = ListInfo::new();
+    info.set_title("x");
+    let mut buffer = io::Cursor::new(bytes.clone());
+    rewrite_info(&mut buffer, &info).unwrap();
+    let shrunk = buffer.into_inner();
+    assert_eq!(shrunk.len(), bytes.len());
+    assert_eq!(&shrunk[data_at..], &audio[..]);
+    assert_eq!(read_info(&shrunk), info);
+
+    // A larger chunk that does not fit is appended after the data chunk.
+    info.set_comment("a comment that is longer than the original chunk");
+    let mut buffer = io::Cursor::new(shrunk);
+    rewrite_info(&mut buffer, &info).unwrap();
+    let grown = buffer.into_inner();
+    assert_eq!(&grown[data_at..data_at + audio.len()], &audio[..]);
+    assert_eq!(read_info(&grown), info);
+
+    // Now that it is the last chunk, it can grow in place.
+    info.set_artist("Hound");
+    let mut buffer = io::Cursor::new(grown.clone());
+    rewrite_info(&mut buffer, &info).unwrap();
+    let regrown = buffer.into_inner();
+    assert_eq!(&regrown[8..data_at + audio.len()], &grown[8..data_at + audio.len()]);
+    assert_eq!(read_info(&regrown), info);
+}
+
+#[test]
+fn rewrite_info_appends_missing_chunk() {
+    use read::WavReader;
+
+    let mut bytes = Vec::new();
+    let mut file = fs::File::open("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    io::Read::read_to_end(&mut file, &mut bytes).unwrap();
+    let mut info = ListInfo::new();
+    info.set_title("Retagged");
+    let mut buffer = io::Cursor::new(bytes.clone());
+    rewrite_info(&mut buffer, &info).unwrap();
+    let tagged = buffer.into_inner();
+    assert_eq!(&tagged[8..bytes.len()], &bytes[8..]);
+    assert_eq!(read_le_u32_at(&tagged, 4) as usize, tagged.len() - 8);
+    let mut reader = WavReader::new(io::Cursor::new(tagged)).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    assert_eq!(reader.info().unwrap(), Some(info));
+}
+
+#[test]
+fn patch_sample_rate_keeps_audio_intact() {
+    use read::WavReader;
+
+    for fname in &["testsamples/pcmwaveformat-16bit-44100Hz-mono-info.wav",
+                   "testsamples/waveformatextensible-24bit-192kHz-mono.wav"] {
+        let mut bytes = Vec::new();
+        let mut file = fs::File::open(fname).unwrap();
+        io::Read::read_to_end(&mut file, &mut bytes).unwrap();
+        let spec = WavReader::new(io::Cursor::new(bytes.clone())).unwrap().spec();
+
+        let mut buffer = io::Cursor::new(bytes.clone());
+        patch_sample_rate(&mut buffer, 22050).unwrap();
+        let patched = buffer.into_inner();
+        assert_eq!(patched.len(), bytes.len());
+        let fmt_at = bytes.windows(4).position(|w| w == b"fmt ").unwrap();
+        assert_eq!(&patched[..fmt_at + 12], &bytes[..fmt_at + 12]);
+        assert_eq!(&patched[fmt_at + 20..], &bytes[fmt_at + 20..]);
+
+        let block_align = (spec.channels * spec.bits_per_sample / 8) as u32;
+        assert_eq!(read_le_u32_at(&patched, fmt_at + 12), 22050);
+        assert_eq!(read_le_u32_at(&patched, fmt_at + 16), 22050 * block_align);
+        let reader = WavReader::new(io::Cursor::new(patched)).unwrap();
+        assert_eq!(reader.spec(), WavSpec { sample_rate: 22050, ..spec });
+    }
+
+    let bytes = fs::read("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    match patch_sample_rate(&mut io::Cursor::new(bytes), 0) {
+        Err(Error::InvalidSpec(_)) => {}
+        _ => panic!("expected InvalidSpec for a zero sample rate"),
+    }
+}
+
+#[test]
+fn patch_sample_rate_scales_the_byte_rate_of_adpcm() {
+    use read::WavReader;
+
+    for fname in &["testsamples/waveformatex-ima-adpcm-4bit-8000Hz-stereo.wav",
+                   "testsamples/waveformatex-ms-adpcm-4bit-8000Hz-stereo.wav"] {
+        let bytes = fs::read(fname).unwrap();
+        let fmt_at = bytes.windows(4).position(|w| w == b"fmt ").unwrap();
+        // A block holds many frames, so the byte rate is not the sample rate
+        // times block align.
+        let byte_rate = read_le_u32_at(&bytes, fmt_at + 16);
+        let mut buffer = io::Cursor::new(bytes.clone());
+        patch_sample_rate(&mut buffer, 16000).unwrap();
+        let patched = buffer.into_inner();
+        assert_eq!(read_le_u32_at(&patched, fmt_at + 12), 16000);
+        assert_eq!(read_le_u32_at(&patched, fmt_at + 16), byte_rate * 2);
+        assert_eq!(&patched[fmt_at + 20..], &bytes[fmt_at + 20..]);
+        let mut reader = WavReader::new(io::Cursor::new(patched)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 16000);
+        assert_eq!(reader.samples::<i16>().count(), 2000);
+    }
+
+    // Other formats are not known to follow either rule.
+    let mut bytes = fs::read("testsamples/pcmwaveformat-16bit-44100Hz-mono.wav").unwrap();
+    let fmt_at = bytes.windows(4).position(|w| w == b"fmt ").unwrap();
+    bytes[fmt_at + 8] = 0x06;
+    match patch_sample_rate(&mut io::Cursor::new(bytes), 22050) {
+        Err(Error::Unsupported(format)) => assert_eq!(format.format_tag, Some(0x0006)),
+        _ => panic!("expected Unsupported for A-law samples"),
+    }
+}
+
+#[test]
+fn write_cart_roundtrips() {
+    use read::WavReader;
+
+    let reader = WavReader::open("testsamples/pcmwaveformat-16bit-44100Hz-mono-cart.wav").unwrap();
+    let mut cart = reader.cart().unwrap().unwrap();
+    cart.title = "Evening Jingle".to_string();
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, reader.spec()).unwrap();
+        writer.set_cart(&cart).unwrap();
+        writer.write_sample(1_i16).unwrap();
+        cart.title.push_str(&"!".repeat(64));
+        assert!(writer.set_cart(&cart).is_err());
+        writer.finalize().unwrap();
+    }
+    let bytes = buffer.into_inner();
+    // The tag text has an odd length, so the chunk is padded.
+    assert_eq!(read_le_u32_at(&bytes, 40), 2048 + 19);
+    assert_eq!(&bytes[44 + 2048 + 20..44 + 2048 + 24], b"data");
+    assert_eq!(read_le_u32_at(&bytes, 4) as usize, bytes.len() - 8);
+    let reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    let read_cart = reader.cart().unwrap().unwrap();
+    assert_eq!(read_cart.title, "Evening Jingle");
+    assert_eq!(read_cart.timers.len(), 3);
+    assert_eq!(read_cart.tag_text, "Produced for KHND\r\n");
+}
+
+#[test]
+fn write_display_title_next_to_info() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut info = ListInfo::new();
+    info.set_title("Title");
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_display_title("First").unwrap();
+        writer.set_info(&info).unwrap();
+        writer.set_display_title("Display title").unwrap();
+    }
+    let bytes = buffer.into_inner();
+    let reader = WavReader::new(io::Cursor::new(bytes)).unwrap();
+    assert_eq!(reader.display_title().unwrap().unwrap(), "Display title");
+    assert_eq!(reader.info().unwrap(), Some(info));
+    let disp = reader.chunks_before_data().iter().find(|c| &c.id == b"DISP").unwrap();
+    assert_eq!(&disp.data[..], b"\x01\x00\x00\x00Display title\x00");
+}
+
+#[test]
+fn write_adm_chunks() {
+    use read::WavReader;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 48000,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let tracks: Vec<AdmTrack> = (1..3).map(|i| AdmTrack {
+        track_index: i,
+        uid: format!("ATU_0000000{}", i),
+        track_ref: format!("AT_0001000{}_01", i),
+        pack_ref: "AP_00010002".to_string(),
+    }).collect();
+    let axml = b"<ebuCoreMain/>";
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        let mut too_many = tracks.clone();
+        too_many[1].track_index = 3;
+        assert!(writer.set_adm_tracks(&too_many).is_err());
+        writer.set_adm_tracks(&tracks).unwrap();
+        writer.set_axml(axml).unwrap();
+        writer.write_sample(5).unwrap();
+        writer.write_sample(-5).unwrap();
+    }
+    let reader = WavReader::new(io::Cursor::new(buffer.into_inner())).unwrap();
+    assert_eq!(reader.adm_tracks().unwrap(), tracks);
+    assert_eq!(reader.axml(), Some(&axml[..]));
+}
+
+#[test]
+fn write_md5_does_not_change_samples() {
+    use read::{Md5Status, WavReader};
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let write = |md5: bool| {
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            if md5 {
+                writer.enable_md5();
+            }
+            writer.write_sample(3_i16).unwrap();
+            writer.write_sample(-3_i16).unwrap();
+            writer.write_silence(40).unwrap();
+            {
+                let mut i16_writer = writer.get_i16_writer(4);
+                for s in 0..4 {
+                    i16_writer.write_sample(s * 100);
+                }
+                i16_writer.flush().unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        buffer.into_inner()
+    };
+    let plain = write(false);
+    let hashed = write(true);
+    let data_len = plain.len() - 44;
+    assert_eq!(&hashed[8..44], &plain[8..44]);
+    assert_eq!(&hashed[44..44 + data_len], &plain[44..]);
+
+    let mut md5 = Md5::new();
+    md5.update(&plain[44..]);
+    assert_eq!(&hashed[44 + data_len..], &[&b"MD5 \x10\0\0\0"[..], &md5.finish()[..]].concat()[..]);
+
+    let mut reader = WavReader::new(io::Cursor::new(hashed)).unwrap();
+    assert_eq!(reader.verify_md5().unwrap(), Md5Status::Match);
+}
+
+#[test]
+fn write_with_length_never_seeks() {
+    use read::WavReader;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let mut expected = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut expected, spec).unwrap();
+        for s in 0..10 {
+            writer.write_sample(s * 1000).unwrap();
+        }
+    }
+    let mut writer = WavWriter::new_with_length(NoSeek::new(Vec::new()), spec, 5).unwrap();
+    assert!(writer.set_info(&ListInfo::new()).is_err());
+    for s in 0..10 {
+        writer.write_sample(s * 1000).unwrap();
+    }
+    writer.flush().unwrap();
+    writer.finalize().unwrap();
+
+    let mut writer = WavWriter::new_with_length(NoSeek::new(Vec::new()), spec, 5).unwrap();
+    for s in 0..4 {
+        writer.write_sample(s * 1000).unwrap();
+    }
+    match writer.finalize() {
+        Err(Error::FormatError(msg)) => {
+            assert_eq!(msg, "4 samples were written, but the length was fixed at 10 samples");
+        }
+        _ => panic!("expected FormatError for a length mismatch"),
+    }
+
+    let mut output = NoSeek::new(Vec::new());
+    let header;
+    {
+        let mut writer = WavWriter::new_with_length(&mut output, spec, 5).unwrap();
+        header = writer.header_bytes();
+        for s in 0..10 {
+            writer.write_sample(s * 1000).unwrap();
+        }
+        assert_eq!(writer.header_bytes(), header);
+        writer.finalize().unwrap();
+    }
+    let bytes = output.into_inner();
+    assert_eq!(&bytes[..header.len()], &header[..]);
+    assert_eq!(bytes, expected.into_inner());
+    let mut reader = WavReader::new(&bytes[..]).unwrap();
+    assert_eq!(reader.samples::<i32>().map(|s| s.unwrap()).collect::<Vec<_>>(),
+               (0..10).map(|s| s * 1000).collect::<Vec<_>>());
+}
+
+#[test]
+fn write_silence_matches_zero_samples() {
+    for &bits in &[8, 16, 24, 32] {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: bits,
+            sample_format: SampleFormat::Int,
+        };
+        let mut expected = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut expected, spec).unwrap();
+            writer.write_sample(1).unwrap();
+            writer.write_sample(-1).unwrap();
+            for _ in 0..2 * 40_000 {
+                writer.write_sample(0).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            writer.write_sample(1).unwrap();
+            writer.write_sample(-1).unwrap();
+            writer.write_silence(40_000).unwrap();
+            assert_eq!(writer.duration(), 40_001);
+            writer.finalize().unwrap();
+        }
+        assert!(buffer.into_inner() == expected.into_inner());
+    }
+}
+
+#[test]
+fn write_playlist() {
+    use read::WavReader;
+
+    let mut buffer = io::Cursor::new(Vec::new());
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let segments = [
+        PlaylistSegment { cue_id: 2, length: 4, repeats: 2 },
+        PlaylistSegment { cue_id: 1, length: 6, repeats: 1 },
+    ];
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        for s in 0..10 {
+            writer.write_sample(s as i16).unwrap();
+        }
+        writer.add_cue_point(CuePoint::new(1, 0)).unwrap();
+        assert!(writer.set_playlist(&segments).is_err());
+        writer.add_cue_point(CuePoint::new(2, 6)).unwrap();
+        writer.set_playlist(&segments).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let ids = reader.chunks_after_data().iter().map(|c| c.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![*b"cue ", *b"plst"]);
+    assert_eq!(reader.playlist().unwrap(), segments.to_vec());
+}
+
+#[test]
+fn format_timestamp_is_utc() {
+    assert_eq!(format_timestamp(0, 0), "1970:01:01:00:00:00:000");
+    assert_eq!(format_timestamp(951_782_400 + 3661, 7), "2000:02:29:01:01:01:007");
+    assert_eq!(format_timestamp(1_791_979_200, 999), "2026:10:14:12:00:00:999");
+}
+
+#[test]
+fn write_i24_saturates_when_requested() {
+    use std::io::Cursor;
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        match writer.write_sample(0x800000) {
+            Err(Error::TooWide) => {}
+            _ => panic!("TooWide error should have been returned by default."),
+        }
+        writer.set_overflow_mode(OverflowMode::Saturate);
+        for &s in &[0x800000, -0x800001, i32::MAX, i32::MIN, 0x7fffff, -5] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.write_sample(-3_i16).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = read::WavReader::new(buffer).unwrap();
+    let samples: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(&samples[..], &[0x7fffff, -0x800000, 0x7fffff, -0x800000, 0x7fffff, -5, -3]);
+}
+
+#[test]
+fn write_peak_envelope() {
+    use read::WavReader;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.enable_peak_envelope(2);
+        for &s in &[100_i16, -50, -300, 20] {
+            writer.write_sample(s).unwrap();
+        }
+        {
+            let mut sample_writer = writer.get_i16_writer(2);
+            sample_writer.write_sample(5_i16);
+            sample_writer.write_sample(-7_i16);
+            sample_writer.flush().unwrap();
+        }
+        writer.write_silence(1).unwrap();
+        writer.write_sample(-32768_i16).unwrap();
+        writer.write_sample(0_i16).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let envelope = reader.peak_envelope().unwrap().unwrap();
+    assert_eq!((envelope.version, envelope.format, envelope.points_per_value), (1, 2, 2));
+    assert_eq!((envelope.block_size, envelope.channels), (2, 2));
+    assert_eq!(envelope.num_peak_frames(), 3);
+    // The last block holds a single frame.
+    assert_eq!(envelope.peaks, vec![100, 300, 20, 50, 5, 0, 0, 7, 0, 32768, 0, 0]);
+    assert_eq!(envelope.peak_of_peaks, 4);
+    assert_eq!(envelope.timestamp.len(), 23);
+}
+
+#[test]
+fn write_peak_info() {
+    use read::WavReader;
+
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.enable_peak_info();
+        for &s in &[100_i16, -50, -16384, 20] {
+            writer.write_sample(s).unwrap();
+        }
+        {
+            let mut sample_writer = writer.get_i16_writer(2);
+            sample_writer.write_sample(16384_i16);
+            sample_writer.write_sample(-8192_i16);
+            sample_writer.flush().unwrap();
+        }
+        writer.write_silence(1).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let info = reader.peak_info().unwrap().unwrap();
+    assert_eq!(info.version, 1);
+    assert!(info.timestamp > 0);
+    // The peak of the first channel occurs twice; the first frame is kept.
+    assert_eq!(info.peaks, vec![ChannelPeak { value: 0.5, position: 1 },
+                                ChannelPeak { value: 0.25, position: 2 }]);
+    assert_eq!(reader.samples::<i16>().count(), 8);
+}
+
+#[test]
+fn write_peak_info_float() {
+    use read::WavReader;
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.enable_peak_info();
+        for &s in &[0.25_f32, -1.5, 0.75] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    buffer.set_position(0);
+    let mut reader = WavReader::new(buffer).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    let info = reader.peak_info().unwrap().unwrap();
+    // Float samples may exceed full scale, the peak is not clamped.
+    assert_eq!(info.peaks, vec![ChannelPeak { value: 1.5, position: 1 }]);
+}
+
+#[test]
+fn write_peak_envelope_scales_to_16_bits() {
+    use read::WavReader;
+
+    let cases: &[(u16, SampleFormat)] = &[
+        (8, SampleFormat::Int),
+        (24, SampleFormat::Int),
+        (32, SampleFormat::Float),
+    ];
+    for &(bits, format) in cases {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: format,
+        };
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            writer.enable_peak_envelope(256);
+            match format {
+                SampleFormat::Int => {
+                    writer.write_sample(1_i32 << (bits - 2)).unwrap();
+                    writer.write_sample(-1_i32 << (bits - 1)).unwrap();
+                }
+                SampleFormat::Float => {
+                    writer.write_sample(0.5_f32).unwrap();
+                    writer.write_sample(-1.0_f32).unwrap();
+                }
+            }
+            writer.finalize().unwrap();
+        }
+        buffer.set_position(0);
+        let mut reader = WavReader::new(buffer).unwrap();
+        reader.read_trailing_chunks().unwrap();
+        let envelope = reader.peak_envelope().unwrap().unwrap();
+        assert_eq!(envelope.peaks, vec![16384, 32768]);
+    }
+}
+
+#[test]
+fn fmt_chunk_is_minimal_for_the_spec() {
+    let fmt_len = |channels, bits, sample_format| {
+        let spec = WavSpec {
+            channels: channels,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: sample_format,
+        };
+        let mut buffer = io::Cursor::new(Vec::new());
+        WavWriter::new(&mut buffer, spec).unwrap().finalize().unwrap();
+        let bytes = buffer.into_inner();
+        assert_eq!(&bytes[12..16], b"fmt ");
+        read_le_u32_at(&bytes, 16)
+    };
+    // Integer formats that PCMWAVEFORMAT can describe never get `cbSize`.
+    assert_eq!(fmt_len(1, 8, SampleFormat::Int), 16);
+    assert_eq!(fmt_len(2, 8, SampleFormat::Int), 16);
+    assert_eq!(fmt_len(1, 16, SampleFormat::Int), 16);
+    assert_eq!(fmt_len(2, 16, SampleFormat::Int), 16);
+    assert_eq!(fmt_len(3, 16, SampleFormat::Int), 40);
+    assert_eq!(fmt_len(2, 24, SampleFormat::Int), 40);
+    assert_eq!(fmt_len(1, 32, SampleFormat::Int), 40);
+}
+
+#[test]
+fn header_is_written_at_once_and_unchanged() {
+    /// A writer that counts the calls to `write()`.
+    struct CountingWriter {
+        inner: io::Cursor<Vec<u8>>,
+        writes: usize,
+    }
+    impl io::Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.inner.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    impl io::Seek for CountingWriter {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    for &(channels, bits) in &[(1, 8), (2, 8), (1, 16), (2, 16), (1, 24), (6, 16)] {
+        let spec = WavSpec {
+            channels: channels,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: SampleFormat::Int,
+        };
+        let mut counting = CountingWriter { inner: io::Cursor::new(Vec::new()), writes: 0 };
+        {
+            let mut writer = WavWriter::new(&mut counting, spec).unwrap();
+            assert_eq!(writer.writer.writer.writes, 1);
+            for i in 0..channels as i32 * 3 {
+                writer.write_sample(i).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+
+        // Write the same file chunk by chunk, as the writer used to do.
+        let mut expected = io::Cursor::new(Vec::new());
+        {
+            let spec_ex = WavSpecEx { spec: spec, bytes_per_sample: bits / 8 };
+            let mut writer = ChunksWriter::new(&mut expected).unwrap();
+            writer.write_fmt(spec_ex).unwrap();
+            writer.start_data_chunk().unwrap();
+            for i in 0..channels as i32 * 3 {
+                writer.write_sample(i).unwrap();
+            }
+            writer.finalize().unwrap();
+        }
+        assert_eq!(counting.inner.into_inner(), expected.into_inner());
+    }
+}
+
+#[test]
+fn reset_starts_a_new_file() {
+    use read::WavReader;
+
+    let mono = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let stereo = WavSpec { channels: 2, bits_per_sample: 24, ..mono };
+    let mut first = io::Cursor::new(Vec::new());
+    let mut second = io::Cursor::new(Vec::new());
+    let mut unused = io::Cursor::new(Vec::new());
+    let mut expected = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut expected, stereo).unwrap();
+        for s in 0..4 {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    {
+        let mut writer = WavWriter::new(&mut first, mono).unwrap();
+        writer.enable_md5();
+        writer.write_sample(7_i16).unwrap();
+        {
+            // Grow the buffer of the 16-bit writer, which the reset keeps.
+            let mut samples = writer.get_i16_writer(4);
+            for s in 0..4 {
+                samples.write_sample(s as i16);
+            }
+            samples.flush().unwrap();
+        }
+        assert!(writer.reset(&mut unused, WavSpec { channels: 0, ..mono }).is_err());
+        writer.reset(&mut second, stereo).unwrap();
+        for s in 0..4 {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    let reader = WavReader::new(io::Cursor::new(first.into_inner())).unwrap();
+    assert_eq!(reader.len(), 5);
+    assert_eq!(second.into_inner(), expected.into_inner());
+}
+
+#[test]
+fn reset_finalizes_the_old_file_once() {
+    use read::WavReader;
+
+    let spec = WavSpec::cd_quality();
+    let mut first = io::Cursor::new(Vec::new());
+    let mut second = io::Cursor::new(Vec::new());
+    let mut third = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut first, spec).unwrap();
+        writer.write_frame_fixed(&[1_i16, 2]).unwrap();
+        writer.add_chunk_after_data(RawChunk { id: *b"aBcD", data: vec![3] }).unwrap();
+        writer.reset(&mut second, spec).unwrap();
+        writer.write_sample(4_i16).unwrap();
+        // The unfinished frame of the second file is reported, and the
+        // writer continues with the third file.
+        match writer.reset(&mut third, spec) {
+            Err(Error::UnfinishedFrame { missing: 1 }) => {}
+            other => panic!("expected UnfinishedFrame, got {:?}", other),
+        }
+        writer.write_frame_fixed(&[5_i16, 6]).unwrap();
+        writer.finalize().unwrap();
+    }
+
+    let mut reader = WavReader::new(io::Cursor::new(first.into_inner())).unwrap();
+    reader.read_trailing_chunks().unwrap();
+    assert_eq!(reader.chunks_after_data(), &[RawChunk { id: *b"aBcD", data: vec![3] }]);
+    let second = second.into_inner();
+    // The second file keeps its single sample, and nothing after it.
+    assert_eq!(second.len(), 46);
+    assert_eq!(read_le_u32_at(&second, 40), 2);
+    let mut reader = WavReader::new(io::Cursor::new(third.into_inner())).unwrap();
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [5, 6]);
+}
+
+#[test]
+fn write_frame_fixed_checks_the_channels() {
+    let spec = WavSpec::cd_quality();
+    let mut frames = io::Cursor::new(Vec::new());
+    let mut samples = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut frames, spec).unwrap();
+        writer.write_frame_fixed(&[1_i16, -1]).unwrap();
+        writer.write_frame_fixed(&[2_i16, -2]).unwrap();
+        match writer.write_frame_fixed(&[3_i16]) {
+            Err(Error::InvalidSpec(..)) => {}
+            other => panic!("expected InvalidSpec, got {:?}", other),
+        }
+        assert_eq!(writer.len(), 4);
+        writer.finalize().unwrap();
+    }
+    {
+        let mut writer = WavWriter::new(&mut samples, spec).unwrap();
+        for &s in &[1_i16, -1, 2, -2] {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+    assert_eq!(frames.into_inner(), samples.into_inner());
+}
+
+#[test]
+fn write_samples_f32_converts_to_the_spec() {
+    use read::WavReader;
+
+    let input = [-1.0, 0.0, 0.5, 1.0, 1.01, -1.01];
+    let cases: &[(u16, [i32; 6])] = &[
+        (8, [-0x80, 0, 0x40, 0x7f, 0x7f, -0x80]),
+        (16, [-0x8000, 0, 0x4000, 0x7fff, 0x7fff, -0x8000]),
+        (24, [-0x800000, 0, 0x400000, 0x7fffff, 0x7fffff, -0x800000]),
+        (32, [-0x80000000, 0, 0x40000000, 0x7fffffff, 0x7fffffff, -0x80000000]),
+    ];
+    for &(bits, expected) in cases {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: bits,
+            sample_format: SampleFormat::Int,
+        };
+        let mut buffer = io::Cursor::new(Vec::new());
+        {
+            let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+            writer.write_samples_f32(&input).unwrap();
+
+            // With the error mode, nothing is written if any sample is clipped.
+            writer.set_float_overflow_mode(OverflowMode::Error);
+            match writer.write_samples_f32(&[0.0, 1.01]) {
+                Err(Error::TooWide) => {}
+                other => panic!("expected TooWide for {} bits, got {:?}", bits, other),
+            }
+            assert!(writer.write_samples_f32(&[1.0]).is_err());
+            writer.write_samples_f32(&[-1.0, 0.25]).unwrap();
+            writer.finalize().unwrap();
+        }
+        buffer.set_position(0);
+        let samples: Vec<i32> = WavReader::new(buffer).unwrap().samples().map(|s| s.unwrap()).collect();
+        assert_eq!(&samples[..6], &expected[..], "converting to {} bits", bits);
+        assert_eq!(&samples[6..], &[expected[0], expected[2] / 2][..]);
+    }
+
+    // Floats are written unchanged, even beyond full scale.
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_float_overflow_mode(OverflowMode::Error);
+        writer.write_samples_f32(&input).unwrap();
+        writer.finalize().unwrap();
+    }
+    buffer.set_position(0);
+    let samples: Vec<f32> = WavReader::new(buffer).unwrap().samples().map(|s| s.unwrap()).collect();
+    assert_eq!(&samples[..], &input[..]);
+}
+
+#[test]
+fn set_channel_mask_writes_an_extensible_fmt_chunk() {
+    use read::WavReader;
+    use SpeakerPosition::*;
+
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    let header;
+    {
+        let mut writer = WavWriter::new(&mut buffer, spec).unwrap();
+        writer.set_data_alignment(64).unwrap();
+        writer.add_chunk(RawChunk { id: *b"abcd", data: vec![1, 2, 3] }).unwrap();
+        writer.set_channel_mask(FrontCenter.mask_bit()).unwrap();
+        writer.write_sample(7_i16).unwrap();
+        header = writer.header_bytes();
+        writer.finalize().unwrap();
+    }
+    let bytes = buffer.into_inner();
+    assert_eq!(&bytes[..header.len()], &header[..]);
+    assert_eq!(&bytes[20..22], &[0xfe, 0xff]);
+    let mut reader = WavReader::new(io::Cursor::new(&bytes[..])).unwrap();
+    assert_eq!(reader.channel_mask(), Some(0x4));
+    assert_eq!(reader.speaker_positions(), [FrontCenter]);
+    // The chunk is followed by filler for the alignment.
+    assert_eq!(reader.chunks_before_data()[0], RawChunk { id: *b"abcd", data: vec![1, 2, 3] });
+    assert_eq!(&reader.chunks_before_data()[1].id, b"JUNK");
+    let samples: Vec<i16> = reader.samples().map(|s| s.unwrap()).collect();
+    assert_eq!(samples, [7]);
+    assert_eq!((bytes.len() - 2) % 64, 0);
+
+    // Without a mask, more than two channels get the default assignment.
+    let spec = WavSpec { channels: 6, ..spec };
+    let mut buffer = io::Cursor::new(Vec::new());
+    WavWriter::new(&mut buffer, spec).unwrap().finalize().unwrap();
+    let reader = WavReader::new(io::Cursor::new(buffer.into_inner())).unwrap();
+    assert_eq!(reader.speaker_positions(),
+               [FrontLeft, FrontRight, FrontCenter, LowFrequency, BackLeft, BackRight]);
+}
+
+#[test]
+fn header_fields_must_fit_the_fmt_chunk() {
+    let spec = |channels, sample_rate, bits_per_sample| WavSpec {
+        channels: channels,
+        sample_rate: sample_rate,
+        bits_per_sample: bits_per_sample,
+        sample_format: SampleFormat::Int,
+    };
+    // A block align of 65535 bytes, and a byte rate of exactly 2^32 - 1.
+    let mut buffer = io::Cursor::new(Vec::new());
+    WavWriter::new(&mut buffer, spec(65535, 65537, 8)).unwrap().finalize().unwrap();
+    let options = read::ReadOptions { max_channels: u16::MAX, ..read::ReadOptions::default() };
+    let reader = read::WavReader::new_with_options(io::Cursor::new(buffer.into_inner()), options).unwrap();
+    assert_eq!(reader.spec_ex().block_align(), 65535);
+    assert_eq!(reader.spec_ex().byte_rate(), u32::MAX as u64);
+
+    // One byte more per frame, and one more frame per second.
+    for &s in &[spec(32768, 8000, 16), spec(65535, 65538, 8)] {
+        match WavWriter::new(io::Cursor::new(Vec::new()), s) {
+            Err(Error::InvalidSpec(..)) => {}
+            Err(err) => panic!("expected InvalidSpec for {:?}, got {:?}", s, err),
+            Ok(..) => panic!("expected InvalidSpec for {:?}", s),
+        }
+    }
+}
+
+#[test]
+fn reader_rejects_a_wrapped_block_align() {
+    // 20000 channels of 32-bit samples, with a block align that wrapped
+    // around, and a byte rate that matches it.
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Int,
+    };
+    let mut buffer = io::Cursor::new(Vec::new());
+    WavWriter::new(&mut buffer, spec).unwrap().finalize().unwrap();
+    let mut bytes = buffer.into_inner();
+    let block_align = (20000_u32 * 4) as u16;
+    bytes[22..24].copy_from_slice(&20000_u16.to_le_bytes());
+    bytes[28..32].copy_from_slice(&(block_align as u32 * 8000).to_le_bytes());
+    bytes[32..34].copy_from_slice(&block_align.to_le_bytes());
+    let options = read::ReadOptions { max_channels: u16::MAX, ..read::ReadOptions::default() };
+    match read::WavReader::new_with_options(&bytes[..], options) {
+        Err(Error::FormatError(msg)) => assert!(msg.contains("not a multiple of 20000 channels"), "{}", msg),
+        Err(err) => panic!("expected FormatError, got {:?}", err),
+        Ok(..) => panic!("expected FormatError"),
+    }
+}
+
+#[test]
+fn write_all_writes_whole_frames() {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let path = std::env::temp_dir().join("hound-write-all.wav");
+    write_all(&path, spec, &[1_i32, -2, 0x7fffff, -0x800000]).unwrap();
+    let (spec_read, samples) = read::read_all::<i32, _>(&path).unwrap();
+    assert_eq!(spec_read, spec);
+    assert_eq!(samples, [1, -2, 0x7fffff, -0x800000]);
+    fs::remove_file(&path).unwrap();
+
+    // An incomplete frame is rejected before the file is created.
+    match write_all(&path, spec, &[1_i32, 2, 3]) {
+        Err(Error::InvalidSpec(..)) => {}
+        Err(err) => panic!("expected InvalidSpec, got {:?}", err),
+        Ok(..) => panic!("expected InvalidSpec"),
+    }
+    assert!(!path.exists());
+}